@@ -0,0 +1,87 @@
+// headless text layout benchmark, run with
+// cargo run --release --example text_bench
+use widgets::*;
+use std::time::Instant;
+
+fn collect_files(path:&str, out:&mut Vec<String>){
+    if let Ok(dir) = std::fs::read_dir(path){
+        for entry in dir.flatten(){
+            let name = entry.path().to_string_lossy().to_string();
+            if name.ends_with("target") || name.contains("/."){
+                continue
+            }
+            if entry.path().is_dir(){
+                collect_files(&name, out);
+            }
+            else{
+                out.push(name);
+            }
+        }
+    }
+}
+
+fn bench<F>(name:&str, iters:usize, mut f:F)->f64
+where F: FnMut()
+{
+    let start = Instant::now();
+    for _ in 0..iters{
+        f();
+    }
+    let ms = start.elapsed().as_secs_f64() * 1000.0 / iters as f64;
+    println!("{:<32} {:>10.3} ms", name, ms);
+    ms
+}
+
+fn main(){
+    let mut cx = Cx::default();
+    set_dark_style(&mut cx);
+    let text = Text::style(&mut cx);
+    let mut mono = Text::style(&mut cx);
+    mono.font_id = cx.load_font(&cx.font("mono_font"));
+    cx.load_binary_deps_from_file();
+
+    let source = std::fs::read_to_string("src/codeeditor.rs").expect("run from the repository root");
+    let tokens:Vec<Vec<char>> = source.split_whitespace().map(|t| t.chars().collect()).collect();
+    let mut file_names = Vec::new();
+    collect_files(".", &mut file_names);
+    let file_names:Vec<Vec<char>> = file_names.iter().map(|f| f.chars().collect()).collect();
+    println!("{} tokens, {} file names", tokens.len(), file_names.len());
+
+    // kerning as a scan of the kern table against the hash map
+    let font = &cx.fonts[text.font_id];
+    let chars:Vec<char> = source.chars().collect();
+    println!("{} kerning pairs", font.kerntable.len());
+    let mut total = 0.0;
+    let scan = bench("kern table scan", 1, ||{
+        for pair in chars.windows(2){
+            if let Some(kern) = font.kerntable.iter().find(|k| k.i == pair[0] as u32 && k.j == pair[1] as u32){
+                total += kern.kern;
+            }
+        }
+    });
+    let mut total_map = 0.0;
+    let map = bench("kern hash map", 1, ||{
+        for pair in chars.windows(2){
+            total_map += font.get_kern(pair[0] as u32, pair[1] as u32);
+        }
+    });
+    assert!(total == total_map);
+    println!("{:<32} {:>10.1}x", "kerning speedup", scan / map);
+
+    let levels = vec![0; 4096];
+    for (name, text, strings) in [("code tokens", &mono, &tokens), ("file names", &text, &file_names)].iter(){
+        let uncached = bench(&format!("{} uncached", name), 10, ||{
+            for s in strings.iter(){
+                text.layout_text_uncached(&mut cx, s, &levels[0..s.len()], 0);
+            }
+        });
+        cx.text_layouts.clear();
+        let cached = bench(&format!("{} cached", name), 10, ||{
+            for s in strings.iter(){
+                text.layout_text(&mut cx, s, &levels[0..s.len()], 0);
+            }
+        });
+        println!("{:<32} {:>10.1}x", format!("{} speedup", name), uncached / cached);
+    }
+    println!("cache {} entries, {} hits, {} misses", cx.text_layouts.entries.len(), cx.text_layouts.hits, cx.text_layouts.misses);
+}
@@ -0,0 +1,112 @@
+// inspects and converts the prebaked fonts, run with
+// cargo run --bin fontinfo -- resources/ubuntu_regular_256.font.html [options]
+//   --glyphs           lists the glyph metrics
+//   --kern             lists the kerning pairs
+//   --atlas out.png    writes the atlas texture as an image
+//   --convert out      rewrites the file as .font.html or .font, by the extension of out
+use widgets::*;
+use std::fs;
+
+fn main(){
+    let args:Vec<String> = std::env::args().collect();
+    if args.len() < 2{
+        println!("usage: fontinfo <file.font|file.font.html> [--glyphs] [--kern] [--atlas out.png] [--convert out]");
+        return
+    }
+    if let Err(err) = run(&args[1], &args[2..]){
+        println!("fontinfo: {}", err);
+        std::process::exit(1);
+    }
+}
+
+fn run(file:&str, opts:&[String])->Result<(), String>{
+    let data = fs::read(file).map_err(|err| format!("cannot read {}: {}", file, err))?;
+    let mut dep = BinaryDep::new_from_vec(file.to_string(), data);
+    let mut tex = Texture2D::default();
+    let font = Font::from_binary_dep(&mut dep, &mut tex)?;
+    dep.parse = 0;
+    let type_id = dep.u32()?;
+    // from_binary_dep appends space, tab, newline and the missing glyph after the stored ones
+    let glyphs = &font.glyphs[0..font.slots];
+
+    println!("file      {} ({} bytes)", file, dep.vec_obj.len());
+    println!("type      {:08x} ({})", type_id, if type_id == FONT_TYPE_FONT_HTML{".font.html"}else{".font"});
+    println!("atlas     {} x {}", font.width, font.height);
+    println!("slots     {} ({} single channel)", font.slots, glyphs.iter().filter(|g| g.tsingle != 0).count());
+    println!("rgbsize   {}", font.rgbsize);
+    println!("onesize   {}", font.onesize);
+    println!("kernsize  {}", font.kernsize);
+    println!("scale     {}", font.scale);
+
+    let mut unicodes:Vec<u32> = glyphs.iter().map(|g| g.unicode).collect();
+    unicodes.sort();
+    unicodes.dedup();
+    println!("coverage  {} codepoints", unicodes.len());
+    let mut i = 0;
+    while i < unicodes.len(){
+        let mut j = i;
+        while j + 1 < unicodes.len() && unicodes[j + 1] == unicodes[j] + 1{
+            j += 1;
+        }
+        println!("  U+{:04X}..U+{:04X}  {:>4}  {}..{}", unicodes[i], unicodes[j], j - i + 1, show(unicodes[i]), show(unicodes[j]));
+        i = j + 1;
+    }
+
+    let mut opts = opts.iter();
+    while let Some(opt) = opts.next(){
+        match opt.as_ref(){
+            "--glyphs"=>{
+                println!("glyphs    unicode       x1      y1      x2      y2  advance  texture");
+                for g in glyphs{
+                    println!("  U+{:04X} {:>4} {:>7.3} {:>7.3} {:>7.3} {:>7.3} {:>8.3}  {}x{} {}", g.unicode, show(g.unicode),
+                        g.x1, g.y1, g.x2, g.y2, g.advance, g.tw, g.th, if g.tsingle != 0{"single"}else{"rgb"});
+                }
+            },
+            "--kern"=>if let Some(ttf) = &font.ttf{
+                // ttf kerning is looked up per pair, not baked
+                println!("kerning   {} kern table pairs, {} GPOS pair subtables", ttf.kern_pairs.len(), ttf.gpos_kern.len());
+            }
+            else{
+                println!("kerning   {} pairs", font.kerntable.len());
+                for k in &font.kerntable{
+                    println!("  {} {} {:>8.4}", show(k.i), show(k.j), k.kern);
+                }
+            },
+            "--atlas"=>{
+                let out = opts.next().ok_or("--atlas needs a file name")?;
+                // the font texture is 0xRRGGBB, images are little endian RGBA
+                let image = ImageData{
+                    width:tex.width,
+                    height:tex.height,
+                    pixels:tex.image.iter().map(|v| 0xff00_0000 | ((v & 0xff) << 16) | (v & 0xff00) | ((v >> 16) & 0xff)).collect()
+                };
+                fs::write(out, image.encode_png()).map_err(|err| format!("cannot write {}: {}", out, err))?;
+                println!("wrote atlas {}", out);
+            },
+            "--convert"=>{
+                let out = opts.next().ok_or("--convert needs a file name")?;
+                // both variants share the layout, the .font.html header adds the scale
+                let to_html = out.ends_with(".font.html");
+                let header = if type_id == FONT_TYPE_FONT_HTML{28}else{24};
+                let mut data = Vec::new();
+                data.extend_from_slice(&(if to_html{FONT_TYPE_FONT_HTML}else{FONT_TYPE_FONT}).to_le_bytes());
+                data.extend_from_slice(&dep.vec_obj[4..24]);
+                if to_html{
+                    data.extend_from_slice(&font.scale.to_le_bytes());
+                }
+                data.extend_from_slice(&dep.vec_obj[header..]);
+                fs::write(out, data).map_err(|err| format!("cannot write {}: {}", out, err))?;
+                println!("wrote {} {}", if to_html{".font.html"}else{".font"}, out);
+            },
+            _=>return Err(format!("unknown option {}", opt))
+        }
+    }
+    Ok(())
+}
+
+fn show(unicode:u32)->String{
+    match std::char::from_u32(unicode){
+        Some(c) if !c.is_control() && c != ' '=>format!("'{}'", c),
+        _=>"   ".to_string()
+    }
+}
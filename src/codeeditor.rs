@@ -1,5 +1,7 @@
 use widgets::*;
 use crate::textbuffer::*;
+use crate::vim::*;
+use crate::keymacro::*;
 
 #[derive(Clone)]
 pub struct CodeEditor{
@@ -14,6 +16,9 @@ pub struct CodeEditor{
     
     pub open_font_size:f32,
     pub folded_font_size:f32,
+    pub soft_wrap:SoftWrap,
+    pub vim:Vim,
+    pub macros:KeyMacros,
 
     pub _hit_state:HitState,
     pub _bg_area:Area,
@@ -24,6 +29,12 @@ pub struct CodeEditor{
     pub _paren_stack:Vec<ParenItem>,
     pub _paren_list:Vec<ParenItem>,
     pub _line_geometry:Vec<LineGeom>,
+    pub _visual_rows:Vec<VisualRow>,
+    pub _visual_row_geometry:Vec<LineGeom>,
+    pub _new_visual_row:bool,
+    pub _line_start:Option<(usize, f32)>,
+    pub _line_indent:Option<f32>,
+    pub _wrap_width:f32,
     pub _anim_select:Vec<AnimSelect>,
     pub _token_chunks:Vec<TokenChunk>,
     pub _visible_lines:usize,
@@ -41,6 +52,13 @@ pub struct CodeEditor{
     pub _draw_cursor:DrawCursor
 }
 
+#[derive(Clone, PartialEq)]
+pub enum SoftWrap{
+    None,
+    ViewWidth,
+    Column(usize)
+}
+
 #[derive(Clone)]
 pub enum AnimFoldingState{
     Open,
@@ -134,8 +152,8 @@ pub struct AnimSelect{
 
 #[derive(Clone, Default)]
 pub struct LineGeom{
-    walk:Vec2,
-    font_size:f32
+    pub walk:Vec2,
+    pub font_size:f32
 }
 
 #[derive(Clone, Default)]
@@ -148,9 +166,9 @@ pub struct SelectScroll{
 
 #[derive(Clone)]
 pub struct ParenItem{
-    start:usize,
-    end:usize,
-    paren_type:ParenType
+    pub start:usize, // token chunk of the open paren
+    pub end:usize, // one past the token chunk of the close paren
+    pub paren_type:ParenType
 }
 
 #[derive(Clone, PartialEq)]
@@ -162,7 +180,9 @@ pub enum ParenType{
 
 impl ElementLife for CodeEditor{
     fn construct(&mut self, _cx:&mut Cx){}
-    fn destruct(&mut self, _cx:&mut Cx){}
+    fn destruct(&mut self, cx:&mut Cx){
+        self.view.destruct(cx);
+    }
 }
 
 impl Style for CodeEditor{
@@ -211,14 +231,19 @@ impl Style for CodeEditor{
             },
             text:Text{
                 font_id:cx.load_font(&cx.font("mono_font")),
+                font_fallbacks:cx.load_font_list(&cx.font_list("mono_font_fallback")),
                 font_size:11.0,
                 brightness:1.05,
                 line_spacing:1.4,
                 wrapping:Wrapping::Line,
+                monospace:true,
                 ..Style::style(cx)
             },
             open_font_size:11.0,
             folded_font_size:0.5,
+            soft_wrap:SoftWrap::None,
+            vim:Vim::new(),
+            macros:KeyMacros::new(),
             _hit_state:HitState{no_scrolling:true, ..Default::default()},
             _monospace_size:Vec2::zero(),
             _last_finger_move:None,
@@ -226,6 +251,12 @@ impl Style for CodeEditor{
             _scroll_pos:Vec2::zero(),
             _visible_lines:0, 
             _line_geometry:Vec::new(),
+            _visual_rows:Vec::new(),
+            _visual_row_geometry:Vec::new(),
+            _new_visual_row:true,
+            _line_start:None,
+            _line_indent:None,
+            _wrap_width:0.0,
             _token_chunks:Vec::new(),
             _anim_select:Vec::new(),
             _grid_select_corner:None,
@@ -246,6 +277,7 @@ impl Style for CodeEditor{
             _paren_list:Vec::new(),
         };
         //tab.animator.default = tab.anim_default(cx);
+        Self::register_commands(cx);
         code_editor
     }
 }
@@ -464,120 +496,37 @@ impl CodeEditor{
                 }
             },
             Event::KeyDown(ke)=>{
-                let cursor_moved = match ke.key_code{
-                    KeyCode::ArrowUp=>{
-                        self.cursors.move_up(1, ke.modifiers.shift, text_buffer);
-                        true
-                    },
-                    KeyCode::ArrowDown=>{
-                        self.cursors.move_down(1, ke.modifiers.shift, text_buffer);
-                        true
-                    },
-                    KeyCode::ArrowLeft=>{
-                        if ke.modifiers.logo || ke.modifiers.control{ // token skipping
-                            self.cursors.move_left_nearest_token(ke.modifiers.shift, &self._token_chunks, text_buffer)
-                        }
-                        else{
-                            self.cursors.move_left(1, ke.modifiers.shift, text_buffer);
-                        }
-                        true
-                    },
-                    KeyCode::ArrowRight=>{
-                        if ke.modifiers.logo || ke.modifiers.control{ // token skipping
-                            self.cursors.move_right_nearest_token(ke.modifiers.shift, &self._token_chunks, text_buffer)
-                        }
-                        else{
-                            self.cursors.move_right(1, ke.modifiers.shift, text_buffer);
-                        }
-                        true
-                    },
-                    KeyCode::PageUp=>{
-                        
-                        self.cursors.move_up(self._visible_lines.max(5) - 4, ke.modifiers.shift, text_buffer);
-                        true
-                    },
-                    KeyCode::PageDown=>{
-                        self.cursors.move_down(self._visible_lines.max(5) - 4, ke.modifiers.shift, text_buffer);
-                        true
-                    },
-                    KeyCode::Home=>{
-                        self.cursors.move_home(ke.modifiers.shift, text_buffer);
-                        true
-                    },
-                    KeyCode::End=>{
-                        self.cursors.move_end(ke.modifiers.shift, text_buffer);
-                        true
-                    },
-                    KeyCode::Backspace=>{
-                        self.cursors.backspace(text_buffer);
-                        true
-                    },
-                    KeyCode::Delete=>{
-                        self.cursors.delete(text_buffer);
-                        true
-                    },
-                    KeyCode::KeyZ=>{
-                        if ke.modifiers.logo || ke.modifiers.control{
-                            if ke.modifiers.shift{ // redo
-                                text_buffer.redo(true, &mut self.cursors);
-                                true
-                            }
-                            else{ // undo
-                                text_buffer.undo(true, &mut self.cursors);
-                                true
-                            }
-                        }
-                        else{
-                            false
-                        }
-                    },
-                    KeyCode::KeyX=>{ // cut, the actual copy comes from the TextCopy event from the platform layer
-                        if ke.modifiers.logo || ke.modifiers.control{ // cut
-                            self.cursors.replace_text("", text_buffer);
-                            true
-                        }
-                        else{
-                            false
-                        }
-                    },
-                    KeyCode::KeyA=>{ // select all
-                        if ke.modifiers.logo || ke.modifiers.control{ // cut
-                            self.cursors.select_all(text_buffer);
-                            // don't scroll!
-                            self.view.redraw_view_area(cx);
-                            false
-                        }
-                        else{
+                if self.macros.recording{
+                    self.macros.record_key_down(cx, &ke);
+                }
+                let cursor_moved = if self.vim.enabled && self.vim_key_down(cx, &ke, text_buffer){
+                    true
+                }
+                else if let Some(command) = cx.key_command(&ke, "editor"){
+                    self.exec_command(cx, &command, text_buffer)
+                }
+                else{
+                    match ke.key_code{
+                        // held down to fold, see KeyUp
+                        KeyCode::Escape | KeyCode::Alt=>{
+                            self.do_code_folding(cx);
                             false
-                        }
-                    },
-                    KeyCode::Escape=>{
-                        self.do_code_folding(cx);
-                        false
-                    },
-                    KeyCode::Alt=>{
-                        // how do we find the center line of the view
-                        // its simply the top line
-                        self.do_code_folding(cx);
-                        false
-                        //return CodeEditorEvent::FoldStart
-                    },
-                    KeyCode::Tab=>{
-                        if ke.modifiers.shift{
-                            //self.cursors.remove_tab(text_buffer);
-                        }
-                        else{
-                            self.cursors.insert_tab(text_buffer,"    ");
-                        }
-                        true
-                    },
-                    _=>false
+                        },
+                        _=>false
+                    }
                 };
                 if cursor_moved{
                     self.scroll_last_cursor_visible(cx, text_buffer);
                     self.view.redraw_view_area(cx);
                 }
             },
+            Event::Command(ce)=>{
+                let cursor_moved = self.exec_command(cx, &ce.command, text_buffer);
+                if cursor_moved{
+                    self.scroll_last_cursor_visible(cx, text_buffer);
+                    self.view.redraw_view_area(cx);
+                }
+            },
             Event::KeyUp(ke)=>{
                 match ke.key_code{
                     KeyCode::Alt=>{
@@ -590,42 +539,13 @@ impl CodeEditor{
                 }
             },
             Event::TextInput(te)=>{
-                if te.replace_last{
-                    text_buffer.undo(false, &mut self.cursors);
+                if self.macros.recording{
+                    self.macros.record_text_input(&te);
                 }
-                
-                if !te.was_paste && te.input.len() == 1{
-                    match te.input.chars().next().unwrap(){
-                        '\n'=>{
-                            self.cursors.insert_newline_with_indent(text_buffer);
-                        },
-                        '('=>{
-                            self.cursors.insert_around("(",")",text_buffer);
-                        },
-                        '['=>{
-                            self.cursors.insert_around("[","]",text_buffer);
-                        },
-                        '{'=>{
-                            self.cursors.insert_around("{","}",text_buffer);
-                        },
-                        ')'=>{
-                            self.cursors.overwrite_if_exists(")", text_buffer);
-                        },
-                        ']'=>{
-                            self.cursors.overwrite_if_exists("]", text_buffer);
-                        },
-                        '}'=>{
-                            self.cursors.overwrite_if_exists("}", text_buffer);
-                        },
-                        _=>{
-                            self.cursors.replace_text(&te.input, text_buffer);
-                        }
-                    }  
-                    // lets insert a newline
-                } 
-                else{
-                    self.cursors.replace_text(&te.input, text_buffer);
+                if self.vim.enabled && self.vim_text_input(&te){
+                    return CodeEditorEvent::None
                 }
+                self.text_input(&te, text_buffer);
                 self.scroll_last_cursor_visible(cx, text_buffer);
                 self.view.redraw_view_area(cx);
             },
@@ -640,6 +560,177 @@ impl CodeEditor{
         CodeEditorEvent::None
    }
 
+    pub fn text_input(&mut self, te:&TextInputEvent, text_buffer:&mut TextBuffer){
+        if te.replace_last{
+            text_buffer.undo(false, &mut self.cursors);
+        }
+        
+        if !te.was_paste && te.input.len() == 1{
+            match te.input.chars().next().unwrap(){
+                '\n'=>{
+                    self.cursors.insert_newline_with_indent(text_buffer);
+                },
+                '('=>{
+                    self.cursors.insert_around("(",")",text_buffer);
+                },
+                '['=>{
+                    self.cursors.insert_around("[","]",text_buffer);
+                },
+                '{'=>{
+                    self.cursors.insert_around("{","}",text_buffer);
+                },
+                ')'=>{
+                    self.cursors.overwrite_if_exists(")", text_buffer);
+                },
+                ']'=>{
+                    self.cursors.overwrite_if_exists("]", text_buffer);
+                },
+                '}'=>{
+                    self.cursors.overwrite_if_exists("}", text_buffer);
+                },
+                _=>{
+                    self.cursors.replace_text(&te.input, text_buffer);
+                }
+            }  
+            // lets insert a newline
+        } 
+        else{
+            self.cursors.replace_text(&te.input, text_buffer);
+        }
+    }
+
+    // the commands the editor runs from the keymap, with their default keys
+    pub fn register_commands(cx:&mut Cx){
+        let commands = [
+            ("editor.cursor_up", "Cursor Up", "Up"),
+            ("editor.cursor_up_select", "Select Up", "Shift+Up"),
+            ("editor.cursor_down", "Cursor Down", "Down"),
+            ("editor.cursor_down_select", "Select Down", "Shift+Down"),
+            ("editor.cursor_left", "Cursor Left", "Left"),
+            ("editor.cursor_left_select", "Select Left", "Shift+Left"),
+            ("editor.cursor_right", "Cursor Right", "Right"),
+            ("editor.cursor_right_select", "Select Right", "Shift+Right"),
+            ("editor.cursor_word_left", "Cursor Word Left", "Mod+Left"),
+            ("editor.cursor_word_left_select", "Select Word Left", "Mod+Shift+Left"),
+            ("editor.cursor_word_right", "Cursor Word Right", "Mod+Right"),
+            ("editor.cursor_word_right_select", "Select Word Right", "Mod+Shift+Right"),
+            ("editor.page_up", "Page Up", "PageUp"),
+            ("editor.page_up_select", "Select Page Up", "Shift+PageUp"),
+            ("editor.page_down", "Page Down", "PageDown"),
+            ("editor.page_down_select", "Select Page Down", "Shift+PageDown"),
+            ("editor.cursor_home", "Cursor Home", "Home"),
+            ("editor.cursor_home_select", "Select To Home", "Shift+Home"),
+            ("editor.cursor_end", "Cursor End", "End"),
+            ("editor.cursor_end_select", "Select To End", "Shift+End"),
+            ("editor.delete_left", "Delete Left", "Backspace"),
+            ("editor.delete_right", "Delete Right", "Delete"),
+            ("editor.undo", "Undo", "Mod+Z"),
+            ("editor.redo", "Redo", "Mod+Shift+Z"),
+            ("editor.cut", "Cut", "Mod+X"),
+            ("editor.select_all", "Select All", "Mod+A"),
+            ("editor.indent", "Indent", "Tab"),
+            ("editor.fold_all", "Fold All", "Mod+K Mod+0"),
+            ("editor.unfold_all", "Unfold All", "Mod+K Mod+J"),
+            ("editor.toggle_soft_wrap", "Toggle Soft Wrap", "Alt+Z"),
+            ("editor.macro_record", "Record Macro", "Mod+K Mod+R"),
+            ("editor.macro_stop", "Stop Recording Macro", "Mod+K Mod+S"),
+            ("editor.macro_play", "Play Macro", "Mod+K Mod+P"),
+            ("editor.macro_play_to_end", "Play Macro To End Of File", "Mod+K Mod+E"),
+        ];
+        for (name, title, keys) in commands.iter(){
+            cx.register_command(name, title);
+            cx.bind_keys(keys, name, Some("editor")).expect("default editor keys parse");
+        }
+        cx.register_command("editor.toggle_vim", "Toggle Vim Mode");
+    }
+
+    // runs a named command, returns if the cursor moved so it can be scrolled into view
+    pub fn exec_command(&mut self, cx:&mut Cx, command:&str, text_buffer:&mut TextBuffer)->bool{
+        let select = command.ends_with("_select");
+        match command.trim_end_matches("_select"){
+            "editor.cursor_up"=>self.move_up(1, select, text_buffer),
+            "editor.cursor_down"=>self.move_down(1, select, text_buffer),
+            "editor.cursor_left"=>self.cursors.move_left(1, select, text_buffer),
+            "editor.cursor_right"=>self.cursors.move_right(1, select, text_buffer),
+            "editor.cursor_word_left"=>self.cursors.move_left_nearest_token(select, &self._token_chunks, text_buffer),
+            "editor.cursor_word_right"=>self.cursors.move_right_nearest_token(select, &self._token_chunks, text_buffer),
+            "editor.page_up"=>self.move_up(self._visible_lines.max(5) - 4, select, text_buffer),
+            "editor.page_down"=>self.move_down(self._visible_lines.max(5) - 4, select, text_buffer),
+            "editor.cursor_home"=>self.cursors.move_home(select, text_buffer),
+            "editor.cursor_end"=>self.cursors.move_end(select, text_buffer),
+            "editor.delete_left"=>self.cursors.backspace(text_buffer),
+            "editor.delete_right"=>self.cursors.delete(text_buffer),
+            "editor.undo"=>text_buffer.undo(true, &mut self.cursors),
+            "editor.redo"=>text_buffer.redo(true, &mut self.cursors),
+            // the actual copy comes from the TextCopy event from the platform layer
+            "editor.cut"=>self.cursors.replace_text("", text_buffer),
+            "editor.select_all"=>{
+                self.cursors.select_all(text_buffer);
+                // don't scroll!
+                self.view.redraw_view_area(cx);
+                return false
+            },
+            "editor.indent"=>self.cursors.insert_tab(text_buffer,"    "),
+            "editor.fold_all"=>{
+                self.do_code_folding(cx);
+                return false
+            },
+            "editor.unfold_all"=>{
+                self.do_code_unfolding(cx);
+                return false
+            },
+            "editor.toggle_soft_wrap"=>{
+                self.soft_wrap = if self.soft_wrap == SoftWrap::None{SoftWrap::ViewWidth}else{SoftWrap::None};
+                self.view.redraw_view_area(cx);
+                return false
+            },
+            "editor.toggle_vim"=>{
+                self.vim.enabled = !self.vim.enabled;
+                self.vim.mode = VimMode::Normal;
+                self.vim._keys.truncate(0);
+                self.view.redraw_view_area(cx);
+                return false
+            },
+            "editor.macro_record"=>{
+                self.macros.start_recording();
+                return false
+            },
+            "editor.macro_stop"=>{
+                self.macros.stop_recording();
+                return false
+            },
+            "editor.macro_play" | "editor.macro_play_to_end"=>{
+                let events = self.macros.last.clone();
+                let count = if command == "editor.macro_play"{Some(1)} else{None};
+                return self.play_macro(cx, &events, count, text_buffer) > 0
+            },
+            name if name.starts_with("editor.macro_play.")=>{
+                return self.play_saved_macro(cx, &name["editor.macro_play.".len()..], Some(1), text_buffer).unwrap_or(0) > 0
+            },
+            _=>return false
+        }
+        true
+    }
+
+    // soft wrapped lines move by the rows they are drawn as
+    fn move_up(&mut self, row_count:usize, only_head:bool, text_buffer:&TextBuffer){
+        if self.soft_wrap == SoftWrap::None{
+            self.cursors.move_up(row_count, only_head, text_buffer);
+        }
+        else{
+            self.cursors.move_up_visual(row_count, only_head, &self._visual_rows, text_buffer);
+        }
+    }
+
+    fn move_down(&mut self, row_count:usize, only_head:bool, text_buffer:&TextBuffer){
+        if self.soft_wrap == SoftWrap::None{
+            self.cursors.move_down(row_count, only_head, text_buffer);
+        }
+        else{
+            self.cursors.move_down_visual(row_count, only_head, &self._visual_rows, text_buffer);
+        }
+    }
+
     pub fn do_code_folding(&mut self, cx:&mut Cx){
         // start code folding anim
         let speed = 0.98;
@@ -705,6 +796,13 @@ impl CodeEditor{
 
             self._monospace_size = self.text.get_monospace_size(cx, None);
             self._line_geometry.truncate(0);
+            self._visual_rows.truncate(0);
+            self._visual_row_geometry.truncate(0);
+            self._new_visual_row = true;
+            self._line_start = None;
+            self._line_indent = None;
+            // keep the room new_line adds to the right inside the view
+            self._wrap_width = cx.width_total(false) - self._monospace_size.x * 3.;
             self._token_chunks.truncate(0);
             self._draw_cursor = DrawCursor::new();
             self._first_on_line = true;
@@ -723,6 +821,7 @@ impl CodeEditor{
             }
            
             self._paren_stack.truncate(0);
+            self._paren_list.truncate(0);
             self._anim_font_size = anim_folding.state.get_font_size(self.open_font_size, self.folded_font_size);
 
             self._draw_cursor.set_next(&self.cursors.set);
@@ -741,6 +840,9 @@ impl CodeEditor{
         cx.walk_turtle(Bounds::Fix(0.0),  Bounds::Fix(self._monospace_size.y),  Margin::zero(), None);
         
         self.text.end_text(cx, self._text_inst.as_ref().unwrap());
+        if self.vim.enabled{
+            self.draw_vim_status(cx);
+        }
         // lets draw cursors and selection rects.
         //let draw_cursor = &self._draw_cursor;
         let pos = cx.turtle_origin();
@@ -916,6 +1018,9 @@ impl CodeEditor{
         );
         cx.turtle_new_line();
         self._first_on_line = true;
+        self._new_visual_row = true;
+        self._line_start = None;
+        self._line_indent = None;
         let mut draw_cursor = &mut self._draw_cursor;
         if !draw_cursor.first{ // we have some selection data to emit
            draw_cursor.emit_selection(true);
@@ -966,64 +1071,128 @@ impl CodeEditor{
 
     pub fn draw_text(&mut self, cx:&mut Cx, chunk:&mut Vec<char>, end_offset:usize, is_whitespace:bool, color:Color){
         if chunk.len()>0{
-
+            let offset = end_offset - chunk.len() - 1;
             self._token_chunks.push(TokenChunk{
-                offset:end_offset - chunk.len() - 1,
+                offset:offset,
                 len:chunk.len(),
                 is_whitespace:is_whitespace,
             });
-            
-            let geom = cx.walk_turtle(
-                Bounds::Fix(self._monospace_size.x * (chunk.len() as f32)), 
-                Bounds::Fix(self._monospace_size.y), 
-                Margin::zero(),
-                None
-            );
-            
-            // lets check if the geom is visible
-            if cx.visible_in_turtle(geom, self._scroll_pos){
-
-                 if self._first_on_line{
-                    self._first_on_line = false;
-                    self._visible_lines += 1;
+            if self._line_start.is_none(){
+                self._line_start = Some((offset, cx.get_rel_turtle_walk().x));
+            }
+            if !is_whitespace && self._line_indent.is_none(){
+                self._line_indent = Some(cx.get_rel_turtle_walk().x);
+            }
+            let wrap_width = match self.soft_wrap{
+                SoftWrap::None=>f32::INFINITY,
+                SoftWrap::ViewWidth=>self._wrap_width,
+                SoftWrap::Column(col)=>self._monospace_size.x * col as f32
+            };
+            let mut start = 0;
+            while start < chunk.len(){
+                let mut len = chunk.len() - start;
+                let walk = cx.get_rel_turtle_walk();
+                // whitespace is allowed to hang past the wrap
+                if !is_whitespace && walk.x + self._monospace_size.x * (len as f32) > wrap_width{
+                    if walk.x > self._line_indent.unwrap_or(0.0){
+                        self.soft_new_line(cx);
+                        continue;
+                    }
+                    // a token wider than the row breaks where it hits the wrap
+                    let fit = ((wrap_width - walk.x) / self._monospace_size.x).floor().max(1.0) as usize;
+                    len = len.min(fit);
                 }
+                self.draw_chunk(cx, &chunk[start..start + len], offset + start, color);
+                start += len;
+            }
+            self._instance_count += chunk.len();
+        }
+    }
 
-                self.text.color = color;
-                // we need to find the next cursor point we need to do something at
-                let cursors = &self.cursors.set;
-                let last_cursor = self.cursors.last_cursor;
-                let draw_cursor = &mut self._draw_cursor;
-                let height = self._monospace_size.y;
-
-                self.text.add_text(cx, geom.x, geom.y, end_offset - chunk.len() - 1, self._text_inst.as_mut().unwrap(), &chunk, |unicode, offset, x, w|{
-                    // check if we need to skip cursors
-                    while offset >= draw_cursor.end{ // jump to next cursor
-                        if offset == draw_cursor.end{ // process the last bit here
-                             draw_cursor.process_geom(last_cursor, offset, x, geom.y, w, height);
-                            draw_cursor.emit_selection(false);
-                        }
-                        if !draw_cursor.set_next(cursors){ // cant go further
-                            return 0.0
-                        }
-                    }
-                    // in current cursor range, update values
-                    if offset >= draw_cursor.start && offset <= draw_cursor.end{
-                        draw_cursor.process_geom(last_cursor, offset, x, geom.y, w, height);
-                        if offset == draw_cursor.end{
-                            draw_cursor.emit_selection(false);
-                        }
-                        if unicode == 10{
-                            return 0.0
-                        }
-                        else if unicode == 32 && offset < draw_cursor.end{
-                            return 2.0
-                        }
-                    }
-                    return 0.0
-                });
+    // continues a soft wrapped line on the next row at the indent of the line
+    fn soft_new_line(&mut self, cx:&mut Cx){
+        cx.turtle_new_line();
+        self._first_on_line = true;
+        self._new_visual_row = true;
+        let draw_cursor = &mut self._draw_cursor;
+        if !draw_cursor.first{
+           draw_cursor.emit_selection(true);
+           draw_cursor.first = true;
+        }
+        let walk = cx.get_rel_turtle_walk();
+        let indent = self._line_indent.unwrap_or(walk.x) - walk.x;
+        cx.walk_turtle(
+            Bounds::Fix(indent.max(0.)),
+            Bounds::Fix(self._monospace_size.y),
+            Margin::zero(),
+            None
+        );
+    }
+
+    fn draw_chunk(&mut self, cx:&mut Cx, chunk:&[char], offset:usize, color:Color){
+        if self._new_visual_row{
+            self._new_visual_row = false;
+            let walk = cx.get_rel_turtle_walk();
+            let (line_offset, line_x) = self._line_start.unwrap_or((offset, walk.x));
+            self._visual_rows.push(VisualRow{
+                offset:offset,
+                row:self._line_geometry.len(),
+                col:offset - line_offset,
+                indent:((walk.x - line_x) / self._monospace_size.x).round() as usize
+            });
+            self._visual_row_geometry.push(LineGeom{
+                walk:walk,
+                font_size:self.text.font_size
+            });
+        }
+        let geom = cx.walk_turtle(
+            Bounds::Fix(self._monospace_size.x * (chunk.len() as f32)), 
+            Bounds::Fix(self._monospace_size.y), 
+            Margin::zero(),
+            None
+        );
+        
+        // lets check if the geom is visible
+        if cx.visible_in_turtle(geom, self._scroll_pos){
+
+             if self._first_on_line{
+                self._first_on_line = false;
+                self._visible_lines += 1;
             }
 
-            self._instance_count += chunk.len();
+            self.text.color = color;
+            // we need to find the next cursor point we need to do something at
+            let cursors = &self.cursors.set;
+            let last_cursor = self.cursors.last_cursor;
+            let draw_cursor = &mut self._draw_cursor;
+            let height = self._monospace_size.y;
+
+            self.text.add_text(cx, geom.x, geom.y, offset, self._text_inst.as_mut().unwrap(), chunk, |unicode, offset, x, w|{
+                // check if we need to skip cursors
+                while offset >= draw_cursor.end{ // jump to next cursor
+                    if offset == draw_cursor.end{ // process the last bit here
+                         draw_cursor.process_geom(last_cursor, offset, x, geom.y, w, height);
+                        draw_cursor.emit_selection(false);
+                    }
+                    if !draw_cursor.set_next(cursors){ // cant go further
+                        return 0.0
+                    }
+                }
+                // in current cursor range, update values
+                if offset >= draw_cursor.start && offset <= draw_cursor.end{
+                    draw_cursor.process_geom(last_cursor, offset, x, geom.y, w, height);
+                    if offset == draw_cursor.end{
+                        draw_cursor.emit_selection(false);
+                    }
+                    if unicode == 10{
+                        return 0.0
+                    }
+                    else if unicode == 32 && offset < draw_cursor.end{
+                        return 2.0
+                    }
+                }
+                return 0.0
+            });
         }
     }
 
@@ -1044,6 +1213,20 @@ impl CodeEditor{
             panic!("LAST CURSOR INVALID");
         }
         //let offset = self.cursors.set[self.cursors.last_cursor].head;
+        if self.soft_wrap != SoftWrap::None && !self._visual_rows.is_empty(){
+            let head = self.cursors.set[self.cursors.last_cursor].head;
+            let index = self._visual_rows.iter().rposition(|r| r.offset <= head).unwrap_or(0);
+            let geom = &self._visual_row_geometry[index];
+            let mono_size = self.text.get_monospace_size(cx, Some(geom.font_size));
+            let rect = Rect{
+                x:geom.walk.x + ((head - self._visual_rows[index].offset) as f32) * mono_size.x,
+                y:geom.walk.y - mono_size.y * 1.,
+                w:mono_size.x * 4.,
+                h:mono_size.y * 4.
+            };
+            self.view.scroll_into_view(cx, rect);
+            return
+        }
         let pos = self.cursors.get_last_cursor_text_pos(text_buffer);
         //text_buffer.offset_to_text_pos(offset);
         // alright now lets query the line geometry
@@ -1066,6 +1249,21 @@ impl CodeEditor{
         // 
         let rel = self._bg_area.abs_to_rel_scrolled(cx, abs);
         let mut mono_size = Vec2::zero();
+        if self.soft_wrap != SoftWrap::None && !self._visual_rows.is_empty(){
+            // wrapped rows map back to the column in their line
+            let mut index = self._visual_rows.len() - 1;
+            for (i, geom) in self._visual_row_geometry.iter().enumerate(){
+                mono_size = self.text.get_monospace_size(cx, Some(geom.font_size));
+                if rel.y < geom.walk.y || rel.y >= geom.walk.y && rel.y <= geom.walk.y + mono_size.y{
+                    index = i;
+                    break;
+                }
+            }
+            let row = &self._visual_rows[index];
+            let geom = &self._visual_row_geometry[index];
+            let col = ((rel.x - geom.walk.x).max(0.) / mono_size.x) as usize;
+            return TextPos{row:row.row, col:row.col + col};
+        }
         for (row, geom) in self._line_geometry.iter().enumerate(){
             //let geom = &self._line_geometry[pos.row];
             mono_size = self.text.get_monospace_size(cx, Some(geom.font_size));
@@ -0,0 +1,212 @@
+use widgets::*;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::cell::RefCell;
+use serde_json::{Value, Map};
+use crate::textbuffer::*;
+use crate::codeeditor::*;
+
+// keyboard macros. The key downs and text inputs the editor gets while recording are kept,
+// playing them sends them through handle_code_editor again and undoes as one step.
+// saved macros are shared by the editors cloned from one template and written to a json file
+//   {"bullet":[{"key":"Home"}, {"text":"- "}, {"key":"Shift+;", "char":":"}]}
+
+#[derive(Clone, Default)]
+pub struct KeyMacros{
+    pub recording:bool,
+    pub last:Vec<Event>,
+    pub saved:Rc<RefCell<HashMap<String, Vec<Event>>>>,
+    pub file:Option<String>, // where saved macros are written, the app loads it at startup
+    pub _record:Vec<Event>,
+    pub _pending:Vec<Event>, // the keys of a key sequence that isn't finished yet
+    pub _playing:bool
+}
+
+impl KeyMacros{
+    pub fn new()->KeyMacros{
+        KeyMacros::default()
+    }
+
+    pub fn start_recording(&mut self){
+        self.recording = true;
+        self._record.truncate(0);
+        self._pending.truncate(0);
+    }
+
+    // an empty recording keeps the macro from before
+    pub fn stop_recording(&mut self){
+        if !self.recording{
+            return
+        }
+        self.recording = false;
+        self._pending.truncate(0);
+        if !self._record.is_empty(){
+            self.last = std::mem::take(&mut self._record);
+        }
+    }
+
+    // the keys that run a macro command are left out, a macro can't record or play itself
+    pub fn record_key_down(&mut self, cx:&mut Cx, ke:&KeyEvent){
+        // key_command gives the editor the same answer when it asks for this event
+        let command = cx.key_command(ke, "editor");
+        self._pending.push(Event::KeyDown(ke.clone()));
+        if command.is_none() && cx.is_key_sequence_pending(){
+            return
+        }
+        let pending = std::mem::take(&mut self._pending);
+        if !command.is_some_and(|command| command.starts_with("editor.macro_")){
+            self._record.extend(pending);
+        }
+    }
+
+    pub fn record_text_input(&mut self, te:&TextInputEvent){
+        self._record.push(Event::TextInput(te.clone()));
+    }
+
+    // keeps the last macro under a name and registers a command to play it, so it shows up
+    // in the command palette and can be bound in keymap.json. With a file the saved macros
+    // are written to it, the macro is kept when that fails
+    pub fn save_macro(&mut self, cx:&mut Cx, name:&str)->Result<(), String>{
+        if self.last.is_empty(){
+            return Err("no macro recorded".to_string())
+        }
+        KeyMacros::check_name(name)?;
+        self.saved.borrow_mut().insert(name.to_string(), self.last.clone());
+        KeyMacros::register_macro(cx, name);
+        if let Some(file) = &self.file{
+            std::fs::write(file, self.saved_to_json()).map_err(|err| format!("macros: cannot write {}: {}", file, err))?;
+        }
+        Ok(())
+    }
+
+    fn check_name(name:&str)->Result<(), String>{
+        if name.is_empty() || name.contains(char::is_whitespace){
+            return Err(format!("bad macro name: {}", name))
+        }
+        Ok(())
+    }
+
+    fn register_macro(cx:&mut Cx, name:&str){
+        cx.register_command(&format!("editor.macro_play.{}", name), &format!("Play Macro {}", name));
+    }
+
+    pub fn saved_to_json(&self)->String{
+        let saved:Map<String, Value> = self.saved.borrow().iter().map(|(name, events)|{
+            (name.clone(), Value::Array(events.iter().filter_map(event_to_json).collect()))
+        }).collect();
+        serde_json::to_string_pretty(&Value::Object(saved)).unwrap_or_default()
+    }
+
+    // adds the macros of a file written by save_macro, returns how many were loaded
+    pub fn load_saved_from_json(&mut self, cx:&mut Cx, json_data:&str)->Result<usize, String>{
+        let value:Value = serde_json::from_str(json_data).map_err(|e| format!("macros: {}", e))?;
+        let macros = if let Value::Object(macros) = value{macros}else{
+            return Err("macros: expected an object of named macros".to_string())
+        };
+        let mut loaded = Vec::new();
+        for (name, events) in &macros{
+            KeyMacros::check_name(name)?;
+            let events = if let Value::Array(events) = events{events}else{
+                return Err(format!("macros: {} is not a list of events", name))
+            };
+            loaded.push((name.clone(), events.iter().map(event_from_json).collect::<Result<Vec<_>, _>>()?));
+        }
+        let count = loaded.len();
+        for (name, events) in loaded{
+            KeyMacros::register_macro(cx, &name);
+            self.saved.borrow_mut().insert(name, events);
+        }
+        Ok(count)
+    }
+}
+
+// keys are named like in keymap.json, keys without a keycode only keep their char
+fn event_to_json(event:&Event)->Option<Value>{
+    let mut out = Map::new();
+    match event{
+        Event::KeyDown(ke)=>{
+            if ke.key_code != KeyCode::Unknown{
+                let chord = KeyChord{key_code:ke.key_code.clone(), modifiers:ke.modifiers.clone(), primary:false};
+                out.insert("key".to_string(), Value::String(chord.name()));
+            }
+            if ke.key_char != '\0'{
+                out.insert("char".to_string(), Value::String(ke.key_char.to_string()));
+            }
+        },
+        Event::TextInput(te)=>{
+            out.insert("text".to_string(), Value::String(te.input.clone()));
+            if te.replace_last{
+                out.insert("replace_last".to_string(), Value::Bool(true));
+            }
+            if te.was_paste{
+                out.insert("paste".to_string(), Value::Bool(true));
+            }
+        },
+        _=>return None
+    }
+    Some(Value::Object(out))
+}
+
+fn event_from_json(value:&Value)->Result<Event, String>{
+    let field = |name:&str| value.get(name).and_then(|v| v.as_str());
+    let flag = |name:&str| value.get(name).and_then(|v| v.as_bool()).unwrap_or(false);
+    if let Some(text) = field("text"){
+        return Ok(Event::TextInput(TextInputEvent{input:text.to_string(), replace_last:flag("replace_last"), was_paste:flag("paste")}))
+    }
+    let key_char = field("char").and_then(|c| c.chars().next());
+    let chord = match field("key"){
+        Some(key)=>KeyChord::parse(key).map_err(|err| format!("macros: {}", err))?,
+        None if key_char.is_some()=>KeyChord{key_code:KeyCode::Unknown, modifiers:KeyModifiers::default(), primary:false},
+        None=>return Err(format!("macros: {} is not a key or text", value))
+    };
+    Ok(Event::KeyDown(KeyEvent{
+        key_code:chord.key_code,
+        key_char:key_char.unwrap_or('\0'),
+        is_repeat:false,
+        modifiers:chord.modifiers,
+        time:0.
+    }))
+}
+
+impl CodeEditor{
+
+    // plays a macro count times, or with None until a run doesn't get the cursor closer to the
+    // end of the file. Returns how often it played
+    pub fn play_macro(&mut self, cx:&mut Cx, events:&[Event], count:Option<usize>, text_buffer:&mut TextBuffer)->usize{
+        if events.is_empty() || self.macros.recording || self.macros._playing{
+            return 0
+        }
+        self.macros._playing = true;
+        let undo_len = text_buffer.undo_stack.len();
+        let mut played = 0;
+        while count.is_none_or(|count| played < count){
+            let left = self.chars_after_cursor(text_buffer);
+            for event in events{
+                // every event gets its own id, the keymap answers once per id
+                cx.event_id += 1;
+                self.handle_code_editor(cx, &mut event.clone(), text_buffer);
+            }
+            played += 1;
+            if count.is_none() && self.chars_after_cursor(text_buffer) >= left{
+                break
+            }
+        }
+        text_buffer.merge_undo(undo_len);
+        self.macros._playing = false;
+        self.view.redraw_view_area(cx);
+        played
+    }
+
+    pub fn play_saved_macro(&mut self, cx:&mut Cx, name:&str, count:Option<usize>, text_buffer:&mut TextBuffer)->Result<usize, String>{
+        let events = self.macros.saved.borrow().get(name).cloned();
+        if let Some(events) = events{
+            return Ok(self.play_macro(cx, &events, count, text_buffer))
+        }
+        Err(format!("no macro named: {}", name))
+    }
+
+    fn chars_after_cursor(&self, text_buffer:&TextBuffer)->usize{
+        let head = self.cursors.set[self.cursors.last_cursor].head;
+        text_buffer.calc_char_count().saturating_sub(head)
+    }
+}
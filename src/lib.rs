@@ -0,0 +1,12 @@
+// the editor of makepad as a library, the app in main.rs and the tests use it
+
+mod textbuffer;
+pub use crate::textbuffer::*;
+mod codeeditor;
+pub use crate::codeeditor::*;
+mod rusteditor;
+pub use crate::rusteditor::*;
+mod vim;
+pub use crate::vim::*;
+mod keymacro;
+pub use crate::keymacro::*;
@@ -3,19 +3,15 @@
 use widgets::*;
 use std::collections::HashMap;
 
-mod textbuffer;
-pub use crate::textbuffer::*;
-mod codeeditor;
-pub use crate::codeeditor::*;
-mod rusteditor;
-pub use crate::rusteditor::*;
+use makepad::*;
 
 #[derive(Clone)]
 enum Panel{
     Color(Color), 
     FileTree,
     FileEditorTarget,
-    FileEditor{path:String, editor_id:u64}
+    FileEditor{path:String, editor_id:u64},
+    ImagePreview{path:String}
 }
 
 struct App{
@@ -28,7 +24,14 @@ struct App{
 
     text_buffers:HashMap<String, TextBuffer>,
     tree_load_id:u64,
-    quad:Quad
+    keymap_load_id:u64,
+    macros_load_id:u64,
+    quad:Quad,
+    image:Image,
+    image_error:Text,
+    layout_debugger:LayoutDebugger,
+    profiler_hud:ProfilerHud,
+    command_palette:CommandPalette
 }
 
 main_app!(App, "Makepad");
@@ -36,6 +39,8 @@ main_app!(App, "Makepad");
 impl Style for App{
     fn style(cx:&mut Cx)->Self{
         set_dark_style(cx);
+        Self::register_commands(cx);
+        cx.set_rtl(std::env::var("LANG").map(|lang| locale_is_rtl(&lang)).unwrap_or(false));
         Self{
             text_buffers:HashMap::new(),
             file_editor_id_alloc:10,
@@ -51,12 +56,36 @@ impl Style for App{
             quad:Quad{
                 ..Style::style(cx)
             },
+            image:Image{
+                ..Style::style(cx)
+            },
+            image_error:Text{
+                color:color("#c44"),
+                ..Style::style(cx)
+            },
+            layout_debugger:LayoutDebugger{
+                ..Style::style(cx)
+            },
+            profiler_hud:ProfilerHud{
+                ..Style::style(cx)
+            },
+            command_palette:CommandPalette{
+                ..Style::style(cx)
+            },
             file_tree:FileTree{
                 ..Style::style(cx)
             },
             tree_load_id:0,
+            keymap_load_id:0,
+            macros_load_id:0,
             file_editors:Elements::new(FileEditorTemplates{
-                rust_editor:RustEditor{..Style::style(cx)}
+                rust_editor:RustEditor{
+                    code_editor:CodeEditor{
+                        macros:KeyMacros{file:Some("./macros.json".to_string()), ..KeyMacros::new()},
+                        ..Style::style(cx)
+                    },
+                    ..Style::style(cx)
+                }
             }),
             dock:Dock{
                 dock_items:Some(DockItem::Splitter{
@@ -110,6 +139,11 @@ impl Style for App{
     }
 }
 
+// only png decodes, other images open as text
+fn path_is_image(path:&str)->bool{
+    path.ends_with(".png")
+}
+
 fn path_file_name(path:&str)->String{
     if let Some(pos) =  path.rfind('/'){
         path[pos+1..path.len()].to_string()
@@ -121,9 +155,35 @@ fn path_file_name(path:&str)->String{
 
 impl App{
     fn handle_app(&mut self, cx:&mut Cx, event:&mut Event){
+        self.layout_debugger.handle_layout_debugger(cx, event);
+        self.profiler_hud.handle_profiler_hud(cx, event);
+        match self.command_palette.handle_command_palette(cx, event){
+            CommandPaletteEvent::Command{command}=>{
+                if command.starts_with("app."){
+                    self.run_command(cx, &command);
+                }
+                else{ // the key focus is back on what had it before the palette
+                    cx.send_command(&command);
+                }
+            },
+            CommandPaletteEvent::OpenFile{path}=>{
+                self.open_file(cx, &path);
+            },
+            CommandPaletteEvent::None=>()
+        }
+        if let Event::KeyDown(ke) = event{
+            if let Some(command) = cx.key_command(ke, "app"){
+                self.run_command(cx, &command);
+            }
+        }
+
         match event{
             Event::Construct=>{
                 self.tree_load_id = cx.read_file("./index.json");
+                // user key bindings on top of the defaults, the file is optional
+                self.keymap_load_id = cx.read_file("./keymap.json");
+                // saved keyboard macros, written next to it when one is saved
+                self.macros_load_id = cx.read_file("./macros.json");
             },
             Event::FileRead(fr)=>{
                 // lets see which file we loaded
@@ -134,6 +194,26 @@ impl App{
                         }
                     }
                 }
+                if fr.id == self.keymap_load_id{
+                    if let Ok(str_data) = &fr.data{
+                        if let Ok(utf8_data) = std::str::from_utf8(&str_data){
+                            if let Err(err) = load_keymap_from_json(cx, utf8_data){
+                                println!("{}", err);
+                            }
+                        }
+                    }
+                }
+                if fr.id == self.macros_load_id{
+                    if let Ok(str_data) = &fr.data{
+                        if let Ok(utf8_data) = std::str::from_utf8(str_data){
+                            // the editors share the saved macros of the template they were cloned from
+                            let macros = &mut self.file_editors.template.rust_editor.code_editor.macros;
+                            if let Err(err) = macros.load_saved_from_json(cx, utf8_data){
+                                println!("{}", err);
+                            }
+                        }
+                    }
+                }
                 for (_path, text_buffer) in &mut self.text_buffers{
                     if text_buffer.load_id == fr.id{
                         text_buffer.load_id = 0;
@@ -155,6 +235,7 @@ impl App{
             match item{
                 Panel::Color(_)=>{}
                 Panel::FileEditorTarget=>{},
+                Panel::ImagePreview{..}=>{},
                 Panel::FileTree=>{
                     file_tree_event = self.file_tree.handle_file_tree(cx, event);
                 },
@@ -184,10 +265,7 @@ impl App{
                 self.dock.dock_drag_end(cx, fe, tabs);
             },
             FileTreeEvent::SelectFile{path}=>{
-                // search for the tabcontrol with the maximum amount of editors
-                if let Some(target_ctrl_id) = self.focus_editor_or_find_editor_target(cx, &path){ // found a control to append to
-                    self.open_new_editor_in_target_ctrl(cx, target_ctrl_id, &path);
-                }
+                self.open_file(cx, &path);
             },
             _=>{}
         }
@@ -234,10 +312,74 @@ impl App{
                     self.file_editors.get_draw(cx, *editor_id, |_cx, tmpl|{
                         FileEditor::create_file_editor_for_path(path, tmpl)
                     }).draw_file_editor(cx, text_buffer);
+                },
+                Panel::ImagePreview{path}=>{
+                    let image_id = cx.load_image(&format!(".{}",path));
+                    if let Some(error) = cx.images[image_id].error.clone(){
+                        self.image_error.draw_text(cx, &error);
+                    }
+                    else{
+                        self.image.draw_image_walk(cx, image_id, Bounds::Fill, Bounds::Fill, Margin::zero());
+                    }
                 }
             }
         }
         self.view.end_view(cx);
+
+        self.layout_debugger.draw_layout_debugger(cx);
+        self.profiler_hud.draw_profiler_hud(cx);
+        self.command_palette.draw_command_palette(cx);
+    }
+
+    fn register_commands(cx:&mut Cx){
+        let commands = [
+            ("app.command_palette", "Show All Commands", "Mod+Shift+P"),
+            ("app.go_to_file", "Go To File", "Mod+P"),
+            ("app.toggle_layout_debugger", "Toggle Layout Debugger", "F12"),
+            ("app.toggle_draw_list_tree", "Toggle Draw List Tree", "Shift+F12"),
+            ("app.toggle_profiler", "Toggle Frame Profiler", "F10"),
+            ("app.save_profile_trace", "Save Frame Profile Trace", "Shift+F10"),
+            ("app.toggle_rtl", "Toggle Right To Left Layout", "Mod+K Mod+D"),
+        ];
+        for (name, title, keys) in commands.iter(){
+            cx.register_command(name, title);
+            cx.bind_keys(keys, name, None).expect("default app keys parse");
+        }
+    }
+
+    fn run_command(&mut self, cx:&mut Cx, command:&str){
+        match command{
+            "app.command_palette"=>{
+                let items = CommandPalette::command_items(cx);
+                self.command_palette.open_palette(cx, PaletteMode::Commands, items);
+            },
+            "app.go_to_file"=>{
+                let items = CommandPalette::file_items(&self.file_tree.file_paths());
+                self.command_palette.open_palette(cx, PaletteMode::Files, items);
+            },
+            "app.toggle_layout_debugger"=>self.layout_debugger.toggle_layout(cx),
+            "app.toggle_draw_list_tree"=>self.layout_debugger.toggle_tree(cx),
+            "app.toggle_profiler"=>self.profiler_hud.toggle_hud(cx),
+            "app.save_profile_trace"=>self.profiler_hud.save_trace(cx),
+            "app.toggle_rtl"=>{
+                let rtl = !cx.rtl;
+                cx.set_rtl(rtl);
+            },
+            _=>()
+        }
+    }
+
+    fn open_file(&mut self, cx:&mut Cx, path:&str){
+        // search for the tabcontrol with the maximum amount of editors
+        if let Some(target_ctrl_id) = self.focus_editor_or_find_editor_target(cx, path){ // found a control to append to
+            let new_tab = if path_is_image(path){
+                self.new_image_preview_tab(path)
+            }
+            else{
+                self.new_file_editor_tab(path)
+            };
+            self.open_new_tab_in_target_ctrl(cx, target_ctrl_id, new_tab);
+        }
     }
 
     fn new_file_editor_tab(&mut self, path:&str)->DockTab<Panel>{
@@ -250,8 +392,15 @@ impl App{
         }
     }
 
-    fn open_new_editor_in_target_ctrl(&mut self, cx:&mut Cx, target_ctrl_id:usize, path:&str){
-        let new_tab = self.new_file_editor_tab(path);
+    fn new_image_preview_tab(&self, path:&str)->DockTab<Panel>{
+        DockTab{
+            closeable:true,
+            title:path_file_name(path),
+            item:Panel::ImagePreview{path:path.to_string()}
+        }
+    }
+
+    fn open_new_tab_in_target_ctrl(&mut self, cx:&mut Cx, target_ctrl_id:usize, new_tab:DockTab<Panel>){
         let mut dock_walker = self.dock.walker();
         let mut ctrl_id = 1;
         while let Some(dock_item) = dock_walker.walk_dock_item(){
@@ -277,7 +426,7 @@ impl App{
                 DockItem::TabControl{current, tabs}=>{
                     for (id,tab) in tabs.iter().enumerate(){
                         match &tab.item{
-                            Panel::FileEditor{path, ..}=>{
+                            Panel::FileEditor{path, ..} | Panel::ImagePreview{path}=>{
                                 if *path == file_path{
                                     *current = id; // focus this one
                                     only_focus_editor = true;
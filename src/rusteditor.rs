@@ -22,7 +22,9 @@ pub struct RustEditor{
 
 impl ElementLife for RustEditor{
     fn construct(&mut self, _cx:&mut Cx){}
-    fn destruct(&mut self, _cx:&mut Cx){}
+    fn destruct(&mut self, cx:&mut Cx){
+        self.code_editor.destruct(cx);
+    }
 }
 
 impl Style for RustEditor{
@@ -19,6 +19,15 @@ pub struct TextPos{
     pub col:usize
 }
 
+// a row as the editor draws it, soft wrapping splits a line into several
+#[derive(Clone, Copy, Default)]
+pub struct VisualRow{
+    pub offset:usize, // of the first char
+    pub row:usize,
+    pub col:usize, // in the line the row starts at
+    pub indent:usize // columns a wrapped row is indented by
+}
+
 impl TextPos{
     fn dist(&self, other:&TextPos)->f64{
         let dr = (self.row as f64) - (other.row as f64);
@@ -196,7 +205,7 @@ impl TextBuffer{
         self.lines.len()
     }
 
-    fn get_range_as_string(&self, start:usize, len:usize, ret:&mut String){
+    pub fn get_range_as_string(&self, start:usize, len:usize, ret:&mut String){
         let mut pos = self.offset_to_text_pos(start);
         for _ in 0..len{
             let line = &self.lines[pos.row];
@@ -384,6 +393,20 @@ impl TextBuffer{
         }
     }
 
+    // folds the undo steps since from into one, so an edit made of several steps undoes at once
+    pub fn merge_undo(&mut self, from:usize){
+        if from >= self.undo_stack.len(){
+            return
+        }
+        let mut steps = self.undo_stack.drain(from..);
+        let mut merged = steps.next().unwrap();
+        for step in steps{
+            merged.ops.extend(step.ops);
+        }
+        merged.grouping = TextUndoGrouping::Block;
+        self.undo_stack.push(merged);
+    }
+
     pub fn redo(&mut self, grouped:bool, cursor_set:&mut CursorSet){
         if self.redo_stack.len() == 0{
             return;
@@ -613,6 +636,31 @@ impl Cursor{
             self.head = total_char_count;
         }
     }
+
+    pub fn move_visual(&mut self, row_delta:isize, rows:&[VisualRow], text_buffer:&TextBuffer){
+        if rows.is_empty(){
+            return
+        }
+        let current = rows.iter().rposition(|r| r.offset <= self.head).unwrap_or(0);
+        let target = current as isize + row_delta;
+        if target < 0{
+            self.head = 0;
+            return
+        }
+        if target >= rows.len() as isize{
+            self.head = text_buffer.calc_char_count();
+            return
+        }
+        let pos = text_buffer.offset_to_text_pos(self.head);
+        let vcol = (pos.col + rows[current].indent).saturating_sub(rows[current].col);
+        let target = target as usize;
+        let row = &rows[target];
+        let mut col = row.col + vcol.saturating_sub(row.indent);
+        if target + 1 < rows.len() && rows[target + 1].row == row.row{ // stay before the wrap
+            col = col.min(rows[target + 1].col - 1);
+        }
+        self.head = text_buffer.text_pos_to_offset(TextPos{row:row.row, col:col});
+    }
 }
 
 #[derive(Clone)]
@@ -1129,6 +1177,22 @@ impl CursorSet{
         self.fuse_adjacent(text_buffer)
     }
 
+    pub fn move_up_visual(&mut self, row_count:usize, only_head:bool, rows:&[VisualRow], text_buffer:&TextBuffer){
+        for cursor in &mut self.set{
+            cursor.move_visual(-(row_count as isize), rows, text_buffer);
+            if !only_head{cursor.tail = cursor.head}
+        }
+        self.fuse_adjacent(text_buffer)
+    }
+
+    pub fn move_down_visual(&mut self, row_count:usize, only_head:bool, rows:&[VisualRow], text_buffer:&TextBuffer){
+        for cursor in &mut self.set{
+            cursor.move_visual(row_count as isize, rows, text_buffer);
+            if !only_head{cursor.tail = cursor.head}
+        }
+        self.fuse_adjacent(text_buffer)
+    }
+
     pub fn move_left(&mut self, char_count:usize, only_head:bool, text_buffer:&TextBuffer){
         let mut old_max = (TextPos{row:0,col:0},0);
         for cursor in &mut self.set{
@@ -0,0 +1,1146 @@
+use widgets::*;
+use std::collections::HashMap;
+use crate::textbuffer::*;
+use crate::codeeditor::*;
+
+// a modal editing layer over the code editor. Keys collect until they parse as a vim command
+// like "2d3w" or "\"ayy", which then runs on the cursors of the editor
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VimMode{
+    Normal,
+    Insert,
+    Visual,
+    VisualLine,
+    VisualBlock,
+    CommandLine
+}
+
+impl VimMode{
+    pub fn is_visual(&self)->bool{
+        matches!(self, VimMode::Visual | VimMode::VisualLine | VimMode::VisualBlock)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum VimMotion{
+    Left,
+    Right,
+    Up,
+    Down,
+    WordStart,
+    WordEnd,
+    WordBack,
+    LineStart,
+    FirstNonBlank,
+    LineEnd,
+    FindChar(char),
+    TillChar(char),
+    FindCharBack(char),
+    TillCharBack(char),
+    RepeatFind,
+    RepeatFindBack,
+    MatchParen,
+    FileStart,
+    FileEnd
+}
+
+impl VimMotion{
+    // an operator takes whole lines with these
+    fn is_linewise(&self)->bool{
+        matches!(self, VimMotion::Up | VimMotion::Down | VimMotion::FileStart | VimMotion::FileEnd)
+    }
+
+    // and includes the char these land on
+    fn is_inclusive(&self)->bool{
+        matches!(self, VimMotion::WordEnd | VimMotion::LineEnd | VimMotion::FindChar(_) | VimMotion::TillChar(_) | VimMotion::MatchParen)
+    }
+
+    fn reversed(&self)->VimMotion{
+        match self{
+            VimMotion::FindChar(ch)=>VimMotion::FindCharBack(*ch),
+            VimMotion::TillChar(ch)=>VimMotion::TillCharBack(*ch),
+            VimMotion::FindCharBack(ch)=>VimMotion::FindChar(*ch),
+            VimMotion::TillCharBack(ch)=>VimMotion::TillChar(*ch),
+            motion=>motion.clone()
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VimOperator{
+    Delete,
+    Change,
+    Yank
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum VimAction{
+    Move(VimMotion),
+    Operate(VimOperator, VimMotion),
+    OperateLines(VimOperator), // dd, cc, yy
+    Insert(char), // i, a, I, A, o or O
+    Put(bool), // P puts before the cursor
+    Replace(char),
+    Undo,
+    Redo,
+    Repeat,
+    Visual(VimMode),
+    VisualOperate(VimOperator),
+    VisualInsert(bool), // I or A on a visual block, true for A
+    VisualSwap,
+    Escape,
+    CommandLine
+}
+
+impl VimAction{
+    fn is_change(&self)->bool{
+        match self{
+            VimAction::Operate(op, _) | VimAction::OperateLines(op) | VimAction::VisualOperate(op)=>*op != VimOperator::Yank,
+            VimAction::Insert(_) | VimAction::Put(_) | VimAction::Replace(_) | VimAction::VisualInsert(_)=>true,
+            _=>false
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct VimCommand{
+    pub count:Option<usize>,
+    pub register:Option<char>,
+    pub action:VimAction
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum VimParse<T>{
+    Pending,
+    Invalid,
+    Done(T)
+}
+
+// what happened in insert mode, kept so . can do it again
+#[derive(Clone, Debug, PartialEq)]
+pub enum VimInput{
+    Text(TextInputEvent),
+    Command(String)
+}
+
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct VimRegister{
+    pub text:String,
+    pub linewise:bool
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct VimChange{
+    pub command:VimCommand,
+    pub inputs:Vec<VimInput>,
+    pub undo_len:usize // the undo steps from here on become one
+}
+
+#[derive(Clone)]
+pub struct Vim{
+    pub enabled:bool,
+    pub mode:VimMode,
+    pub registers:HashMap<char, VimRegister>,
+    pub command_line:String,
+    pub message:String,
+    pub _keys:Vec<char>,
+    pub _last_find:Option<VimMotion>,
+    pub _change:Option<VimChange>,
+    pub _last_change:Option<VimChange>,
+    pub _visual_anchor:usize,
+    pub _visual_head:usize,
+    pub _visual_max:usize,
+    pub _swallow_text:Option<char> // the key that also arrives as text input
+}
+
+impl Default for Vim{
+    fn default()->Vim{
+        Vim::new()
+    }
+}
+
+impl Vim{
+    pub fn new()->Vim{
+        Vim{
+            enabled:false,
+            mode:VimMode::Normal,
+            registers:HashMap::new(),
+            command_line:String::new(),
+            message:String::new(),
+            _keys:Vec::new(),
+            _last_find:None,
+            _change:None,
+            _last_change:None,
+            _visual_anchor:0,
+            _visual_head:0,
+            _visual_max:0,
+            _swallow_text:None
+        }
+    }
+
+    fn parse_count(keys:&[char], index:&mut usize)->Option<usize>{
+        let start = *index;
+        while *index < keys.len() && keys[*index].is_ascii_digit() && (keys[*index] != '0' || *index > start){
+            *index += 1;
+        }
+        if *index == start{
+            return None
+        }
+        keys[start..*index].iter().collect::<String>().parse().ok()
+    }
+
+    pub fn parse_motion(keys:&[char])->VimParse<VimMotion>{
+        let key = match keys.first(){
+            Some(key)=>*key,
+            None=>return VimParse::Pending
+        };
+        let motion = match key{
+            'h' | '\x08'=>VimMotion::Left,
+            'l' | ' '=>VimMotion::Right,
+            'j' | '\n'=>VimMotion::Down,
+            'k'=>VimMotion::Up,
+            'w'=>VimMotion::WordStart,
+            'e'=>VimMotion::WordEnd,
+            'b'=>VimMotion::WordBack,
+            '0'=>VimMotion::LineStart,
+            '^'=>VimMotion::FirstNonBlank,
+            '$'=>VimMotion::LineEnd,
+            ';'=>VimMotion::RepeatFind,
+            ','=>VimMotion::RepeatFindBack,
+            '%'=>VimMotion::MatchParen,
+            'G'=>VimMotion::FileEnd,
+            'g' | 'f' | 't' | 'F' | 'T'=>{
+                let next = match keys.get(1){
+                    Some(next)=>*next,
+                    None=>return VimParse::Pending
+                };
+                match key{
+                    'g'=>if next == 'g'{VimMotion::FileStart} else{return VimParse::Invalid},
+                    'f'=>VimMotion::FindChar(next),
+                    't'=>VimMotion::TillChar(next),
+                    'F'=>VimMotion::FindCharBack(next),
+                    _=>VimMotion::TillCharBack(next)
+                }
+            },
+            _=>return VimParse::Invalid
+        };
+        VimParse::Done(motion)
+    }
+
+    // ["x][count]command, where an operator takes another [count]motion or itself for whole lines
+    pub fn parse(mode:VimMode, keys:&[char])->VimParse<VimCommand>{
+        let mut index = 0;
+        let mut register = None;
+        if keys.first() == Some(&'"'){
+            match keys.get(1){
+                Some(name)=>register = Some(*name),
+                None=>return VimParse::Pending
+            }
+            index = 2;
+        }
+        let mut count = Self::parse_count(keys, &mut index);
+        let key = match keys.get(index){
+            Some(key)=>*key,
+            None=>return VimParse::Pending
+        };
+        let visual = mode.is_visual();
+        let operator = match key{
+            'd' | 'x' if visual=>Some(VimOperator::Delete),
+            'c' | 's' if visual=>Some(VimOperator::Change),
+            'd'=>Some(VimOperator::Delete),
+            'c'=>Some(VimOperator::Change),
+            'y'=>Some(VimOperator::Yank),
+            _=>None
+        };
+        let action = if let Some(operator) = operator{
+            if visual{
+                VimAction::VisualOperate(operator)
+            }
+            else{
+                index += 1;
+                if let Some(motion_count) = Self::parse_count(keys, &mut index){
+                    count = Some(count.unwrap_or(1) * motion_count);
+                }
+                if keys.get(index) == Some(&key){
+                    VimAction::OperateLines(operator)
+                }
+                else{
+                    match Self::parse_motion(&keys[index..]){
+                        VimParse::Done(motion)=>VimAction::Operate(operator, motion),
+                        VimParse::Pending=>return VimParse::Pending,
+                        VimParse::Invalid=>return VimParse::Invalid
+                    }
+                }
+            }
+        }
+        else{
+            match key{
+                '\x1b' if visual=>VimAction::Escape,
+                'o' if visual=>VimAction::VisualSwap,
+                'I' if mode == VimMode::VisualBlock=>VimAction::VisualInsert(false),
+                'A' if mode == VimMode::VisualBlock=>VimAction::VisualInsert(true),
+                'x'=>VimAction::Operate(VimOperator::Delete, VimMotion::Right),
+                'X'=>VimAction::Operate(VimOperator::Delete, VimMotion::Left),
+                's'=>VimAction::Operate(VimOperator::Change, VimMotion::Right),
+                'D'=>VimAction::Operate(VimOperator::Delete, VimMotion::LineEnd),
+                'C'=>VimAction::Operate(VimOperator::Change, VimMotion::LineEnd),
+                'Y'=>VimAction::OperateLines(VimOperator::Yank),
+                'i' | 'a' | 'I' | 'A' | 'o' | 'O'=>VimAction::Insert(key),
+                'p'=>VimAction::Put(false),
+                'P'=>VimAction::Put(true),
+                'r'=>match keys.get(index + 1){
+                    Some(with)=>VimAction::Replace(*with),
+                    None=>return VimParse::Pending
+                },
+                'u'=>VimAction::Undo,
+                '\x12'=>VimAction::Redo,
+                '.'=>VimAction::Repeat,
+                'v'=>VimAction::Visual(VimMode::Visual),
+                'V'=>VimAction::Visual(VimMode::VisualLine),
+                '\x16'=>VimAction::Visual(VimMode::VisualBlock),
+                ':'=>VimAction::CommandLine,
+                _=>match Self::parse_motion(&keys[index..]){
+                    VimParse::Done(motion)=>VimAction::Move(motion),
+                    VimParse::Pending=>return VimParse::Pending,
+                    VimParse::Invalid=>return VimParse::Invalid
+                }
+            }
+        };
+        VimParse::Done(VimCommand{count, register, action})
+    }
+
+    // what goes in the status line at the bottom of the editor
+    pub fn status(&self)->String{
+        match self.mode{
+            VimMode::CommandLine=>format!(":{}", self.command_line),
+            VimMode::Insert=>"-- INSERT --".to_string(),
+            VimMode::Visual=>"-- VISUAL --".to_string(),
+            VimMode::VisualLine=>"-- VISUAL LINE --".to_string(),
+            VimMode::VisualBlock=>"-- VISUAL BLOCK --".to_string(),
+            VimMode::Normal=>if !self.message.is_empty(){
+                self.message.clone()
+            }
+            else{
+                self._keys.iter().filter(|key| !key.is_control()).collect()
+            }
+        }
+    }
+}
+
+fn line_start(text_buffer:&TextBuffer, row:usize)->usize{
+    text_buffer.text_pos_to_offset(TextPos{row, col:0})
+}
+
+fn line_end(text_buffer:&TextBuffer, row:usize)->usize{
+    line_start(text_buffer, row) + text_buffer.lines[row].len()
+}
+
+// the start of the next line, or the end of the text on the last one
+fn line_next(text_buffer:&TextBuffer, row:usize)->usize{
+    if row + 1 < text_buffer.lines.len(){
+        return line_start(text_buffer, row + 1)
+    }
+    text_buffer.calc_char_count()
+}
+
+fn first_non_blank(text_buffer:&TextBuffer, row:usize)->usize{
+    let line = &text_buffer.lines[row];
+    let col = line.iter().position(|ch| *ch != ' ' && *ch != '\t').unwrap_or(line.len());
+    line_start(text_buffer, row) + col
+}
+
+fn char_at(text_buffer:&TextBuffer, offset:usize)->char{
+    let pos = text_buffer.offset_to_text_pos(offset);
+    *text_buffer.lines[pos.row].get(pos.col).unwrap_or(&'\n')
+}
+
+// normal mode sits on a char, not past the end of the line
+fn clamp_to_char(text_buffer:&TextBuffer, offset:usize)->usize{
+    let pos = text_buffer.offset_to_text_pos(offset);
+    let len = text_buffer.lines[pos.row].len();
+    if len > 0 && pos.col >= len{
+        return offset - (pos.col - len) - 1
+    }
+    offset
+}
+
+fn lines_text(text_buffer:&TextBuffer, top:usize, bottom:usize)->String{
+    let mut text = String::new();
+    for line in &text_buffer.lines[top..(bottom + 1)]{
+        text.extend(line.iter());
+        text.push('\n');
+    }
+    text
+}
+
+fn chunk_index(chunks:&[TokenChunk], offset:usize)->Option<usize>{
+    chunks.iter().position(|chunk| offset >= chunk.offset && offset < chunk.offset + chunk.len)
+}
+
+// the start of the next token that isn't whitespace
+fn next_token_start(chunks:&[TokenChunk], offset:usize, end:usize)->usize{
+    if let Some(index) = chunk_index(chunks, offset){
+        if let Some(next) = chunks[(index + 1)..].iter().find(|chunk| !chunk.is_whitespace){
+            return next.offset
+        }
+    }
+    end
+}
+
+// the last char of the token, or of the next one when already there
+fn token_end(chunks:&[TokenChunk], offset:usize, end:usize)->usize{
+    if let Some(index) = chunk_index(chunks, offset){
+        let chunk = &chunks[index];
+        if !chunk.is_whitespace && offset + 1 < chunk.offset + chunk.len{
+            return chunk.offset + chunk.len - 1
+        }
+        if let Some(next) = chunks[(index + 1)..].iter().find(|chunk| !chunk.is_whitespace){
+            return next.offset + next.len - 1
+        }
+    }
+    end
+}
+
+impl CodeEditor{
+
+    // returns if vim took the key, the rest goes to the keymap
+    pub fn vim_key_down(&mut self, cx:&mut Cx, ke:&KeyEvent, text_buffer:&mut TextBuffer)->bool{
+        self.vim._swallow_text = None;
+        let modifiers = &ke.modifiers;
+        // chords bound in the keymap win, like the second key of Mod+K Mod+R
+        if (modifiers.control || modifiers.logo) && cx.key_command(ke, "editor").is_some(){
+            return false
+        }
+        let key = match ke.key_code{
+            KeyCode::Escape=>'\x1b',
+            KeyCode::Return=>'\n',
+            KeyCode::Backspace=>'\x08',
+            KeyCode::KeyR if modifiers.control=>'\x12',
+            KeyCode::KeyV if modifiers.control=>'\x16',
+            _ if modifiers.control || modifiers.logo || ke.key_char.is_control()=>'\0',
+            _=>ke.key_char
+        };
+        if self.vim.mode == VimMode::Insert{
+            if key == '\x1b'{
+                self.vim_leave_insert(cx, text_buffer);
+                return true
+            }
+            // the editor does the rest, the commands are kept to repeat the insert
+            if let Some(command) = cx.key_command(ke, "editor"){
+                self.vim_record(VimInput::Command(command.clone()));
+                self.exec_command(cx, &command, text_buffer);
+                return true
+            }
+            return false
+        }
+        if key == '\0'{
+            return false
+        }
+        if !key.is_control(){
+            self.vim._swallow_text = Some(key);
+        }
+        self.vim_key(cx, key, text_buffer);
+        self.view.redraw_view_area(cx);
+        true
+    }
+
+    // returns if vim ate the text, outside insert mode the keys are commands
+    pub fn vim_text_input(&mut self, te:&TextInputEvent)->bool{
+        if let Some(key) = self.vim._swallow_text.take(){
+            if te.input.len() == key.len_utf8() && te.input.starts_with(key){
+                return true
+            }
+        }
+        if self.vim.mode != VimMode::Insert{
+            return true
+        }
+        self.vim_record(VimInput::Text(te.clone()));
+        false
+    }
+
+    // one key outside insert mode, escape is \x1b, enter \n, backspace \x08, ctrl+r \x12 and ctrl+v \x16
+    pub fn vim_key(&mut self, cx:&mut Cx, key:char, text_buffer:&mut TextBuffer){
+        if self.vim.mode == VimMode::CommandLine{
+            match key{
+                '\x1b'=>self.vim.mode = VimMode::Normal,
+                '\n'=>{
+                    self.vim.mode = VimMode::Normal;
+                    let line = std::mem::take(&mut self.vim.command_line);
+                    if let Err(message) = self.vim_ex_command(cx, &line, text_buffer){
+                        self.vim.message = message;
+                    }
+                    self.vim_normalize(false, text_buffer);
+                },
+                '\x08'=>if self.vim.command_line.pop().is_none(){
+                    self.vim.mode = VimMode::Normal;
+                },
+                _=>self.vim.command_line.push(key)
+            }
+            return
+        }
+        if key == '\x1b' && !self.vim._keys.is_empty(){
+            self.vim._keys.truncate(0);
+            return
+        }
+        if self.vim._keys.is_empty(){
+            self.vim.message.truncate(0);
+        }
+        self.vim._keys.push(key);
+        match Vim::parse(self.vim.mode, &self.vim._keys){
+            VimParse::Pending=>(),
+            VimParse::Invalid=>self.vim._keys.truncate(0),
+            VimParse::Done(command)=>{
+                self.vim._keys.truncate(0);
+                self.vim_execute(cx, command, text_buffer);
+            }
+        }
+    }
+
+    pub fn vim_execute(&mut self, cx:&mut Cx, command:VimCommand, text_buffer:&mut TextBuffer){
+        if self.vim.mode == VimMode::Normal{
+            self.vim_normalize(true, text_buffer);
+        }
+        if command.action.is_change(){
+            self.vim._change = Some(VimChange{
+                command:command.clone(),
+                inputs:Vec::new(),
+                undo_len:text_buffer.undo_stack.len()
+            });
+        }
+        let count = command.count.unwrap_or(1);
+        let mut keep_max = false;
+        match command.action.clone(){
+            VimAction::Move(motion)=>{
+                keep_max = motion == VimMotion::Up || motion == VimMotion::Down;
+                let motion = match self.vim_resolve_motion(motion){
+                    Some(motion)=>motion,
+                    None=>return
+                };
+                let visual = self.vim.mode.is_visual();
+                let cursor = if visual{
+                    Cursor{head:self.vim._visual_head, tail:self.vim._visual_head, max:self.vim._visual_max}
+                }
+                else{
+                    self.cursors.set[0].clone()
+                };
+                if let Some(target) = self.vim_motion_target(&motion, command.count, &cursor, false, text_buffer){
+                    let target = clamp_to_char(text_buffer, target);
+                    if visual{
+                        self.vim._visual_head = target;
+                        if !keep_max{
+                            self.vim._visual_max = text_buffer.offset_to_text_pos(target).col;
+                        }
+                    }
+                    else{
+                        self.cursors.set[0].head = target;
+                        self.cursors.set[0].tail = target;
+                    }
+                }
+            },
+            VimAction::Operate(operator, motion)=>{
+                if let Some(motion) = self.vim_resolve_motion(motion){
+                    self.vim_operate(operator, Some(motion), command.count, command.register, text_buffer);
+                }
+            },
+            VimAction::OperateLines(operator)=>{
+                self.vim_operate(operator, None, command.count, command.register, text_buffer);
+            },
+            VimAction::Insert(key)=>self.vim_insert(key, text_buffer),
+            VimAction::Put(before)=>self.vim_put(before, count, command.register, text_buffer),
+            VimAction::Replace(with)=>{
+                let head = self.cursors.set[0].head;
+                let pos = text_buffer.offset_to_text_pos(head);
+                if pos.col + count <= text_buffer.lines[pos.row].len(){
+                    let text = with.to_string().repeat(count);
+                    self.vim_replace(head, head + count, &text, text_buffer);
+                    self.cursors.set[0].head = head + count - 1;
+                }
+            },
+            VimAction::Undo=>for _ in 0..count{
+                text_buffer.undo(true, &mut self.cursors);
+            },
+            VimAction::Redo=>for _ in 0..count{
+                text_buffer.redo(true, &mut self.cursors);
+            },
+            VimAction::Repeat=>if let Some(change) = self.vim._last_change.clone(){
+                let mut repeat = change.command.clone();
+                if command.count.is_some(){
+                    repeat.count = command.count;
+                }
+                self.vim_execute(cx, repeat, text_buffer);
+                if self.vim.mode == VimMode::Insert{
+                    for input in &change.inputs{
+                        self.vim_record(input.clone());
+                        self.vim_apply_input(cx, input, text_buffer);
+                    }
+                    self.vim_leave_insert(cx, text_buffer);
+                }
+            },
+            VimAction::Visual(mode)=>{
+                if self.vim.mode == mode{
+                    self.vim_leave_visual(text_buffer);
+                }
+                else{
+                    if !self.vim.mode.is_visual(){
+                        let head = self.cursors.set[0].head;
+                        self.vim._visual_anchor = head;
+                        self.vim._visual_head = head;
+                        self.vim._visual_max = text_buffer.offset_to_text_pos(head).col;
+                    }
+                    self.vim.mode = mode;
+                }
+            },
+            VimAction::VisualOperate(operator)=>self.vim_visual_operate(operator, command.register, text_buffer),
+            VimAction::VisualInsert(append)=>{
+                for cursor in &mut self.cursors.set{
+                    let (start, end) = cursor.order();
+                    cursor.head = if append{end} else{start};
+                    cursor.tail = cursor.head;
+                }
+                self.vim.mode = VimMode::Insert;
+            },
+            VimAction::VisualSwap=>{
+                std::mem::swap(&mut self.vim._visual_anchor, &mut self.vim._visual_head);
+            },
+            VimAction::Escape=>self.vim_leave_visual(text_buffer),
+            VimAction::CommandLine=>{
+                self.vim.mode = VimMode::CommandLine;
+                self.vim.command_line.truncate(0);
+            }
+        }
+        if self.vim.mode.is_visual(){
+            self.vim_select_visual(text_buffer);
+        }
+        else if self.vim.mode == VimMode::Normal{
+            self.vim_finish_change(text_buffer);
+            self.vim_normalize(keep_max, text_buffer);
+        }
+    }
+
+    fn vim_record(&mut self, input:VimInput){
+        if let Some(change) = &mut self.vim._change{
+            change.inputs.push(input);
+        }
+    }
+
+    fn vim_apply_input(&mut self, cx:&mut Cx, input:&VimInput, text_buffer:&mut TextBuffer){
+        match input{
+            VimInput::Text(te)=>self.text_input(te, text_buffer),
+            VimInput::Command(command)=>{
+                self.exec_command(cx, command, text_buffer);
+            }
+        }
+    }
+
+    // the undo steps of a change become one, and . repeats it
+    fn vim_finish_change(&mut self, text_buffer:&mut TextBuffer){
+        if let Some(change) = self.vim._change.take(){
+            text_buffer.merge_undo(change.undo_len);
+            match change.command.action{
+                VimAction::VisualOperate(_) | VimAction::VisualInsert(_)=>(),
+                _=>self.vim._last_change = Some(change)
+            }
+        }
+    }
+
+    // normal mode has one cursor without a selection
+    fn vim_normalize(&mut self, keep_max:bool, text_buffer:&TextBuffer){
+        let last_cursor = self.cursors.last_cursor.min(self.cursors.set.len() - 1);
+        let mut cursor = self.cursors.set[last_cursor].clone();
+        let (start, _) = cursor.order();
+        cursor.head = clamp_to_char(text_buffer, start.min(text_buffer.calc_char_count()));
+        cursor.tail = cursor.head;
+        if !keep_max{
+            cursor.calc_max(text_buffer, (TextPos{row:0, col:0}, 0));
+        }
+        self.cursors.set = vec![cursor];
+        self.cursors.last_cursor = 0;
+    }
+
+    fn vim_set_head(&mut self, offset:usize, text_buffer:&TextBuffer){
+        self.cursors.clear_and_set_last_cursor_head_and_tail(offset, text_buffer);
+    }
+
+    fn vim_replace(&mut self, start:usize, end:usize, text:&str, text_buffer:&mut TextBuffer){
+        self.cursors.set = vec![Cursor{head:end, tail:start, max:0}];
+        self.cursors.last_cursor = 0;
+        self.cursors.replace_text(text, text_buffer);
+    }
+
+    fn vim_leave_insert(&mut self, cx:&mut Cx, text_buffer:&mut TextBuffer){
+        // a count types it again
+        if let Some(change) = self.vim._change.clone(){
+            if let VimAction::Insert(key) = change.command.action{
+                for _ in 1..change.command.count.unwrap_or(1){
+                    if key == 'o' || key == 'O'{
+                        self.cursors.insert_newline_with_indent(text_buffer);
+                    }
+                    for input in &change.inputs{
+                        self.vim_apply_input(cx, input, text_buffer);
+                    }
+                }
+            }
+        }
+        self.vim.mode = VimMode::Normal;
+        // a block insert ends on its first line, and escape steps back onto the last typed char
+        self.cursors.last_cursor = 0;
+        let head = self.cursors.set[0].head;
+        if text_buffer.offset_to_text_pos(head).col > 0{
+            self.cursors.set[0].head = head - 1;
+            self.cursors.set[0].tail = head - 1;
+        }
+        self.vim_finish_change(text_buffer);
+        self.vim_normalize(false, text_buffer);
+    }
+
+    fn vim_leave_visual(&mut self, text_buffer:&TextBuffer){
+        self.vim.mode = VimMode::Normal;
+        let head = self.vim._visual_head;
+        self.vim_set_head(head, text_buffer);
+    }
+
+    fn vim_resolve_motion(&mut self, motion:VimMotion)->Option<VimMotion>{
+        match motion{
+            VimMotion::RepeatFind=>self.vim._last_find.clone(),
+            VimMotion::RepeatFindBack=>self.vim._last_find.as_ref().map(|find| find.reversed()),
+            VimMotion::FindChar(_) | VimMotion::TillChar(_) | VimMotion::FindCharBack(_) | VimMotion::TillCharBack(_)=>{
+                self.vim._last_find = Some(motion.clone());
+                Some(motion)
+            },
+            _=>Some(motion)
+        }
+    }
+
+    // where a motion goes from the cursor, an operator may also go to the end of a line
+    pub fn vim_motion_target(&self, motion:&VimMotion, count:Option<usize>, cursor:&Cursor, for_operator:bool, text_buffer:&TextBuffer)->Option<usize>{
+        let n = count.unwrap_or(1);
+        let head = cursor.head;
+        let pos = text_buffer.offset_to_text_pos(head);
+        let line = &text_buffer.lines[pos.row];
+        let line_offset = head - pos.col;
+        let last_row = text_buffer.lines.len() - 1;
+        let total = text_buffer.calc_char_count();
+        let chunks = &self._token_chunks;
+        let target = match motion{
+            VimMotion::Left=>head - pos.col.min(n),
+            VimMotion::Right=>{
+                let max_col = if for_operator{line.len()} else{line.len().max(1) - 1};
+                line_offset + (pos.col + n).min(max_col).max(pos.col)
+            },
+            VimMotion::Up | VimMotion::Down=>{
+                let row = if *motion == VimMotion::Up{pos.row.saturating_sub(n)} else{(pos.row + n).min(last_row)};
+                let len = text_buffer.lines[row].len();
+                text_buffer.text_pos_to_offset(TextPos{row, col:cursor.max.min(len.max(1) - 1)})
+            },
+            VimMotion::WordStart=>{
+                let mut offset = head;
+                for _ in 0..n{
+                    offset = next_token_start(chunks, offset, total);
+                }
+                offset
+            },
+            VimMotion::WordEnd=>{
+                let mut offset = head;
+                for _ in 0..n{
+                    offset = token_end(chunks, offset, total);
+                }
+                offset
+            },
+            VimMotion::WordBack=>{
+                let mut cursors = CursorSet::new();
+                cursors.set[0].head = head;
+                for _ in 0..n{
+                    cursors.move_left_nearest_token(false, chunks, text_buffer);
+                }
+                cursors.set[0].head
+            },
+            VimMotion::LineStart=>line_offset,
+            VimMotion::FirstNonBlank=>first_non_blank(text_buffer, pos.row),
+            VimMotion::LineEnd=>{
+                let row = (pos.row + n - 1).min(last_row);
+                line_start(text_buffer, row) + text_buffer.lines[row].len().max(1) - 1
+            },
+            VimMotion::FindChar(ch) | VimMotion::TillChar(ch)=>{
+                let col = ((pos.col + 1)..line.len()).filter(|col| line[*col] == *ch).nth(n - 1)?;
+                line_offset + if let VimMotion::TillChar(_) = motion{col - 1} else{col}
+            },
+            VimMotion::FindCharBack(ch) | VimMotion::TillCharBack(ch)=>{
+                let col = (0..pos.col).rev().filter(|col| line[*col] == *ch).nth(n - 1)?;
+                line_offset + if let VimMotion::TillCharBack(_) = motion{col + 1} else{col}
+            },
+            VimMotion::RepeatFind | VimMotion::RepeatFindBack=>return None,
+            VimMotion::MatchParen=>self.vim_match_paren(head, text_buffer)?,
+            VimMotion::FileStart=>first_non_blank(text_buffer, count.map(|row| row - 1).unwrap_or(0).min(last_row)),
+            VimMotion::FileEnd=>first_non_blank(text_buffer, count.map(|row| row - 1).unwrap_or(last_row).min(last_row))
+        };
+        Some(target.min(total))
+    }
+
+    // % jumps between the parens the tokenizer paired up, from the first one at or after the cursor on its line
+    fn vim_match_paren(&self, head:usize, text_buffer:&TextBuffer)->Option<usize>{
+        let pos = text_buffer.offset_to_text_pos(head);
+        let end = line_end(text_buffer, pos.row);
+        let chunks = &self._token_chunks;
+        let start = chunk_index(chunks, head)?;
+        for index in start..chunks.len(){
+            let chunk = &chunks[index];
+            if chunk.offset >= end{
+                break
+            }
+            match char_at(text_buffer, chunk.offset){
+                '(' | ')' | '[' | ']' | '{' | '}' if chunk.len == 1=>(),
+                _=>continue
+            }
+            for paren in &self._paren_list{
+                if paren.start == index{
+                    return Some(chunks[paren.end - 1].offset)
+                }
+                if paren.end - 1 == index{
+                    return Some(chunks[paren.start].offset)
+                }
+            }
+            return None
+        }
+        None
+    }
+
+    fn vim_operate(&mut self, operator:VimOperator, motion:Option<VimMotion>, count:Option<usize>, register:Option<char>, text_buffer:&mut TextBuffer){
+        let cursor = self.cursors.set[0].clone();
+        let head = cursor.head;
+        let pos = text_buffer.offset_to_text_pos(head);
+        let last_row = text_buffer.lines.len() - 1;
+        let rows = match &motion{
+            None=>Some((pos.row, (pos.row + count.unwrap_or(1) - 1).min(last_row))),
+            Some(motion) if motion.is_linewise()=>{
+                let target = match self.vim_motion_target(motion, count, &cursor, true, text_buffer){
+                    Some(target)=>target,
+                    None=>return
+                };
+                let row = text_buffer.offset_to_text_pos(target).row;
+                Some((pos.row.min(row), pos.row.max(row)))
+            },
+            _=>None
+        };
+        let (start, end) = if let Some((top, bottom)) = rows{
+            if operator == VimOperator::Change{ // keeps the indent and a line to type on
+                (first_non_blank(text_buffer, top), line_end(text_buffer, bottom))
+            }
+            else if bottom == last_row && top > 0{ // the last line takes the newline before it along
+                (line_start(text_buffer, top) - 1, text_buffer.calc_char_count())
+            }
+            else{
+                (line_start(text_buffer, top), line_next(text_buffer, bottom))
+            }
+        }
+        else{
+            let mut motion = motion.unwrap();
+            // cw changes to the end of the word like ce
+            if operator == VimOperator::Change && motion == VimMotion::WordStart && !char_at(text_buffer, head).is_whitespace(){
+                motion = VimMotion::WordEnd;
+            }
+            let target = match self.vim_motion_target(&motion, count, &cursor, true, text_buffer){
+                Some(target)=>target,
+                None=>return
+            };
+            let (start, mut end) = if target < head{(target, head)} else{(head, target)};
+            if motion == VimMotion::WordStart && text_buffer.offset_to_text_pos(end).row > pos.row{
+                // dw stops at the end of the line
+                end = line_end(text_buffer, pos.row).max(start);
+            }
+            if motion.is_inclusive() && char_at(text_buffer, end) != '\n'{
+                end += 1;
+            }
+            if start == end{
+                return
+            }
+            (start, end)
+        };
+        let linewise = rows.is_some();
+        let text = if let Some((top, bottom)) = rows{
+            lines_text(text_buffer, top, bottom)
+        }
+        else{
+            let mut text = String::new();
+            text_buffer.get_range_as_string(start, end - start, &mut text);
+            text
+        };
+        self.vim_set_register(register, text, linewise, operator == VimOperator::Yank);
+        match operator{
+            VimOperator::Yank=>{
+                if !linewise{
+                    self.vim_set_head(start, text_buffer);
+                }
+            },
+            VimOperator::Delete | VimOperator::Change=>{
+                if start != end{
+                    self.vim_replace(start, end, "", text_buffer);
+                }
+                else{
+                    self.vim_set_head(start, text_buffer);
+                }
+                if operator == VimOperator::Change{
+                    self.vim.mode = VimMode::Insert;
+                }
+                else if linewise{
+                    let row = text_buffer.offset_to_text_pos(self.cursors.set[0].head).row;
+                    self.vim_set_head(first_non_blank(text_buffer, row), text_buffer);
+                }
+            }
+        }
+    }
+
+    // uppercase names append, yanks also go in 0 and everything but _ in the unnamed register
+    fn vim_set_register(&mut self, register:Option<char>, text:String, linewise:bool, yank:bool){
+        let value = VimRegister{text, linewise};
+        let registers = &mut self.vim.registers;
+        match register{
+            Some('_')=>return,
+            Some(name) if name.is_ascii_uppercase()=>{
+                let named = registers.entry(name.to_ascii_lowercase()).or_default();
+                named.text.push_str(&value.text);
+                named.linewise |= linewise;
+                let named = named.clone();
+                registers.insert('"', named);
+                return
+            },
+            Some(name) if name != '"'=>{
+                registers.insert(name, value.clone());
+            },
+            _=>if yank{
+                registers.insert('0', value.clone());
+            }
+        }
+        registers.insert('"', value);
+    }
+
+    fn vim_put(&mut self, before:bool, count:usize, register:Option<char>, text_buffer:&mut TextBuffer){
+        let name = register.unwrap_or('"').to_ascii_lowercase();
+        let value = match self.vim.registers.get(&name){
+            Some(value)=>value.clone(),
+            None=>return
+        };
+        let text = value.text.repeat(count);
+        if text.is_empty(){
+            return
+        }
+        let head = self.cursors.set[0].head;
+        let pos = text_buffer.offset_to_text_pos(head);
+        if value.linewise{
+            let (offset, text, row) = if before{
+                (line_start(text_buffer, pos.row), text, pos.row)
+            }
+            else if pos.row + 1 < text_buffer.lines.len(){
+                (line_start(text_buffer, pos.row + 1), text, pos.row + 1)
+            }
+            else{ // past the last line the newline goes in front
+                (text_buffer.calc_char_count(), format!("\n{}", &text[..text.len() - 1]), pos.row + 1)
+            };
+            self.vim_replace(offset, offset, &text, text_buffer);
+            self.vim_set_head(first_non_blank(text_buffer, row), text_buffer);
+        }
+        else{
+            let offset = if before || text_buffer.lines[pos.row].is_empty(){head} else{head + 1};
+            self.vim_replace(offset, offset, &text, text_buffer);
+            self.vim_set_head(offset + text.chars().count() - 1, text_buffer);
+        }
+    }
+
+    fn vim_insert(&mut self, key:char, text_buffer:&mut TextBuffer){
+        let head = self.cursors.set[0].head;
+        let pos = text_buffer.offset_to_text_pos(head);
+        match key{
+            'a' if !text_buffer.lines[pos.row].is_empty()=>self.vim_set_head(head + 1, text_buffer),
+            'I'=>self.vim_set_head(first_non_blank(text_buffer, pos.row), text_buffer),
+            'A'=>self.vim_set_head(line_end(text_buffer, pos.row), text_buffer),
+            'o'=>{
+                self.vim_set_head(line_end(text_buffer, pos.row), text_buffer);
+                self.cursors.insert_newline_with_indent(text_buffer);
+            },
+            'O'=>{
+                let indent:String = text_buffer.lines[pos.row].iter().take_while(|ch| **ch == ' ').collect();
+                let start = line_start(text_buffer, pos.row);
+                self.vim_replace(start, start, &format!("{}\n", indent), text_buffer);
+                self.vim_set_head(start + indent.len(), text_buffer);
+            },
+            _=>()
+        }
+        self.vim.mode = VimMode::Insert;
+    }
+
+    // the selection is inclusive of the char under the cursor, visual block is a grid select
+    fn vim_select_visual(&mut self, text_buffer:&TextBuffer){
+        let total = text_buffer.calc_char_count();
+        let anchor = self.vim._visual_anchor;
+        let head = self.vim._visual_head;
+        let anchor_pos = text_buffer.offset_to_text_pos(anchor);
+        let head_pos = text_buffer.offset_to_text_pos(head);
+        let (tail, head) = match self.vim.mode{
+            VimMode::VisualLine=>if head_pos.row >= anchor_pos.row{
+                (line_start(text_buffer, anchor_pos.row), line_next(text_buffer, head_pos.row))
+            }
+            else{
+                (line_next(text_buffer, anchor_pos.row), line_start(text_buffer, head_pos.row))
+            },
+            VimMode::VisualBlock=>{
+                let left = anchor_pos.col.min(head_pos.col);
+                let right = anchor_pos.col.max(head_pos.col) + 1;
+                self.cursors.grid_select(TextPos{row:anchor_pos.row, col:left}, TextPos{row:head_pos.row, col:right}, text_buffer);
+                if !self.cursors.set.is_empty(){
+                    return
+                }
+                (head, head)
+            },
+            _=>if head >= anchor{
+                (anchor, (head + 1).min(total))
+            }
+            else{
+                ((anchor + 1).min(total), head)
+            }
+        };
+        self.cursors.set = vec![Cursor{head, tail, max:0}];
+        self.cursors.last_cursor = 0;
+    }
+
+    fn vim_visual_operate(&mut self, operator:VimOperator, register:Option<char>, text_buffer:&mut TextBuffer){
+        let linewise = self.vim.mode == VimMode::VisualLine;
+        let mut text = Vec::new();
+        for cursor in &self.cursors.set{
+            let (start, end) = cursor.order();
+            let mut part = String::new();
+            text_buffer.get_range_as_string(start, end - start, &mut part);
+            text.push(part);
+        }
+        let mut text = text.join("\n");
+        if linewise && !text.ends_with('\n'){
+            text.push('\n');
+        }
+        self.vim_set_register(register, text, linewise, operator == VimOperator::Yank);
+        let total = text_buffer.calc_char_count();
+        let (start, end) = self.cursors.set[0].order();
+        match operator{
+            VimOperator::Yank=>{
+                self.vim.mode = VimMode::Normal;
+                self.vim_set_head(start, text_buffer);
+            },
+            VimOperator::Delete=>{
+                if linewise && end == total && start > 0{ // the last line takes the newline before it along
+                    self.cursors.set[0] = Cursor{head:end, tail:start - 1, max:0};
+                }
+                self.cursors.replace_text("", text_buffer);
+                self.vim.mode = VimMode::Normal;
+                self.cursors.last_cursor = 0;
+                if linewise{
+                    let row = text_buffer.offset_to_text_pos(self.cursors.set[0].head).row;
+                    self.vim_set_head(first_non_blank(text_buffer, row), text_buffer);
+                }
+            },
+            VimOperator::Change=>{
+                if linewise && end > start && char_at(text_buffer, end - 1) == '\n'{ // keep a line to type on
+                    self.cursors.set[0] = Cursor{head:end - 1, tail:start, max:0};
+                }
+                // on a block every line gets what is typed
+                self.cursors.replace_text("", text_buffer);
+                self.vim.mode = VimMode::Insert;
+            }
+        }
+    }
+
+    // a minimal ex command line: a line number, $, d, y, [%]s/find/replace/[g], set [no]wrap
+    // and macro name to save the last keyboard macro
+    pub fn vim_ex_command(&mut self, cx:&mut Cx, line:&str, text_buffer:&mut TextBuffer)->Result<(), String>{
+        let line = line.trim();
+        let last_row = text_buffer.lines.len() - 1;
+        if let Ok(row) = line.parse::<usize>(){
+            self.vim_set_head(first_non_blank(text_buffer, row.max(1).min(last_row + 1) - 1), text_buffer);
+            return Ok(())
+        }
+        match line{
+            ""=>(),
+            "$"=>self.vim_set_head(first_non_blank(text_buffer, last_row), text_buffer),
+            "d" | "delete"=>{
+                let undo_len = text_buffer.undo_stack.len();
+                self.vim_operate(VimOperator::Delete, None, None, None, text_buffer);
+                text_buffer.merge_undo(undo_len);
+            },
+            "y" | "yank"=>self.vim_operate(VimOperator::Yank, None, None, None, text_buffer),
+            "set wrap"=>{
+                self.soft_wrap = SoftWrap::ViewWidth;
+                self.view.redraw_view_area(cx);
+            },
+            "set nowrap"=>{
+                self.soft_wrap = SoftWrap::None;
+                self.view.redraw_view_area(cx);
+            },
+            _=>if let Some(name) = line.strip_prefix("macro "){
+                return self.macros.save_macro(cx, name.trim())
+            }
+            else{
+                return self.vim_substitute(line, text_buffer)
+            }
+        }
+        Ok(())
+    }
+
+    // the find part is plain text, not a pattern
+    fn vim_substitute(&mut self, line:&str, text_buffer:&mut TextBuffer)->Result<(), String>{
+        let (all, rest) = if let Some(rest) = line.strip_prefix("%s"){
+            (true, rest)
+        }
+        else if let Some(rest) = line.strip_prefix('s'){
+            (false, rest)
+        }
+        else{
+            return Err(format!("not an editor command: {}", line))
+        };
+        let delim = match rest.chars().next(){
+            Some(delim) if !delim.is_alphanumeric() && !delim.is_whitespace()=>delim,
+            _=>return Err(format!("not an editor command: {}", line))
+        };
+        let parts:Vec<&str> = rest[delim.len_utf8()..].split(delim).collect();
+        if parts.len() < 2 || parts[0].is_empty(){
+            return Err(format!("bad substitute: {}", line))
+        }
+        let (find, replace) = (parts[0], parts[1]);
+        let global = parts.get(2).is_some_and(|flags| flags.contains('g'));
+        let row = self.cursors.get_last_cursor_text_pos(text_buffer).row;
+        let (top, bottom) = if all{(0, text_buffer.lines.len() - 1)} else{(row, row)};
+        let undo_len = text_buffer.undo_stack.len();
+        let mut last = None;
+        for row in top..(bottom + 1){
+            let old:String = text_buffer.lines[row].iter().collect();
+            if !old.contains(find){
+                continue
+            }
+            let new = if global{old.replace(find, replace)} else{old.replacen(find, replace, 1)};
+            let start = line_start(text_buffer, row);
+            self.vim_replace(start, start + text_buffer.lines[row].len(), &new, text_buffer);
+            last = Some(row);
+        }
+        text_buffer.merge_undo(undo_len);
+        match last{
+            Some(row)=>{
+                self.vim_set_head(first_non_blank(text_buffer, row), text_buffer);
+                Ok(())
+            },
+            None=>Err(format!("not found: {}", find))
+        }
+    }
+
+    // the mode, command line or pending keys along the bottom of the view
+    pub fn draw_vim_status(&mut self, cx:&mut Cx){
+        let status:Vec<char> = self.vim.status().chars().collect();
+        if status.is_empty(){
+            return
+        }
+        let origin = cx.turtle_origin();
+        self.text.font_size = self.open_font_size;
+        let mono_size = self.text.get_monospace_size(cx, None);
+        self.text.color = color("#c");
+        let mut aligned = self.text.begin_text(cx);
+        let x = origin.x + self._scroll_pos.x + 4.;
+        let y = origin.y + self._scroll_pos.y + cx.height_total(false) - mono_size.y;
+        self.text.add_text(cx, x, y, 0, &mut aligned, &status, |_, _, _, _| 0.0);
+        self.text.end_text(cx, &aligned);
+    }
+}
@@ -0,0 +1,138 @@
+// headless checks of the command palette and its fuzzy matching
+use widgets::*;
+
+fn draw_frame(cx:&mut Cx, palette:&mut CommandPalette){
+    cx.headless_draw(|cx| palette.draw_command_palette(cx));
+}
+
+fn send(cx:&mut Cx, palette:&mut CommandPalette, mut event:Event)->CommandPaletteEvent{
+    cx.event_id += 1;
+    palette.handle_command_palette(cx, &mut event)
+}
+
+const EDITOR_FOCUS:Area = Area::DrawList(DrawListArea{draw_list_id:7, redraw_id:1});
+
+// a palette over the files in index.json, the key focus on a made up editor
+fn palette_cx()->(Cx, CommandPalette, Vec<String>){
+    let mut cx = Cx::new_headless(Vec2{x:1200., y:600.});
+    set_dark_style(&mut cx);
+    let palette = CommandPalette::style(&mut cx);
+    let mut file_tree = FileTree::style(&mut cx);
+    cx.headless_compile().expect("shaders compile");
+    file_tree.load_from_json(&mut cx, &std::fs::read_to_string("./index.json").expect("index.json"));
+    cx.set_key_focus(EDITOR_FOCUS);
+    (cx, palette, file_tree.file_paths())
+}
+
+#[test]
+fn fuzzy_matching(){
+    // subsequences only, word starts and runs win
+    assert_eq!(fuzzy_match("", "anything"), Some((0, vec![])));
+    assert_eq!(fuzzy_match("xyz", "codeeditor.rs"), None);
+    assert_eq!(fuzzy_match("ce", "src/code_editor.rs").unwrap().1, vec![4, 9]);
+    assert_eq!(fuzzy_match("FT", "fileTree").unwrap().1, vec![0, 4]);
+    assert_eq!(fuzzy_match("main", "src/main.rs").unwrap().1, vec![4, 5, 6, 7]);
+    assert!(fuzzy_match("main", "src/main.rs").unwrap().0 > fuzzy_match("main", "widgets/src/domain_init.rs").unwrap().0);
+    // a word start that leaves no room for the rest is not taken
+    assert_eq!(fuzzy_match("ab", "xa_b").unwrap().1, vec![1, 3]);
+}
+
+#[test]
+fn file_paths_include_closed_folders(){
+    let (_cx, _palette, paths) = palette_cx();
+    assert!(paths.contains(&"/src/main.rs".to_string()));
+    assert!(paths.contains(&"/widgets/render/src/cx.rs".to_string()));
+}
+
+#[test]
+fn picking_a_file_opens_its_path(){
+    let (mut cx, mut palette, paths) = palette_cx();
+    palette.open_palette(&mut cx, PaletteMode::Files, CommandPalette::file_items(&paths));
+    draw_frame(&mut cx, &mut palette);
+    assert_eq!(cx.key_focus, palette._bg_area);
+    assert_eq!(palette.matches().len(), paths.len());
+    // only the rows that fit are drawn
+    assert_eq!(palette._row_rects.len(), palette.visible_rows);
+    // the best match first
+    let input = TextInputEvent{input:"mainrs".to_string(), replace_last:false, was_paste:false};
+    send(&mut cx, &mut palette, Event::TextInput(input));
+    assert_eq!(palette.matches()[0].value, "/src/main.rs");
+    assert_eq!(palette.exec_command(&mut cx, "palette.accept"), CommandPaletteEvent::OpenFile{path:"/src/main.rs".to_string()});
+    assert_eq!(palette.mode, None);
+    assert_eq!(cx.key_focus, EDITOR_FOCUS);
+}
+
+#[test]
+fn scrolling_follows_the_selection(){
+    let (mut cx, mut palette, paths) = palette_cx();
+    palette.open_palette(&mut cx, PaletteMode::Files, CommandPalette::file_items(&paths));
+    draw_frame(&mut cx, &mut palette);
+    for _ in 0..15{
+        palette.exec_command(&mut cx, "palette.select_next");
+    }
+    draw_frame(&mut cx, &mut palette);
+    // the selected row stays in the window of drawn rows
+    assert_eq!(palette._selected, 15);
+    assert_eq!(palette._scroll, 15 + 1 - palette.visible_rows);
+    assert_eq!(palette._row_rects.last().unwrap().0, 15);
+    for (_, rect) in &palette._row_rects{
+        assert!(palette._panel_rect.contains_rect(*rect), "{:?} outside {:?}", rect, palette._panel_rect);
+    }
+    // escape through the keymap closes it
+    let escape = KeyEvent{key_code:KeyCode::Escape, key_char:'\0', is_repeat:false, modifiers:KeyModifiers::default(), time:0.};
+    send(&mut cx, &mut palette, Event::KeyDown(escape));
+    assert_eq!(palette.mode, None);
+}
+
+#[test]
+fn commands_run_on_the_key_focus(){
+    let (mut cx, mut palette, _paths) = palette_cx();
+    // commands show their keys
+    cx.register_command("app.go_to_file", "Go To File");
+    cx.bind_keys("Mod+P", "app.go_to_file", None).unwrap();
+    let items = CommandPalette::command_items(&cx);
+    palette.open_palette(&mut cx, PaletteMode::Commands, items);
+    assert!(palette.items.iter().all(|i| !i.value.starts_with("palette.")));
+    let open = palette.items.iter().find(|i| i.value == "file_tree.open").unwrap();
+    assert_eq!((open.label.as_ref(), open.detail.as_ref()), ("File Tree: Open File Or Folder", "Enter"));
+    draw_frame(&mut cx, &mut palette);
+    palette.set_query(&mut cx, "gtf");
+    assert_eq!(palette.matches()[0].value, "app.go_to_file");
+    palette.set_query(&mut cx, "ftopen");
+    assert_eq!(palette.matches()[0].value, "file_tree.open");
+    assert_eq!(palette.exec_command(&mut cx, "palette.accept"), CommandPaletteEvent::Command{command:"file_tree.open".to_string()});
+    // and go to what had the focus before
+    cx.send_command("file_tree.open");
+    assert_eq!(cx.user_events.len(), 1);
+    let mut event = cx.user_events[0].clone();
+    match event.hits(&mut cx, EDITOR_FOCUS, &mut HitState::default()){
+        Event::Command(ce)=>assert_eq!(ce.command, "file_tree.open"),
+        _=>panic!("command goes to the key focus")
+    }
+}
+
+// rects of the quads the palette drew with its quad shader, in drawing order
+fn quad_rects(cx:&Cx, palette:&CommandPalette)->Vec<Rect>{
+    let draw_list = &cx.draw_lists[palette.view.draw_list_id.unwrap()];
+    let draw_call = draw_list.draw_calls[0..draw_list.draw_calls_len].iter().find(|dc| dc.shader_id == palette.cursor.shader_id).unwrap();
+    let csh = &cx.compiled_shaders[draw_call.shader_id];
+    let props = &csh.rect_instance_props;
+    draw_call.instance.chunks(csh.instance_slots).map(|inst|{
+        Rect{x:inst[props.x.unwrap()], y:inst[props.y.unwrap()], w:inst[props.w.unwrap()], h:inst[props.h.unwrap()]}
+    }).collect()
+}
+
+#[test]
+fn the_caret_centers_in_the_input_with_the_query(){
+    let (mut cx, mut palette, _paths) = palette_cx();
+    let items = CommandPalette::command_items(&cx);
+    palette.open_palette(&mut cx, PaletteMode::Commands, items);
+    palette.set_query(&mut cx, "open");
+    draw_frame(&mut cx, &mut palette);
+    let rects = quad_rects(&cx, &palette);
+    // the panel, the input and the caret drawn after the query text
+    let (input, caret) = (rects[1], rects[2]);
+    assert_eq!(caret.w, 1.);
+    assert!(caret.x > input.x + 6.);
+    assert_eq!(caret.y + caret.h * 0.5, input.y + input.h * 0.5);
+}
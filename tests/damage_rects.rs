@@ -0,0 +1,113 @@
+// headless checks of the damage rects a repaint scissors to
+use widgets::*;
+
+// an editor like view with lines of text blocks and a cursor, returns the cursor area
+fn draw_editor(cx:&mut Cx, view:&mut View<NoScrollBar>, line:&mut Quad, cursor:&mut Quad, lines:usize, show:bool)->Area{
+    let mut area = Area::Empty;
+    if show{
+        view.begin_view(cx, &Layout{
+            abs_start:Some(Vec2{x:100., y:50.}),
+            width:Bounds::Fix(400.),
+            height:Bounds::Fix(300.),
+            ..Default::default()
+        });
+        for i in 0..lines{
+            line.draw_quad(cx, Rect{x:0., y:i as f32 * 20., w:200., h:16.});
+        }
+        area = cursor.draw_quad(cx, Rect{x:50., y:40., w:2., h:16.}).into_area();
+        view.end_view(cx);
+    }
+    area
+}
+
+const SCREEN:Rect = Rect{x:0., y:0., w:1200., h:600.};
+const VIEW_RECT:Rect = Rect{x:100., y:50., w:400., h:300.};
+
+struct Harness{
+    cx:Cx,
+    view:View<NoScrollBar>,
+    line:Quad,
+    cursor:Quad
+}
+
+impl Harness{
+    // the first frame paints everything
+    fn new()->Harness{
+        let mut cx = Cx::new_headless(Vec2{x:1200., y:600.});
+        set_dark_style(&mut cx);
+        let line = Quad::style(&mut cx);
+        let mut cursor_sh = Quad::def_quad_shader(&mut cx);
+        cursor_sh.add_ast(shader_ast!({
+            fn pixel()->vec4{
+                return vec4(color.rgb, 1.0);
+            }
+        }));
+        let cursor = Quad{
+            shader_id:cx.add_shader(cursor_sh, "Cursor"),
+            ..Style::style(&mut cx)
+        };
+        cx.headless_compile().expect("shaders compile");
+        let view = View::<NoScrollBar>{..Style::style(&mut cx)};
+        let mut h = Harness{cx, view, line, cursor};
+        h.draw(10, true);
+        assert_eq!(h.cx.damage, vec![SCREEN]);
+        h
+    }
+
+    fn draw(&mut self, lines:usize, show:bool)->Area{
+        let (view, line, cursor) = (&mut self.view, &mut self.line, &mut self.cursor);
+        let area = self.cx.headless_draw(|cx| draw_editor(cx, view, line, cursor, lines, show));
+        self.cx.prepare_frame();
+        area
+    }
+}
+
+#[test]
+fn unchanged_frames_are_not_damaged(){
+    let mut h = Harness::new();
+    h.cx.prepare_frame();
+    assert_eq!(h.cx.damage, vec![]);
+    // redrawing the same thing is not damage either
+    h.draw(10, true);
+    assert_eq!(h.cx.damage, vec![]);
+}
+
+#[test]
+fn a_cursor_blink_damages_the_cursor(){
+    let mut h = Harness::new();
+    let cursor_area = h.draw(10, true);
+    cursor_area.write_color(&mut h.cx, "color", color("#0000"));
+    h.cx.prepare_frame();
+    assert_eq!(h.cx.damage, vec![Rect{x:150., y:90., w:2., h:16.}]);
+}
+
+#[test]
+fn scrolling_damages_inside_the_view(){
+    let mut h = Harness::new();
+    let view_list = h.view.draw_list_id.unwrap();
+    h.cx.draw_lists[view_list].set_scroll_y(10.);
+    h.cx.prepare_frame();
+    assert!(!h.cx.damage.is_empty() && h.cx.damage.iter().all(|r| VIEW_RECT.contains_rect(*r)));
+}
+
+#[test]
+fn removed_items_leave_damage(){
+    let mut h = Harness::new();
+    // a line less damages the lines
+    h.draw(9, true);
+    assert_eq!(h.cx.damage, vec![Rect{x:100., y:50., w:200., h:196.}]);
+    // a view that goes away leaves a hole where it was
+    h.draw(9, false);
+    assert_eq!(h.cx.damage.len(), 1);
+    assert!(VIEW_RECT.contains_rect(h.cx.damage[0]));
+}
+
+#[test]
+fn without_partial_paint_the_window_repaints(){
+    let mut h = Harness::new();
+    h.cx.partial_paint = false;
+    h.draw(9, true);
+    assert_eq!(h.cx.damage, vec![SCREEN]);
+    h.cx.prepare_frame();
+    assert_eq!(h.cx.damage, vec![]);
+}
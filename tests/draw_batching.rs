@@ -0,0 +1,85 @@
+// headless checks of the draw call batching
+use widgets::*;
+
+// a row of clipped views that each draw a background and a marker with another shader,
+// every view at the same spot when overlapping
+fn draw_tabs(cx:&mut Cx, views:&mut [View<NoScrollBar>], bg:&mut Quad, marker:&mut Quad, overlapping:bool){
+    for (i, view) in views.iter_mut().enumerate(){
+        view.begin_view(cx, &Layout{
+            abs_start:Some(Vec2{x:if overlapping{0.}else{i as f32 * 100.}, y:0.}),
+            width:Bounds::Fix(80.),
+            height:Bounds::Fix(30.),
+            ..Default::default()
+        });
+        bg.draw_quad_walk(cx, Bounds::Fill, Bounds::Fill, Margin::zero());
+        marker.draw_quad(cx, Rect{x:4., y:4., w:10., h:10.});
+        view.end_view(cx);
+    }
+}
+
+struct Harness{
+    cx:Cx,
+    views:Vec<View<NoScrollBar>>,
+    bg:Quad,
+    marker:Quad
+}
+
+impl Harness{
+    fn new()->Harness{
+        let mut cx = Cx::new_headless(Vec2{x:1200., y:600.});
+        set_dark_style(&mut cx);
+        let bg = Quad::style(&mut cx);
+        let mut marker_sh = Quad::def_quad_shader(&mut cx);
+        marker_sh.add_ast(shader_ast!({
+            fn pixel()->vec4{
+                return vec4(color.rgb, 1.0);
+            }
+        }));
+        let marker = Quad{
+            shader_id:cx.add_shader(marker_sh, "Marker"),
+            ..Style::style(&mut cx)
+        };
+        cx.headless_compile().expect("shaders compile");
+        let views = (0..10).map(|_| View{..Style::style(&mut cx)}).collect();
+        Harness{cx, views, bg, marker}
+    }
+
+    fn draw(&mut self, batching:bool, overlapping:bool)->FrameStats{
+        self.cx.batching = batching;
+        let (views, bg, marker) = (&mut self.views, &mut self.bg, &mut self.marker);
+        self.cx.headless_draw(|cx| draw_tabs(cx, views, bg, marker, overlapping));
+        self.cx.prepare_frame();
+        let stats = self.cx.frame_stats;
+        assert_eq!(stats.draw_calls, 20);
+        assert_eq!(stats.instances, 20);
+        stats
+    }
+}
+
+#[test]
+fn side_by_side_views_batch_per_shader(){
+    let mut h = Harness::new();
+    assert_eq!(h.draw(true, false).batched_draw_calls, 2);
+
+    // all backgrounds go first, then all markers, each in view order
+    let batches = &h.cx.draw_lists[0].batches;
+    assert_eq!(batches.len(), 2);
+    for (batch, shader_id) in batches.iter().zip([h.bg.shader_id, h.marker.shader_id].iter()){
+        let lists:Vec<usize> = batch.members.iter().map(|(list, _)| *list).collect();
+        let expect:Vec<usize> = h.views.iter().map(|v| v.draw_list_id.unwrap()).collect();
+        assert_eq!(lists, expect);
+        assert!(batch.members.iter().all(|(list, call)| h.cx.draw_lists[*list].draw_calls[*call].shader_id == *shader_id));
+    }
+}
+
+#[test]
+fn nothing_batches_when_turned_off(){
+    let mut h = Harness::new();
+    assert_eq!(h.draw(false, false).batched_draw_calls, 20);
+}
+
+#[test]
+fn overlapping_views_keep_their_order(){
+    let mut h = Harness::new();
+    assert_eq!(h.draw(true, true).batched_draw_calls, 20);
+}
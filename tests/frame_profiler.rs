@@ -0,0 +1,94 @@
+// headless checks of the frame profiler and its hud
+use widgets::*;
+
+fn draw_frame(cx:&mut Cx, hud:&mut ProfilerHud){
+    cx.profile_begin("draw");
+    cx.headless_draw(|cx| hud.draw_profiler_hud(cx));
+    cx.profile_end();
+    cx.profile_begin("repaint");
+    cx.prepare_frame();
+    cx.profiler.count_draw_call(3);
+    cx.profiler.count_upload(64);
+    cx.profile_end();
+    cx.profile_end_frame();
+}
+
+fn profiler_cx()->(Cx, ProfilerHud){
+    let mut cx = Cx::new_headless(Vec2{x:1200., y:600.});
+    set_dark_style(&mut cx);
+    let hud = ProfilerHud::style(&mut cx);
+    cx.headless_compile().expect("shaders compile");
+    (cx, hud)
+}
+
+// 20 frames into a ring of 8
+fn profiled_cx()->(Cx, ProfilerHud){
+    let (mut cx, mut hud) = profiler_cx();
+    cx.enable_profiler(true);
+    hud.show = true;
+    cx.profiler.capacity = 8;
+    for _ in 0..20{
+        draw_frame(&mut cx, &mut hud);
+    }
+    (cx, hud)
+}
+
+#[test]
+fn nothing_is_kept_while_disabled(){
+    let (mut cx, mut hud) = profiler_cx();
+    draw_frame(&mut cx, &mut hud);
+    assert_eq!(cx.profiler.frames().len(), 0);
+}
+
+#[test]
+fn the_ring_keeps_the_newest_frames(){
+    let (cx, _hud) = profiled_cx();
+    // oldest first
+    let frames = cx.profiler.frames();
+    assert_eq!(frames.len(), 8);
+    assert_eq!(frames.iter().map(|f| f.frame).collect::<Vec<_>>(), (13..21).collect::<Vec<u64>>());
+    let last = frames[7];
+    assert_eq!((last.draw_calls, last.instances, last.uploaded_bytes), (1, 3, 64));
+    // draw and repaint at the top, prepare inside repaint
+    let names:Vec<(&str, usize)> = last.spans.iter().map(|s| (s.name, s.depth)).collect();
+    assert_eq!(names, vec![("draw", 0), ("repaint", 0), ("prepare", 1)]);
+    assert!(last.spans.iter().all(|s| s.end >= s.start));
+    assert!(last.busy_ms(None) >= last.busy_ms(Some("draw")));
+}
+
+#[test]
+fn chrome_trace_has_every_span(){
+    let (cx, _hud) = profiled_cx();
+    // one complete event per span plus one per frame and a counter per frame
+    let trace = cx.profiler_chrome_trace();
+    assert!(trace.starts_with("{\"displayTimeUnit\":\"ms\",\"traceEvents\":["));
+    assert_eq!(trace.matches("\"ph\":\"X\"").count(), 8 * 4);
+    assert_eq!(trace.matches("\"ph\":\"C\"").count(), 8);
+    assert_eq!(trace.matches('{').count(), trace.matches('}').count());
+}
+
+#[test]
+fn the_hud_graph_fits_its_panel(){
+    let (mut cx, mut hud) = profiled_cx();
+    // the hud records its turtles
+    cx.debug_layout = true;
+    draw_frame(&mut cx, &mut hud);
+    let panel = cx.debug_turtles.iter().find(|t| t.layout.abs_start.map(|p| p.x) == Some(8.)).expect("hud panel").rect;
+    assert!(panel.h > hud.graph_height);
+    // every bar of the graph lies inside the panel
+    let draw_list = &cx.draw_lists[hud.view.draw_list_id.unwrap()];
+    let mut bars = 0;
+    for draw_call in &draw_list.draw_calls[0..draw_list.draw_calls_len]{
+        if draw_call.shader_id != hud.bar.shader_id{
+            continue
+        }
+        let csh = &cx.compiled_shaders[draw_call.shader_id];
+        let props = &csh.rect_instance_props;
+        for inst in draw_call.instance.chunks(csh.instance_slots){
+            let rect = Rect{x:inst[props.x.unwrap()], y:inst[props.y.unwrap()], w:inst[props.w.unwrap()], h:inst[props.h.unwrap()]};
+            assert!(panel.contains_rect(rect), "{:?} outside the panel", rect);
+            bars += 1;
+        }
+    }
+    assert!(bars > 0);
+}
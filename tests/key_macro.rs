@@ -0,0 +1,204 @@
+// headless checks of recording and playing keyboard macros in the code editor
+use widgets::*;
+use makepad::*;
+
+struct Harness{
+    cx:Cx,
+    editor:RustEditor,
+    text_buffer:TextBuffer
+}
+
+impl Harness{
+    fn new(text:&str)->Harness{
+        let mut cx = Cx::new_headless(Vec2{x:1200., y:600.});
+        set_dark_style(&mut cx);
+        let editor = RustEditor::style(&mut cx);
+        cx.headless_compile().expect("shaders compile");
+        let mut text_buffer = TextBuffer::default();
+        text_buffer.load_buffer(&text.as_bytes().to_vec());
+        let mut harness = Harness{cx, editor, text_buffer};
+        harness.draw();
+        harness.cx.set_key_focus(harness.editor.code_editor._bg_area);
+        harness
+    }
+
+    fn draw(&mut self){
+        let (editor, text_buffer) = (&mut self.editor, &self.text_buffer);
+        self.cx.headless_draw(|cx| editor.draw_rust_editor(cx, text_buffer));
+    }
+
+    fn send(&mut self, mut event:Event){
+        self.cx.event_id += 1;
+        self.editor.handle_rust_editor(&mut self.cx, &mut event, &mut self.text_buffer);
+        self.draw();
+    }
+
+    // typed text, with chords in between like <Mod+K> or <Down>
+    fn keys(&mut self, keys:&str){
+        let mut chars = keys.chars();
+        while let Some(ch) = chars.next(){
+            if ch == '<'{
+                let name:String = chars.by_ref().take_while(|ch| *ch != '>').collect();
+                let chord = KeyChord::parse(&name).expect("chord parses");
+                let key_char = if chord.key_code == KeyCode::Escape{'\x1b'} else{'\0'};
+                self.send(Event::KeyDown(KeyEvent{
+                    key_code:chord.key_code,
+                    key_char,
+                    is_repeat:false,
+                    modifiers:KeyModifiers{control:chord.primary || chord.modifiers.control, ..chord.modifiers},
+                    time:0.
+                }));
+                continue
+            }
+            let key_code = KeyChord::parse(&ch.to_uppercase().to_string()).map(|chord| chord.key_code).unwrap_or(KeyCode::Unknown);
+            self.send(Event::KeyDown(KeyEvent{
+                key_code,
+                key_char:ch,
+                is_repeat:false,
+                modifiers:KeyModifiers{shift:ch.is_uppercase(), ..Default::default()},
+                time:0.
+            }));
+            self.send(Event::TextInput(TextInputEvent{input:ch.to_string(), replace_last:false, was_paste:false}));
+        }
+    }
+
+    fn command(&mut self, command:&str){
+        self.send(Event::Command(CommandEvent{command:command.to_string()}));
+    }
+
+    fn text(&self)->String{
+        self.text_buffer.lines.iter().map(|line| line.iter().collect::<String>()).collect::<Vec<String>>().join("\n")
+    }
+}
+
+// a recorded macro that bullets a line and moves down, played on none of the lines yet
+fn recorded()->Harness{
+    let mut h = Harness::new("one\ntwo\nthree\nfour");
+    h.keys("<Mod+K><Mod+R>");
+    assert!(h.editor.code_editor.macros.recording);
+    h.keys("<Home>- <End>;<Down>");
+    h.keys("<Mod+K><Mod+S>");
+    assert!(!h.editor.code_editor.macros.recording);
+    assert_eq!(h.text(), "- one;\ntwo\nthree\nfour");
+    h
+}
+
+#[test]
+fn recording_leaves_out_the_macro_keys(){
+    let h = recorded();
+    let macros = &h.editor.code_editor.macros;
+    assert_eq!(macros.last.len(), 9);
+    assert!(macros.last.iter().all(|event| match event{
+        Event::KeyDown(ke)=>!ke.modifiers.control,
+        _=>true
+    }));
+}
+
+#[test]
+fn a_play_is_one_undo_step(){
+    let mut h = recorded();
+    h.keys("<Mod+K><Mod+P>");
+    assert_eq!(h.text(), "- one;\n- two;\nthree\nfour");
+    h.keys("<Mod+Z>");
+    assert_eq!(h.text(), "- one;\ntwo\nthree\nfour");
+    h.keys("<Mod+Shift+Z>");
+    assert_eq!(h.text(), "- one;\n- two;\nthree\nfour");
+
+    // to the end of the file stops once a run doesn't move on, the last line still gets done
+    h.command("editor.macro_play_to_end");
+    assert_eq!(h.text(), "- one;\n- two;\n- three;\n- four;");
+    h.keys("<Mod+Z>");
+    assert_eq!(h.text(), "- one;\n- two;\nthree\nfour");
+}
+
+#[test]
+fn plays_a_number_of_times_from_code(){
+    let mut h = recorded();
+    let events = h.editor.code_editor.macros.last.clone();
+    let played = h.editor.code_editor.play_macro(&mut h.cx, &events, Some(2), &mut h.text_buffer);
+    assert_eq!(played, 2);
+    assert_eq!(h.text(), "- one;\n- two;\n- three;\nfour");
+    let played = h.editor.code_editor.play_macro(&mut h.cx, &events, Some(0), &mut h.text_buffer);
+    assert_eq!(played, 0);
+}
+
+#[test]
+fn saved_macros_become_commands(){
+    let mut h = recorded();
+    assert!(h.editor.code_editor.macros.save_macro(&mut h.cx, "two words").is_err());
+    h.editor.code_editor.macros.save_macro(&mut h.cx, "bullet").expect("macro saves");
+    assert!(h.cx.keymap.commands.iter().any(|c| c.name == "editor.macro_play.bullet" && c.title == "Play Macro bullet"));
+    // an empty recording keeps the last macro
+    h.keys("<Mod+K><Mod+R><Mod+K><Mod+S>");
+    assert_eq!(h.editor.code_editor.macros.last.len(), 9);
+    h.keys("<Up>");
+    h.command("editor.macro_play.bullet");
+    assert_eq!(h.text(), "- - one;;\ntwo\nthree\nfour");
+    let mut none = h.editor.code_editor.play_saved_macro(&mut h.cx, "nope", None, &mut h.text_buffer);
+    assert_eq!(none, Err("no macro named: nope".to_string()));
+    none = h.editor.code_editor.play_saved_macro(&mut h.cx, "bullet", Some(0), &mut h.text_buffer);
+    assert_eq!(none, Ok(0));
+}
+
+#[test]
+fn vim_mode_keys_go_through_vim(){
+    // when played, and :macro saves
+    let mut h = Harness::new("a\nb\nc\nd");
+    h.command("editor.toggle_vim");
+    h.keys("<Mod+K><Mod+R>A;<Esc>j<Mod+K><Mod+S>");
+    assert_eq!(h.text(), "a;\nb\nc\nd");
+    h.keys("<Mod+K><Mod+E>");
+    assert_eq!(h.text(), "a;\nb;\nc;\nd;");
+    h.keys("u");
+    assert_eq!(h.text(), "a;\nb\nc\nd");
+    h.keys(":macro semi");
+    h.keys("<Enter>");
+    assert!(h.editor.code_editor.macros.saved.borrow().contains_key("semi"));
+}
+
+#[test]
+fn saved_macros_are_written_and_load_in_a_new_editor(){
+    let file = std::env::temp_dir().join(format!("makepad_macros_{}.json", std::process::id()));
+    let mut h = recorded();
+    h.editor.code_editor.macros.file = Some(file.to_string_lossy().to_string());
+    h.editor.code_editor.macros.save_macro(&mut h.cx, "bullet").expect("macro saves");
+    let json = std::fs::read_to_string(&file).expect("macros are written");
+    std::fs::remove_file(&file).unwrap();
+    // keys keep their keymap names, the space has no keycode in the harness and keeps its char
+    assert!(json.contains("\"key\": \"Home\"") && json.contains("\"char\": \" \""));
+
+    // a restart, the macro plays the same as before
+    let mut again = Harness::new("one\ntwo");
+    assert_eq!(again.editor.code_editor.macros.load_saved_from_json(&mut again.cx, &json), Ok(1));
+    assert!(again.cx.keymap.commands.iter().any(|c| c.name == "editor.macro_play.bullet"));
+    assert_eq!(again.editor.code_editor.macros.saved.borrow()["bullet"], h.editor.code_editor.macros.last);
+    again.command("editor.macro_play.bullet");
+    assert_eq!(again.text(), "- one;\ntwo");
+
+    // the file is left alone when it can't be written, the macro is still saved
+    h.editor.code_editor.macros.file = Some(file.join("missing").to_string_lossy().to_string());
+    assert!(h.editor.code_editor.macros.save_macro(&mut h.cx, "again").unwrap_err().starts_with("macros: cannot write"));
+    assert!(h.editor.code_editor.macros.saved.borrow().contains_key("again"));
+}
+
+#[test]
+fn bad_macro_files_load_nothing(){
+    let mut h = Harness::new("one");
+    let macros = &mut h.editor.code_editor.macros;
+    assert!(macros.load_saved_from_json(&mut h.cx, "[]").is_err());
+    assert!(macros.load_saved_from_json(&mut h.cx, "{\"ok\":[{\"text\":\"a\"}], \"two words\":[]}").is_err());
+    assert!(macros.load_saved_from_json(&mut h.cx, "{\"ok\":[{\"text\":\"a\"}], \"bad\":[{\"key\":\"Ctrl+Nope\"}]}").is_err());
+    assert!(macros.load_saved_from_json(&mut h.cx, "{\"ok\":[{\"text\":\"a\"}], \"empty\":[{}]}").is_err());
+    assert!(macros.saved.borrow().is_empty());
+    assert!(!h.cx.keymap.commands.iter().any(|c| c.name == "editor.macro_play.ok"));
+}
+
+#[test]
+fn editors_of_one_template_share_saved_macros(){
+    let mut h = recorded();
+    let mut other = h.editor.clone();
+    h.editor.code_editor.macros.save_macro(&mut h.cx, "bullet").expect("macro saves");
+    let mut text_buffer = TextBuffer::default();
+    text_buffer.load_buffer(&"x".as_bytes().to_vec());
+    assert_eq!(other.code_editor.play_saved_macro(&mut h.cx, "bullet", Some(1), &mut text_buffer), Ok(1));
+}
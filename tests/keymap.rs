@@ -0,0 +1,124 @@
+// headless checks of key bindings, sequences, contexts and keymap files
+use widgets::*;
+
+fn key(cx:&mut Cx, chord:&str)->KeyEvent{
+    let chord = KeyChord::parse(chord).expect("chord parses");
+    cx.event_id += 1;
+    KeyEvent{
+        key_code:chord.key_code,
+        key_char:'\0',
+        is_repeat:false,
+        modifiers:KeyModifiers{control:chord.primary || chord.modifiers.control, ..chord.modifiers},
+        time:0.
+    }
+}
+
+fn press(cx:&mut Cx, chord:&str, context:&str)->Option<String>{
+    let ke = key(cx, chord);
+    cx.key_command(&ke, context)
+}
+
+// a few editor bindings and the file tree's own
+fn keymap_cx()->Cx{
+    let mut cx = Cx::default();
+    cx.register_command("editor.undo", "Undo");
+    cx.register_command("editor.fold_all", "Fold All");
+    cx.bind_keys("Mod+Z", "editor.undo", Some("editor")).unwrap();
+    cx.bind_keys("Mod+K Mod+0", "editor.fold_all", Some("editor")).unwrap();
+    cx.bind_keys("Up", "editor.cursor_up", Some("editor")).unwrap();
+    FileTree::register_commands(&mut cx);
+    cx
+}
+
+#[test]
+fn bad_keys_dont_bind(){
+    let mut cx = keymap_cx();
+    assert!(cx.bind_keys("Ctrl+Hyper+Z", "x", None).is_err());
+    assert!(cx.bind_keys("", "x", None).is_err());
+}
+
+#[test]
+fn mod_is_ctrl_or_cmd(){
+    let mut cx = keymap_cx();
+    // mod is ctrl or cmd, and exact about the other modifiers
+    assert_eq!(press(&mut cx, "Ctrl+Z", "editor"), Some("editor.undo".to_string()));
+    assert_eq!(press(&mut cx, "Cmd+Z", "editor"), Some("editor.undo".to_string()));
+    assert_eq!(press(&mut cx, "Ctrl+Shift+Z", "editor"), None);
+}
+
+#[test]
+fn contexts_pick_the_binding(){
+    let mut cx = keymap_cx();
+    // the same key means different things depending on who has the focus
+    assert_eq!(press(&mut cx, "Up", "editor"), Some("editor.cursor_up".to_string()));
+    assert_eq!(press(&mut cx, "Up", "file_tree"), Some("file_tree.select_prev".to_string()));
+    assert_eq!(press(&mut cx, "Ctrl+Z", "file_tree"), None);
+}
+
+#[test]
+fn sequences_wait_for_their_next_key(){
+    let mut cx = keymap_cx();
+    // sequences wait for their next key, pressing a modifier in between doesn't break them
+    assert_eq!(press(&mut cx, "Ctrl+K", "editor"), None);
+    assert!(cx.is_key_sequence_pending());
+    let ctrl = KeyEvent{key_code:KeyCode::Control, modifiers:KeyModifiers{control:true, ..Default::default()}, ..key(&mut cx, "Ctrl+0")};
+    assert_eq!(cx.key_command(&ctrl, "editor"), None);
+    assert_eq!(press(&mut cx, "Ctrl+0", "editor"), Some("editor.fold_all".to_string()));
+    assert!(!cx.is_key_sequence_pending());
+    // a wrong second key ends the sequence
+    press(&mut cx, "Ctrl+K", "editor");
+    assert_eq!(press(&mut cx, "Ctrl+Z", "editor"), None);
+    assert_eq!(press(&mut cx, "Ctrl+Z", "editor"), Some("editor.undo".to_string()));
+}
+
+#[test]
+fn one_answer_per_key_event(){
+    let mut cx = keymap_cx();
+    // asking twice for one key event gives the same answer
+    let ke = key(&mut cx, "Ctrl+K");
+    assert_eq!(cx.key_command(&ke, "editor"), None);
+    assert_eq!(cx.key_command(&ke, "file_tree"), None);
+    let ke = key(&mut cx, "Ctrl+0");
+    assert_eq!(cx.key_command(&ke, "file_tree"), None);
+    assert_eq!(cx.key_command(&ke, "editor"), Some("editor.fold_all".to_string()));
+}
+
+#[test]
+fn keymap_files_bind_on_top(){
+    let mut cx = keymap_cx();
+    // an emacs flavoured keymap file on top of the defaults
+    let applied = load_keymap_from_json(&mut cx, r#"[
+        {"keys":"Ctrl+A", "command":"editor.cursor_home", "when":"editor"},
+        {"keys":"Ctrl+X U", "command":"editor.undo", "when":"editor"},
+        {"keys":"Mod+Z", "command":"-editor.undo"},
+        {"keys":"Ctrl+P", "command":"file_tree.select_prev", "when":"!editor"}
+    ]"#).expect("keymap loads");
+    assert_eq!(applied, 4);
+    assert_eq!(press(&mut cx, "Ctrl+A", "editor"), Some("editor.cursor_home".to_string()));
+    assert_eq!(press(&mut cx, "Ctrl+Z", "editor"), None);
+    press(&mut cx, "Ctrl+X", "editor");
+    assert_eq!(press(&mut cx, "U", "editor"), Some("editor.undo".to_string()));
+    assert_eq!(press(&mut cx, "Ctrl+P", "file_tree"), Some("file_tree.select_prev".to_string()));
+    assert_eq!(press(&mut cx, "Ctrl+P", "editor"), None);
+    assert_eq!(cx.command_keys("editor.undo", "editor"), Some("Ctrl+X U".to_string()));
+    assert_eq!(cx.command_keys("file_tree.open", "file_tree"), Some("Enter".to_string()));
+}
+
+#[test]
+fn bad_keymap_entries_are_reported(){
+    let mut cx = keymap_cx();
+    // bad entries are reported, the good ones still apply
+    let err = load_keymap_from_json(&mut cx, r#"[
+        {"keys":"Ctrl+Nope", "command":"editor.undo"},
+        {"keys":"F5", "command":"editor.undo", "when":"editor"}
+    ]"#).unwrap_err();
+    assert!(err.contains("Nope"));
+    assert_eq!(press(&mut cx, "F5", "editor"), Some("editor.undo".to_string()));
+    assert!(load_keymap_from_json(&mut cx, "{").is_err());
+    assert!(load_keymap_from_json(&mut cx, r#"{"keys":"F6"}"#).is_err());
+    assert!(load_keymap_from_json(&mut cx, r#"[{"keys":"F6"}]"#).unwrap_err().contains("missing command"));
+    assert!(load_keymap_from_json(&mut cx, r#"[{"keys":6, "command":"editor.undo"}]"#).unwrap_err().contains("keys is not a string"));
+    // keys are optional for removals
+    assert_eq!(load_keymap_from_json(&mut cx, r#"[{"command":"-editor.undo"}]"#), Ok(1));
+    assert_eq!(press(&mut cx, "F5", "editor"), None);
+}
@@ -0,0 +1,193 @@
+// headless checks of soft wrapping long lines in the code editor
+use widgets::*;
+use makepad::*;
+
+const LONG:&str = "fn main(){\n    let total = alpha + beta + gamma + delta + epsilon + zeta + eta + theta + iota + kappa + lambda + omicron + sigma + upsilon + omega + alpha + beta + gamma + delta + epsilon + zeta + eta + theta + iota + kappa + lambda;\n}";
+
+struct Harness{
+    cx:Cx,
+    editor:RustEditor,
+    text_buffer:TextBuffer
+}
+
+impl Harness{
+    fn new(text:&str)->Harness{
+        let mut cx = Cx::new_headless(Vec2{x:1200., y:600.});
+        set_dark_style(&mut cx);
+        let editor = RustEditor::style(&mut cx);
+        cx.headless_compile().expect("shaders compile");
+        let mut text_buffer = TextBuffer::default();
+        text_buffer.load_buffer(&text.as_bytes().to_vec());
+        let mut harness = Harness{cx, editor, text_buffer};
+        harness.draw();
+        harness.cx.set_key_focus(harness.editor.code_editor._bg_area);
+        harness
+    }
+
+    fn wrapped(text:&str, soft_wrap:SoftWrap)->Harness{
+        let mut h = Harness::new(text);
+        h.editor.code_editor.soft_wrap = soft_wrap;
+        h.draw();
+        h
+    }
+
+    fn draw(&mut self){
+        let (editor, text_buffer) = (&mut self.editor, &self.text_buffer);
+        self.cx.headless_draw(|cx| editor.draw_rust_editor(cx, text_buffer));
+    }
+
+    fn send(&mut self, mut event:Event){
+        self.cx.event_id += 1;
+        self.editor.handle_rust_editor(&mut self.cx, &mut event, &mut self.text_buffer);
+        self.draw();
+    }
+
+    fn key(&mut self, chord:&str){
+        let chord = KeyChord::parse(chord).expect("chord parses");
+        self.send(Event::KeyDown(KeyEvent{
+            key_code:chord.key_code,
+            key_char:'\0',
+            is_repeat:false,
+            modifiers:KeyModifiers{control:chord.primary || chord.modifiers.control, ..chord.modifiers},
+            time:0.
+        }));
+    }
+
+    // a finger down a few columns into a visual row
+    fn click(&mut self, index:usize, cols:f32, modifiers:KeyModifiers){
+        let ce = &self.editor.code_editor;
+        let origin = ce._bg_area.get_rect_scrolled(&self.cx);
+        let walk = ce._visual_row_geometry[index].walk;
+        let abs = Vec2{
+            x:origin.x + walk.x + ce._monospace_size.x * cols,
+            y:origin.y + walk.y + ce._monospace_size.y * 0.5
+        };
+        self.send(Event::FingerDown(FingerDownEvent{abs, tap_count:1, modifiers, ..Default::default()}));
+    }
+
+    fn head(&self)->usize{
+        self.editor.code_editor.cursors.set[0].head
+    }
+
+    fn rows_of_line(&self, line:usize)->Vec<VisualRow>{
+        self.editor.code_editor._visual_rows.iter().filter(|row| row.row == line).copied().collect()
+    }
+
+    // the length in chars of a visual row, up to the next row or the end of its line
+    fn row_len(&self, index:usize)->usize{
+        let rows = &self.editor.code_editor._visual_rows;
+        let row = &rows[index];
+        if index + 1 < rows.len() && rows[index + 1].row == row.row{
+            return rows[index + 1].col - row.col
+        }
+        self.text_buffer.lines[row.row].len() - row.col
+    }
+}
+
+#[test]
+fn lines_are_not_wrapped_by_default(){
+    let h = Harness::new(LONG);
+    assert!(h.editor.code_editor.soft_wrap == SoftWrap::None);
+    assert_eq!(h.rows_of_line(1).len(), 1);
+}
+
+#[test]
+fn the_toggle_command_wraps_at_the_view_width(){
+    let mut h = Harness::new(LONG);
+    h.key("Alt+Z");
+    assert!(h.editor.code_editor.soft_wrap == SoftWrap::ViewWidth);
+    let rows = h.rows_of_line(1);
+    assert!(rows.len() > 1, "the long line wraps");
+    let ce = &h.editor.code_editor;
+    let first = ce._visual_rows.iter().position(|row| row.row == 1).unwrap();
+    for i in first..first + rows.len(){
+        let row = &ce._visual_rows[i];
+        // trailing whitespace may hang past the wrap
+        let line = &h.text_buffer.lines[1];
+        let text:String = line[row.col..row.col + h.row_len(i)].iter().collect();
+        let width = ce._visual_row_geometry[i].walk.x + ce._monospace_size.x * text.trim_end().len() as f32;
+        assert!(width <= ce._wrap_width + 0.01, "row {} is {} wide, wraps at {}", i, width, ce._wrap_width);
+    }
+    h.key("Alt+Z");
+    assert!(h.editor.code_editor.soft_wrap == SoftWrap::None);
+    assert_eq!(h.rows_of_line(1).len(), 1);
+}
+
+#[test]
+fn continuation_rows_keep_the_indent_and_break_between_tokens(){
+    let h = Harness::wrapped(LONG, SoftWrap::Column(40));
+    let rows = h.rows_of_line(1);
+    assert!(rows.len() > 2);
+    let line = &h.text_buffer.lines[1];
+    for row in &rows[1..]{
+        assert_eq!(row.indent, 4, "continuation rows sit at the indent of the line");
+        let prev = line[row.col - 1];
+        let next = line[row.col];
+        assert!(!prev.is_alphanumeric() || !next.is_alphanumeric(), "row at col {} splits a word", row.col);
+    }
+}
+
+#[test]
+fn column_wrap_keeps_rows_within_the_column(){
+    let h = Harness::wrapped(LONG, SoftWrap::Column(20));
+    let ce = &h.editor.code_editor;
+    let first = ce._visual_rows.iter().position(|row| row.row == 1).unwrap();
+    let count = h.rows_of_line(1).len();
+    assert!(count > 5);
+    for i in first..first + count{
+        let row = &ce._visual_rows[i];
+        let line = &h.text_buffer.lines[1];
+        let text:String = line[row.col..row.col + h.row_len(i)].iter().collect();
+        assert!(row.indent + text.trim_end().len() <= 20, "row {:?} is past column 20", text);
+    }
+    // the rows cover the whole line in order
+    let rows = h.rows_of_line(1);
+    assert_eq!(rows[0].col, 0);
+    assert!(rows.windows(2).all(|w| w[0].col < w[1].col));
+}
+
+#[test]
+fn up_and_down_move_by_visual_row(){
+    let mut h = Harness::wrapped(LONG, SoftWrap::Column(40));
+    let rows = h.rows_of_line(1);
+    let line_start = h.text_buffer.text_pos_to_offset(TextPos{row:1, col:0});
+    h.key("Down");
+    assert_eq!(h.head(), line_start);
+    h.key("Right");
+    h.key("Right");
+    h.key("Right");
+    h.key("Right");
+    h.key("Right");
+    h.key("Right");
+    // col 6 on the first row is 2 past the indent, so is col+2 on the continuation row
+    h.key("Down");
+    assert_eq!(h.head(), rows[1].offset + 2);
+    h.key("Down");
+    assert_eq!(h.head(), rows[2].offset + 2);
+    h.key("Up");
+    assert_eq!(h.head(), rows[1].offset + 2);
+    h.key("Up");
+    assert_eq!(h.head(), line_start + 6);
+}
+
+#[test]
+fn a_click_on_a_continuation_row_lands_in_that_row(){
+    let mut h = Harness::wrapped(LONG, SoftWrap::Column(40));
+    let index = h.editor.code_editor._visual_rows.iter().position(|row| row.row == 1).unwrap() + 2;
+    let row = h.editor.code_editor._visual_rows[index];
+    h.click(index, 2.2, KeyModifiers::default());
+    assert_eq!(h.head(), row.offset + 2);
+}
+
+#[test]
+fn a_grid_click_on_a_continuation_row_maps_to_its_line_column(){
+    let mut h = Harness::wrapped(LONG, SoftWrap::Column(40));
+    let index = h.editor.code_editor._visual_rows.iter().position(|row| row.row == 1).unwrap() + 1;
+    let row = h.editor.code_editor._visual_rows[index];
+    h.click(index, 3.2, KeyModifiers{shift:true, control:true, ..Default::default()});
+    // the grid runs from the cursor at the top, its bottom cursor ends in the clicked column
+    let cursors = &h.editor.code_editor.cursors.set;
+    assert_eq!(cursors.len(), 2);
+    let head = h.text_buffer.offset_to_text_pos(cursors[1].head);
+    assert_eq!((head.row, head.col), (1, row.col + 3));
+}
@@ -0,0 +1,333 @@
+// headless checks of the vim mode of the code editor, keys go in as the platform sends them
+use widgets::*;
+use makepad::*;
+
+const CODE:&str = "fn main(){\n    let foo = bar(1, 2);\n    if foo{\n        baz();\n    }\n}";
+
+struct Harness{
+    cx:Cx,
+    editor:RustEditor,
+    text_buffer:TextBuffer
+}
+
+impl Harness{
+    fn new()->Harness{
+        let mut cx = Cx::new_headless(Vec2{x:1200., y:600.});
+        set_dark_style(&mut cx);
+        let editor = RustEditor::style(&mut cx);
+        cx.headless_compile().expect("shaders compile");
+        let mut harness = Harness{cx, editor, text_buffer:TextBuffer::default()};
+        harness.load(CODE);
+        harness.send(Event::Command(CommandEvent{command:"editor.toggle_vim".to_string()}));
+        harness
+    }
+
+    fn load(&mut self, text:&str){
+        self.text_buffer = TextBuffer::default();
+        self.text_buffer.load_buffer(&text.as_bytes().to_vec());
+        self.editor.code_editor.cursors = CursorSet::new();
+        self.draw();
+        self.cx.set_key_focus(self.editor.code_editor._bg_area);
+    }
+
+    fn draw(&mut self){
+        let (editor, text_buffer) = (&mut self.editor, &self.text_buffer);
+        self.cx.headless_draw(|cx| editor.draw_rust_editor(cx, text_buffer));
+    }
+
+    fn send(&mut self, mut event:Event){
+        self.cx.event_id += 1;
+        self.editor.handle_rust_editor(&mut self.cx, &mut event, &mut self.text_buffer);
+        self.draw();
+    }
+
+    // keys like vim writes them, <Esc> <CR> <BS> <C-v> and <C-r> are the special ones
+    fn keys(&mut self, keys:&str){
+        let mut chars = keys.chars();
+        while let Some(ch) = chars.next(){
+            let (key_code, key_char, control) = if ch == '<'{
+                let name:String = chars.by_ref().take_while(|ch| *ch != '>').collect();
+                match name.as_ref(){
+                    "Esc"=>(KeyCode::Escape, '\x1b', false),
+                    "CR"=>(KeyCode::Return, '\r', false),
+                    "BS"=>(KeyCode::Backspace, '\x08', false),
+                    "C-v"=>(KeyCode::KeyV, 'v', true),
+                    "C-r"=>(KeyCode::KeyR, 'r', true),
+                    _=>panic!("unknown key <{}>", name)
+                }
+            }
+            else{
+                let key_code = KeyChord::parse(&ch.to_uppercase().to_string()).map(|chord| chord.key_code).unwrap_or(KeyCode::Unknown);
+                (key_code, ch, false)
+            };
+            self.send(Event::KeyDown(KeyEvent{
+                key_code:key_code.clone(),
+                key_char,
+                is_repeat:false,
+                modifiers:KeyModifiers{control, shift:ch.is_uppercase(), ..Default::default()},
+                time:0.
+            }));
+            // typed chars come in as text input as well
+            if !control && (!key_char.is_control() || key_code == KeyCode::Return){
+                let input = if key_code == KeyCode::Return{"\n".to_string()} else{key_char.to_string()};
+                self.send(Event::TextInput(TextInputEvent{input, replace_last:false, was_paste:false}));
+            }
+        }
+    }
+
+    fn lines(&self)->Vec<String>{
+        self.text_buffer.lines.iter().map(|line| line.iter().collect()).collect()
+    }
+
+    fn line(&self, row:usize)->String{
+        self.lines()[row].clone()
+    }
+
+    fn pos(&self)->(usize, usize){
+        let pos = self.editor.code_editor.cursors.get_last_cursor_text_pos(&self.text_buffer);
+        (pos.row, pos.col)
+    }
+
+    fn vim(&self)->&Vim{
+        &self.editor.code_editor.vim
+    }
+
+    fn register(&self, name:char)->VimRegister{
+        self.vim().registers.get(&name).cloned().unwrap_or_default()
+    }
+}
+
+#[test]
+fn vim_mode_toggles(){
+    let mut h = Harness::new();
+    assert!(h.vim().enabled);
+    h.send(Event::Command(CommandEvent{command:"editor.toggle_vim".to_string()}));
+    assert!(!h.vim().enabled);
+}
+
+#[test]
+fn parser_waits_and_multiplies_counts(){
+    // the parser waits for the rest of a command and multiplies counts
+    let parse = |keys:&str| Vim::parse(VimMode::Normal, &keys.chars().collect::<Vec<char>>());
+    assert_eq!(parse("2d"), VimParse::Pending);
+    assert_eq!(parse("\"a2d3w"), VimParse::Done(VimCommand{
+        count:Some(6),
+        register:Some('a'),
+        action:VimAction::Operate(VimOperator::Delete, VimMotion::WordStart)
+    }));
+    assert_eq!(parse("dgx"), VimParse::Invalid);
+    assert_eq!(parse("0"), VimParse::Done(VimCommand{count:None, register:None, action:VimAction::Move(VimMotion::LineStart)}));
+    assert_eq!(parse("10j"), VimParse::Done(VimCommand{count:Some(10), register:None, action:VimAction::Move(VimMotion::Down)}));
+}
+
+#[test]
+fn motions_move_without_typing(){
+    let mut h = Harness::new();
+    // motions over the tokens, keys in normal mode don't type
+    h.keys("w");
+    assert_eq!(h.pos(), (0, 3));
+    h.keys("we");
+    assert_eq!(h.pos(), (0, 8));
+    h.keys("b");
+    assert_eq!(h.pos(), (0, 7));
+    h.keys("0$");
+    assert_eq!(h.pos(), (0, 9));
+    h.keys("02w");
+    assert_eq!(h.pos(), (0, 7));
+    assert_eq!(h.lines().join("\n"), CODE);
+}
+
+#[test]
+fn paren_matching_uses_the_tokenizer_pairs(){
+    let mut h = Harness::new();
+    // paren matching uses the pairs the tokenizer found
+    h.keys("0%");
+    assert_eq!(h.pos(), (0, 8));
+    h.keys("j^f(");
+    assert_eq!(h.pos(), (1, 17));
+    h.keys("%");
+    assert_eq!(h.pos(), (1, 22));
+    h.keys("%");
+    assert_eq!(h.pos(), (1, 17));
+    h.keys("j$%");
+    assert_eq!(h.pos(), (4, 4));
+}
+
+#[test]
+fn find_till_and_go_to_line(){
+    let mut h = Harness::new();
+    // f and t with ; and , to repeat
+    h.keys("gg");
+    assert_eq!(h.pos(), (0, 0));
+    h.keys("j0fo");
+    assert_eq!(h.pos(), (1, 9));
+    h.keys(";");
+    assert_eq!(h.pos(), (1, 10));
+    h.keys(",");
+    assert_eq!(h.pos(), (1, 9));
+    h.keys("G");
+    assert_eq!(h.pos(), (5, 0));
+    h.keys("3G");
+    assert_eq!(h.pos(), (2, 4));
+}
+
+#[test]
+fn operators_take_motions_and_counts(){
+    let mut h = Harness::new();
+    // operators with motions and counts, each change undoes at once
+    h.keys("j^dw");
+    assert_eq!(h.line(1), "    foo = bar(1, 2);");
+    assert_eq!(h.register('"'), VimRegister{text:"let ".to_string(), linewise:false});
+    h.keys("u");
+    assert_eq!(h.lines().join("\n"), CODE);
+    assert_eq!(h.pos(), (1, 4));
+    h.keys("<C-r>");
+    assert_eq!(h.line(1), "    foo = bar(1, 2);");
+    h.keys("u");
+    h.keys("d2w");
+    assert_eq!(h.line(1), "    = bar(1, 2);");
+    h.keys("u2dw");
+    assert_eq!(h.line(1), "    = bar(1, 2);");
+    h.keys("u");
+    h.keys("f(dt)");
+    assert_eq!(h.line(1), "    let foo = bar);");
+    h.keys("uf(d%");
+    assert_eq!(h.line(1), "    let foo = bar;");
+    h.keys("u^D");
+    assert_eq!(h.line(1), "    ");
+    assert_eq!(h.pos(), (1, 3));
+    h.keys("ugg3x");
+    assert_eq!(h.line(0), "main(){");
+    h.keys("u");
+    assert_eq!(h.lines().join("\n"), CODE);
+}
+
+#[test]
+fn change_and_repeat(){
+    let mut h = Harness::new();
+    // change goes to insert mode, typing and all undo as one
+    h.keys("j^cwvar<Esc>");
+    assert_eq!(h.line(1), "    var foo = bar(1, 2);");
+    assert_eq!(h.vim().mode, VimMode::Normal);
+    assert_eq!(h.pos(), (1, 6));
+    // . does it again on the next word
+    h.keys("w.");
+    assert_eq!(h.line(1), "    var var = bar(1, 2);");
+    h.keys("uu");
+    assert_eq!(h.lines().join("\n"), CODE);
+    h.keys("^dw.");
+    assert_eq!(h.line(1), "    = bar(1, 2);");
+    // a count on . replaces the one of the change
+    h.keys("2.");
+    assert_eq!(h.line(1), "    (1, 2);");
+    h.keys("uuu");
+    assert_eq!(h.lines().join("\n"), CODE);
+}
+
+#[test]
+fn counted_inserts(){
+    let mut h = Harness::new();
+    // counted inserts, o keeps the indent
+    h.keys("G3ihi<Esc>");
+    assert_eq!(h.line(5), "hihihi}");
+    assert_eq!(h.pos(), (5, 5));
+    h.keys("u3Go");
+    assert_eq!(h.vim().mode, VimMode::Insert);
+    h.keys("x<Esc>");
+    assert_eq!(h.line(3), "        x");
+    h.keys("j0A;<Esc>");
+    assert_eq!(h.line(4), "        baz();;");
+    h.keys("j.");
+    assert_eq!(h.line(5), "    };");
+    h.keys("uuu");
+    assert_eq!(h.lines().join("\n"), CODE);
+    h.keys("ggrX");
+    assert_eq!(h.line(0), "Xn main(){");
+    h.keys("u");
+}
+
+#[test]
+fn lines_and_registers(){
+    let mut h = Harness::new();
+    // whole lines and registers
+    h.keys("jdd");
+    assert_eq!(h.line(1), "    if foo{");
+    assert_eq!(h.register('"'), VimRegister{text:"    let foo = bar(1, 2);\n".to_string(), linewise:true});
+    assert_eq!(h.pos(), (1, 4));
+    h.keys("p");
+    assert_eq!(h.line(2), "    let foo = bar(1, 2);");
+    assert_eq!(h.pos(), (2, 4));
+    h.keys("uu");
+    assert_eq!(h.lines().join("\n"), CODE);
+    h.keys("gg\"ayyj\"Ayy");
+    assert_eq!(h.register('a').text, "fn main(){\n    let foo = bar(1, 2);\n");
+    h.keys("G\"ap");
+    assert_eq!(&h.lines()[5..], &["}", "fn main(){", "    let foo = bar(1, 2);"]);
+    h.keys("u");
+    h.keys("ggyw");
+    assert_eq!(h.register('0').text, "fn ");
+    h.keys("jddk0\"0P");
+    assert_eq!(h.line(0), "fn fn main(){");
+    h.keys("uu");
+    h.keys("3GdG");
+    assert_eq!(h.lines(), vec!["fn main(){", "    let foo = bar(1, 2);"]);
+    assert_eq!(h.pos(), (1, 4));
+    h.keys("u");
+    assert_eq!(h.lines().join("\n"), CODE);
+}
+
+#[test]
+fn visual_modes(){
+    let mut h = Harness::new();
+    // visual modes select inclusively and take operators
+    h.keys("ggve");
+    assert_eq!(h.vim().mode, VimMode::Visual);
+    assert_eq!(h.editor.code_editor.cursors.get_last_cursor_order(), (0, 2));
+    h.keys("d");
+    assert_eq!(h.line(0), " main(){");
+    assert_eq!(h.vim().mode, VimMode::Normal);
+    h.keys("uVjy");
+    assert_eq!(h.register('"'), VimRegister{text:"fn main(){\n    let foo = bar(1, 2);\n".to_string(), linewise:true});
+    h.keys("Vjjd");
+    assert_eq!(h.line(0), "        baz();");
+    h.keys("u");
+    // visual block is a grid select, inserting on it types on every line
+    h.keys("j0<C-v>jj");
+    assert_eq!(h.vim().mode, VimMode::VisualBlock);
+    assert_eq!(h.editor.code_editor.cursors.set.len(), 3);
+    h.keys("I//<Esc>");
+    assert_eq!(&h.lines()[1..4], &["//    let foo = bar(1, 2);", "//    if foo{", "//        baz();"]);
+    assert_eq!(h.pos(), (1, 1));
+    h.keys("u");
+    assert_eq!(h.lines().join("\n"), CODE);
+    h.keys("0<C-v>jjlllx");
+    assert_eq!(&h.lines()[1..4], &["let foo = bar(1, 2);", "if foo{", "    baz();"]);
+    assert_eq!(h.register('"').text, "    \n    \n    ");
+    h.keys("u");
+}
+
+#[test]
+fn ex_command_line(){
+    let mut h = Harness::new();
+    // the ex command line
+    h.keys(":%s/foo/qux/g<CR>");
+    assert_eq!(h.line(1), "    let qux = bar(1, 2);");
+    assert_eq!(h.line(2), "    if qux{");
+    h.keys("u");
+    assert_eq!(h.lines().join("\n"), CODE);
+    h.keys(":4<CR>");
+    assert_eq!(h.pos(), (3, 8));
+    h.keys(":nope<CR>");
+    assert_eq!(h.vim().message, "not an editor command: nope");
+    assert_eq!(h.vim().status(), "not an editor command: nope");
+    h.keys(":s/zzz/y/<CR>");
+    assert_eq!(h.vim().message, "not found: zzz");
+    h.keys(":d<CR>");
+    assert_eq!(h.line(3), "    }");
+    h.keys("u:se<BS><BS><BS>");
+    assert_eq!(h.vim().mode, VimMode::Normal);
+    h.keys("2d");
+    assert_eq!(h.vim().status(), "2d");
+    h.keys("<Esc>");
+    assert_eq!(h.lines().join("\n"), CODE);
+}
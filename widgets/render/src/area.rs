@@ -15,12 +15,22 @@ pub struct DrawListArea{
     pub redraw_id:u64
 }
 
+// instance areas drawn together into several draw calls, like a text with glyphs from
+// fallback fonts. Writes go to all of them, reads and rects come from the first
+#[derive(Clone, Default, Debug, PartialEq, Copy)]
+pub struct GroupArea{
+    pub draw_list_id:usize,
+    pub group_id:usize,
+    pub redraw_id:u64
+}
+
 #[derive(Clone, Debug, PartialEq, Copy)]
 pub enum Area{
     Empty,
     All,
     Instance(InstanceArea),
-    DrawList(DrawListArea)
+    DrawList(DrawListArea),
+    Group(GroupArea)
 }
 
 impl Default for Area{
@@ -52,6 +62,22 @@ impl Area{
         false
     }
 
+    // the instance areas of a group, the area itself otherwise
+    pub fn group_members(&self, cx:&Cx)->Vec<Area>{
+        if let Area::Group(group) = self{
+            let draw_list = &cx.draw_lists[group.draw_list_id];
+            if draw_list.redraw_id != group.redraw_id{
+                return Vec::new()
+            }
+            return draw_list.area_groups[group.group_id].iter().map(|inst| Area::Instance(*inst)).collect()
+        }
+        vec![*self]
+    }
+
+    fn group_first(&self, cx:&Cx)->Area{
+        self.group_members(cx).first().cloned().unwrap_or(Area::Empty)
+    }
+
     pub fn is_valid(&self, cx:&Cx)->bool{
         return match self{
             Area::Instance(inst)=>{
@@ -71,12 +97,15 @@ impl Area{
                 }
                 return true
             },
+            Area::Group(_)=>self.group_first(cx).is_valid(cx),
             _=>false,
         }
     }
     
     pub fn get_scroll_pos(&self, cx:&Cx)->Vec2{
         return match self{
+            Area::Group(_)=>self.group_first(cx).get_scroll_pos(cx),
+
             Area::Instance(inst)=>{
                 let draw_list = &cx.draw_lists[inst.draw_list_id];
                 if draw_list.redraw_id != inst.redraw_id {
@@ -97,6 +126,8 @@ impl Area{
     pub fn get_rect_scrolled(&self, cx:&Cx)->Rect{
 
         return match self{
+            Area::Group(_)=>self.group_first(cx).get_rect_scrolled(cx),
+
             Area::Instance(inst)=>{
                 if inst.instance_count == 0{
                     println!("get_rect called on instance_count ==0 area pointer, use mark/sweep correctly!");
@@ -134,6 +165,8 @@ impl Area{
 
     pub fn abs_to_rel_scrolled(&self, cx:&Cx, abs:Vec2)->Vec2{
         return match self{
+            Area::Group(_)=>self.group_first(cx).abs_to_rel_scrolled(cx, abs),
+
             Area::Instance(inst)=>{
                 if inst.instance_count == 0{
                     println!("abs_to_rel_scroll called on instance_count ==0 area pointer, use mark/sweep correctly!");
@@ -172,6 +205,8 @@ impl Area{
 
     pub fn get_rect_not_scrolled(&self, cx:&Cx)->Rect{
         return match self{
+            Area::Group(_)=>self.group_first(cx).get_rect_not_scrolled(cx),
+
             Area::Instance(inst)=>{
                 if inst.instance_count == 0{
                     println!("get_rect called on instance_count ==0 area pointer, use mark/sweep correctly!");
@@ -209,6 +244,8 @@ impl Area{
 
     pub fn set_rect(&self, cx:&mut Cx, rect:&Rect){
          match self{
+            Area::Group(_)=>self.group_first(cx).set_rect(cx, rect),
+
             Area::Instance(inst)=>{
                 let draw_list = &mut cx.draw_lists[inst.draw_list_id];
                 if draw_list.redraw_id != inst.redraw_id {
@@ -241,6 +278,8 @@ impl Area{
 
     pub fn get_prop_offset(&self, cx:&Cx, prop_name:&str)->usize{
         match self{
+            Area::Group(_)=>return self.group_first(cx).get_prop_offset(cx, prop_name),
+
             Area::Instance(inst)=>{
                 let draw_list = &cx.draw_lists[inst.draw_list_id];
                 let draw_call = &draw_list.draw_calls[inst.draw_call_id];
@@ -259,6 +298,8 @@ impl Area{
 
     pub fn get_read_ref<'a>(&self, cx:&'a Cx)->Option<InstanceReadRef<'a>>{
         match self{
+            Area::Group(_)=>return self.group_first(cx).get_read_ref(cx),
+
             Area::Instance(inst)=>{
                 let draw_list = &cx.draw_lists[inst.draw_list_id];
                 let draw_call = &draw_list.draw_calls[inst.draw_call_id];
@@ -283,6 +324,8 @@ impl Area{
 
     pub fn get_write_ref<'a>(&self, cx:&'a mut Cx)->Option<InstanceWriteRef<'a>>{
         match self{
+            Area::Group(_)=>return self.group_first(cx).get_write_ref(cx),
+
             Area::Instance(inst)=>{
                 let draw_list = &mut cx.draw_lists[inst.draw_list_id];
                 let draw_call = &mut draw_list.draw_calls[inst.draw_call_id];
@@ -308,6 +351,12 @@ impl Area{
     }
 
     pub fn write_float(&self, cx:&mut Cx, prop_name:&str, value:f32){
+        if let Area::Group(_) = self{
+            for member in self.group_members(cx){
+                member.write_float(cx, prop_name, value);
+            }
+            return
+        }
         let prop_offset = self.get_prop_offset(cx, prop_name);
         let write = self.get_write_ref(cx);
         if let Some(write) = write{
@@ -329,6 +378,12 @@ impl Area{
     }
 
    pub fn write_vec2(&self, cx:&mut Cx, prop_name:&str, value:Vec2){
+        if let Area::Group(_) = self{
+            for member in self.group_members(cx){
+                member.write_vec2(cx, prop_name, value);
+            }
+            return
+        }
         let prop_offset = self.get_prop_offset(cx, prop_name);
         let write = self.get_write_ref(cx);
         if let Some(write) = write{
@@ -354,6 +409,12 @@ impl Area{
     }
 
    pub fn write_vec3(&self, cx:&mut Cx, prop_name:&str, value:Vec3){
+        if let Area::Group(_) = self{
+            for member in self.group_members(cx){
+                member.write_vec3(cx, prop_name, value);
+            }
+            return
+        }
         let prop_offset = self.get_prop_offset(cx, prop_name);
         let write = self.get_write_ref(cx);
         if let Some(write) = write{
@@ -381,6 +442,12 @@ impl Area{
     }
 
    pub fn write_vec4(&self, cx:&mut Cx, prop_name:&str, value:Vec4){
+        if let Area::Group(_) = self{
+            for member in self.group_members(cx){
+                member.write_vec4(cx, prop_name, value);
+            }
+            return
+        }
         let prop_offset = self.get_prop_offset(cx, prop_name);
         let write = self.get_write_ref(cx);
         if let Some(write) = write{
@@ -410,6 +477,12 @@ impl Area{
     }
 
     pub fn write_color(&self, cx:&mut Cx, prop_name:&str, value:Color){
+        if let Area::Group(_) = self{
+            for member in self.group_members(cx){
+                member.write_color(cx, prop_name, value);
+            }
+            return
+        }
         let prop_offset = self.get_prop_offset(cx, prop_name);
         let write = self.get_write_ref(cx);
         if let Some(write) = write{
@@ -0,0 +1,646 @@
+// Unicode Bidirectional Algorithm (UAX #9): resolves embedding levels per char
+// and reorders a line into visual runs. Bracket pairs (N0) are not resolved,
+// and the class table covers the scripts and symbols text is likely to use.
+
+use self::BidiClass::*;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BidiClass{
+    L, R, AL, EN, ES, ET, AN, CS, NSM, BN, B, S, WS, ON,
+    LRE, LRO, RLE, RLO, PDF, LRI, RLI, FSI, PDI
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TextDirection{
+    Auto, // from the first strong char of each paragraph
+    Ltr,
+    Rtl
+}
+
+impl Default for TextDirection{
+    fn default()->Self{
+        TextDirection::Auto
+    }
+}
+
+// resolved levels per char, odd levels are right to left
+#[derive(Clone, Default, Debug)]
+pub struct BidiLevels{
+    pub levels:Vec<u8>,
+    pub para_levels:Vec<u8>
+}
+
+const BIDI_MAX_DEPTH:u8 = 125;
+
+// non L ranges above ascii
+const BIDI_RANGES:[(u32, u32, BidiClass);251] = [
+    (0x80, 0x84, BN), (0x85, 0x85, B), (0x86, 0x9f, BN), (0xa0, 0xa0, CS), (0xa1, 0xa1, ON), (0xa2, 0xa5, ET),
+    (0xa6, 0xa9, ON), (0xab, 0xac, ON), (0xad, 0xad, BN), (0xae, 0xaf, ON), (0xb0, 0xb1, ET),
+    (0xb2, 0xb3, EN), (0xb4, 0xb4, ON), (0xb6, 0xb8, ON), (0xb9, 0xb9, EN), (0xbb, 0xbf, ON),
+    (0xd7, 0xd7, ON), (0xf7, 0xf7, ON), (0x2b9, 0x2ba, ON), (0x2c2, 0x2cf, ON), (0x2d2, 0x2df, ON),
+    (0x2e5, 0x2ed, ON), (0x2ef, 0x2ff, ON), (0x300, 0x36f, NSM), (0x374, 0x375, ON), (0x37e, 0x37e, ON),
+    (0x384, 0x385, ON), (0x387, 0x387, ON), (0x3f6, 0x3f6, ON), (0x483, 0x489, NSM), (0x58a, 0x58a, ON),
+    (0x58d, 0x58e, ON), (0x58f, 0x58f, ET), (0x590, 0x590, R), (0x591, 0x5bd, NSM), (0x5be, 0x5be, R),
+    (0x5bf, 0x5bf, NSM), (0x5c0, 0x5c0, R), (0x5c1, 0x5c2, NSM), (0x5c3, 0x5c3, R), (0x5c4, 0x5c5, NSM),
+    (0x5c6, 0x5c6, R), (0x5c7, 0x5c7, NSM), (0x5c8, 0x5ff, R), (0x600, 0x605, AN), (0x606, 0x607, ON),
+    (0x608, 0x608, AL), (0x609, 0x60a, ET), (0x60b, 0x60b, AL), (0x60c, 0x60c, CS), (0x60d, 0x60d, AL),
+    (0x60e, 0x60f, ON), (0x610, 0x61a, NSM), (0x61b, 0x64a, AL), (0x64b, 0x65f, NSM), (0x660, 0x669, AN),
+    (0x66a, 0x66a, ET), (0x66b, 0x66c, AN), (0x66d, 0x66f, AL), (0x670, 0x670, NSM), (0x671, 0x6d5, AL),
+    (0x6d6, 0x6dc, NSM), (0x6dd, 0x6dd, AN), (0x6de, 0x6de, ON), (0x6df, 0x6e4, NSM), (0x6e5, 0x6e6, AL),
+    (0x6e7, 0x6e8, NSM), (0x6e9, 0x6e9, ON), (0x6ea, 0x6ed, NSM), (0x6ee, 0x6ef, AL), (0x6f0, 0x6f9, EN),
+    (0x6fa, 0x710, AL), (0x711, 0x711, NSM), (0x712, 0x72f, AL), (0x730, 0x74a, NSM), (0x74b, 0x7a5, AL),
+    (0x7a6, 0x7b0, NSM), (0x7b1, 0x7bf, AL), (0x7c0, 0x7ea, R), (0x7eb, 0x7f3, NSM), (0x7f4, 0x7f5, R),
+    (0x7f6, 0x7f9, ON), (0x7fa, 0x7fc, R), (0x7fd, 0x7fd, NSM), (0x7fe, 0x815, R), (0x816, 0x819, NSM),
+    (0x81a, 0x81a, R), (0x81b, 0x823, NSM), (0x824, 0x824, R), (0x825, 0x827, NSM), (0x828, 0x828, R),
+    (0x829, 0x82d, NSM), (0x82e, 0x858, R), (0x859, 0x85b, NSM), (0x85c, 0x85f, R), (0x860, 0x88f, AL),
+    (0x890, 0x891, AN), (0x892, 0x897, AL), (0x898, 0x89f, NSM), (0x8a0, 0x8c9, AL), (0x8ca, 0x8e1, NSM),
+    (0x8e2, 0x8e2, AN), (0x8e3, 0x8ff, NSM), (0x2000, 0x200a, WS), (0x200b, 0x200d, BN), (0x200f, 0x200f, R),
+    (0x2010, 0x2027, ON), (0x2028, 0x2028, WS), (0x2029, 0x2029, B), (0x202a, 0x202a, LRE),
+    (0x202b, 0x202b, RLE), (0x202c, 0x202c, PDF), (0x202d, 0x202d, LRO), (0x202e, 0x202e, RLO),
+    (0x202f, 0x202f, CS), (0x2030, 0x2034, ET), (0x2035, 0x2043, ON), (0x2044, 0x2044, CS),
+    (0x2045, 0x205e, ON), (0x205f, 0x205f, WS), (0x2060, 0x2065, BN), (0x2066, 0x2066, LRI),
+    (0x2067, 0x2067, RLI), (0x2068, 0x2068, FSI), (0x2069, 0x2069, PDI), (0x206a, 0x206f, BN),
+    (0x2070, 0x2070, EN), (0x2074, 0x2079, EN), (0x207a, 0x207b, ES), (0x207c, 0x207e, ON),
+    (0x2080, 0x2089, EN), (0x208a, 0x208b, ES), (0x208c, 0x208e, ON), (0x20a0, 0x20cf, ET),
+    (0x20d0, 0x20ff, NSM), (0x212e, 0x212e, ET), (0x2150, 0x215f, ON), (0x2189, 0x218b, ON),
+    (0x2190, 0x2211, ON), (0x2212, 0x2212, ES), (0x2213, 0x2213, ET), (0x2214, 0x2487, ON),
+    (0x2488, 0x249b, EN), (0x24ea, 0x26ab, ON), (0x26ad, 0x27ff, ON), (0x2900, 0x2bff, ON),
+    (0x2ce5, 0x2cea, ON), (0x2cef, 0x2cf1, NSM), (0x2cf9, 0x2cff, ON), (0x2de0, 0x2dff, NSM),
+    (0x2e00, 0x2fff, ON), (0x3000, 0x3000, WS), (0x3001, 0x3004, ON), (0x3008, 0x3020, ON),
+    (0x302a, 0x302d, NSM), (0x3030, 0x3030, ON), (0x303d, 0x303f, ON), (0x3099, 0x309a, NSM),
+    (0x309b, 0x309c, ON), (0x30a0, 0x30a0, ON), (0x30fb, 0x30fb, ON), (0x4dc0, 0x4dff, ON),
+    (0xa490, 0xa4c6, ON), (0xa66f, 0xa672, NSM), (0xa674, 0xa67d, NSM), (0xa69e, 0xa69f, NSM),
+    (0xa700, 0xa721, ON), (0xa788, 0xa788, ON), (0xfb1d, 0xfb1d, R), (0xfb1e, 0xfb1e, NSM),
+    (0xfb1f, 0xfb28, R), (0xfb29, 0xfb29, ES), (0xfb2a, 0xfb4f, R), (0xfb50, 0xfd3d, AL),
+    (0xfd3e, 0xfd4f, ON), (0xfd50, 0xfdce, AL), (0xfdcf, 0xfdcf, ON), (0xfdd0, 0xfdfc, AL),
+    (0xfdfd, 0xfdff, ON), (0xfe00, 0xfe0f, NSM), (0xfe10, 0xfe19, ON), (0xfe20, 0xfe2f, NSM),
+    (0xfe30, 0xfe4f, ON), (0xfe50, 0xfe50, CS), (0xfe51, 0xfe51, ON), (0xfe52, 0xfe52, CS),
+    (0xfe54, 0xfe54, ON), (0xfe55, 0xfe55, CS), (0xfe56, 0xfe5e, ON), (0xfe5f, 0xfe5f, ET),
+    (0xfe60, 0xfe61, ON), (0xfe62, 0xfe63, ES), (0xfe64, 0xfe66, ON), (0xfe68, 0xfe68, ON),
+    (0xfe69, 0xfe6a, ET), (0xfe6b, 0xfe6b, ON), (0xfe70, 0xfefe, AL), (0xfeff, 0xfeff, BN),
+    (0xff01, 0xff02, ON), (0xff03, 0xff05, ET), (0xff06, 0xff0a, ON), (0xff0b, 0xff0b, ES),
+    (0xff0c, 0xff0c, CS), (0xff0d, 0xff0d, ES), (0xff0e, 0xff0f, CS), (0xff10, 0xff19, EN),
+    (0xff1a, 0xff1a, CS), (0xff1b, 0xff20, ON), (0xff3b, 0xff40, ON), (0xff5b, 0xff65, ON),
+    (0xffe0, 0xffe1, ET), (0xffe2, 0xffe4, ON), (0xffe5, 0xffe6, ET), (0xffe8, 0xffee, ON),
+    (0xfff9, 0xfffd, ON), (0x10800, 0x10cff, R), (0x10d00, 0x10d23, AL), (0x10d24, 0x10d27, NSM),
+    (0x10d28, 0x10d2f, R), (0x10d30, 0x10d39, AN), (0x10d3a, 0x10e5f, R), (0x10e60, 0x10e7e, AN),
+    (0x10e7f, 0x10f2f, R), (0x10f30, 0x10f45, AL), (0x10f46, 0x10f50, NSM), (0x10f51, 0x10f59, AL),
+    (0x10f5a, 0x10fff, R), (0x1e800, 0x1e8cf, R), (0x1e8d0, 0x1e8d6, NSM), (0x1e8d7, 0x1e943, R),
+    (0x1e944, 0x1e94a, NSM), (0x1e94b, 0x1ec6f, R), (0x1ec70, 0x1ecbf, AL), (0x1ecc0, 0x1ecff, R),
+    (0x1ed00, 0x1ed4f, AL), (0x1ed50, 0x1edff, R), (0x1ee00, 0x1eeef, AL), (0x1eef0, 0x1eef1, ON),
+    (0x1eef2, 0x1eeff, AL), (0x1ef00, 0x1efff, R), (0x1f000, 0x1f0ff, ON), (0x1f100, 0x1f10a, EN),
+    (0x1f10b, 0x1f10f, ON), (0x1f12f, 0x1f12f, ON), (0x1f16a, 0x1f16f, ON), (0x1f260, 0x1fbef, ON),
+    (0x1fbf0, 0x1fbf9, EN), (0x1fbfa, 0x1fbff, ON), (0xe0001, 0xe0001, BN), (0xe0020, 0xe007f, BN),
+    (0xe0100, 0xe01ef, NSM),
+];
+
+pub fn bidi_class(c:char)->BidiClass{
+    let u = c as u32;
+    if u < 0x80{
+        return match u{
+            0x09 | 0x0b | 0x1f=>S,
+            0x0a | 0x0d | 0x1c..=0x1e=>B,
+            0x0c | 0x20=>WS,
+            0x00..=0x1f | 0x7f=>BN,
+            0x23..=0x25=>ET,
+            0x2b | 0x2d=>ES,
+            0x2c | 0x2e | 0x2f | 0x3a=>CS,
+            0x30..=0x39=>EN,
+            0x41..=0x5a | 0x61..=0x7a=>L,
+            _=>ON
+        }
+    }
+    let mut lo = 0;
+    let mut hi = BIDI_RANGES.len();
+    while lo < hi{
+        let mid = (lo + hi) / 2;
+        let (start, end, class) = BIDI_RANGES[mid];
+        if u < start{
+            hi = mid;
+        }
+        else if u > end{
+            lo = mid + 1;
+        }
+        else{
+            return class
+        }
+    }
+    L
+}
+
+// true if the text needs the bidi algorithm at all
+pub fn bidi_has_rtl(chars:&[char])->bool{
+    chars.iter().any(|c| match bidi_class(*c){
+        R | AL | AN | RLE | RLO | RLI | FSI=>true,
+        _=>false
+    })
+}
+
+// true for locales like ar_EG.UTF-8 or he whose language is written right to left
+pub fn locale_is_rtl(locale:&str)->bool{
+    let lang = locale.split(|c| c == '_' || c == '-' || c == '.' || c == '@').next().unwrap_or("");
+    ["ar", "arc", "ckb", "dv", "fa", "he", "iw", "ps", "sd", "ug", "ur", "yi"].contains(&lang.to_lowercase().as_str())
+}
+
+fn is_isolate_initiator(class:BidiClass)->bool{
+    class == LRI || class == RLI || class == FSI
+}
+
+fn is_removed(class:BidiClass)->bool{
+    match class{
+        LRE | RLE | LRO | RLO | PDF | BN=>true,
+        _=>false
+    }
+}
+
+fn is_neutral(class:BidiClass)->bool{
+    match class{
+        B | S | WS | ON | LRI | RLI | FSI | PDI=>true,
+        _=>false
+    }
+}
+
+// P2, P3: 1 if the first strong char outside isolates is right to left
+fn bidi_first_strong(classes:&[BidiClass])->Option<u8>{
+    let mut isolates = 0;
+    for class in classes{
+        match class{
+            L if isolates == 0=>return Some(0),
+            R | AL if isolates == 0=>return Some(1),
+            LRI | RLI | FSI=>isolates += 1,
+            PDI if isolates > 0=>isolates -= 1,
+            B=>break,
+            _=>()
+        }
+    }
+    None
+}
+
+// BD9: the matching PDI of every isolate initiator
+fn bidi_matching_pdis(classes:&[BidiClass])->Vec<Option<usize>>{
+    let mut out = vec![None; classes.len()];
+    let mut stack = Vec::new();
+    for (i, class) in classes.iter().enumerate(){
+        if is_isolate_initiator(*class){
+            stack.push(i);
+        }
+        else if *class == PDI{
+            if let Some(open) = stack.pop(){
+                out[open] = Some(i);
+            }
+        }
+    }
+    out
+}
+
+pub fn bidi_levels(chars:&[char], direction:TextDirection)->BidiLevels{
+    let mut out = BidiLevels{
+        levels:Vec::with_capacity(chars.len()),
+        para_levels:Vec::with_capacity(chars.len())
+    };
+    // paragraphs end after a paragraph separator
+    let mut start = 0;
+    while start < chars.len(){
+        let mut end = start;
+        while end < chars.len() && bidi_class(chars[end]) != B{
+            end += 1;
+        }
+        end = (end + 1).min(chars.len());
+        let classes:Vec<BidiClass> = chars[start..end].iter().map(|c| bidi_class(*c)).collect();
+        let para_level = match direction{
+            TextDirection::Ltr=>0,
+            TextDirection::Rtl=>1,
+            TextDirection::Auto=>bidi_first_strong(&classes).unwrap_or(0)
+        };
+        out.levels.extend(bidi_paragraph_levels(&classes, para_level));
+        out.para_levels.extend(std::iter::repeat(para_level).take(end - start));
+        start = end;
+    }
+    out
+}
+
+#[derive(Clone, Copy)]
+struct BidiStatus{
+    level:u8,
+    override_class:Option<BidiClass>,
+    isolate:bool
+}
+
+fn bidi_paragraph_levels(orig:&[BidiClass], para_level:u8)->Vec<u8>{
+    let len = orig.len();
+    let mut classes = orig.to_vec();
+    let mut levels = vec![para_level; len];
+    let matching = bidi_matching_pdis(orig);
+
+    // X1 - X8, explicit embeddings, overrides and isolates
+    let mut stack = vec![BidiStatus{level:para_level, override_class:None, isolate:false}];
+    let mut overflow_isolates = 0;
+    let mut overflow_embeddings = 0;
+    let mut valid_isolates = 0;
+    for i in 0..len{
+        let top = *stack.last().unwrap();
+        match orig[i]{
+            RLE | LRE | RLO | LRO=>{
+                let rtl = orig[i] == RLE || orig[i] == RLO;
+                let level = if rtl{(top.level + 1) | 1}else{(top.level + 2) & !1};
+                if level <= BIDI_MAX_DEPTH && overflow_isolates == 0 && overflow_embeddings == 0{
+                    stack.push(BidiStatus{
+                        level:level,
+                        override_class:match orig[i]{RLO=>Some(R), LRO=>Some(L), _=>None},
+                        isolate:false
+                    });
+                }
+                else if overflow_isolates == 0{
+                    overflow_embeddings += 1;
+                }
+                levels[i] = top.level;
+            },
+            RLI | LRI | FSI=>{
+                levels[i] = top.level;
+                if let Some(class) = top.override_class{
+                    classes[i] = class;
+                }
+                let rtl = match orig[i]{
+                    RLI=>true,
+                    LRI=>false,
+                    _=>{
+                        let end = matching[i].unwrap_or(len);
+                        bidi_first_strong(&orig[i + 1..end]) == Some(1)
+                    }
+                };
+                let level = if rtl{(top.level + 1) | 1}else{(top.level + 2) & !1};
+                if level <= BIDI_MAX_DEPTH && overflow_isolates == 0 && overflow_embeddings == 0{
+                    valid_isolates += 1;
+                    stack.push(BidiStatus{level:level, override_class:None, isolate:true});
+                }
+                else{
+                    overflow_isolates += 1;
+                }
+            },
+            PDI=>{
+                if overflow_isolates > 0{
+                    overflow_isolates -= 1;
+                }
+                else if valid_isolates > 0{
+                    overflow_embeddings = 0;
+                    while !stack.last().unwrap().isolate{
+                        stack.pop();
+                    }
+                    stack.pop();
+                    valid_isolates -= 1;
+                }
+                let top = *stack.last().unwrap();
+                levels[i] = top.level;
+                if let Some(class) = top.override_class{
+                    classes[i] = class;
+                }
+            },
+            PDF=>{
+                if overflow_isolates > 0{
+                }
+                else if overflow_embeddings > 0{
+                    overflow_embeddings -= 1;
+                }
+                else if !top.isolate && stack.len() >= 2{
+                    stack.pop();
+                }
+                levels[i] = top.level;
+            },
+            B=>{
+                levels[i] = para_level;
+            },
+            BN=>{
+                levels[i] = top.level;
+            },
+            _=>{
+                levels[i] = top.level;
+                if let Some(class) = top.override_class{
+                    classes[i] = class;
+                }
+            }
+        }
+    }
+    // X9, removed chars are kept as BN and skipped by the rules below
+    for i in 0..len{
+        if is_removed(orig[i]){
+            classes[i] = BN;
+        }
+    }
+
+    // X10, level runs chained into isolating run sequences
+    let mut runs:Vec<Vec<usize>> = Vec::new();
+    let mut last_level = None;
+    for i in 0..len{
+        if classes[i] == BN{
+            continue
+        }
+        if last_level != Some(levels[i]){
+            runs.push(Vec::new());
+            last_level = Some(levels[i]);
+        }
+        runs.last_mut().unwrap().push(i);
+    }
+    let mut sequences:Vec<Vec<usize>> = Vec::new();
+    let mut run_used = vec![false; runs.len()];
+    for r in 0..runs.len(){
+        if run_used[r]{
+            continue
+        }
+        let mut seq = Vec::new();
+        let mut run = r;
+        loop{
+            run_used[run] = true;
+            seq.extend_from_slice(&runs[run]);
+            let last = *seq.last().unwrap();
+            // an isolate initiator continues with the run of its matching PDI
+            let next = if is_isolate_initiator(orig[last]){
+                matching[last].and_then(|pdi| runs.iter().position(|run| run[0] == pdi))
+            }
+            else{
+                None
+            };
+            if let Some(next) = next{
+                run = next;
+            }
+            else{
+                break
+            }
+        }
+        sequences.push(seq);
+    }
+
+    for seq in &sequences{
+        let level = levels[seq[0]];
+        let first = seq[0];
+        let last = *seq.last().unwrap();
+        let before = (0..first).rev().find(|i| classes[*i] != BN).map_or(para_level, |i| levels[i]);
+        let after = if is_isolate_initiator(orig[last]){
+            para_level
+        }
+        else{
+            (last + 1..len).find(|i| classes[*i] != BN).map_or(para_level, |i| levels[i])
+        };
+        let sos = if level.max(before) & 1 == 1{R}else{L};
+        let eos = if level.max(after) & 1 == 1{R}else{L};
+        bidi_resolve_sequence(&mut classes, &mut levels, seq, sos, eos);
+    }
+
+    // removed chars take the level of what precedes them
+    for i in 0..len{
+        if is_removed(orig[i]){
+            levels[i] = if i > 0{levels[i - 1]}else{para_level};
+        }
+    }
+    levels
+}
+
+// W1 - W7, N1 - N2 and I1 - I2 on one isolating run sequence
+fn bidi_resolve_sequence(classes:&mut [BidiClass], levels:&mut [u8], seq:&[usize], sos:BidiClass, eos:BidiClass){
+    let mut t:Vec<BidiClass> = seq.iter().map(|i| classes[*i]).collect();
+    let n = t.len();
+    // W1
+    for k in 0..n{
+        if t[k] == NSM{
+            t[k] = if k == 0{sos}else if is_isolate_initiator(t[k - 1]) || t[k - 1] == PDI{ON}else{t[k - 1]};
+        }
+    }
+    // W2, W3
+    let mut strong = sos;
+    for k in 0..n{
+        match t[k]{
+            L | R | AL=>strong = t[k],
+            EN if strong == AL=>t[k] = AN,
+            _=>()
+        }
+    }
+    for k in 0..n{
+        if t[k] == AL{
+            t[k] = R;
+        }
+    }
+    // W4
+    for k in 1..n.saturating_sub(1){
+        if t[k] == ES && t[k - 1] == EN && t[k + 1] == EN{
+            t[k] = EN;
+        }
+        else if t[k] == CS && (t[k - 1] == EN || t[k - 1] == AN) && t[k + 1] == t[k - 1]{
+            t[k] = t[k - 1];
+        }
+    }
+    // W5
+    let mut k = 0;
+    while k < n{
+        if t[k] == ET{
+            let mut end = k;
+            while end < n && t[end] == ET{
+                end += 1;
+            }
+            if (k > 0 && t[k - 1] == EN) || (end < n && t[end] == EN){
+                for e in k..end{
+                    t[e] = EN;
+                }
+            }
+            k = end;
+        }
+        else{
+            k += 1;
+        }
+    }
+    // W6
+    for k in 0..n{
+        if t[k] == ES || t[k] == ET || t[k] == CS{
+            t[k] = ON;
+        }
+    }
+    // W7
+    let mut strong = sos;
+    for k in 0..n{
+        match t[k]{
+            L | R=>strong = t[k],
+            EN if strong == L=>t[k] = L,
+            _=>()
+        }
+    }
+    // N1, N2, numbers count as R
+    let embedding = if levels[seq[0]] & 1 == 1{R}else{L};
+    let strong_of = |c:BidiClass| if c == EN || c == AN{R}else{c};
+    let mut k = 0;
+    while k < n{
+        if is_neutral(t[k]){
+            let mut end = k;
+            while end < n && is_neutral(t[end]){
+                end += 1;
+            }
+            let before = if k == 0{sos}else{strong_of(t[k - 1])};
+            let after = if end == n{eos}else{strong_of(t[end])};
+            let class = if before == after{before}else{embedding};
+            for e in k..end{
+                t[e] = class;
+            }
+            k = end;
+        }
+        else{
+            k += 1;
+        }
+    }
+    // I1, I2
+    for k in 0..n{
+        let i = seq[k];
+        classes[i] = t[k];
+        if levels[i] & 1 == 0{
+            match t[k]{
+                R=>levels[i] += 1,
+                AN | EN=>levels[i] += 2,
+                _=>()
+            }
+        }
+        else if t[k] == L || t[k] == EN || t[k] == AN{
+            levels[i] += 1;
+        }
+    }
+}
+
+// L1 and L2 for one line, the logical runs as (start, end, level) in visual order
+pub fn bidi_line_runs(chars:&[char], levels:&[u8], para_level:u8)->Vec<(usize, usize, u8)>{
+    let mut levels = levels.to_vec();
+    // L1, separators and trailing whitespace go back to the paragraph level
+    let mut trailing = true;
+    for i in (0..chars.len()).rev(){
+        match bidi_class(chars[i]){
+            S | B=>{
+                levels[i] = para_level;
+                trailing = true;
+            },
+            WS | LRI | RLI | FSI | PDI | BN | LRE | RLE | LRO | RLO | PDF=>{
+                if trailing{
+                    levels[i] = para_level;
+                }
+            },
+            _=>trailing = false
+        }
+    }
+    let mut runs:Vec<(usize, usize, u8)> = Vec::new();
+    for i in 0..levels.len(){
+        match runs.last_mut(){
+            Some(run) if run.2 == levels[i]=>run.1 = i + 1,
+            _=>runs.push((i, i + 1, levels[i]))
+        }
+    }
+    // L2, from the highest level down to the lowest odd one reverse every sequence at that level or higher
+    let highest = runs.iter().map(|r| r.2).max().unwrap_or(0);
+    let lowest_odd = runs.iter().map(|r| r.2 | 1).min().unwrap_or(1);
+    let mut level = highest;
+    while level >= lowest_odd && level > 0{
+        let mut i = 0;
+        while i < runs.len(){
+            if runs[i].2 >= level{
+                let mut end = i;
+                while end < runs.len() && runs[end].2 >= level{
+                    end += 1;
+                }
+                runs[i..end].reverse();
+                i = end;
+            }
+            else{
+                i += 1;
+            }
+        }
+        level -= 1;
+    }
+    runs
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn chars(text:&str)->Vec<char>{
+        text.chars().collect()
+    }
+
+    fn levels(text:&str, direction:TextDirection)->Vec<u8>{
+        bidi_levels(&chars(text), direction).levels
+    }
+
+    // the text of a line in visual order
+    fn visual(text:&str, direction:TextDirection)->String{
+        let chars = chars(text);
+        let bidi = bidi_levels(&chars, direction);
+        let mut out = String::new();
+        for (start, end, level) in bidi_line_runs(&chars, &bidi.levels, bidi.para_levels[0]){
+            if level & 1 == 1{
+                out.extend(chars[start..end].iter().rev());
+            }
+            else{
+                out.extend(chars[start..end].iter());
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn classes(){
+        for (c, class) in [
+            ('a', L), ('\u{5d0}', R), ('\u{627}', AL), ('1', EN), ('\u{661}', AN), ('+', ES), ('$', ET),
+            (',', CS), ('\u{301}', NSM), ('\u{200b}', BN), ('\n', B), ('\t', S), (' ', WS), ('!', ON),
+            ('\u{202b}', RLE), ('\u{202c}', PDF), ('\u{2067}', RLI), ('\u{2069}', PDI), ('\u{4e2d}', L)
+        ].iter(){
+            assert_eq!(bidi_class(*c), *class, "{:?}", c);
+        }
+        assert!(bidi_has_rtl(&chars("abc \u{5d0}")));
+        assert!(!bidi_has_rtl(&chars("abc 123")));
+    }
+
+    #[test]
+    fn paragraph_level_comes_from_the_first_strong_char(){
+        let bidi = bidi_levels(&chars("123 \u{5d0}\u{5d1} abc\nabc \u{5d0}"), TextDirection::Auto);
+        assert_eq!(bidi.para_levels[0], 1);
+        assert_eq!(*bidi.para_levels.last().unwrap(), 0);
+        assert_eq!(bidi_levels(&chars("abc"), TextDirection::Rtl).para_levels[0], 1);
+        assert_eq!(bidi_levels(&chars("\u{5d0}"), TextDirection::Ltr).para_levels[0], 0);
+    }
+
+    #[test]
+    fn resolved_levels(){
+        // rtl in ltr, the spaces between go with the paragraph
+        assert_eq!(levels("ab \u{5d0}\u{5d1} cd", TextDirection::Auto), vec![0, 0, 0, 1, 1, 0, 0, 0]);
+        // ltr in rtl goes up to an even level, numbers after it are ltr too
+        assert_eq!(levels("\u{5d0} ab 12", TextDirection::Auto), vec![1, 1, 2, 2, 2, 2, 2]);
+        // numbers after rtl stay numbers, they go up to an even level as well
+        assert_eq!(levels("\u{5d0} 12", TextDirection::Auto), vec![1, 1, 2, 2]);
+        // arabic numbers after arabic letters
+        assert_eq!(levels("\u{627}\u{661}\u{662}", TextDirection::Auto), vec![1, 2, 2]);
+        // a mark takes the level of its base
+        assert_eq!(levels("\u{5d0}\u{5b8}a", TextDirection::Ltr), vec![1, 1, 0]);
+        // embeddings and isolates push a level, the controls themselves follow their surroundings
+        let embedded = levels("a\u{202b}bc\u{202c}d", TextDirection::Ltr);
+        assert_eq!((embedded[2], embedded[3], embedded[5]), (1 + 1, 1 + 1, 0));
+        let isolated = levels("a\u{2067}b\u{2069}c", TextDirection::Ltr);
+        assert_eq!(isolated, vec![0, 0, 2, 0, 0]);
+    }
+
+    #[test]
+    fn line_runs_come_out_in_visual_order(){
+        let text = chars("ab \u{5d0}\u{5d1} cd");
+        let bidi = bidi_levels(&text, TextDirection::Auto);
+        assert_eq!(bidi_line_runs(&text, &bidi.levels, 0), vec![(0, 3, 0), (3, 5, 1), (5, 8, 0)]);
+        assert_eq!(visual("ab \u{5d0}\u{5d1} cd", TextDirection::Auto), "ab \u{5d1}\u{5d0} cd");
+        // in an rtl paragraph ltr runs and numbers keep their order but move left
+        assert_eq!(visual("\u{5d0}\u{5d1} ab 12", TextDirection::Auto), "ab 12 \u{5d1}\u{5d0}");
+        assert_eq!(visual("\u{5d0}\u{5d1} 12 \u{5d2}", TextDirection::Auto), "\u{5d2} 12 \u{5d1}\u{5d0}");
+        // trailing whitespace stays at the end of the paragraph direction
+        let text = chars("\u{5d0}\u{5d1}  ");
+        let bidi = bidi_levels(&text, TextDirection::Ltr);
+        assert_eq!(bidi_line_runs(&text, &bidi.levels, 0), vec![(0, 2, 1), (2, 4, 0)]);
+    }
+
+    #[test]
+    fn rtl_locales(){
+        assert!(locale_is_rtl("ar_EG.UTF-8"));
+        assert!(locale_is_rtl("he"));
+        assert!(locale_is_rtl("fa-IR"));
+        assert!(!locale_is_rtl("en_US.UTF-8"));
+        assert!(!locale_is_rtl("C"));
+        assert!(!locale_is_rtl(""));
+    }
+}
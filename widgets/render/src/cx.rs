@@ -2,10 +2,19 @@ use std::collections::HashMap;
 use std::collections::BTreeMap;
 
 pub use crate::shadergen::*;
+pub use crate::shaderlib::*;
 pub use crate::cx_fonts::*;
+pub use crate::ttf::*;
+pub use crate::bidi::*;
+pub use crate::linebreak::*;
+pub use crate::cx_images::*;
+pub use crate::cx_atlas::*;
 pub use crate::cx_turtle::*;
 pub use crate::cx_cursor::*;
 pub use crate::cx_drawlist::*;
+pub use crate::cx_batch::*;
+pub use crate::cx_profile::*;
+pub use crate::cx_keymap::*;
 pub use crate::math::*;
 pub use crate::events::*;
 pub use crate::shader::*;
@@ -32,23 +41,40 @@ pub use crate::cx_glsl::*;
 #[cfg(any(feature = "ogl", feature="mtl"))]
 pub use crate::cx_desktop::*; 
 
+#[cfg(any(feature = "ogl", feature="mtl"))]
+pub use crate::cx_shader_cache::*; 
+
 #[derive(Clone)]
 pub struct Cx{
     pub title:String,
     pub running:bool,
 
     pub fonts:Vec<Font>,
+    pub text_layouts:TextLayoutCache,
+    pub images:Vec<CxImage>,
+    pub atlases:Vec<CxAtlas>,
     pub textures_2d:Vec<Texture2D>,
+    pub textures_2d_free:Vec<usize>,
     pub uniforms:Vec<f32>,
 
     pub draw_lists: Vec<DrawList>,
     pub draw_lists_free: Vec<usize>,
     pub draw_list_stack: Vec<usize>,
     pub current_draw_list_id: usize,
+    pub batching:bool, // merge compatible draw calls when a frame is prepared
+    pub frame_stats:FrameStats,
+    pub partial_paint:bool, // only repaint the damaged parts of the window
+    pub damage:Vec<Rect>, // what changed on screen since the last paint
+    pub damage_frame:u64,
+    pub painted_size:Vec2,
+    pub painted_dpi_factor:f32,
+    pub profiler:CxProfiler,
+    pub keymap:CxKeymap,
 
     pub compiled_shaders: Vec<CompiledShader>,
     pub shaders: Vec<Shader>,
     pub shader_map: HashMap<Shader, usize>,
+    pub shader_modules: HashMap<String, ShaderModule>,
 
     pub redraw_areas:Vec<Area>,
     pub incr_areas:Vec<Area>,
@@ -63,9 +89,12 @@ pub struct Cx{
     pub key_focus:Area,
 
     pub debug_area:Area,
+    pub debug_layout:bool, // record every turtle into debug_turtles for the layout debugger
+    pub debug_turtles:Vec<DebugTurtle>,
 
     pub turtles:Vec<Turtle>,
     pub align_list:Vec<Area>,
+    pub rtl:bool, // Direction::Inline turtles walk right to left
     pub target_size:Vec2,
     pub target_dpi_factor:f32,
 
@@ -105,17 +134,31 @@ impl Default for Cx{
             running: true,
 
             fonts:Vec::new(),
+            text_layouts:TextLayoutCache::default(),
+            images:Vec::new(),
+            atlases:Vec::new(),
             textures_2d:Vec::new(),
+            textures_2d_free:Vec::new(),
             uniforms:Vec::new(),
 
             draw_lists:Vec::new(),
             draw_lists_free:Vec::new(),
             draw_list_stack:Vec::new(),
             current_draw_list_id:0,
+            batching:true,
+            frame_stats:FrameStats::default(),
+            partial_paint:true,
+            damage:Vec::new(),
+            damage_frame:0,
+            painted_size:Vec2::zero(),
+            painted_dpi_factor:0.0,
+            profiler:CxProfiler::default(),
+            keymap:CxKeymap::default(),
 
             compiled_shaders:Vec::new(),
             shaders:Vec::new(),
             shader_map:HashMap::new(),
+            shader_modules:ShaderModule::def_builtin_modules(),
 
             redraw_areas:Vec::new(),
             incr_areas:Vec::new(),
@@ -129,6 +172,7 @@ impl Default for Cx{
             is_in_redraw_cycle:false,
             turtles:Vec::new(),
             align_list:Vec::new(),
+            rtl:false,
             target_size:Vec2::zero(),
             target_dpi_factor:0.0,
             
@@ -136,6 +180,8 @@ impl Default for Cx{
             key_focus:Area::Empty,
 
             debug_area:Area::Empty,
+            debug_layout:false,
+            debug_turtles:Vec::new(),
 
             down_mouse_cursor:None,
             hover_mouse_cursor:None,
@@ -242,17 +288,30 @@ impl Cx{
     }
 
     pub fn new_empty_texture_2d(&mut self)->&mut Texture2D{
-        //let id = self.textures.len();
-        let id = self.textures_2d.len();
-        self.textures_2d.push(
-            Texture2D{
-                texture_id:id,
-                ..Default::default()
-            }
-        );
+        let id = if let Some(id) = self.textures_2d_free.pop(){
+            id
+        }
+        else{
+            self.textures_2d.push(Texture2D::default());
+            self.textures_2d.len() - 1
+        };
+        self.textures_2d[id] = Texture2D{
+            texture_id:id,
+            ..Default::default()
+        };
         &mut self.textures_2d[id]
     }
 
+    // releases what the texture holds on the device, new_empty_texture_2d hands its id out again
+    pub fn free_texture_2d(&mut self, texture_id:usize){
+        self.textures_2d[texture_id].free_device();
+        self.textures_2d[texture_id] = Texture2D{
+            texture_id:texture_id,
+            ..Default::default()
+        };
+        self.textures_2d_free.push(texture_id);
+    }
+
     pub fn prepare_frame(&mut self){
         let camera_projection = Mat4::ortho(
                 0.0, self.target_size.x, 0.0, self.target_size.y, -100.0, 100.0, 
@@ -260,6 +319,10 @@ impl Cx{
         );
         self.uniform_camera_projection(camera_projection);
         self.align_list.truncate(0);
+        self.profile_begin("prepare");
+        self.plan_draw_batches();
+        self.compute_damage();
+        self.profile_end();
     }
 
     pub fn check_ended_anim_areas(&mut self, time:f64){
@@ -285,8 +348,8 @@ impl Cx{
         if let Some(anim_anim) = self.playing_anim_areas.iter_mut().find(|v| v.area == old_area){
             anim_anim.area = new_area.clone()
         }
-        //Update mouse capture areas
-        if let Some(digit_area) = self.captured_fingers.iter_mut().find(|v| **v == old_area){
+        //Update mouse capture areas, fingers that capture nothing hold Area::Empty
+        if let Some(digit_area) = self.captured_fingers.iter_mut().find(|v| **v == old_area && old_area != Area::Empty){
             *digit_area = new_area.clone()
         }
         // update capture keyboard
@@ -313,6 +376,13 @@ impl Cx{
         panic!("Cannot find style font key {}", name);
     }
 
+    pub fn font_list(&self, name:&str)->Vec<String>{
+        if let Some(StyleValue::FontList(val)) = self.style_values.get(name){
+            return val.clone();
+        }
+        panic!("Cannot find style font list key {}", name);
+    }
+
     pub fn size(&self, name:&str)->f64{
         if let Some(StyleValue::Size(val)) = self.style_values.get(name){
             return *val;
@@ -328,6 +398,10 @@ impl Cx{
         self.style_values.insert(name.to_string(), StyleValue::Font(val.to_string()));
     }
 
+    pub fn set_font_list(&mut self, name:&str, val:&[&str]){
+        self.style_values.insert(name.to_string(), StyleValue::FontList(val.iter().map(|v| v.to_string()).collect()));
+    }
+
     pub fn set_size(&mut self, name:&str, val:f64){
         self.style_values.insert(name.to_string(), StyleValue::Size(val));
     }
@@ -336,13 +410,29 @@ impl Cx{
         self.key_focus = focus_area;
     }
 
+    // flips Direction::Inline turtles, everything redraws in the new direction
+    pub fn set_rtl(&mut self, rtl:bool){
+        if self.rtl != rtl{
+            self.rtl = rtl;
+            self.redraw_area(Area::All);
+        }
+    }
+
 
     // event handler wrappers
 
     pub fn call_event_handler<F>(&mut self, mut event_handler:F, event:&mut Event)
     where F: FnMut(&mut Cx, &mut Event)
     { 
+        // draw events are timed as part of the draw
+        let profile = !self.is_in_redraw_cycle;
+        if profile{
+            self.profile_begin("event");
+        }
         self.event_id += 1;
+        if let Event::FileRead(fr) = event{
+            self.process_image_file_read(fr);
+        }
         event_handler(self, event);
 
         if self.last_key_focus != self.key_focus{
@@ -363,20 +453,26 @@ impl Cx{
                 event_handler(self, &mut user_event);
             }
         }
+        if profile{
+            self.profile_end();
+        }
     }
 
     pub fn call_draw_event<F, T>(&mut self, mut event_handler:F, root_view:&mut View<T>)
     where F: FnMut(&mut Cx, &mut Event), T: ScrollBarLike<T> + Clone + ElementLife
     { 
         //for i in 0..10{
+        self.profile_begin("draw");
         self.is_in_redraw_cycle = true;
         self.redraw_id += 1;
+        self.debug_turtles.truncate(0);
         root_view.begin_view(self, &Layout{..Default::default()});
         self.incr_areas = self.redraw_areas.clone();
         self.redraw_areas.truncate(0);
         self.call_event_handler(&mut event_handler, &mut Event::Draw);
         root_view.end_view(self);
         self.is_in_redraw_cycle = false;
+        self.profile_end();
         //}
     }
 
@@ -404,67 +500,27 @@ impl Cx{
         }
         self.next_frame_callbacks.push(area);
     }
-/*
-    pub fn debug_draw_tree_recur(&mut self, draw_list_id: usize, depth:usize){
+
+    // the draw list tree as (depth, line), with the draw calls, their shader and instance count
+    pub fn debug_draw_tree_recur(&self, draw_list_id:usize, depth:usize, out:&mut Vec<(usize, String)>){
         if draw_list_id >= self.draw_lists.len(){
-            println!("---------- Drawlist still empty ---------");
             return
         }
-        let mut indent = String::new();
-        for _i in 0..depth{
-            indent.push_str("  ");
-        }
-        let draw_calls_len = self.draw_lists[draw_list_id].draw_calls_len;
-        if draw_list_id == 0{
-            println!("---------- Begin Debug draw tree for redraw_id: {} ---------", self.redraw_id)
-        }
-        println!("{}list {}: len:{} rect:{:?}", indent, draw_list_id, draw_calls_len, self.draw_lists[draw_list_id].rect);        
-        indent.push_str("  ");
-        for draw_call_id in 0..draw_calls_len{
-            let sub_list_id = self.draw_lists[draw_list_id].draw_calls[draw_call_id].sub_list_id;
-            if sub_list_id != 0{
-                self.debug_draw_tree_recur(sub_list_id, depth + 1);
+        let draw_list = &self.draw_lists[draw_list_id];
+        let r = draw_list.rect;
+        out.push((depth, format!("list {}: calls:{} rect:({},{} {}x{})", draw_list_id, draw_list.draw_calls_len, r.x, r.y, r.w, r.h)));
+        for draw_call_id in 0..draw_list.draw_calls_len{
+            let draw_call = &draw_list.draw_calls[draw_call_id];
+            if draw_call.sub_list_id != 0{
+                self.debug_draw_tree_recur(draw_call.sub_list_id, depth + 1, out);
             }
             else{
-                let draw_list = &mut self.draw_lists[draw_list_id];
-                let draw_call = &mut draw_list.draw_calls[draw_call_id];
-                let sh = &self.shaders[draw_call.shader_id];
-                let shc = &self.compiled_shaders[draw_call.shader_id];
-                let slots = shc.instance_slots;
-                let instances = draw_call.instance.len() / slots;
-                println!("{}call {}: {}({}) x:{}", indent, draw_call_id, sh.name, draw_call.shader_id, instances);        
-                // lets dump the instance geometry
-                for inst in 0..instances.min(1){
-                    let mut out = String::new();
-                    let mut off = 0;
-                    for prop in &shc.named_instance_props.props{
-                        match prop.slots{
-                            1=>out.push_str(&format!("{}:{} ", prop.name, 
-                                draw_call.instance[inst*slots + off])),
-                            2=>out.push_str(&format!("{}:v2({},{}) ", prop.name, 
-                                draw_call.instance[inst*slots+ off], 
-                                draw_call.instance[inst*slots+1+ off])),
-                            3=>out.push_str(&format!("{}:v3({},{},{}) ", prop.name, 
-                                draw_call.instance[inst*slots+ off], 
-                                draw_call.instance[inst*slots+1+ off], 
-                                draw_call.instance[inst*slots+1+ off])),
-                            4=>out.push_str(&format!("{}:v4({},{},{},{}) ", prop.name, 
-                                draw_call.instance[inst*slots+ off], 
-                                draw_call.instance[inst*slots+1+ off], 
-                                draw_call.instance[inst*slots+2+ off], 
-                                draw_call.instance[inst*slots+3+ off])),
-                            _=>{}
-                        }
-                        off += prop.slots;
-                    }
-                    println!("  {}instance {}: {}", indent, inst, out);        
-                }
+                let slots = self.compiled_shaders[draw_call.shader_id].instance_slots.max(1);
+                out.push((depth + 1, format!("call {}: {}({}) x{}", draw_call_id, self.shaders[draw_call.shader_id].name,
+                    draw_call.shader_id, draw_call.instance.len() / slots)));
             }
         }
-        if draw_list_id == 0{
-            println!("---------- End Debug draw tree for redraw_id: {} ---------", self.redraw_id)
-        }
-    }*/
+    }
 }
 
 
@@ -472,6 +528,7 @@ impl Cx{
 pub enum StyleValue{
     Color(Color),
     Font(String),
+    FontList(Vec<String>),
     Size(f64)
 }
 
@@ -0,0 +1,506 @@
+use crate::cx::*;
+use std::collections::HashMap;
+
+// Shelf packed texture atlases. AtlasPacker only does the rect bookkeeping so it can be
+// driven without a GPU, CxAtlas ties one to a Texture2D and keeps the pixels in sync when
+// the packer grows, defragments or evicts.
+
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub struct AtlasRect{
+    pub x:usize,
+    pub y:usize,
+    pub w:usize,
+    pub h:usize
+}
+
+#[derive(Clone, Default, Debug)]
+pub struct AtlasShelf{
+    pub y:usize,
+    pub h:usize,
+    pub free:Vec<(usize, usize)> // free spans (x, w), sorted on x
+}
+
+#[derive(Clone, Default, Debug)]
+pub struct AtlasEntry{
+    pub rect:AtlasRect, // including padding
+    pub shelf:usize,
+    pub evictable:bool,
+    pub last_used:u64
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AtlasMove{
+    pub alloc_id:u64,
+    pub from:AtlasRect,
+    pub to:AtlasRect
+}
+
+#[derive(Clone, Default, Debug)]
+pub struct AtlasPacker{
+    pub width:usize,
+    pub height:usize,
+    pub max_size:usize,
+    pub padding:usize,
+    pub shelves:Vec<AtlasShelf>,
+    pub entries:HashMap<u64, AtlasEntry>,
+    pub alloc_id:u64,
+    pub use_counter:u64
+}
+
+impl AtlasPacker{
+    pub fn new(width:usize, height:usize, max_size:usize, padding:usize)->AtlasPacker{
+        AtlasPacker{
+            width:width,
+            height:height,
+            max_size:max_size.max(width).max(height),
+            padding:padding,
+            alloc_id:1,
+            ..Default::default()
+        }
+    }
+
+    // the usable rect of an allocation, None if it was freed or evicted
+    pub fn rect(&self, alloc_id:u64)->Option<AtlasRect>{
+        if let Some(entry) = self.entries.get(&alloc_id){
+            return Some(AtlasRect{
+                x:entry.rect.x,
+                y:entry.rect.y,
+                w:entry.rect.w - self.padding,
+                h:entry.rect.h - self.padding
+            })
+        }
+        None
+    }
+
+    // texture coordinates as (u0, v0, u1, v1), these change when the atlas grows
+    pub fn uv(&self, alloc_id:u64)->Option<(f32, f32, f32, f32)>{
+        if let Some(r) = self.rect(alloc_id){
+            let (tw, th) = (self.width as f32, self.height as f32);
+            return Some((r.x as f32 / tw, r.y as f32 / th, (r.x + r.w) as f32 / tw, (r.y + r.h) as f32 / th))
+        }
+        None
+    }
+
+    pub fn touch(&mut self, alloc_id:u64){
+        self.use_counter += 1;
+        if let Some(entry) = self.entries.get_mut(&alloc_id){
+            entry.last_used = self.use_counter;
+        }
+    }
+
+    pub fn used_area(&self)->usize{
+        self.entries.values().fold(0, |sum, e| sum + e.rect.w * e.rect.h)
+    }
+
+    // only packs into the current size, see alloc_or_make_room to grow, defrag and evict
+    pub fn alloc(&mut self, w:usize, h:usize, evictable:bool)->Option<u64>{
+        let pw = w + self.padding;
+        let ph = h + self.padding;
+        if pw > self.width || ph > self.height{
+            return None
+        }
+        // find the shelf that wastes the least height
+        let mut best:Option<(usize, usize)> = None;
+        for (shelf_id, shelf) in self.shelves.iter().enumerate(){
+            if shelf.h < ph || shelf.free.iter().find(|(_, fw)| *fw >= pw).is_none(){
+                continue
+            }
+            let waste = shelf.h - ph;
+            if best.is_none() || waste < best.unwrap().1{
+                best = Some((shelf_id, waste));
+            }
+        }
+        // too much waste, prefer a new shelf if we have the room
+        let shelf_bottom = self.shelves.last().map_or(0, |s| s.y + s.h);
+        if let Some((_, waste)) = best{
+            if waste > ph && shelf_bottom + ph <= self.height{
+                best = None;
+            }
+        }
+        let shelf_id = if let Some((shelf_id, _)) = best{
+            shelf_id
+        }
+        else{
+            if shelf_bottom + ph > self.height{
+                return None
+            }
+            self.shelves.push(AtlasShelf{
+                y:shelf_bottom,
+                h:ph,
+                free:vec![(0, self.width)]
+            });
+            self.shelves.len() - 1
+        };
+        let shelf = &mut self.shelves[shelf_id];
+        let span = shelf.free.iter().position(|(_, fw)| *fw >= pw).unwrap();
+        let (x, fw) = shelf.free[span];
+        if fw == pw{
+            shelf.free.remove(span);
+        }
+        else{
+            shelf.free[span] = (x + pw, fw - pw);
+        }
+        let alloc_id = self.alloc_id;
+        self.alloc_id += 1;
+        self.use_counter += 1;
+        self.entries.insert(alloc_id, AtlasEntry{
+            rect:AtlasRect{x:x, y:shelf.y, w:pw, h:ph},
+            shelf:shelf_id,
+            evictable:evictable,
+            last_used:self.use_counter
+        });
+        Some(alloc_id)
+    }
+
+    pub fn free(&mut self, alloc_id:u64){
+        let entry = if let Some(entry) = self.entries.remove(&alloc_id){entry}else{return};
+        let shelf = &mut self.shelves[entry.shelf];
+        let pos = shelf.free.iter().position(|(x, _)| *x > entry.rect.x).unwrap_or(shelf.free.len());
+        shelf.free.insert(pos, (entry.rect.x, entry.rect.w));
+        // merge with the neighbours
+        if pos + 1 < shelf.free.len() && shelf.free[pos].0 + shelf.free[pos].1 == shelf.free[pos + 1].0{
+            shelf.free[pos].1 += shelf.free[pos + 1].1;
+            shelf.free.remove(pos + 1);
+        }
+        if pos > 0 && shelf.free[pos - 1].0 + shelf.free[pos - 1].1 == shelf.free[pos].0{
+            shelf.free[pos - 1].1 += shelf.free[pos].1;
+            shelf.free.remove(pos);
+        }
+        // empty shelves at the bottom give their height back
+        while let Some(shelf) = self.shelves.last(){
+            if shelf.free.len() == 1 && shelf.free[0] == (0, self.width){
+                self.shelves.pop();
+            }
+            else{
+                break
+            }
+        }
+    }
+
+    // existing allocations keep their pixel position, only the uvs change
+    pub fn grow(&mut self)->bool{
+        if self.width >= self.max_size && self.height >= self.max_size{
+            return false
+        }
+        let old_width = self.width;
+        // keep it roughly square, grow the smaller side first
+        if self.height < self.width || self.width >= self.max_size{
+            self.height = (self.height * 2).min(self.max_size);
+        }
+        else{
+            self.width = (self.width * 2).min(self.max_size);
+        }
+        if self.width != old_width{
+            for shelf in &mut self.shelves{
+                if let Some(last) = shelf.free.last_mut(){
+                    if last.0 + last.1 == old_width{
+                        last.1 += self.width - old_width;
+                        continue
+                    }
+                }
+                shelf.free.push((old_width, self.width - old_width));
+            }
+        }
+        true
+    }
+
+    // repacks all allocations tallest first, returns the moves needed or None if they dont fit
+    pub fn defrag(&mut self)->Option<Vec<AtlasMove>>{
+        let mut ids:Vec<u64> = self.entries.keys().cloned().collect();
+        ids.sort_by(|a, b|{
+            let (ea, eb) = (&self.entries[a], &self.entries[b]);
+            eb.rect.h.cmp(&ea.rect.h).then(eb.rect.w.cmp(&ea.rect.w)).then(a.cmp(b))
+        });
+        let mut packed = AtlasPacker{
+            shelves:Vec::new(),
+            entries:HashMap::new(),
+            ..self.clone()
+        };
+        let mut moves = Vec::new();
+        for id in ids{
+            let old = self.entries[&id].clone();
+            let new_id = packed.alloc(old.rect.w - self.padding, old.rect.h - self.padding, old.evictable)?;
+            // keep the callers ids stable
+            let mut entry = packed.entries.remove(&new_id).unwrap();
+            entry.last_used = old.last_used;
+            if entry.rect != old.rect{
+                moves.push(AtlasMove{alloc_id:id, from:old.rect, to:entry.rect});
+            }
+            packed.entries.insert(id, entry);
+        }
+        packed.alloc_id = self.alloc_id;
+        packed.use_counter = self.use_counter;
+        *self = packed;
+        Some(moves)
+    }
+
+    // frees the least recently used evictable allocation, returns its id
+    pub fn evict_one(&mut self)->Option<u64>{
+        let victim = self.entries.iter()
+            .filter(|(_, e)| e.evictable)
+            .min_by_key(|(_, e)| e.last_used)
+            .map(|(id, _)| *id)?;
+        self.free(victim);
+        Some(victim)
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct CxAtlas{
+    pub texture_id:usize,
+    pub packer:AtlasPacker,
+    pub generation:u64 // bumped when the texture grows or allocations move, uvs taken before are stale
+}
+
+impl Cx{
+    pub fn new_atlas(&mut self, width:usize, height:usize, max_size:usize)->usize{
+        let tex = self.new_empty_texture_2d();
+        tex.resize(width, height);
+        let texture_id = tex.texture_id;
+        self.atlases.push(CxAtlas{
+            texture_id:texture_id,
+            packer:AtlasPacker::new(width, height, max_size, 1),
+            generation:0
+        });
+        self.atlases.len() - 1
+    }
+
+    // tries, in order: packing, defragmenting, growing and evicting least recently used entries
+    pub fn atlas_alloc(&mut self, atlas_id:usize, w:usize, h:usize, evictable:bool)->Option<u64>{
+        // what can't fit at the largest size shouldn't evict everything on its way to failing
+        let packer = &self.atlases[atlas_id].packer;
+        if w + packer.padding > packer.max_size || h + packer.padding > packer.max_size{
+            return None
+        }
+        loop{
+            if let Some(alloc_id) = self.atlases[atlas_id].packer.alloc(w, h, evictable){
+                return Some(alloc_id)
+            }
+            let packer = &self.atlases[atlas_id].packer;
+            let free_area = packer.width * packer.height - packer.used_area();
+            if free_area >= (w + packer.padding) * (h + packer.padding) * 2 && self.atlas_defrag(atlas_id){
+                if let Some(alloc_id) = self.atlases[atlas_id].packer.alloc(w, h, evictable){
+                    return Some(alloc_id)
+                }
+            }
+            if self.atlas_grow(atlas_id){
+                continue
+            }
+            if self.atlases[atlas_id].packer.evict_one().is_none(){
+                return None
+            }
+        }
+    }
+
+    pub fn atlas_free(&mut self, atlas_id:usize, alloc_id:u64){
+        self.atlases[atlas_id].packer.free(alloc_id);
+    }
+
+    pub fn atlas_rect(&self, atlas_id:usize, alloc_id:u64)->Option<AtlasRect>{
+        self.atlases[atlas_id].packer.rect(alloc_id)
+    }
+
+    // also marks the allocation as used, for eviction
+    pub fn atlas_uv(&mut self, atlas_id:usize, alloc_id:u64)->Option<(f32, f32, f32, f32)>{
+        let packer = &mut self.atlases[atlas_id].packer;
+        packer.touch(alloc_id);
+        packer.uv(alloc_id)
+    }
+
+    // pixels are row major and exactly the size of the allocation
+    pub fn atlas_write(&mut self, atlas_id:usize, alloc_id:u64, pixels:&[u32])->Result<(), String>{
+        let atlas = &self.atlases[atlas_id];
+        let r = if let Some(r) = atlas.packer.rect(alloc_id){r}else{
+            return Err(format!("atlas allocation {} is gone", alloc_id))
+        };
+        if pixels.len() != r.w * r.h{
+            return Err(format!("atlas write of {} pixels into {}x{}", pixels.len(), r.w, r.h))
+        }
+        let tex = &mut self.textures_2d[atlas.texture_id];
+        for y in 0..r.h{
+            let dst = (r.y + y) * tex.width + r.x;
+            tex.image[dst..dst + r.w].copy_from_slice(&pixels[y * r.w..(y + 1) * r.w]);
+        }
+        tex.dirty = true;
+        Ok(())
+    }
+
+    fn atlas_grow(&mut self, atlas_id:usize)->bool{
+        let atlas = &mut self.atlases[atlas_id];
+        let (old_w, old_h) = (atlas.packer.width, atlas.packer.height);
+        if !atlas.packer.grow(){
+            return false
+        }
+        let tex = &mut self.textures_2d[atlas.texture_id];
+        let mut image = vec![0u32; atlas.packer.width * atlas.packer.height];
+        for y in 0..old_h{
+            image[y * atlas.packer.width..y * atlas.packer.width + old_w].copy_from_slice(&tex.image[y * old_w..(y + 1) * old_w]);
+        }
+        tex.width = atlas.packer.width;
+        tex.height = atlas.packer.height;
+        tex.image = image;
+        tex.dirty = true;
+        atlas.generation += 1;
+        true
+    }
+
+    fn atlas_defrag(&mut self, atlas_id:usize)->bool{
+        let atlas = &mut self.atlases[atlas_id];
+        let moves = if let Some(moves) = atlas.packer.defrag(){moves}else{return false};
+        if moves.len() == 0{
+            return false
+        }
+        let tex = &mut self.textures_2d[atlas.texture_id];
+        let old = tex.image.clone();
+        for mv in &moves{
+            for y in 0..mv.from.h{
+                let src = (mv.from.y + y) * tex.width + mv.from.x;
+                let dst = (mv.to.y + y) * tex.width + mv.to.x;
+                tex.image[dst..dst + mv.from.w].copy_from_slice(&old[src..src + mv.from.w]);
+            }
+        }
+        tex.dirty = true;
+        atlas.generation += 1;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[test]
+    fn too_large_fails_without_evicting(){
+        let mut cx = Cx::default();
+        let atlas = cx.new_atlas(16, 16, 64);
+        let a = cx.atlas_alloc(atlas, 8, 8, true).unwrap();
+        let b = cx.atlas_alloc(atlas, 8, 8, true).unwrap();
+        let size = (cx.atlases[atlas].packer.width, cx.atlases[atlas].packer.height);
+        assert_eq!(cx.atlas_alloc(atlas, 64, 4, true), None);
+        assert_eq!(cx.atlas_alloc(atlas, 4, 100, true), None);
+        assert!(cx.atlas_rect(atlas, a).is_some() && cx.atlas_rect(atlas, b).is_some());
+        assert_eq!((cx.atlases[atlas].packer.width, cx.atlases[atlas].packer.height), size);
+        // 63 plus the padding just fits at the largest size
+        assert!(cx.atlas_alloc(atlas, 63, 63, true).is_some());
+    }
+
+    #[test]
+    fn write_checks_the_size(){
+        let mut cx = Cx::default();
+        let atlas = cx.new_atlas(16, 16, 16);
+        let id = cx.atlas_alloc(atlas, 2, 3, false).unwrap();
+        assert_eq!(cx.atlas_write(atlas, id, &[1; 5]), Err("atlas write of 5 pixels into 2x3".to_string()));
+        assert_eq!(cx.atlas_write(atlas, id, &[1; 7]), Err("atlas write of 7 pixels into 2x3".to_string()));
+        cx.atlas_write(atlas, id, &[1, 2, 3, 4, 5, 6]).unwrap();
+        let r = cx.atlas_rect(atlas, id).unwrap();
+        let tex = &cx.textures_2d[cx.atlases[atlas].texture_id];
+        assert_eq!(&tex.image[(r.y + 2) * 16 + r.x..(r.y + 2) * 16 + r.x + 2], &[5, 6]);
+        cx.atlas_free(atlas, id);
+        assert_eq!(cx.atlas_write(atlas, id, &[1; 6]), Err(format!("atlas allocation {} is gone", id)));
+    }
+
+    fn spans(packer:&AtlasPacker, shelf:usize)->Vec<(usize, usize)>{
+        packer.shelves[shelf].free.clone()
+    }
+
+    #[test]
+    fn packer_alloc_and_free_merge_spans(){
+        let mut packer = AtlasPacker::new(32, 32, 64, 0);
+        let a = packer.alloc(8, 8, false).unwrap();
+        let b = packer.alloc(8, 8, false).unwrap();
+        let c = packer.alloc(8, 8, false).unwrap();
+        assert_eq!(packer.rect(c), Some(AtlasRect{x:16, y:0, w:8, h:8}));
+        assert_eq!(spans(&packer, 0), vec![(24, 8)]);
+        // a lower item goes on the shelf, a much taller one gets its own
+        let low = packer.alloc(4, 6, false).unwrap();
+        assert_eq!(packer.rect(low), Some(AtlasRect{x:24, y:0, w:4, h:6}));
+        let tall = packer.alloc(4, 20, false).unwrap();
+        assert_eq!(packer.rect(tall), Some(AtlasRect{x:0, y:8, w:4, h:20}));
+        assert_eq!(packer.alloc(33, 1, false), None);
+
+        packer.free(b);
+        assert_eq!(spans(&packer, 0), vec![(8, 8), (28, 4)]);
+        packer.free(low);
+        assert_eq!(spans(&packer, 0), vec![(8, 8), (24, 8)]);
+        packer.free(c);
+        assert_eq!(spans(&packer, 0), vec![(8, 24)]);
+        packer.free(a);
+        assert_eq!(spans(&packer, 0), vec![(0, 32)]);
+        assert_eq!(packer.rect(a), None);
+        // empty shelves at the bottom are given back
+        packer.free(tall);
+        assert_eq!(packer.shelves.len(), 0);
+        assert_eq!(packer.used_area(), 0);
+    }
+
+    #[test]
+    fn packer_padding(){
+        let mut packer = AtlasPacker::new(16, 16, 16, 1);
+        let a = packer.alloc(4, 4, false).unwrap();
+        let b = packer.alloc(4, 4, false).unwrap();
+        assert_eq!(packer.rect(a), Some(AtlasRect{x:0, y:0, w:4, h:4}));
+        assert_eq!(packer.rect(b), Some(AtlasRect{x:5, y:0, w:4, h:4}));
+        assert_eq!(packer.used_area(), 50);
+        assert_eq!(packer.alloc(16, 1, false), None);
+    }
+
+    #[test]
+    fn packer_grow_keeps_positions(){
+        let mut packer = AtlasPacker::new(16, 16, 64, 0);
+        let a = packer.alloc(16, 8, false).unwrap();
+        assert!(packer.alloc(8, 8, false).is_some());
+        assert!(packer.alloc(8, 8, false).is_some());
+        assert_eq!(packer.alloc(8, 8, false), None);
+        assert!(packer.grow());
+        assert_eq!((packer.width, packer.height), (32, 16));
+        // the new width extends every shelf
+        assert_eq!(spans(&packer, 0), vec![(16, 16)]);
+        assert_eq!(spans(&packer, 1), vec![(16, 16)]);
+        assert_eq!(packer.rect(a), Some(AtlasRect{x:0, y:0, w:16, h:8}));
+        assert_eq!(packer.uv(a), Some((0.0, 0.0, 0.5, 0.5)));
+        assert!(packer.grow());
+        assert_eq!((packer.width, packer.height), (32, 32));
+        assert!(packer.grow() && packer.grow());
+        assert_eq!((packer.width, packer.height), (64, 64));
+        assert!(!packer.grow());
+        assert_eq!(packer.rect(a), Some(AtlasRect{x:0, y:0, w:16, h:8}));
+    }
+
+    #[test]
+    fn packer_defrag_moves(){
+        let mut packer = AtlasPacker::new(32, 32, 32, 0);
+        let a = packer.alloc(8, 4, false).unwrap();
+        let b = packer.alloc(8, 8, false).unwrap();
+        let c = packer.alloc(4, 4, false).unwrap();
+        assert_eq!(packer.rect(b), Some(AtlasRect{x:0, y:4, w:8, h:8}));
+        assert_eq!(packer.rect(c), Some(AtlasRect{x:8, y:0, w:4, h:4}));
+        packer.free(a);
+        let moves = packer.defrag().unwrap();
+        // tallest first, ids stay the same
+        assert_eq!(moves, vec![
+            AtlasMove{alloc_id:b, from:AtlasRect{x:0, y:4, w:8, h:8}, to:AtlasRect{x:0, y:0, w:8, h:8}},
+            AtlasMove{alloc_id:c, from:AtlasRect{x:8, y:0, w:4, h:4}, to:AtlasRect{x:8, y:0, w:4, h:4}},
+        ].into_iter().filter(|mv| mv.from != mv.to).collect::<Vec<_>>());
+        assert_eq!(packer.rect(b), Some(AtlasRect{x:0, y:0, w:8, h:8}));
+        assert_eq!(packer.rect(a), None);
+        assert_eq!(packer.shelves.len(), 1);
+        // a defrag of a packed atlas moves nothing, new ids don't collide
+        assert_eq!(packer.defrag(), Some(Vec::new()));
+        assert!(packer.alloc(1, 1, false).unwrap() > c);
+    }
+
+    #[test]
+    fn packer_evicts_least_recently_used(){
+        let mut packer = AtlasPacker::new(32, 32, 32, 0);
+        let a = packer.alloc(4, 4, true).unwrap();
+        let b = packer.alloc(4, 4, true).unwrap();
+        let c = packer.alloc(4, 4, true).unwrap();
+        let d = packer.alloc(4, 4, false).unwrap();
+        packer.touch(a);
+        assert_eq!(packer.evict_one(), Some(b));
+        assert_eq!(packer.evict_one(), Some(c));
+        assert_eq!(packer.evict_one(), Some(a));
+        assert_eq!(packer.evict_one(), None);
+        assert!(packer.rect(d).is_some());
+    }
+}
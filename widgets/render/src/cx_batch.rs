@@ -0,0 +1,175 @@
+use crate::cx::*;
+
+// how many batches back a draw call looks for one it can join
+const BATCH_LOOKBACK:usize = 16;
+
+// draw calls that are issued as one instanced draw. The first member supplies the
+// draw list uniforms and the buffers the merged instances are uploaded to
+#[derive(Clone, Default, Debug)]
+pub struct DrawBatch{
+    pub draw_list_id:usize,
+    pub members:Vec<(usize, usize)>, // (draw_list_id, draw_call_id) in draw order
+    pub bounds:Option<Rect>, // on screen, None when a member has instances without a rect
+    pub clip:Rect, // what the batch is clipped with, grows when members of other clips join
+    pub contained:bool, // every member lies inside the clip of its own list
+    pub uniforms:Vec<f32>, // the draw list uniforms of the first member with the batch clip
+    pub instance:Vec<f32> // the merged instances, kept until the platform has uploaded them
+}
+
+#[derive(Clone, Copy, Default, Debug)]
+pub struct FrameStats{
+    pub draw_lists:usize,
+    pub draw_calls:usize, // what drawing every draw call of every list would issue
+    pub batched_draw_calls:usize, // what is issued after batching
+    pub instances:usize
+}
+
+impl Cx{
+    // plans the draws of the main pass and of every render target, called when a frame is prepared
+    // since scrolling and animations change where things are without a redraw
+    pub fn plan_draw_batches(&mut self){
+        if self.draw_lists.len() == 0{
+            return
+        }
+        // hit testing clips with these too, so every list gets them and not just the ones heading a batch
+        for draw_list in &mut self.draw_lists{
+            draw_list.set_clipping_uniforms();
+        }
+        let mut roots = Vec::new();
+        self.collect_render_targets(0, &mut roots);
+        roots.push(0);
+        let mut stats = FrameStats::default();
+        for root in roots{
+            let mut items = Vec::new();
+            self.collect_draw_items(root, root, &mut items, &mut stats);
+            let batches = self.batch_draw_items(items);
+            stats.batched_draw_calls += batches.len();
+            self.draw_lists[root].batches = batches;
+        }
+        self.frame_stats = stats;
+    }
+
+    // the draw calls in the order exec_draw_list used to walk them, render targets are their own roots
+    fn collect_draw_items(&self, root:usize, draw_list_id:usize, items:&mut Vec<(usize, usize, Option<Rect>)>, stats:&mut FrameStats){
+        let draw_list = &self.draw_lists[draw_list_id];
+        stats.draw_lists += 1;
+        for draw_call_id in 0..draw_list.draw_calls_len{
+            let draw_call = &draw_list.draw_calls[draw_call_id];
+            if draw_call.sub_list_id != 0{
+                if self.draw_lists[draw_call.sub_list_id].render_target.is_none() || draw_call.sub_list_id == root{
+                    self.collect_draw_items(root, draw_call.sub_list_id, items, stats);
+                }
+                continue;
+            }
+            stats.draw_calls += 1;
+            let csh = &self.compiled_shaders[draw_call.shader_id];
+            let instances = draw_call.instance.len() / csh.instance_slots.max(1);
+            if instances == 0{
+                continue;
+            }
+            stats.instances += instances;
+            items.push((draw_list_id, draw_call_id, self.draw_call_bounds(draw_list, draw_call, csh)));
+        }
+    }
+
+    // the screen rect the instances of a draw call cover. Quads can opt out of scrolling so
+    // both the scrolled and the unscrolled rect count
+    pub fn draw_call_bounds(&self, draw_list:&DrawList, draw_call:&DrawCall, csh:&CompiledShader)->Option<Rect>{
+        let props = &csh.rect_instance_props;
+        let (x, y, w, h) = if let (Some(x), Some(y), Some(w), Some(h)) = (props.x, props.y, props.w, props.h){
+            (x, y, w, h)
+        }
+        else{
+            return None
+        };
+        let mut bounds:Option<Rect> = None;
+        for inst in draw_call.instance.chunks(csh.instance_slots){
+            if inst.len() < csh.instance_slots{
+                break;
+            }
+            let rect = Rect{x:inst[x], y:inst[y], w:inst[w], h:inst[h]};
+            if rect.w.is_nan() || rect.h.is_nan() || rect.w < 0.0 || rect.h < 0.0{
+                return None
+            }
+            bounds = Some(if let Some(bounds) = bounds{bounds.union(rect)}else{rect});
+        }
+        let scroll = draw_list.get_scroll_pos();
+        bounds.map(|b| b.union(Rect{x:b.x - scroll.x, y:b.y - scroll.y, w:b.w, h:b.h}))
+    }
+
+    fn batch_draw_items(&self, items:Vec<(usize, usize, Option<Rect>)>)->Vec<DrawBatch>{
+        let mut batches:Vec<DrawBatch> = Vec::new();
+        for (draw_list_id, draw_call_id, bounds) in items{
+            let mut target = None;
+            if self.batching{
+                for index in (0..batches.len()).rev().take(BATCH_LOOKBACK){
+                    if self.can_join_batch(&batches[index], draw_list_id, draw_call_id, bounds){
+                        target = Some(index);
+                        break;
+                    }
+                    // joining an earlier batch draws us before this one, fine only if we dont overlap
+                    if let (Some(a), Some(b)) = (bounds, batches[index].bounds){
+                        if a.x < b.x + b.w && b.x < a.x + a.w && a.y < b.y + b.h && b.y < a.y + a.h{
+                            break;
+                        }
+                    }
+                    else{
+                        break;
+                    }
+                }
+            }
+            let clip = self.draw_lists[draw_list_id].clip_rect();
+            let contained = if let Some(bounds) = bounds{clip.contains_rect(bounds)}else{false};
+            if let Some(index) = target{
+                let batch = &mut batches[index];
+                batch.members.push((draw_list_id, draw_call_id));
+                batch.bounds = if let (Some(a), Some(b)) = (batch.bounds, bounds){Some(a.union(b))}else{None};
+                if batch.clip != clip{
+                    batch.clip = batch.clip.union(clip);
+                }
+                batch.contained = batch.contained && contained;
+            }
+            else{
+                batches.push(DrawBatch{
+                    draw_list_id:draw_list_id,
+                    members:vec![(draw_list_id, draw_call_id)],
+                    bounds:bounds,
+                    clip:clip,
+                    contained:contained,
+                    uniforms:Vec::new(),
+                    instance:Vec::new()
+                });
+            }
+        }
+        batches
+    }
+
+    // same shader, draw call uniforms, textures and scroll, and a clip that doesnt change what is drawn.
+    // Lists with another clip can join as long as everything in the batch lies inside its own clip
+    fn can_join_batch(&self, batch:&DrawBatch, draw_list_id:usize, draw_call_id:usize, bounds:Option<Rect>)->bool{
+        let (head_list_id, head_call_id) = batch.members[0];
+        let head_list = &self.draw_lists[head_list_id];
+        let head = &head_list.draw_calls[head_call_id];
+        let draw_list = &self.draw_lists[draw_list_id];
+        let draw_call = &draw_list.draw_calls[draw_call_id];
+        if head.shader_id != draw_call.shader_id || head.uniforms != draw_call.uniforms ||
+            head.textures_2d != draw_call.textures_2d || head_list.get_scroll_pos() != draw_list.get_scroll_pos(){
+            return false
+        }
+        let clip = draw_list.clip_rect();
+        if batch.clip == clip{
+            return true
+        }
+        if let Some(bounds) = bounds{
+            batch.contained && clip.contains_rect(bounds)
+        }
+        else{
+            false
+        }
+    }
+
+    // the draw list uniforms a batch is drawn with
+    pub fn batch_draw_list_uniforms(&self, batch:&mut DrawBatch){
+        self.draw_lists[batch.members[0].0].uniforms_with_clip(batch.clip, &mut batch.uniforms);
+    }
+}
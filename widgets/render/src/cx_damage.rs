@@ -0,0 +1,147 @@
+use crate::cx::*;
+
+// past this many separate rects a frame repaints their union instead
+const DAMAGE_RECTS_MAX:usize = 4;
+
+impl Cx{
+    // diffs what every draw call paints against what it painted last frame and puts the screen
+    // rects that changed in self.damage. Empty means the frame on screen is still correct
+    pub fn compute_damage(&mut self){
+        self.damage.truncate(0);
+        if self.draw_lists.len() == 0{
+            return
+        }
+        self.damage_frame += 1;
+        let screen = Rect{x:0., y:0., w:self.target_size.x, h:self.target_size.y};
+        let resized = self.painted_size != self.target_size || self.painted_dpi_factor != self.target_dpi_factor;
+        self.painted_size = self.target_size;
+        self.painted_dpi_factor = self.target_dpi_factor;
+
+        // render targets first, the draw calls sampling one only change when something inside it did
+        let mut targets = Vec::new();
+        self.collect_render_targets(0, &mut targets);
+        let mut changed_textures = Vec::new();
+        let mut damage = Vec::new();
+        for target in targets{
+            let mut target_damage = Vec::new();
+            self.damage_draw_list(target, target, &changed_textures, &mut target_damage);
+            if target_damage.len() > 0{
+                changed_textures.push(self.draw_lists[target].render_target.unwrap() as u32);
+            }
+        }
+        self.damage_draw_list(0, 0, &changed_textures, &mut damage);
+
+        // lists that are no longer drawn leave a hole where they were
+        let frame = self.damage_frame;
+        for draw_list in &mut self.draw_lists{
+            if draw_list.damage_frame != frame{
+                for draw_call in &mut draw_list.draw_calls{
+                    if let Some(rect) = draw_call.paint_rect.take(){
+                        damage.push(rect);
+                    }
+                }
+            }
+        }
+
+        if resized || !self.partial_paint{
+            if resized || damage.len() > 0{
+                self.damage.push(screen);
+            }
+            return
+        }
+        self.damage = merge_damage(damage, screen);
+    }
+
+    fn damage_draw_list(&mut self, root:usize, draw_list_id:usize, changed_textures:&[u32], damage:&mut Vec<Rect>){
+        let frame = self.damage_frame;
+        let draw_list = &mut self.draw_lists[draw_list_id];
+        draw_list.damage_frame = frame;
+        let scroll = draw_list.get_scroll_pos();
+        let clip = draw_list.clip_rect();
+        let moved = draw_list.paint_scroll != scroll || draw_list.paint_clip != clip;
+        draw_list.paint_scroll = scroll;
+        draw_list.paint_clip = clip;
+
+        for draw_call_id in 0..self.draw_lists[draw_list_id].draw_calls.len(){
+            let draw_list = &self.draw_lists[draw_list_id];
+            let draw_call = &draw_list.draw_calls[draw_call_id];
+            let in_use = draw_call_id < draw_list.draw_calls_len;
+            let sub_list_id = draw_call.sub_list_id;
+            if !in_use || sub_list_id != 0{
+                if in_use && (self.draw_lists[sub_list_id].render_target.is_none() || sub_list_id == root){
+                    self.damage_draw_list(root, sub_list_id, changed_textures, damage);
+                }
+                // a slot that no longer draws instances clears what it painted
+                if let Some(rect) = self.draw_lists[draw_list_id].draw_calls[draw_call_id].paint_rect.take(){
+                    damage.push(rect);
+                }
+                continue;
+            }
+            let textures_changed = draw_call.textures_2d.iter().any(|tex|{
+                changed_textures.contains(tex) || self.textures_2d[*tex as usize].dirty
+            });
+            if !moved && !draw_call.instance_dirty && !textures_changed && draw_call.paint_rect.is_some(){
+                continue;
+            }
+            let csh = &self.compiled_shaders[draw_call.shader_id];
+            let mut hash = hash_u32s(0xcbf29ce484222325, &[draw_call.shader_id as u32]);
+            hash = hash_f32s(hash, &draw_call.instance);
+            hash = hash_f32s(hash, &draw_call.uniforms);
+            hash = hash_u32s(hash, &draw_call.textures_2d);
+            hash = hash_f32s(hash, &[scroll.x, scroll.y, clip.x, clip.y, clip.w, clip.h]);
+            let rect = if draw_call.instance.len() < csh.instance_slots.max(1){
+                Rect::zero()
+            }
+            else if let Some(bounds) = self.draw_call_bounds(draw_list, draw_call, csh){
+                bounds.clip(clip)
+            }
+            else{
+                clip
+            };
+            let draw_call = &mut self.draw_lists[draw_list_id].draw_calls[draw_call_id];
+            if textures_changed || draw_call.paint_hash != hash || draw_call.paint_rect != Some(rect){
+                if let Some(old_rect) = draw_call.paint_rect{
+                    damage.push(old_rect);
+                }
+                damage.push(rect);
+            }
+            draw_call.paint_hash = hash;
+            draw_call.paint_rect = Some(rect);
+        }
+    }
+}
+
+// clips to the screen and merges overlapping rects, few enough to scissor each
+fn merge_damage(rects:Vec<Rect>, screen:Rect)->Vec<Rect>{
+    let mut out:Vec<Rect> = Vec::new();
+    for rect in rects{
+        let mut rect = rect.clip(screen);
+        if rect.w <= 0. || rect.h <= 0.{
+            continue;
+        }
+        while let Some(index) = out.iter().position(|r| r.intersects(rect)){
+            rect = rect.union(out.remove(index));
+        }
+        out.push(rect);
+    }
+    if out.len() > DAMAGE_RECTS_MAX{
+        let all = out.iter().skip(1).fold(out[0], |a, r| a.union(*r));
+        out.truncate(0);
+        out.push(all);
+    }
+    out
+}
+
+fn hash_u32s(mut hash:u64, data:&[u32])->u64{
+    for v in data{
+        hash = (hash ^ *v as u64).wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+fn hash_f32s(mut hash:u64, data:&[f32])->u64{
+    for v in data{
+        hash = (hash ^ v.to_bits() as u64).wrapping_mul(0x100000001b3);
+    }
+    hash
+}
@@ -13,19 +13,61 @@ pub struct FileReadRequest{
 pub struct CxDesktop{
     pub file_read_id:u64,
     pub file_read_requests:Vec<FileReadRequest>,
+    pub shader_cache_dir:Option<String>,
+    // problems reading or writing the shader cache, compiling carries on without it
+    pub shader_cache_errors:Vec<String>
 }
 
 impl Default for CxDesktop{
     fn default()->CxDesktop{
         CxDesktop{
             file_read_id:1,
-            file_read_requests:Vec::new()
+            file_read_requests:Vec::new(),
+            shader_cache_dir:CxDesktop::default_shader_cache_dir(),
+            shader_cache_errors:Vec::new()
         }
     }
 }
 
+impl CxDesktop{
+    // the per-user cache directory of the platform, None when the environment doesn't say where home is
+    pub fn default_shader_cache_dir()->Option<String>{
+        let var = |name:&str| std::env::var(name).ok().filter(|v| !v.is_empty());
+        let base = if cfg!(target_os = "windows"){
+            var("LOCALAPPDATA")
+        }
+        else if cfg!(target_os = "macos"){
+            var("HOME").map(|home| format!("{}/Library/Caches", home))
+        }
+        else{
+            var("XDG_CACHE_HOME").or_else(|| var("HOME").map(|home| format!("{}/.cache", home)))
+        };
+        base.map(|base| format!("{}/makepad/shaders", base))
+    }
+}
+
 impl Cx{
 
+    // compiled shaders get cached in this directory between runs, by default the per-user
+    // cache dir. None turns the cache off
+    pub fn set_shader_cache_dir(&mut self, dir:Option<&str>){
+        self.platform.desktop.shader_cache_dir = dir.map(|dir| dir.to_string());
+    }
+
+    // the clock the profiler reads
+    pub fn profile_time_ms()->f64{
+        time::precise_time_ns() as f64 / 1_000_000.0
+    }
+
+    // writes the profiler history for chrome://tracing
+    pub fn save_chrome_trace(&mut self, path:&str){
+        let trace = self.profiler_chrome_trace();
+        match File::create(path).and_then(|mut file| file.write_all(trace.as_bytes())){
+            Ok(_)=>println!("Saved profile to {}", path),
+            Err(err)=>println!("Failed to save profile to {}: {}", path, err)
+        }
+    }
+
     pub fn read_file(&mut self, path:&str)->u64{
         let desktop = &mut self.platform.desktop;
         let id = desktop.file_read_id;
@@ -53,23 +95,23 @@ impl Cx{
                 let mut buffer = Vec::new();
                 // read the whole file
                 if file.read_to_end(&mut buffer).is_ok(){
-                    event_handler(self, &mut Event::FileRead(FileReadEvent{
+                    self.call_event_handler(&mut event_handler, &mut Event::FileRead(FileReadEvent{
                         id:read_req.id,
                         data:Ok(buffer)
-                    }))
+                    }));
                 }
                 else{ 
-                    event_handler(self, &mut Event::FileRead(FileReadEvent{
+                    self.call_event_handler(&mut event_handler, &mut Event::FileRead(FileReadEvent{
                         id:read_req.id,
                         data:Err(format!("Failed to read {}", read_req.path))
-                    }))
+                    }));
                 }
             }
             else{
-                event_handler(self, &mut Event::FileRead(FileReadEvent{
+                self.call_event_handler(&mut event_handler, &mut Event::FileRead(FileReadEvent{
                     id:read_req.id,
                     data:Err(format!("Failed to open {}", read_req.path))
-                }))
+                }));
             }
         }
 
@@ -87,7 +129,7 @@ impl Cx{
         for i in 0..len{
             let resource_name = &self.fonts[i].name.clone();
             // lets turn a file into a binary dep
-            let file_result = File::open(&resource_name);
+            let file_result = File::open(font_file_and_face(&resource_name).0);
             if let Ok(mut file) = file_result{
                 let mut buffer = Vec::<u8>::new();
                 // read the whole file
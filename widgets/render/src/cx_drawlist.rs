@@ -22,6 +22,9 @@ impl Cx{
                 textures_2d:Vec::new(),
                 current_instance_offset:0,
                 instance_dirty:true,
+                batched:false,
+                paint_hash:0,
+                paint_rect:None,
                 platform:DrawCallPlatform{..Default::default()}
             });
             let dc = &mut draw_list.draw_calls[draw_call_id];
@@ -68,6 +71,137 @@ impl Cx{
         self.new_instance_layer(shader_id,instance_count)
     }
 
+    // like new_instance but only appends to a drawcall that samples the same texture
+    pub fn new_texture_instance(&mut self, shader_id:usize, texture_id:usize, instance_count:usize)->InstanceArea{
+        if !self.is_in_redraw_cycle{
+            panic!("calling get_instance outside of redraw cycle is not possible!");
+        }
+
+        let draw_list = &mut self.draw_lists[self.current_draw_list_id];
+        let sh = &self.compiled_shaders[shader_id];
+
+        for i in (0..draw_list.draw_calls_len).rev(){
+            let dc = &mut draw_list.draw_calls[i];
+            if dc.sub_list_id == 0 && dc.shader_id == sh.shader_id && dc.textures_2d.get(0) == Some(&(texture_id as u32)){
+                dc.current_instance_offset = dc.instance.len();
+                let slot_align = dc.instance.len() % sh.instance_slots;
+                if slot_align != 0{
+                    panic!("Instance offset disaligned! shader: {} misalign: {} slots: {}", shader_id, slot_align, sh.instance_slots);
+                }
+                return dc.get_current_instance_area(instance_count);
+            }
+        }
+
+        self.new_instance_layer(shader_id,instance_count)
+    }
+
+    // render target draw lists nested in draw_list_id, innermost first so
+    // a target can sample the textures of the targets nested inside it
+    pub fn collect_render_targets(&self, draw_list_id:usize, out:&mut Vec<usize>){
+        let draw_list = &self.draw_lists[draw_list_id];
+        for draw_call_id in 0..draw_list.draw_calls_len{
+            let sub_list_id = draw_list.draw_calls[draw_call_id].sub_list_id;
+            if sub_list_id != 0{
+                self.collect_render_targets(sub_list_id, out);
+            }
+        }
+        if draw_list.render_target.is_some() && draw_list_id != 0{
+            out.push(draw_list_id);
+        }
+    }
+
+    // a render target keeps its texture between frames. It only renders again when something in it
+    // got redrawn or written to, a render target in it rendered, or it moved, scrolled or resized
+    pub fn render_target_is_stale(&self, draw_list_id:usize, rendered:&[usize])->bool{
+        let dpi_factor = if self.target_dpi_factor > 0.0{self.target_dpi_factor}else{1.0};
+        let draw_list = &self.draw_lists[draw_list_id];
+        let tex = &self.textures_2d[draw_list.render_target.unwrap()];
+        if draw_list.target_redraw_id == 0 || draw_list.target_rect != draw_list.rect ||
+            draw_list.target_scroll != draw_list.get_scroll_pos() ||
+            tex.width != ((draw_list.rect.w * dpi_factor) as usize).max(1) ||
+            tex.height != ((draw_list.rect.h * dpi_factor) as usize).max(1){
+            return true
+        }
+        self.draw_list_changed_since(draw_list_id, draw_list.target_redraw_id, rendered)
+    }
+
+    fn draw_list_changed_since(&self, draw_list_id:usize, redraw_id:u64, rendered:&[usize])->bool{
+        let draw_list = &self.draw_lists[draw_list_id];
+        if draw_list.redraw_id > redraw_id{
+            return true
+        }
+        for draw_call in &draw_list.draw_calls[0..draw_list.draw_calls_len]{
+            let sub_list_id = draw_call.sub_list_id;
+            if sub_list_id == 0{
+                if draw_call.instance_dirty{
+                    return true
+                }
+            }
+            else if self.draw_lists[sub_list_id].render_target.is_some(){
+                // we only sample its texture, so it matters if that changed
+                if rendered.contains(&sub_list_id){
+                    return true
+                }
+            }
+            else if self.draw_list_changed_since(sub_list_id, redraw_id, rendered){
+                return true
+            }
+        }
+        false
+    }
+
+    // sizes the target texture to the draw list rect and sets up a camera that maps the rect onto it
+    pub fn prepare_render_target(&mut self, draw_list_id:usize)->usize{
+        let dpi_factor = if self.target_dpi_factor > 0.0{self.target_dpi_factor}else{1.0};
+        let redraw_id = self.redraw_id;
+        let draw_list = &mut self.draw_lists[draw_list_id];
+        let texture_id = draw_list.render_target.unwrap();
+        let rect = draw_list.rect;
+        draw_list.target_redraw_id = redraw_id;
+        draw_list.target_rect = rect;
+        draw_list.target_scroll = draw_list.get_scroll_pos();
+        // top and bottom are swapped so the top of the rect ends up in texture row 0
+        let camera_projection = Mat4::ortho(
+            rect.x, rect.x + rect.w, rect.y + rect.h, rect.y, -100.0, 100.0,
+            1.0, 1.0
+        );
+        draw_list.target_uniforms.resize(self.uniforms.len(), 0.0);
+        for i in 0..16{
+            draw_list.target_uniforms[i] = camera_projection.v[i];
+        }
+        let tex = &mut self.textures_2d[texture_id];
+        tex.width = ((rect.w * dpi_factor) as usize).max(1);
+        tex.height = ((rect.h * dpi_factor) as usize).max(1);
+        texture_id
+    }
+
+    // one area for instances drawn together into several draw calls, empty ones are left out
+    pub fn new_area_group(&mut self, members:&[InstanceArea])->Area{
+        let drawn:Vec<InstanceArea> = members.iter().filter(|inst| inst.instance_count > 0).cloned().collect();
+        if drawn.len() < 2{
+            return Area::Instance(drawn.first().cloned().unwrap_or(members[0]))
+        }
+        let draw_list_id = drawn[0].draw_list_id;
+        let draw_list = &mut self.draw_lists[draw_list_id];
+        draw_list.area_groups.push(drawn);
+        Area::Group(GroupArea{
+            draw_list_id:draw_list_id,
+            group_id:draw_list.area_groups.len() - 1,
+            redraw_id:draw_list.redraw_id
+        })
+    }
+
+    // hands the draw list back for the next view that begins, with the texture it rendered into
+    pub fn free_draw_list(&mut self, draw_list_id:usize){
+        if let Some(texture_id) = self.draw_lists[draw_list_id].render_target.take(){
+            self.free_texture_2d(texture_id);
+        }
+        let draw_list = &mut self.draw_lists[draw_list_id];
+        draw_list.draw_calls_len = 0;
+        draw_list.target_redraw_id = 0;
+        self.draw_lists_free.push(draw_list_id);
+    }
+
     pub fn new_aligned_instance(&mut self, shader_id:usize, instance_count:usize)->AlignedInstance{
         let instance_area = self.new_instance(shader_id, instance_count);
         
@@ -79,6 +213,17 @@ impl Cx{
         }
     }
 
+    pub fn new_aligned_texture_instance(&mut self, shader_id:usize, texture_id:usize, instance_count:usize)->AlignedInstance{
+        let instance_area = self.new_texture_instance(shader_id, texture_id, instance_count);
+        
+        let align_index = self.align_list.len();
+        self.align_list.push(Area::Instance(instance_area.clone()));
+        AlignedInstance{
+            inst:instance_area,
+            index:align_index
+        }
+    }
+
     pub fn update_aligned_instance_count(&mut self,align:&AlignedInstance){
         if let Area::Instance(instance) = &mut self.align_list[align.index]{
             instance.instance_count = align.inst.instance_count;
@@ -105,6 +250,9 @@ pub struct DrawCall{
     pub uniforms:Vec<f32>,  // draw uniforms
     pub textures_2d:Vec<u32>,
     pub instance_dirty:bool,
+    pub batched:bool, // the platform buffer holds the instances of a batch this draw call leads
+    pub paint_hash:u64, // of what was painted last, to find damage
+    pub paint_rect:Option<Rect>, // where on screen that went, None when nothing is painted
     pub platform:DrawCallPlatform
 }
 
@@ -137,13 +285,25 @@ pub struct DrawList{
     pub uniforms:Vec<f32>, // cmdlist uniforms
     pub platform:DrawListPlatform,
     pub rect:Rect,
-    pub clipped:bool
+    pub clipped:bool,
+    pub render_target:Option<usize>, // texture we render into instead of our parent
+    pub target_uniforms:Vec<f32>, // cx uniforms used while rendering into the texture
+    pub target_redraw_id:u64, // the redraw the texture was last rendered after, 0 if never
+    pub target_rect:Rect,
+    pub target_scroll:Vec2,
+    pub batches:Vec<DrawBatch>, // how the lists drawn with this one are issued, for the main list and render targets
+    pub damage_frame:u64, // last frame compute_damage saw us drawn
+    pub area_groups:Vec<Vec<InstanceArea>>, // the members of the Area::Group areas drawn into us
+    pub paint_scroll:Vec2,
+    pub paint_clip:Rect
 }
 
 impl DrawList{
     pub fn initialize(&mut self, clipped:bool, redraw_id:u64){
         self.clipped = clipped;
         self.redraw_id = redraw_id;
+        self.render_target = None;
+        self.area_groups.truncate(0);
         self.uniforms.resize(DL_UNI_SIZE, 0.0);
     }
 
@@ -192,6 +352,26 @@ impl DrawList{
         return Rect{x:x1, y:y1, w:x2-x1, h:y2-y1};
     }
 
+    // what set_clipping_uniforms clips with
+    pub fn clip_rect(&self)->Rect{
+        if self.clipped{
+            self.rect
+        }
+        else{
+            Rect{x:-50000., y:-50000., w:100000., h:100000.}
+        }
+    }
+
+    // our uniforms with another clip, for batches that span lists
+    pub fn uniforms_with_clip(&self, clip:Rect, out:&mut Vec<f32>){
+        out.truncate(0);
+        out.extend_from_slice(&self.uniforms);
+        out[DL_UNI_CLIP+0] = clip.x;
+        out[DL_UNI_CLIP+1] = clip.y;
+        out[DL_UNI_CLIP+2] = clip.x + clip.w;
+        out[DL_UNI_CLIP+3] = clip.y + clip.h;
+    }
+
     pub fn uniform_draw_list_clip(&mut self, min_x:f32, min_y:f32, max_x:f32, max_y:f32){
         
         self.uniforms[DL_UNI_CLIP+0] = min_x;
@@ -200,3 +380,100 @@ impl DrawList{
         self.uniforms[DL_UNI_CLIP+3] = max_y;
     }
 }
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn draw_call(sub_list_id:usize)->DrawCall{
+        DrawCall{
+            draw_call_id:0,
+            draw_list_id:0,
+            redraw_id:0,
+            sub_list_id,
+            shader_id:0,
+            instance:Vec::new(),
+            current_instance_offset:0,
+            uniforms:Vec::new(),
+            textures_2d:Vec::new(),
+            instance_dirty:false,
+            batched:false,
+            paint_hash:0,
+            paint_rect:None,
+            platform:DrawCallPlatform{..Default::default()}
+        }
+    }
+
+    // a root list 0 holding target 1, which holds a plain list 2 and target 3
+    fn targets_cx()->Cx{
+        let mut cx = Cx::default();
+        cx.uniforms.resize(32, 0.0);
+        cx.redraw_id = 5;
+        for _ in 0..4{
+            cx.draw_lists.push(DrawList{..Default::default()});
+        }
+        for id in 0..4{
+            cx.draw_lists[id].initialize(false, cx.redraw_id);
+            cx.draw_lists[id].rect = Rect{x:10., y:10., w:100., h:50.};
+        }
+        cx.draw_lists[0].draw_calls = vec![draw_call(1)];
+        cx.draw_lists[1].draw_calls = vec![draw_call(0), draw_call(2), draw_call(3)];
+        cx.draw_lists[2].draw_calls = vec![draw_call(0)];
+        for (id, len) in [(0, 1), (1, 3), (2, 1)].iter(){
+            cx.draw_lists[*id].draw_calls_len = *len;
+        }
+        for id in [1, 3].iter(){
+            let texture_id = cx.new_empty_texture_2d().texture_id;
+            cx.draw_lists[*id].render_target = Some(texture_id);
+        }
+        cx
+    }
+
+    fn render(cx:&mut Cx)->Vec<usize>{
+        let mut targets = Vec::new();
+        cx.collect_render_targets(0, &mut targets);
+        let mut rendered = Vec::new();
+        for id in targets{
+            if cx.render_target_is_stale(id, &rendered){
+                rendered.push(id);
+                cx.prepare_render_target(id);
+            }
+        }
+        cx.redraw_id += 1;
+        rendered
+    }
+
+    #[test]
+    fn unchanged_targets_are_not_rendered_again(){
+        let mut cx = targets_cx();
+        assert_eq!(render(&mut cx), vec![3, 1]);
+        assert_eq!(render(&mut cx), Vec::<usize>::new());
+        // a redraw of the root list alone doesn't touch the targets
+        cx.draw_lists[0].redraw_id = cx.redraw_id;
+        assert_eq!(render(&mut cx), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn changes_inside_a_target_render_it(){
+        let mut cx = targets_cx();
+        render(&mut cx);
+        // a plain list inside redrew
+        cx.draw_lists[2].redraw_id = cx.redraw_id;
+        assert_eq!(render(&mut cx), vec![1]);
+        // an instance got written to, like an animation does
+        cx.draw_lists[2].draw_calls[0].instance_dirty = true;
+        assert_eq!(render(&mut cx), vec![1]);
+        cx.draw_lists[2].draw_calls[0].instance_dirty = false;
+        // the nested target changed, so its parent samples a new texture
+        cx.draw_lists[3].redraw_id = cx.redraw_id;
+        assert_eq!(render(&mut cx), vec![3, 1]);
+        // moved, scrolled and resized
+        cx.draw_lists[1].rect.x += 1.;
+        assert_eq!(render(&mut cx), vec![1]);
+        cx.draw_lists[1].set_scroll_y(3.);
+        assert_eq!(render(&mut cx), vec![1]);
+        cx.target_dpi_factor = 2.0;
+        assert_eq!(render(&mut cx), vec![3, 1]);
+        assert_eq!(render(&mut cx), Vec::<usize>::new());
+    }
+}
@@ -1,4 +1,20 @@
 use crate::cx::*;
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+
+// ttf glyphs are rasterized into a multi channel distance field at this many pixels per em
+const TTF_SDF_SIZE:f32 = 48.0;
+const TTF_SDF_PAD:usize = 4;
+const TEXT_LAYOUT_CACHE_SIZE:usize = 8192;
+
+// type ids of the prebaked formats, .font.html files carry a scale after the header
+pub const FONT_TYPE_FONT:u32 = 0x03F01175;
+pub const FONT_TYPE_FONT_HTML:u32 = 0x03F01176;
+
+// the codepoints baked when a ttf loads
+const TTF_BAKE_RANGES:[(u32, u32);4] = [(0x20, 0x17f), (0x2000, 0x206f), (0x20a0, 0x20bf), (0x2190, 0x21ff)];
 
 #[derive(Clone)]
 pub struct BinaryDep{
@@ -75,6 +91,16 @@ impl BinaryDep{
         Ok(len)
     }
 }
+// a font name is its file, with #n behind it for the nth face of a font collection
+pub fn font_file_and_face(name:&str)->(&str, usize){
+    if let Some(hash) = name.rfind('#'){
+        if let Ok(face) = name[hash + 1..].parse(){
+            return (&name[..hash], face)
+        }
+    }
+    (name, 0)
+}
+
 impl Cx{
     pub fn load_font(&mut self, file_name: &str)->usize{
         let found = self.fonts.iter().position(|v| v.name == file_name);
@@ -90,21 +116,174 @@ impl Cx{
         font_id
     }
 
+    // rasterizes ttf glyphs the first time they are drawn, so the whole font is usable
+    pub fn load_font_glyphs(&mut self, font_id:usize, chars:&[char]){
+        let ttf = if let Some(ttf) = &self.fonts[font_id].ttf{ttf.clone()}else{return};
+        for c in chars{
+            let unicode = *c as u32;
+            if self.fonts[font_id].unicodes.get(unicode).is_some(){
+                continue
+            }
+            let slot = self.load_font_glyph_id(font_id, ttf.glyph_index(unicode));
+            self.fonts[font_id].unicodes.insert(unicode, slot);
+        }
+    }
+
+    // the slot of a ttf glyph by glyph id, shaping produces glyphs no codepoint maps to
+    pub fn load_font_glyph_id(&mut self, font_id:usize, glyph:u32)->usize{
+        let font = &self.fonts[font_id];
+        let ttf = if let Some(ttf) = &font.ttf{ttf.clone()}else{return font.missing_slot};
+        if glyph == 0{
+            return font.missing_slot
+        }
+        if let Some(slot) = font.glyph_ids.get(&glyph){
+            return *slot
+        }
+        let generation = font.atlas_generation;
+        let slot = match self.add_ttf_glyph(font_id, &ttf, glyph){
+            Ok(slot)=>slot,
+            Err(err)=>{
+                println!("Cannot rasterize glyph {} of {}: {}", glyph, self.fonts[font_id].name, err);
+                self.fonts[font_id].missing_slot
+            }
+        };
+        let font = &mut self.fonts[font_id];
+        font.glyph_ids.insert(glyph, slot);
+        font.slots = font.glyphs.len();
+        // texcoords drawn earlier this frame are stale
+        if generation != font.atlas_generation{
+            self.redraw_area(Area::All);
+        }
+        slot
+    }
+
+    // rasterizes a ttf glyph into the atlas of the font
+    fn add_ttf_glyph(&mut self, font_id:usize, ttf:&TrueTypeFont, glyph:u32)->Result<usize, String>{
+        let atlas_id = if let Some(atlas_id) = self.fonts[font_id].atlas_id{atlas_id}else{
+            return Err("Font has no atlas".to_string())
+        };
+        let outline = ttf.outline(glyph)?;
+        let em = ttf.units_per_em;
+        let (tw, th, ox, oy, pixels) = outline.rasterize_msdf(TTF_SDF_SIZE / em, TTF_SDF_PAD);
+        let mut g = Glyph{
+            advance:ttf.advance(glyph) / em,
+            tw:tw,
+            th:th,
+            ..Default::default()
+        };
+        if tw > 0 && th > 0{
+            g.x1 = -ox / TTF_SDF_SIZE;
+            g.y1 = -oy / TTF_SDF_SIZE;
+            g.x2 = g.x1 + tw as f32 / TTF_SDF_SIZE;
+            g.y2 = g.y1 + th as f32 / TTF_SDF_SIZE;
+            g.alloc_id = if let Some(alloc_id) = self.atlas_alloc(atlas_id, tw, th, false){alloc_id}else{
+                return Err(format!("Font atlas full at glyph {}", glyph))
+            };
+            self.atlas_write(atlas_id, g.alloc_id, &pixels)?;
+        }
+        let font = &mut self.fonts[font_id];
+        font.glyphs.push(g);
+        let slot = font.glyphs.len() - 1;
+        self.update_ttf_texcoords(font_id, slot);
+        Ok(slot)
+    }
+
+    // when the atlas grew or moved glyphs since the last update all uvs change, otherwise only the new one
+    fn update_ttf_texcoords(&mut self, font_id:usize, slot:usize){
+        let font = &mut self.fonts[font_id];
+        let atlas = if let Some(atlas_id) = font.atlas_id{&self.atlases[atlas_id]}else{return};
+        let slots = if font.atlas_generation != atlas.generation{
+            font.atlas_generation = atlas.generation;
+            font.width = atlas.packer.width;
+            font.height = atlas.packer.height;
+            0..font.glyphs.len()
+        }
+        else{
+            slot..slot + 1
+        };
+        for g in &mut font.glyphs[slots]{
+            if let Some((u0, v0, u1, v1)) = atlas.packer.uv(g.alloc_id){
+                g.tx1 = u0;
+                g.ty1 = v1;
+                g.tx2 = u1;
+                g.ty2 = v0;
+            }
+        }
+    }
+
+    // bakes the common codepoints up front, the rest is rasterized the first time it is drawn
+    pub fn load_ttf_font(&mut self, font_id:usize, data:Vec<u8>, face:usize)->Result<(), String>{
+        let ttf = Rc::new(TrueTypeFont::parse_face(data, face)?);
+        let atlas_id = self.new_atlas(512, 512, 4096);
+        let font = &mut self.fonts[font_id];
+        font.scale = 1.0;
+        font.sdf_range = 2.0 * TTF_SDF_PAD as f32;
+        font.texture_id = self.atlases[atlas_id].texture_id;
+        font.atlas_id = Some(atlas_id);
+        font.atlas_generation = self.atlases[atlas_id].generation;
+        font.width = 512;
+        font.height = 512;
+        font.ttf = Some(ttf.clone());
+        for (start, end) in TTF_BAKE_RANGES.iter(){
+            for unicode in *start..=*end{
+                let glyph = ttf.glyph_index(unicode);
+                if glyph == 0{
+                    continue
+                }
+                let slot = if let Some(slot) = self.fonts[font_id].glyph_ids.get(&glyph){
+                    *slot
+                }
+                else{
+                    let slot = self.add_ttf_glyph(font_id, &ttf, glyph)?;
+                    self.fonts[font_id].glyph_ids.insert(glyph, slot);
+                    slot
+                };
+                self.fonts[font_id].unicodes.insert(unicode, slot);
+            }
+        }
+        let font = &mut self.fonts[font_id];
+        // tabs and newlines take the space of a space
+        if let Some(space) = font.unicodes.get(32){
+            for unicode in &[9, 10]{
+                font.unicodes.insert(*unicode, font.glyphs.len());
+                font.glyphs.push(Glyph{unicode:*unicode, tx1:0.0, ty1:0.0, tx2:0.0, ty2:0.0, alloc_id:0, ..font.glyphs[space].clone()});
+            }
+        }
+        font.add_missing_glyph();
+        font.add_control_glyph();
+        font.slots = font.glyphs.len();
+        // kerning is looked up per pair in get_kern, there is no table to bake
+        Ok(())
+    }
+
+    pub fn load_font_list(&mut self, file_names:&[String])->Vec<usize>{
+        file_names.iter().map(|file_name| self.load_font(file_name)).collect()
+    }
+
     pub fn load_font_from_binary_dep(&mut self, bin_dep: &mut BinaryDep)-> Result<(), String>{
         let found = self.fonts.iter().position(|v| v.name == bin_dep.name);
         if found.is_none(){
             return Err("Binary dep not a font".to_string());
         }
-        let (font,font_id, texture_id)={
-            let mut out_tex = self.new_empty_texture_2d();
-            (Font::from_binary_dep(bin_dep, &mut out_tex)?, found.unwrap(), out_tex.texture_id)
-        };
-        self.fonts[font_id] = Font{
-            font_id:self.fonts.len(),
-            texture_id: texture_id,
-            loaded:true,
-            ..font
-        };
+        let font_id = found.unwrap();
+        if TrueTypeFont::is_ttf(&bin_dep.vec_obj){
+            self.load_ttf_font(font_id, bin_dep.vec_obj.clone(), font_file_and_face(&bin_dep.name).1)?;
+            self.fonts[font_id].loaded = true;
+        }
+        else{
+            let (font, texture_id)={
+                let mut out_tex = self.new_empty_texture_2d();
+                (Font::from_binary_dep(bin_dep, &mut out_tex)?, out_tex.texture_id)
+            };
+            self.fonts[font_id] = Font{
+                font_id:self.fonts.len(),
+                texture_id: texture_id,
+                loaded:true,
+                ..font
+            };
+        }
+        // layouts may have used a fallback or the missing glyph for this font
+        self.text_layouts.clear();
         Ok(())
     }
 }
@@ -124,7 +303,39 @@ pub struct Glyph{
     pub tx1:f32,
     pub ty1:f32,
     pub tx2:f32,
-    pub ty2:f32
+    pub ty2:f32,
+    pub alloc_id:u64 // atlas allocation of runtime rasterized glyphs
+}
+
+// Sparse codepoint to glyph slot map covering all of unicode, pages of 256 codepoints
+// are only allocated when something maps into them
+#[derive(Default, Clone)]
+pub struct GlyphMap{
+    pub pages:Vec<Option<Box<[u32;256]>>> // slot + 1, 0 is unmapped
+}
+
+impl GlyphMap{
+    pub fn get(&self, unicode:u32)->Option<usize>{
+        if let Some(Some(page)) = self.pages.get((unicode >> 8) as usize){
+            let v = page[(unicode & 0xff) as usize];
+            if v != 0{
+                return Some(v as usize - 1)
+            }
+        }
+        None
+    }
+
+    pub fn insert(&mut self, unicode:u32, slot:usize){
+        if unicode > 0x10ffff{
+            return
+        }
+        let page_id = (unicode >> 8) as usize;
+        if page_id >= self.pages.len(){
+            self.pages.resize(page_id + 1, None);
+        }
+        let page = self.pages[page_id].get_or_insert_with(|| Box::new([0;256]));
+        page[(unicode & 0xff) as usize] = slot as u32 + 1;
+    }
 }
 
 #[derive(Default, Clone)]
@@ -146,15 +357,25 @@ pub struct Font{
     pub onesize:usize,
     pub kernsize:usize, 
     pub scale:f32,
+    pub sdf_range:f32, // distance field range in atlas pixels for fonts rasterized at load, 0 for baked fonts
     pub glyphs:Vec<Glyph>,
-    pub unicodes:Vec<usize>,
+    pub unicodes:GlyphMap,
+    pub glyph_ids:HashMap<u32, usize>, // ttf glyph id to slot
+    pub missing_slot:usize,
     pub kerntable:Vec<Kern>,
-    pub texture_id:usize
+    pub kern_map:HashMap<(u32, u32), f32>, // kerntable by unicode pair
+    pub texture_id:usize,
+    pub ttf:Option<Rc<TrueTypeFont>>,
+    pub atlas_id:Option<usize>, // cx atlas the ttf glyphs are rasterized into
+    pub atlas_generation:u64
 }
 
 impl Font{
     pub fn from_binary_dep(inp: &mut BinaryDep, tex:&mut Texture2D) -> Result<Font, String> {
-        let _type_id = inp.u32()?;
+        let type_id = inp.u32()?;
+        if type_id != FONT_TYPE_FONT && type_id != FONT_TYPE_FONT_HTML{
+            return Err(format!("{} is not a font, type id {:x}", inp.name, type_id))
+        }
 
         let mut ff = Font{
             font_id: 0,
@@ -164,11 +385,9 @@ impl Font{
             rgbsize: inp.u32()? as usize,
             onesize: inp.u32()? as usize,
             kernsize:inp.u32()? as usize,
-            scale:inp.f32()?,
+            scale:if type_id == FONT_TYPE_FONT_HTML{inp.f32()?}else{1.0},
             ..Default::default()
         };
-        ff.unicodes.resize(65535, 0);
-
         ff.glyphs.reserve(ff.slots as usize);
         for _i in 0..(ff.slots as usize){
             ff.glyphs.push(Glyph{
@@ -185,7 +404,8 @@ impl Font{
                 tx1:0.0,
                 ty1:0.0,
                 tx2:0.0,
-                ty2:0.0
+                ty2:0.0,
+                alloc_id:0
             })
         }
         // read the kerning table
@@ -260,7 +480,7 @@ impl Font{
             b.ty1 = ((oy+b.th) as f32) / (ff.height as f32);
             b.tx2 = ((ox+b.tw) as f32) / (ff.width as f32);
             b.ty2 = (oy as f32) / (ff.height as f32);
-            ff.unicodes[b.unicode as usize] = i as usize;
+            ff.unicodes.insert(b.unicode, i as usize);
             //ff.unicodes.insert(b.unicode, i as  u32);
             ox += b.tw+1;
         }
@@ -283,7 +503,7 @@ impl Font{
             ty2:0.0,
         });*/
 
-        let mut excl_slot = ff.glyphs[ff.unicodes[33]].clone();
+        let mut excl_slot = ff.glyphs[ff.unicodes.get(33).unwrap_or(0)].clone();
 
         // set texture coord to 0
         excl_slot.tx1 = 0.0;
@@ -291,24 +511,295 @@ impl Font{
         excl_slot.tx2 = 0.0;
         excl_slot.ty2 = 0.0;
 
-        ff.unicodes[32] = ff.glyphs.len();
+        ff.unicodes.insert(32, ff.glyphs.len());
         ff.glyphs.push(Glyph{
             unicode:32,
             ..excl_slot.clone()
         });
 
-        ff.unicodes[10] = ff.glyphs.len();
+        ff.unicodes.insert(10, ff.glyphs.len());
         ff.glyphs.push(Glyph{
             unicode:10,
             ..excl_slot.clone()
         });
 
-        ff.unicodes[9] = ff.glyphs.len();
+        ff.unicodes.insert(9, ff.glyphs.len());
         ff.glyphs.push(Glyph{
             unicode:9,
             ..excl_slot.clone()
         });
 
+        ff.add_missing_glyph();
+        ff.add_control_glyph();
+        ff.build_kern_map();
         Ok(ff)
     }
-}
\ No newline at end of file
+
+    // the box drawn for codepoints the font doesnt have, negative texcoords tell the text shader
+    pub fn add_missing_glyph(&mut self){
+        let advance = if let Some(space) = self.unicodes.get(32){self.glyphs[space].advance}else{0.6};
+        self.missing_slot = self.glyphs.len();
+        self.glyphs.push(Glyph{
+            unicode:0,
+            x1:0.1 * advance,
+            y1:0.0,
+            x2:0.9 * advance,
+            y2:0.7,
+            advance:advance,
+            tx1:-1.0,
+            ty1:-1.0,
+            tx2:-1.0,
+            ty2:-1.0,
+            ..Default::default()
+        });
+    }
+
+    // control chars like the \r of a crlf line draw nothing and take no room, tabs and newlines keep their own glyph
+    pub fn add_control_glyph(&mut self){
+        let slot = self.glyphs.len();
+        self.glyphs.push(Glyph::default());
+        for unicode in (0..32).chain(127..160){
+            if self.unicodes.get(unicode).is_none(){
+                self.unicodes.insert(unicode, slot);
+            }
+        }
+    }
+
+    pub fn has_glyph(&self, unicode:u32)->bool{
+        if let Some(slot) = self.unicodes.get(unicode){
+            return slot != self.missing_slot
+        }
+        false
+    }
+
+    pub fn glyph_slot(&self, unicode:u32)->usize{
+        self.unicodes.get(unicode).unwrap_or(self.missing_slot)
+    }
+
+    pub fn build_kern_map(&mut self){
+        self.kern_map = self.kerntable.iter().map(|k| ((k.i, k.j), k.kern)).collect();
+    }
+
+    // kerning between two codepoints in ems
+    pub fn get_kern(&self, i:u32, j:u32)->f32{
+        if let Some(ttf) = &self.ttf{
+            return ttf.pair_kern(ttf.glyph_index(i), ttf.glyph_index(j)) / ttf.units_per_em
+        }
+        if let Some(kern) = self.kern_map.get(&(i, j)){
+            return *kern
+        }
+        0.0
+    }
+}
+
+// a line of text laid out in ems, so it fits every font size and position
+#[derive(Clone, Default, Debug)]
+pub struct TextLayout{
+    pub glyphs:Vec<TextLayoutGlyph>, // in visual order
+    pub width:f32
+}
+
+#[derive(Clone, Copy, Default, Debug)]
+pub struct TextLayoutGlyph{
+    pub logical:usize, // index of the char
+    pub font_id:usize,
+    pub slot:Option<usize>, // None for the chars a ligature covers after its first
+    pub x:f32,
+    pub w:f32,
+    pub dx:f32,
+    pub dy:f32
+}
+
+// borrows the chars so a cache hit doesn't allocate
+#[derive(Clone, Copy, Hash, PartialEq)]
+pub struct TextLayoutKey<'a>{
+    pub font_id:usize,
+    pub font_fallbacks:&'a [usize],
+    pub ligatures:bool,
+    pub monospace:bool,
+    pub para_level:u8,
+    pub levels:&'a [u8], // empty when all left to right
+    pub chars:&'a [char]
+}
+
+#[derive(Clone)]
+pub struct TextLayoutEntry{
+    pub font_id:usize,
+    pub font_fallbacks:Vec<usize>,
+    pub ligatures:bool,
+    pub monospace:bool,
+    pub para_level:u8,
+    pub levels:Vec<u8>,
+    pub chars:Vec<char>,
+    pub layout:Rc<TextLayout>,
+    pub last_used:u64
+}
+
+impl TextLayoutEntry{
+    fn key(&self)->TextLayoutKey<'_>{
+        TextLayoutKey{
+            font_id:self.font_id,
+            font_fallbacks:&self.font_fallbacks,
+            ligatures:self.ligatures,
+            monospace:self.monospace,
+            para_level:self.para_level,
+            levels:&self.levels,
+            chars:&self.chars
+        }
+    }
+}
+
+// text layouts by font and string, the least recently used ones are evicted
+#[derive(Clone, Default)]
+pub struct TextLayoutCache{
+    pub entries:HashMap<u64, TextLayoutEntry>, // by key hash, a colliding key replaces the entry
+    pub use_counter:u64,
+    pub hits:u64,
+    pub misses:u64
+}
+
+impl TextLayoutCache{
+    fn hash_key(key:&TextLayoutKey)->u64{
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    pub fn get(&mut self, key:&TextLayoutKey)->Option<Rc<TextLayout>>{
+        self.use_counter += 1;
+        if let Some(entry) = self.entries.get_mut(&TextLayoutCache::hash_key(key)){
+            if entry.key() == *key{
+                entry.last_used = self.use_counter;
+                self.hits += 1;
+                return Some(entry.layout.clone())
+            }
+        }
+        self.misses += 1;
+        None
+    }
+
+    pub fn insert(&mut self, key:&TextLayoutKey, layout:Rc<TextLayout>){
+        if self.entries.len() >= TEXT_LAYOUT_CACHE_SIZE{
+            // evict a quarter at a time so inserting stays cheap
+            let mut uses:Vec<u64> = self.entries.values().map(|entry| entry.last_used).collect();
+            uses.sort();
+            let threshold = uses[uses.len() / 4];
+            self.entries.retain(|_, entry| entry.last_used > threshold);
+        }
+        self.entries.insert(TextLayoutCache::hash_key(key), TextLayoutEntry{
+            font_id:key.font_id,
+            font_fallbacks:key.font_fallbacks.to_vec(),
+            ligatures:key.ligatures,
+            monospace:key.monospace,
+            para_level:key.para_level,
+            levels:key.levels.to_vec(),
+            chars:key.chars.to_vec(),
+            layout:layout,
+            last_used:self.use_counter
+        });
+    }
+
+    pub fn clear(&mut self){
+        self.entries.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn ttf_font(cx:&mut Cx, file:&str)->usize{
+        let data = std::fs::read(file).expect("dejavu is installed");
+        let font_id = cx.load_font(file);
+        cx.load_ttf_font(font_id, data, 0).expect("font parses");
+        font_id
+    }
+
+    // every rasterized glyph has its pixels in the shared atlas and the uvs of its allocation
+    fn assert_glyphs_in_atlas(cx:&Cx, font_id:usize){
+        let font = &cx.fonts[font_id];
+        let atlas = &cx.atlases[font.atlas_id.unwrap()];
+        let tex = &cx.textures_2d[atlas.texture_id];
+        assert_eq!((font.width, font.height), (atlas.packer.width, atlas.packer.height));
+        assert_eq!((tex.width, tex.height), (atlas.packer.width, atlas.packer.height));
+        for g in font.glyphs.iter().filter(|g| g.alloc_id != 0){
+            let (u0, v0, u1, v1) = atlas.packer.uv(g.alloc_id).unwrap();
+            assert_eq!((g.tx1, g.ty1, g.tx2, g.ty2), (u0, v1, u1, v0));
+            let r = atlas.packer.rect(g.alloc_id).unwrap();
+            assert!((r.y..r.y + r.h).any( | y | tex.image[y * tex.width + r.x..y * tex.width + r.x + r.w].iter().any( | p | *p != 0)));
+        }
+    }
+
+    #[test]
+    fn ttf_glyphs_go_through_the_shared_atlas(){
+        let mut cx = Cx::default();
+        let font_id = ttf_font(&mut cx, "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf");
+        let atlas_id = cx.fonts[font_id].atlas_id.unwrap();
+        assert_eq!(cx.fonts[font_id].texture_id, cx.atlases[atlas_id].texture_id);
+        assert_glyphs_in_atlas(&cx, font_id);
+        // drawing more than fits makes the atlas grow, which moves the uvs of the glyphs baked before
+        let generation = cx.atlases[atlas_id].generation;
+        let chars:Vec<char> = (0x370..0x530).chain(0x1f00..0x1fff).filter_map(std::char::from_u32).collect();
+        cx.load_font_glyphs(font_id, &chars);
+        assert!(cx.atlases[atlas_id].generation > generation);
+        assert_eq!(cx.fonts[font_id].atlas_generation, cx.atlases[atlas_id].generation);
+        assert_glyphs_in_atlas(&cx, font_id);
+    }
+
+    #[test]
+    fn glyph_map_is_sparse_over_all_of_unicode(){
+        let mut map = GlyphMap::default();
+        assert_eq!(map.get(65), None);
+        map.insert(65, 0);
+        map.insert(0x1f600, 7);
+        map.insert(0x10ffff, 3);
+        assert_eq!(map.get(65), Some(0));
+        assert_eq!(map.get(0x1f600), Some(7));
+        assert_eq!(map.get(0x10ffff), Some(3));
+        // neighbours in a page stay unmapped, and pages nothing maps into are not allocated
+        assert_eq!(map.get(66), None);
+        assert_eq!(map.get(0x1f601), None);
+        assert_eq!(map.pages.iter().filter(|page| page.is_some()).count(), 3);
+        // remapping replaces, codepoints past unicode are ignored
+        map.insert(65, 9);
+        assert_eq!(map.get(65), Some(9));
+        map.insert(0x110000, 1);
+        assert_eq!(map.get(0x110000), None);
+        assert_eq!(map.pages.len(), 0x1100);
+    }
+
+    #[test]
+    fn control_chars_take_no_room(){
+        let mut cx = Cx::default();
+        let font_id = ttf_font(&mut cx, "/usr/share/fonts/truetype/dejavu/DejaVuSansMono.ttf");
+        let font = &cx.fonts[font_id];
+        for unicode in &[0x0d, 0x00, 0x1b, 0x7f, 0x85]{
+            assert!(font.has_glyph(*unicode), "{:x} draws the missing box", unicode);
+            let glyph = &font.glyphs[font.glyph_slot(*unicode)];
+            assert_eq!(glyph.advance, 0.0);
+            assert_eq!((glyph.x1, glyph.x2), (0.0, 0.0));
+        }
+        // tabs and newlines still take the room of a space
+        let space = font.glyphs[font.glyph_slot(32)].advance;
+        assert!(space > 0.0);
+        assert_eq!(font.glyphs[font.glyph_slot(9)].advance, space);
+        assert_eq!(font.glyphs[font.glyph_slot(10)].advance, space);
+        // and what the font lacks is still a box
+        assert!(!font.has_glyph(0xe000));
+        assert_eq!(font.glyph_slot(0xe000), font.missing_slot);
+    }
+
+    #[test]
+    fn ttf_kerning_is_looked_up_per_pair(){
+        let mut cx = Cx::default();
+        let font_id = ttf_font(&mut cx, "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf");
+        let font = &cx.fonts[font_id];
+        assert!(font.kerntable.is_empty());
+        let ttf = font.ttf.clone().unwrap();
+        let (a, v) = (ttf.glyph_index('A' as u32), ttf.glyph_index('V' as u32));
+        assert!(font.get_kern('A' as u32, 'V' as u32) < 0.0);
+        assert_eq!(font.get_kern('A' as u32, 'V' as u32), ttf.pair_kern(a, v) / ttf.units_per_em);
+        // codepoints the font lacks dont kern
+        assert_eq!(font.get_kern('A' as u32, 0x10ffff), 0.0);
+    }
+}
@@ -488,17 +488,84 @@ impl Cx{
         
         // lets composite our ShAst structure into a set of methods
         Ok(AssembledGLShader{
-            geometry_slots:geometry_slots,
-            instance_slots:instance_slots,
-            uniforms_dr:uniforms_dr,
-            uniforms_dl:uniforms_dl,
-            uniforms_cx:uniforms_cx,
-            texture_slots:texture_slots,
             fragment:pix_out,
             vertex:vtx_out,
+            ..Self::gl_shader_layout(sh)
+        })
+    }
+
+    // the slots, uniforms and instance props of a shader without generating its glsl,
+    // for when the program comes from somewhere else like the shader cache
+    pub fn gl_shader_layout(sh:&Shader)->AssembledGLShader{
+        let geometries = sh.flat_vars(ShVarStore::Geometry);
+        let instances = sh.flat_vars(ShVarStore::Instance);
+        AssembledGLShader{
+            geometry_slots:sh.compute_slot_total(&geometries),
+            instance_slots:sh.compute_slot_total(&instances),
+            uniforms_dr:sh.flat_vars(ShVarStore::Uniform),
+            uniforms_dl:sh.flat_vars(ShVarStore::UniformDl),
+            uniforms_cx:sh.flat_vars(ShVarStore::UniformCx),
+            texture_slots:sh.flat_vars(ShVarStore::Texture),
+            fragment:String::new(),
+            vertex:String::new(),
             named_instance_props:NamedInstanceProps::construct(sh, &instances),
             rect_instance_props:RectInstanceProps::construct(sh, &instances)
-        })
+        }
+    }
+
+    // compiles the shaders added since the last call without a gl context, they get their
+    // instance layout but no program. Enough to draw and lay out headless, like in tests
+    pub fn gl_compile_shaders_headless(&mut self)->Result<(), String>{
+        for shader_id in self.compiled_shaders.len()..self.shaders.len(){
+            let sh = &self.shaders[shader_id];
+            let ash = match Self::gl_assemble_shader(sh, GLShaderType::OpenGL){
+                Ok(ash)=>ash,
+                Err(err)=>return Err(format!("shader {} doesn't assemble: {}", sh.name, err.msg))
+            };
+            self.compiled_shaders.push(CompiledShader{
+                shader_id,
+                instance_slots:ash.instance_slots,
+                named_instance_props:ash.named_instance_props,
+                rect_instance_props:ash.rect_instance_props,
+                ..Default::default()
+            });
+        }
+        Ok(())
+    }
+
+    // a cx without a window, draw list 0 is the root of a window of size. Set the styles and
+    // create the elements, then headless_compile loads what they need
+    pub fn new_headless(size:Vec2)->Cx{
+        let mut cx = Cx::default();
+        cx.target_size = size;
+        cx.target_dpi_factor = 1.0;
+        cx.draw_lists.push(DrawList{..Default::default()});
+        cx.draw_lists[0].initialize(false, 0);
+        cx
+    }
+
+    // loads the fonts and compiles the shaders of the elements created so far
+    pub fn headless_compile(&mut self)->Result<(), String>{
+        #[cfg(feature = "ogl")]
+        self.load_binary_deps_from_file();
+        self.gl_compile_shaders_headless()
+    }
+
+    // draws one frame of the root view in draw list 0
+    pub fn headless_draw<R, F>(&mut self, draw:F)->R
+    where F:FnOnce(&mut Cx)->R
+    {
+        self.is_in_redraw_cycle = true;
+        self.redraw_id += 1;
+        let mut root = View::<NoScrollBar>{..Style::style(self)};
+        root.draw_list_id = Some(0);
+        root.begin_view(self, &Layout{..Default::default()});
+        let ret = draw(self);
+        root.end_view(self);
+        self.is_in_redraw_cycle = false;
+        // the clip uniforms hit testing uses are set when a frame is planned
+        self.plan_draw_batches();
+        ret
     }
 }
 
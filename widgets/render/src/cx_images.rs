@@ -0,0 +1,828 @@
+use crate::cx::*;
+
+// Images are loaded through read_file and decoded into a Texture2D when the data arrives.
+// Pixels are packed so that the little endian bytes come out as RGBA, which is what the
+// texture upload expects. Only PNG is decoded for now.
+
+// the largest texture side GPUs commonly take, and a cap on the decoded size (256MB of pixels)
+pub const IMAGE_MAX_SIZE:usize = 16384;
+pub const IMAGE_MAX_PIXELS:usize = 1 << 26;
+
+#[derive(Clone, Default)]
+pub struct CxImage{
+    pub path:String,
+    pub read_id:Option<u64>,
+    pub texture_id:usize,
+    pub loaded:bool,
+    pub error:Option<String>, // why the file couldn't be read or decoded
+    pub width:usize,
+    pub height:usize
+}
+
+#[derive(Clone, Default)]
+pub struct ImageData{
+    pub width:usize,
+    pub height:usize,
+    pub pixels:Vec<u32>
+}
+
+impl Cx{
+    // returns an image id right away, the texture fills in once the file is read
+    pub fn load_image(&mut self, path:&str)->usize{
+        if let Some(image_id) = self.images.iter().position(|v| v.path == path){
+            return image_id
+        }
+        let texture_id = self.new_empty_texture_2d().texture_id;
+        let read_id = self.read_file(path);
+        self.images.push(CxImage{
+            path:path.to_string(),
+            read_id:Some(read_id),
+            texture_id:texture_id,
+            ..Default::default()
+        });
+        self.images.len() - 1
+    }
+
+    pub fn process_image_file_read(&mut self, fr:&FileReadEvent){
+        let image_id = if let Some(image_id) = self.images.iter().position(|v| v.read_id == Some(fr.id)){image_id}else{return};
+        self.images[image_id].read_id = None;
+        let data = match &fr.data{
+            Ok(data)=>data,
+            Err(err)=>{
+                self.images[image_id].error = Some(format!("Cannot load image {}: {}", self.images[image_id].path, err));
+                return
+            }
+        };
+        match ImageData::decode(data){
+            Ok(image)=>{
+                self.images[image_id].error = None;
+                let cximage = &mut self.images[image_id];
+                cximage.width = image.width;
+                cximage.height = image.height;
+                cximage.loaded = true;
+                let tex = &mut self.textures_2d[cximage.texture_id];
+                tex.width = image.width;
+                tex.height = image.height;
+                tex.image = image.pixels;
+                tex.dirty = true;
+                self.redraw_area(Area::All);
+            },
+            Err(err)=>{
+                self.images[image_id].error = Some(format!("Cannot decode image {}: {}", self.images[image_id].path, err));
+            }
+        }
+    }
+}
+
+impl ImageData{
+    pub fn decode(data:&[u8])->Result<ImageData, String>{
+        if data.len() >= 8 && &data[0..8] == b"\x89PNG\r\n\x1a\n"{
+            return ImageData::decode_png(data)
+        }
+        if data.len() >= 2 && data[0] == 0xff && data[1] == 0xd8{
+            return Err("JPEG images are not supported".to_string())
+        }
+        Err("Unknown image format".to_string())
+    }
+
+    pub fn decode_png(data:&[u8])->Result<ImageData, String>{
+        let mut pos = 8;
+        let mut width = 0;
+        let mut height = 0;
+        let mut bit_depth = 0;
+        let mut color_type = 0;
+        let mut interlace = 0;
+        let mut palette:Vec<[u8;4]> = Vec::new();
+        let mut trns:Vec<u8> = Vec::new();
+        let mut idat:Vec<u8> = Vec::new();
+        loop{
+            if pos + 8 > data.len(){
+                return Err("PNG truncated".to_string())
+            }
+            let len = be_u32(data, pos) as usize;
+            let ty = &data[pos+4..pos+8];
+            let start = pos + 8;
+            let end = start + len;
+            if end + 4 > data.len(){
+                return Err("PNG chunk truncated".to_string())
+            }
+            let chunk = &data[start..end];
+            match ty{
+                b"IHDR"=>{
+                    if len < 13{
+                        return Err("PNG header too short".to_string())
+                    }
+                    width = be_u32(chunk, 0) as usize;
+                    height = be_u32(chunk, 4) as usize;
+                    bit_depth = chunk[8];
+                    color_type = chunk[9];
+                    interlace = chunk[12];
+                },
+                b"PLTE"=>{
+                    for i in 0..len / 3{
+                        palette.push([chunk[i*3], chunk[i*3+1], chunk[i*3+2], 255]);
+                    }
+                },
+                b"tRNS"=>{
+                    trns = chunk.to_vec();
+                },
+                b"IDAT"=>{
+                    idat.extend_from_slice(chunk);
+                },
+                b"IEND"=>break,
+                _=>()
+            }
+            pos = end + 4; // skip crc
+        }
+        if width == 0 || height == 0{
+            return Err("PNG has no size".to_string())
+        }
+        // the size comes from the file, so it has to be sane before anything gets allocated by it
+        let pixel_count = width.checked_mul(height).filter(|count| *count <= IMAGE_MAX_PIXELS);
+        if width > IMAGE_MAX_SIZE || height > IMAGE_MAX_SIZE || pixel_count.is_none(){
+            return Err(format!("PNG of {}x{} is too large", width, height))
+        }
+        for (i, alpha) in trns.iter().enumerate(){
+            if color_type == 3 && i < palette.len(){
+                palette[i][3] = *alpha;
+            }
+        }
+        let channels = match color_type{
+            0=>1,
+            2=>3,
+            3=>1,
+            4=>2,
+            6=>4,
+            _=>return Err(format!("PNG color type {} not supported", color_type))
+        };
+        match bit_depth{
+            1 | 2 | 4 | 8 | 16=>(),
+            _=>return Err(format!("PNG bit depth {} not supported", bit_depth))
+        }
+        let bits_per_pixel = channels * bit_depth as usize;
+        // the filtered rows the header asks for, image data inflating to more than that is broken
+        let pass_len = |w:usize, h:usize| ((w * bits_per_pixel + 7) / 8 + 1) * h;
+        let raw_len = if interlace == 0{
+            pass_len(width, height)
+        }
+        else{
+            ADAM7_PASSES.iter().filter(|(xs, ys, _, _)| *xs < width && *ys < height)
+                .map(|(xs, ys, dx, dy)| pass_len((width - xs + dx - 1) / dx, (height - ys + dy - 1) / dy)).sum()
+        };
+        let raw = zlib_decompress(&idat, raw_len)?;
+        let mut out = ImageData{
+            width:width,
+            height:height,
+            pixels:vec![0; width * height]
+        };
+        let png = PngFormat{
+            color_type:color_type,
+            bit_depth:bit_depth,
+            channels:channels,
+            palette:palette,
+            trns:trns
+        };
+        if interlace == 0{
+            png.unfilter_pass(&raw, 0, width, height, bits_per_pixel, |x, y, px|{
+                out.pixels[y * width + x] = px;
+            })?;
+        }
+        else{
+            let mut offset = 0;
+            for (xs, ys, dx, dy) in ADAM7_PASSES.iter(){
+                if *xs >= width || *ys >= height{
+                    continue
+                }
+                let pw = (width - xs + dx - 1) / dx;
+                let ph = (height - ys + dy - 1) / dy;
+                offset += png.unfilter_pass(&raw[offset..], 0, pw, ph, bits_per_pixel, |x, y, px|{
+                    out.pixels[(ys + y * dy) * width + xs + x * dx] = px;
+                })?;
+            }
+        }
+        Ok(out)
+    }
+
+    // writes an 8 bit RGBA png, the image data is stored without compression
+    pub fn encode_png(&self)->Vec<u8>{
+        let mut raw = Vec::with_capacity((self.width * 4 + 1) * self.height);
+        for y in 0..self.height{
+            raw.push(0); // no filter
+            for px in &self.pixels[y * self.width..(y + 1) * self.width]{
+                raw.extend_from_slice(&px.to_le_bytes());
+            }
+        }
+        let mut ihdr = Vec::new();
+        ihdr.extend_from_slice(&(self.width as u32).to_be_bytes());
+        ihdr.extend_from_slice(&(self.height as u32).to_be_bytes());
+        ihdr.extend_from_slice(&[8, 6, 0, 0, 0]);
+        let mut out = b"\x89PNG\r\n\x1a\n".to_vec();
+        for (ty, chunk) in [(b"IHDR", ihdr), (b"IDAT", zlib_store(&raw)), (b"IEND", Vec::new())].iter(){
+            out.extend_from_slice(&(chunk.len() as u32).to_be_bytes());
+            let start = out.len();
+            out.extend_from_slice(*ty);
+            out.extend_from_slice(chunk);
+            let crc = crc32(&out[start..]);
+            out.extend_from_slice(&crc.to_be_bytes());
+        }
+        out
+    }
+}
+
+// adam7, (x start, y start, x step, y step)
+const ADAM7_PASSES:[(usize, usize, usize, usize);7] = [(0,0,8,8),(4,0,8,8),(0,4,4,8),(2,0,4,4),(0,2,2,4),(1,0,2,2),(0,1,1,2)];
+
+struct PngFormat{
+    color_type:u8,
+    bit_depth:u8,
+    channels:usize,
+    palette:Vec<[u8;4]>,
+    trns:Vec<u8>
+}
+
+impl PngFormat{
+    // unfilters one (sub)image and hands out the pixels, returns the bytes consumed
+    fn unfilter_pass<F>(&self, raw:&[u8], start:usize, width:usize, height:usize, bits_per_pixel:usize, mut pixel:F)->Result<usize, String>
+    where F: FnMut(usize, usize, u32)
+    {
+        let stride = (width * bits_per_pixel + 7) / 8;
+        let bpp = ((bits_per_pixel + 7) / 8).max(1);
+        if raw.len() < start + (stride + 1) * height{
+            return Err("PNG image data truncated".to_string())
+        }
+        let mut prev = vec![0u8; stride];
+        let mut line = vec![0u8; stride];
+        let mut pos = start;
+        for y in 0..height{
+            let filter = raw[pos];
+            line.copy_from_slice(&raw[pos+1..pos+1+stride]);
+            pos += stride + 1;
+            for i in 0..stride{
+                let a = if i >= bpp{line[i - bpp] as i32}else{0};
+                let b = prev[i] as i32;
+                let c = if i >= bpp{prev[i - bpp] as i32}else{0};
+                let add = match filter{
+                    0=>0,
+                    1=>a,
+                    2=>b,
+                    3=>(a + b) / 2,
+                    4=>{
+                        let p = a + b - c;
+                        let pa = (p - a).abs();
+                        let pb = (p - b).abs();
+                        let pc = (p - c).abs();
+                        if pa <= pb && pa <= pc{a} else if pb <= pc{b} else {c}
+                    },
+                    _=>return Err(format!("PNG filter {} invalid", filter))
+                };
+                line[i] = (line[i] as i32 + add) as u8;
+            }
+            for x in 0..width{
+                pixel(x, y, self.read_pixel(&line, x));
+            }
+            std::mem::swap(&mut prev, &mut line);
+        }
+        Ok(pos - start)
+    }
+
+    fn sample(&self, line:&[u8], index:usize)->u16{
+        match self.bit_depth{
+            16=>((line[index * 2] as u16) << 8) | line[index * 2 + 1] as u16,
+            8=>line[index] as u16,
+            depth=>{
+                let depth = depth as usize;
+                let bit = index * depth;
+                let byte = line[bit >> 3];
+                let shift = 8 - depth - (bit & 7);
+                ((byte >> shift) as u16) & ((1 << depth) - 1)
+            }
+        }
+    }
+
+    fn to_u8(&self, v:u16)->u32{
+        match self.bit_depth{
+            16=>(v >> 8) as u32,
+            8=>v as u32,
+            depth=>(v as u32 * 255) / ((1 << depth) - 1)
+        }
+    }
+
+    fn read_pixel(&self, line:&[u8], x:usize)->u32{
+        let base = x * self.channels;
+        let (r, g, b, a) = match self.color_type{
+            0=>{
+                let s = self.sample(line, base);
+                let v = self.to_u8(s);
+                let a = if self.trns.len() >= 2 && s == be_u16(&self.trns, 0){0}else{255};
+                (v, v, v, a)
+            },
+            2=>{
+                let (sr, sg, sb) = (self.sample(line, base), self.sample(line, base + 1), self.sample(line, base + 2));
+                let a = if self.trns.len() >= 6 && sr == be_u16(&self.trns, 0) && sg == be_u16(&self.trns, 2) && sb == be_u16(&self.trns, 4){0}else{255};
+                (self.to_u8(sr), self.to_u8(sg), self.to_u8(sb), a)
+            },
+            3=>{
+                let index = self.sample(line, base) as usize;
+                let c = if index < self.palette.len(){self.palette[index]}else{[0,0,0,255]};
+                (c[0] as u32, c[1] as u32, c[2] as u32, c[3] as u32)
+            },
+            4=>{
+                let v = self.to_u8(self.sample(line, base));
+                (v, v, v, self.to_u8(self.sample(line, base + 1)))
+            },
+            _=>{
+                (
+                    self.to_u8(self.sample(line, base)),
+                    self.to_u8(self.sample(line, base + 1)),
+                    self.to_u8(self.sample(line, base + 2)),
+                    self.to_u8(self.sample(line, base + 3))
+                )
+            }
+        };
+        (a << 24) | (b << 16) | (g << 8) | r
+    }
+}
+
+fn be_u32(data:&[u8], pos:usize)->u32{
+    ((data[pos] as u32) << 24) | ((data[pos+1] as u32) << 16) | ((data[pos+2] as u32) << 8) | data[pos+3] as u32
+}
+
+fn be_u16(data:&[u8], pos:usize)->u16{
+    ((data[pos] as u16) << 8) | data[pos+1] as u16
+}
+
+// Inflate, as per RFC 1950/1951
+
+// max_len caps the output, so a small stream can't inflate into a huge allocation
+pub fn zlib_decompress(data:&[u8], max_len:usize)->Result<Vec<u8>, String>{
+    if data.len() < 2{
+        return Err("zlib stream too short".to_string())
+    }
+    if data[0] & 0x0f != 8 || ((data[0] as u32) << 8 | data[1] as u32) % 31 != 0{
+        return Err("zlib header invalid".to_string())
+    }
+    if data[1] & 0x20 != 0{
+        return Err("zlib preset dictionary not supported".to_string())
+    }
+    inflate(&data[2..], max_len)
+}
+
+// a zlib stream of stored deflate blocks
+pub fn zlib_store(data:&[u8])->Vec<u8>{
+    let mut out = vec![0x78, 0x01];
+    let mut chunks = data.chunks(65535).peekable();
+    if chunks.peek().is_none(){
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(chunk) = chunks.next(){
+        let len = chunk.len() as u16;
+        out.push(if chunks.peek().is_none(){1}else{0});
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(chunk);
+    }
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data{
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    out.extend_from_slice(&((b << 16) | a).to_be_bytes());
+    out
+}
+
+fn crc32(data:&[u8])->u32{
+    let mut crc = 0xffff_ffffu32;
+    for byte in data{
+        crc ^= *byte as u32;
+        for _ in 0..8{
+            crc = if crc & 1 != 0{(crc >> 1) ^ 0xedb8_8320}else{crc >> 1};
+        }
+    }
+    !crc
+}
+
+struct BitReader<'a>{
+    data:&'a [u8],
+    pos:usize,
+    bit:u32,
+    bits:u32
+}
+
+impl<'a> BitReader<'a>{
+    fn need(&mut self, count:u32)->Result<(), String>{
+        while self.bits < count{
+            if self.pos >= self.data.len(){
+                return Err("deflate stream truncated".to_string())
+            }
+            self.bit |= (self.data[self.pos] as u32) << self.bits;
+            self.pos += 1;
+            self.bits += 8;
+        }
+        Ok(())
+    }
+
+    fn read(&mut self, count:u32)->Result<u32, String>{
+        if count == 0{
+            return Ok(0)
+        }
+        self.need(count)?;
+        let v = self.bit & ((1 << count) - 1);
+        self.bit >>= count;
+        self.bits -= count;
+        Ok(v)
+    }
+
+    fn align_byte(&mut self){
+        let drop = self.bits & 7;
+        self.bit >>= drop;
+        self.bits -= drop;
+    }
+}
+
+struct Huffman{
+    counts:[u16;16],
+    symbols:Vec<u16>
+}
+
+impl Huffman{
+    fn new(lengths:&[u8])->Huffman{
+        let mut counts = [0u16;16];
+        for len in lengths{
+            counts[*len as usize] += 1;
+        }
+        counts[0] = 0;
+        let mut offsets = [0u16;16];
+        for i in 1..16{
+            offsets[i] = offsets[i-1] + counts[i-1];
+        }
+        let mut symbols = vec![0u16; lengths.len()];
+        for (symbol, len) in lengths.iter().enumerate(){
+            if *len != 0{
+                symbols[offsets[*len as usize] as usize] = symbol as u16;
+                offsets[*len as usize] += 1;
+            }
+        }
+        Huffman{
+            counts:counts,
+            symbols:symbols
+        }
+    }
+
+    fn decode(&self, br:&mut BitReader)->Result<u16, String>{
+        let mut code:i32 = 0;
+        let mut first:i32 = 0;
+        let mut index:i32 = 0;
+        for len in 1..16{
+            code |= br.read(1)? as i32;
+            let count = self.counts[len] as i32;
+            if code - count < first{
+                return Ok(self.symbols[(index + (code - first)) as usize])
+            }
+            index += count;
+            first += count;
+            first <<= 1;
+            code <<= 1;
+        }
+        Err("deflate huffman code invalid".to_string())
+    }
+}
+
+const LEN_BASE:[u16;29] = [3,4,5,6,7,8,9,10,11,13,15,17,19,23,27,31,35,43,51,59,67,83,99,115,131,163,195,227,258];
+const LEN_EXTRA:[u8;29] = [0,0,0,0,0,0,0,0,1,1,1,1,2,2,2,2,3,3,3,3,4,4,4,4,5,5,5,5,0];
+const DIST_BASE:[u16;30] = [1,2,3,4,5,7,9,13,17,25,33,49,65,97,129,193,257,385,513,769,1025,1537,2049,3073,4097,6145,8193,12289,16385,24577];
+const DIST_EXTRA:[u8;30] = [0,0,0,0,1,1,2,2,3,3,4,4,5,5,6,6,7,7,8,8,9,9,10,10,11,11,12,12,13,13];
+const CODE_LENGTH_ORDER:[usize;19] = [16,17,18,0,8,7,9,6,10,5,11,4,12,3,13,2,14,1,15];
+
+pub fn inflate(data:&[u8], max_len:usize)->Result<Vec<u8>, String>{
+    let mut out = Vec::new();
+    let mut br = BitReader{data:data, pos:0, bit:0, bits:0};
+    loop{
+        let last = br.read(1)?;
+        let ty = br.read(2)?;
+        match ty{
+            0=>{
+                br.align_byte();
+                let len = br.read(16)?;
+                let nlen = br.read(16)?;
+                if len != !nlen & 0xffff{
+                    return Err("deflate stored block length invalid".to_string())
+                }
+                if out.len() + len as usize > max_len{
+                    return Err(INFLATE_TOO_LONG.to_string())
+                }
+                for _ in 0..len{
+                    out.push(br.read(8)? as u8);
+                }
+            },
+            1=>{
+                let mut lengths = [0u8;288];
+                for i in 0..288{
+                    lengths[i] = if i < 144{8} else if i < 256{9} else if i < 280{7} else {8};
+                }
+                let lit = Huffman::new(&lengths);
+                let dist = Huffman::new(&[5u8;30]);
+                inflate_block(&mut br, &mut out, max_len, &lit, &dist)?;
+            },
+            2=>{
+                let hlit = br.read(5)? as usize + 257;
+                let hdist = br.read(5)? as usize + 1;
+                let hclen = br.read(4)? as usize + 4;
+                let mut cl_lengths = [0u8;19];
+                for i in 0..hclen{
+                    cl_lengths[CODE_LENGTH_ORDER[i]] = br.read(3)? as u8;
+                }
+                let cl = Huffman::new(&cl_lengths);
+                let mut lengths = Vec::new();
+                while lengths.len() < hlit + hdist{
+                    let sym = cl.decode(&mut br)?;
+                    match sym{
+                        0..=15=>lengths.push(sym as u8),
+                        16=>{
+                            let prev = if let Some(prev) = lengths.last(){*prev}else{return Err("deflate repeat without length".to_string())};
+                            for _ in 0..3 + br.read(2)?{
+                                lengths.push(prev);
+                            }
+                        },
+                        17=>for _ in 0..3 + br.read(3)?{
+                            lengths.push(0);
+                        },
+                        _=>for _ in 0..11 + br.read(7)?{
+                            lengths.push(0);
+                        }
+                    }
+                }
+                let lit = Huffman::new(&lengths[0..hlit]);
+                let dist = Huffman::new(&lengths[hlit..hlit + hdist]);
+                inflate_block(&mut br, &mut out, max_len, &lit, &dist)?;
+            },
+            _=>return Err("deflate block type invalid".to_string())
+        }
+        if last != 0{
+            break
+        }
+    }
+    Ok(out)
+}
+
+const INFLATE_TOO_LONG:&str = "deflate output longer than expected";
+
+fn inflate_block(br:&mut BitReader, out:&mut Vec<u8>, max_len:usize, lit:&Huffman, dist:&Huffman)->Result<(), String>{
+    loop{
+        let sym = lit.decode(br)? as usize;
+        if sym < 256{
+            if out.len() >= max_len{
+                return Err(INFLATE_TOO_LONG.to_string())
+            }
+            out.push(sym as u8);
+        }
+        else if sym == 256{
+            return Ok(())
+        }
+        else{
+            let sym = sym - 257;
+            if sym >= 29{
+                return Err("deflate length code invalid".to_string())
+            }
+            let len = LEN_BASE[sym] as usize + br.read(LEN_EXTRA[sym] as u32)? as usize;
+            let dsym = dist.decode(br)? as usize;
+            if dsym >= 30{
+                return Err("deflate distance code invalid".to_string())
+            }
+            let d = DIST_BASE[dsym] as usize + br.read(DIST_EXTRA[dsym] as u32)? as usize;
+            if d > out.len(){
+                return Err("deflate distance too far back".to_string())
+            }
+            if out.len() + len > max_len{
+                return Err(INFLATE_TOO_LONG.to_string())
+            }
+            let start = out.len() - d;
+            for i in 0..len{
+                let v = out[start + i];
+                out.push(v);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    // a png with the size in the header replaced, the decoder doesn't check crcs
+    fn png_with_size(width:u32, height:u32)->Vec<u8>{
+        let mut png = ImageData{width:2, height:2, pixels:vec![0xff0000ff, 0xff00ff00, 0xffff0000, 0]}.encode_png();
+        png[16..20].copy_from_slice(&width.to_be_bytes());
+        png[20..24].copy_from_slice(&height.to_be_bytes());
+        png
+    }
+
+    #[test]
+    fn png_round_trip(){
+        let image = ImageData::decode_png(&png_with_size(2, 2)).unwrap();
+        assert_eq!((image.width, image.height), (2, 2));
+        assert_eq!(image.pixels, vec![0xff0000ff, 0xff00ff00, 0xffff0000, 0]);
+    }
+
+    #[test]
+    fn png_size_is_limited(){
+        assert_eq!(ImageData::decode_png(&png_with_size(0xffff_ffff, 0xffff_ffff)).err(), Some("PNG of 4294967295x4294967295 is too large".to_string()));
+        assert!(ImageData::decode_png(&png_with_size(IMAGE_MAX_SIZE as u32 + 1, 1)).is_err());
+        assert!(ImageData::decode_png(&png_with_size(16384, 16384)).err().is_some_and(|err| err.contains("too large")));
+        // within the limits it fails on the image data instead of the size
+        assert_eq!(ImageData::decode_png(&png_with_size(4096, 4096)).err(), Some("PNG image data truncated".to_string()));
+        assert_eq!(ImageData::decode_png(&png_with_size(0, 2)).err(), Some("PNG has no size".to_string()));
+    }
+
+    // a png around raw (filtered) image data, with extra chunks like PLTE and tRNS before it
+    fn png(width:u32, height:u32, bit_depth:u8, color_type:u8, interlace:u8, chunks:&[(&[u8;4], Vec<u8>)], raw:&[u8])->Vec<u8>{
+        let mut ihdr = Vec::new();
+        ihdr.extend_from_slice(&width.to_be_bytes());
+        ihdr.extend_from_slice(&height.to_be_bytes());
+        ihdr.extend_from_slice(&[bit_depth, color_type, 0, 0, interlace]);
+        let mut all = vec![(b"IHDR", ihdr)];
+        all.extend(chunks.iter().cloned());
+        all.push((b"IDAT", zlib_store(raw)));
+        all.push((b"IEND", Vec::new()));
+        let mut out = b"\x89PNG\r\n\x1a\n".to_vec();
+        for (ty, chunk) in all{
+            out.extend_from_slice(&(chunk.len() as u32).to_be_bytes());
+            let start = out.len();
+            out.extend_from_slice(ty);
+            out.extend_from_slice(&chunk);
+            let crc = crc32(&out[start..]);
+            out.extend_from_slice(&crc.to_be_bytes());
+        }
+        out
+    }
+
+    // filters rows the way an encoder would, bpp is the bytes per pixel rounded up
+    fn filter_rows(rows:&[Vec<u8>], bpp:usize, filter:u8)->Vec<u8>{
+        let mut out = Vec::new();
+        let mut prev = vec![0u8; rows[0].len()];
+        for row in rows{
+            out.push(filter);
+            for i in 0..row.len(){
+                let a = if i >= bpp{row[i - bpp] as i32}else{0};
+                let b = prev[i] as i32;
+                let c = if i >= bpp{prev[i - bpp] as i32}else{0};
+                let predict = match filter{
+                    0=>0,
+                    1=>a,
+                    2=>b,
+                    3=>(a + b) / 2,
+                    _=>{
+                        let p = a + b - c;
+                        if (p - a).abs() <= (p - b).abs() && (p - a).abs() <= (p - c).abs(){a}
+                        else if (p - b).abs() <= (p - c).abs(){b}
+                        else{c}
+                    }
+                };
+                out.push((row[i] as i32 - predict) as u8);
+            }
+            prev = row.clone();
+        }
+        out
+    }
+
+    fn rgba(r:u32, g:u32, b:u32, a:u32)->u32{
+        (a << 24) | (b << 16) | (g << 8) | r
+    }
+
+    // payload of the deflate streams below, made with zlib
+    fn payload(len:usize)->Vec<u8>{
+        (0..len).map(|i| ((i * i * 7 + i / 3) % 11 + 97) as u8).collect()
+    }
+
+    #[test]
+    fn inflate_fixed_huffman(){
+        let data = [
+            0x78, 0x01, 0x4b, 0xcc, 0x48, 0xcf, 0x4a, 0x49, 0x4c, 0x4a, 0xcd, 0xce, 0xca, 0x4e, 0x49, 0xcc,
+            0x4e, 0xca, 0x48, 0x4d, 0xcd, 0x4c, 0x49, 0x4a, 0xc9, 0x48, 0x49, 0x49, 0xcb, 0xce, 0xcc, 0x4c,
+            0x4c, 0xc4, 0x2b, 0x0b, 0x00, 0xd8, 0x78, 0x17, 0xe3
+        ];
+        // the second half is a back reference to the first
+        let mut expect = payload(30);
+        expect.extend(payload(30));
+        assert_eq!(zlib_decompress(&data, 60), Ok(expect));
+    }
+
+    #[test]
+    fn inflate_dynamic_huffman(){
+        let data = [
+            0x78, 0xda, 0x55, 0xc9, 0x31, 0x12, 0x00, 0x20, 0x0c, 0x02, 0xb0, 0xb7, 0xc2, 0x81, 0x96, 0xf2,
+            0xff, 0xdd, 0xd9, 0xac, 0xc1, 0xdc, 0x15, 0xe8, 0x6e, 0x85, 0x72, 0xec, 0x88, 0x1a, 0xe9, 0x34,
+            0x01, 0xfe, 0x7d, 0x9b, 0xf8, 0x11, 0xea
+        ];
+        let mut expect = payload(30);
+        expect.extend(payload(15));
+        assert_eq!(zlib_decompress(&data, 1000), Ok(expect));
+    }
+
+    #[test]
+    fn inflate_output_is_capped(){
+        // 100000 zeros in 120 bytes
+        let mut zeros = vec![0x78, 0xda, 0xed, 0xc1, 0x31, 0x01, 0x00, 0x00, 0x00, 0xc2, 0xa0, 0xf5, 0x4f, 0x6d, 0x0d, 0x0f, 0xa0];
+        zeros.extend_from_slice(&[0; 96]);
+        zeros.extend_from_slice(&[0x80, 0x57, 0x03, 0x86, 0xaf, 0x00, 0x01]);
+        assert_eq!(zlib_decompress(&zeros, 100000).map(|out| out.len()), Ok(100000));
+        assert_eq!(zlib_decompress(&zeros, 99999).err(), Some(INFLATE_TOO_LONG.to_string()));
+        assert_eq!(zlib_decompress(&zlib_store(&[1; 10]), 9).err(), Some(INFLATE_TOO_LONG.to_string()));
+        // and a png whose data runs past what its header says
+        let raw = filter_rows(&[vec![0; 8], vec![0; 8], vec![0; 8]], 4, 0);
+        assert_eq!(ImageData::decode_png(&png(2, 2, 8, 6, 0, &[], &raw)).err(), Some(INFLATE_TOO_LONG.to_string()));
+    }
+
+    #[test]
+    fn png_filters(){
+        let rows:Vec<Vec<u8>> = (0..4).map(|y| (0..12).map(|i| (i * 37 + y * 91) as u8).collect()).collect();
+        let pixels:Vec<u32> = rows.iter().flat_map(|row| row.chunks(4).map(|p| rgba(p[0] as u32, p[1] as u32, p[2] as u32, p[3] as u32)).collect::<Vec<_>>()).collect();
+        for filter in 0..5{
+            let image = ImageData::decode_png(&png(3, 4, 8, 6, 0, &[], &filter_rows(&rows, 4, filter))).unwrap();
+            assert_eq!(image.pixels, pixels, "filter {}", filter);
+        }
+        // rgb has a bpp of 3
+        let rgb:Vec<Vec<u8>> = rows.iter().map(|row| row[0..9].to_vec()).collect();
+        let image = ImageData::decode_png(&png(3, 4, 8, 2, 0, &[], &filter_rows(&rgb, 3, 4))).unwrap();
+        assert_eq!(image.pixels[4], rgba(rgb[1][3] as u32, rgb[1][4] as u32, rgb[1][5] as u32, 255));
+        assert!(ImageData::decode_png(&png(3, 4, 8, 2, 0, &[], &filter_rows(&rgb, 3, 5))).err().is_some_and(|err| err.contains("filter 5")));
+    }
+
+    #[test]
+    fn png_palette_with_transparency(){
+        let plte = vec![255,0,0, 0,255,0, 0,0,255, 9,9,9];
+        // 2 bit indices, 3 to a row, the first two entries get alpha
+        let raw = filter_rows(&[vec![0b00_01_10_00], vec![0b11_00_01_00]], 1, 0);
+        let image = ImageData::decode_png(&png(3, 2, 2, 3, 0, &[(b"PLTE", plte), (b"tRNS", vec![0, 128])], &raw)).unwrap();
+        assert_eq!(image.pixels, vec![
+            rgba(255,0,0,0), rgba(0,255,0,128), rgba(0,0,255,255),
+            rgba(9,9,9,255), rgba(255,0,0,0), rgba(0,255,0,128)
+        ]);
+    }
+
+    #[test]
+    fn png_16_bit(){
+        // rgb, where the tRNS color is transparent
+        let raw = filter_rows(&[vec![0x12,0x34, 0xab,0xcd, 0xff,0xff, 0x00,0x01, 0x00,0x02, 0x00,0x03]], 6, 1);
+        let trns = vec![0x00,0x01, 0x00,0x02, 0x00,0x03];
+        let image = ImageData::decode_png(&png(2, 1, 16, 2, 0, &[(b"tRNS", trns)], &raw)).unwrap();
+        assert_eq!(image.pixels, vec![rgba(0x12, 0xab, 0xff, 255), rgba(0, 0, 0, 0)]);
+        // grey with alpha
+        let raw = filter_rows(&[vec![0x80,0x00, 0x40,0xff]], 4, 0);
+        let image = ImageData::decode_png(&png(1, 1, 16, 4, 0, &[], &raw)).unwrap();
+        assert_eq!(image.pixels, vec![rgba(0x80, 0x80, 0x80, 0x40)]);
+    }
+
+    #[test]
+    fn png_sub_byte_grey(){
+        // 1 bit, 10 pixels spill into a second byte
+        let raw = filter_rows(&[vec![0b1010_0000, 0b1100_0000]], 1, 0);
+        let image = ImageData::decode_png(&png(10, 1, 1, 0, 0, &[], &raw)).unwrap();
+        let grey:Vec<u32> = image.pixels.iter().map(|px| px & 0xff).collect();
+        assert_eq!(grey, vec![255, 0, 255, 0, 0, 0, 0, 0, 255, 255]);
+        assert!(image.pixels.iter().all(|px| px >> 24 == 255));
+        // 4 bit scales to the full range, and a tRNS grey is transparent
+        let raw = filter_rows(&[vec![0xf7, 0x00]], 1, 0);
+        let image = ImageData::decode_png(&png(3, 1, 4, 0, 0, &[(b"tRNS", vec![0, 7])], &raw)).unwrap();
+        assert_eq!(image.pixels, vec![rgba(255, 255, 255, 255), rgba(119, 119, 119, 0), rgba(0, 0, 0, 255)]);
+    }
+
+    #[test]
+    fn png_adam7(){
+        let (width, height) = (9, 10);
+        let pixel = |x:usize, y:usize| [(x * 20) as u8, (y * 20) as u8, (x * y) as u8, 200];
+        // each pass is a small image of its own, filtered on its own
+        let mut raw = Vec::new();
+        for (pass, (xs, ys, dx, dy)) in ADAM7_PASSES.iter().enumerate(){
+            let rows:Vec<Vec<u8>> = (*ys..height).step_by(*dy).map(|y| (*xs..width).step_by(*dx).flat_map(|x| pixel(x, y).to_vec()).collect()).collect();
+            raw.extend(filter_rows(&rows, 4, (pass % 5) as u8));
+        }
+        let image = ImageData::decode_png(&png(width as u32, height as u32, 8, 6, 1, &[], &raw)).unwrap();
+        for y in 0..height{
+            for x in 0..width{
+                let p = pixel(x, y);
+                assert_eq!(image.pixels[y * width + x], rgba(p[0] as u32, p[1] as u32, p[2] as u32, p[3] as u32), "pixel {},{}", x, y);
+            }
+        }
+    }
+
+    #[cfg(feature = "ogl")]
+    #[test]
+    fn failures_are_kept_on_the_image(){
+        let mut cx = Cx::new_headless(Vec2{x:100., y:100.});
+        let jpg = cx.load_image("photo.jpg");
+        let read_id = cx.images[jpg].read_id.unwrap();
+        cx.process_image_file_read(&FileReadEvent{id:read_id, data:Ok(vec![0xff, 0xd8, 0xff, 0xe0])});
+        assert!(!cx.images[jpg].loaded);
+        assert_eq!(cx.images[jpg].error, Some("Cannot decode image photo.jpg: JPEG images are not supported".to_string()));
+
+        let gone = cx.load_image("gone.png");
+        let read_id = cx.images[gone].read_id.unwrap();
+        cx.process_image_file_read(&FileReadEvent{id:read_id, data:Err("not found".to_string())});
+        assert_eq!(cx.images[gone].error, Some("Cannot load image gone.png: not found".to_string()));
+
+        let png_id = cx.load_image("fine.png");
+        let read_id = cx.images[png_id].read_id.unwrap();
+        cx.process_image_file_read(&FileReadEvent{id:read_id, data:Ok(png_with_size(2, 2))});
+        assert!(cx.images[png_id].loaded && cx.images[png_id].error.is_none());
+    }
+}
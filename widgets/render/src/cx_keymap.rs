@@ -0,0 +1,226 @@
+use crate::cx::*;
+
+// one key press of a binding like Ctrl+Shift+Z. Mod stands for Cmd on macos and Ctrl elsewhere,
+// but matches either so a keymap works on both
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyChord{
+    pub key_code:KeyCode,
+    pub modifiers:KeyModifiers,
+    pub primary:bool
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyBinding{
+    pub keys:Vec<KeyChord>,
+    pub command:String,
+    pub when:Option<String> // the key context it applies in like "editor", or "!editor", any for None
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommandInfo{
+    pub name:String,
+    pub title:String
+}
+
+#[derive(Clone, Default)]
+pub struct CxKeymap{
+    pub commands:Vec<CommandInfo>,
+    pub bindings:Vec<KeyBinding>, // later ones win, so user bindings go last
+    pub _pending:Vec<KeyEvent>, // the keys of a sequence that isn't finished yet
+    pub _prefix:Vec<KeyEvent>, // what was pending before the current key event
+    pub _event_id:u64
+}
+
+const KEY_NAMES:&[(&str, KeyCode)] = &[
+    ("Escape", KeyCode::Escape), ("Esc", KeyCode::Escape),
+    ("`", KeyCode::Backtick), ("0", KeyCode::Key0), ("1", KeyCode::Key1), ("2", KeyCode::Key2),
+    ("3", KeyCode::Key3), ("4", KeyCode::Key4), ("5", KeyCode::Key5), ("6", KeyCode::Key6),
+    ("7", KeyCode::Key7), ("8", KeyCode::Key8), ("9", KeyCode::Key9), ("-", KeyCode::Minus),
+    ("=", KeyCode::Equals), ("Backspace", KeyCode::Backspace), ("Tab", KeyCode::Tab),
+    ("Q", KeyCode::KeyQ), ("W", KeyCode::KeyW), ("E", KeyCode::KeyE), ("R", KeyCode::KeyR),
+    ("T", KeyCode::KeyT), ("Y", KeyCode::KeyY), ("U", KeyCode::KeyU), ("I", KeyCode::KeyI),
+    ("O", KeyCode::KeyO), ("P", KeyCode::KeyP), ("[", KeyCode::LBracket), ("]", KeyCode::RBracket),
+    ("Enter", KeyCode::Return), ("Return", KeyCode::Return),
+    ("A", KeyCode::KeyA), ("S", KeyCode::KeyS), ("D", KeyCode::KeyD), ("F", KeyCode::KeyF),
+    ("G", KeyCode::KeyG), ("H", KeyCode::KeyH), ("J", KeyCode::KeyJ), ("K", KeyCode::KeyK),
+    ("L", KeyCode::KeyL), (";", KeyCode::Semicolon), ("'", KeyCode::Quote), ("\\", KeyCode::Backslash),
+    ("Z", KeyCode::KeyZ), ("X", KeyCode::KeyX), ("C", KeyCode::KeyC), ("V", KeyCode::KeyV),
+    ("B", KeyCode::KeyB), ("N", KeyCode::KeyN), ("M", KeyCode::KeyM), (",", KeyCode::Comma),
+    (".", KeyCode::Period), ("/", KeyCode::Slash), ("Space", KeyCode::Space),
+    ("F1", KeyCode::F1), ("F2", KeyCode::F2), ("F3", KeyCode::F3), ("F4", KeyCode::F4),
+    ("F5", KeyCode::F5), ("F6", KeyCode::F6), ("F7", KeyCode::F7), ("F8", KeyCode::F8),
+    ("F9", KeyCode::F9), ("F10", KeyCode::F10), ("F11", KeyCode::F11), ("F12", KeyCode::F12),
+    ("Insert", KeyCode::Insert), ("Delete", KeyCode::Delete), ("Home", KeyCode::Home),
+    ("End", KeyCode::End), ("PageUp", KeyCode::PageUp), ("PageDown", KeyCode::PageDown),
+    ("Up", KeyCode::ArrowUp), ("Down", KeyCode::ArrowDown), ("Left", KeyCode::ArrowLeft),
+    ("Right", KeyCode::ArrowRight), ("Ctrl", KeyCode::Control), ("Alt", KeyCode::Alt),
+    ("Shift", KeyCode::Shift), ("Cmd", KeyCode::Logo), ("CapsLock", KeyCode::Capslock),
+    ("PrintScreen", KeyCode::PrintScreen), ("ScrollLock", KeyCode::Scrolllock), ("Pause", KeyCode::Pause),
+    ("Numpad0", KeyCode::Numpad0), ("Numpad1", KeyCode::Numpad1), ("Numpad2", KeyCode::Numpad2),
+    ("Numpad3", KeyCode::Numpad3), ("Numpad4", KeyCode::Numpad4), ("Numpad5", KeyCode::Numpad5),
+    ("Numpad6", KeyCode::Numpad6), ("Numpad7", KeyCode::Numpad7), ("Numpad8", KeyCode::Numpad8),
+    ("Numpad9", KeyCode::Numpad9), ("NumpadEquals", KeyCode::NumpadEquals),
+    ("NumpadSubtract", KeyCode::NumpadSubtract), ("NumpadAdd", KeyCode::NumpadAdd),
+    ("NumpadDecimal", KeyCode::NumpadDecimal), ("NumpadMultiply", KeyCode::NumpadMultiply),
+    ("NumpadDivide", KeyCode::NumpadDivide), ("NumLock", KeyCode::Numlock), ("NumpadEnter", KeyCode::NumpadEnter),
+];
+
+impl KeyChord{
+    // parses Ctrl+Shift+K, modifiers are Ctrl, Shift, Alt, Cmd (or Logo) and Mod
+    pub fn parse(chord:&str)->Result<KeyChord, String>{
+        let mut out = KeyChord{key_code:KeyCode::Unknown, modifiers:KeyModifiers::default(), primary:false};
+        let parts:Vec<&str> = chord.split('+').collect();
+        for (index, part) in parts.iter().enumerate(){
+            // a trailing empty part is the + key itself, which we have no keycode for
+            if index == parts.len() - 1{
+                if let Some((_, key_code)) = KEY_NAMES.iter().find(|(name, _)| name.eq_ignore_ascii_case(part)){
+                    out.key_code = key_code.clone();
+                    return Ok(out)
+                }
+                return Err(format!("unknown key {} in {}", part, chord))
+            }
+            match part.to_lowercase().as_ref(){
+                "ctrl" | "control"=>out.modifiers.control = true,
+                "shift"=>out.modifiers.shift = true,
+                "alt" | "option"=>out.modifiers.alt = true,
+                "cmd" | "logo" | "meta"=>out.modifiers.logo = true,
+                "mod"=>out.primary = true,
+                _=>return Err(format!("unknown modifier {} in {}", part, chord))
+            }
+        }
+        Err(format!("empty key chord {}", chord))
+    }
+
+    // space separated chords, Ctrl+K Ctrl+C
+    pub fn parse_sequence(keys:&str)->Result<Vec<KeyChord>, String>{
+        let keys:Result<Vec<KeyChord>, String> = keys.split_whitespace().map(KeyChord::parse).collect();
+        let keys = keys?;
+        if keys.len() == 0{
+            return Err("no keys in binding".to_string())
+        }
+        Ok(keys)
+    }
+
+    pub fn matches(&self, ke:&KeyEvent)->bool{
+        let m = &ke.modifiers;
+        if self.key_code != ke.key_code || self.modifiers.shift != m.shift || self.modifiers.alt != m.alt{
+            return false
+        }
+        if self.primary{
+            // any extra ctrl or cmd on top of mod still has to be asked for
+            return (m.control || m.logo) && (!self.modifiers.control || m.control) && (!self.modifiers.logo || m.logo)
+        }
+        self.modifiers.control == m.control && self.modifiers.logo == m.logo
+    }
+
+    pub fn name(&self)->String{
+        let mut name = String::new();
+        if self.primary{
+            name.push_str(if cfg!(target_os = "macos"){"Cmd+"} else{"Ctrl+"});
+        }
+        if self.modifiers.control{
+            name.push_str("Ctrl+");
+        }
+        if self.modifiers.alt{
+            name.push_str("Alt+");
+        }
+        if self.modifiers.shift{
+            name.push_str("Shift+");
+        }
+        if self.modifiers.logo{
+            name.push_str("Cmd+");
+        }
+        if let Some((key_name, _)) = KEY_NAMES.iter().find(|(_, key_code)| *key_code == self.key_code){
+            name.push_str(key_name);
+        }
+        name
+    }
+}
+
+impl KeyBinding{
+    pub fn applies_in(&self, context:&str)->bool{
+        match &self.when{
+            None=>true,
+            Some(when)=>if when.starts_with('!'){&when[1..] != context} else{when == context}
+        }
+    }
+
+    pub fn keys_name(&self)->String{
+        self.keys.iter().map(|k| k.name()).collect::<Vec<_>>().join(" ")
+    }
+}
+
+impl Cx{
+    // names a command so keymaps and the ui can refer to it, registering it again renames it
+    pub fn register_command(&mut self, name:&str, title:&str){
+        let commands = &mut self.keymap.commands;
+        if let Some(command) = commands.iter_mut().find(|c| c.name == name){
+            command.title = title.to_string();
+        }
+        else{
+            commands.push(CommandInfo{name:name.to_string(), title:title.to_string()});
+        }
+    }
+
+    pub fn bind_keys(&mut self, keys:&str, command:&str, when:Option<&str>)->Result<(), String>{
+        let binding = KeyBinding{
+            keys:KeyChord::parse_sequence(keys)?,
+            command:command.to_string(),
+            when:when.map(|w| w.to_string())
+        };
+        let bindings = &mut self.keymap.bindings;
+        if let Some(index) = bindings.iter().position(|b| *b == binding){
+            bindings.remove(index);
+        }
+        bindings.push(binding);
+        Ok(())
+    }
+
+    // removes the bindings of a command, only the ones on these keys if given
+    pub fn unbind_keys(&mut self, keys:Option<&str>, command:&str)->Result<(), String>{
+        let keys = if let Some(keys) = keys{Some(KeyChord::parse_sequence(keys)?)} else{None};
+        self.keymap.bindings.retain(|b| b.command != command || keys.is_some() && keys.as_ref() != Some(&b.keys));
+        Ok(())
+    }
+
+    // the command a key down finishes in a key context. Every widget that has the key focus asks
+    // with its own context, asking again for the same event gives the same answer
+    pub fn key_command(&mut self, ke:&KeyEvent, context:&str)->Option<String>{
+        match ke.key_code{
+            KeyCode::Control | KeyCode::Shift | KeyCode::Alt | KeyCode::Logo=>return None,
+            _=>()
+        }
+        let keymap = &mut self.keymap;
+        if keymap._event_id != self.event_id{
+            keymap._event_id = self.event_id;
+            keymap._prefix = std::mem::replace(&mut keymap._pending, Vec::new());
+        }
+        let mut keys = keymap._prefix.clone();
+        keys.push(ke.clone());
+        let matching = |binding:&&KeyBinding|{
+            binding.applies_in(context) && binding.keys.len() >= keys.len() &&
+            keys.iter().zip(binding.keys.iter()).all(|(ke, chord)| chord.matches(ke))
+        };
+        if let Some(binding) = keymap.bindings.iter().rev().filter(matching).find(|b| b.keys.len() == keys.len()){
+            return Some(binding.command.clone())
+        }
+        if keymap.bindings.iter().any(|b| matching(&b)){
+            keymap._pending = keys;
+        }
+        None
+    }
+
+    // runs a command on whatever has the key focus, after the current event
+    pub fn send_command(&mut self, command:&str){
+        self.user_events.push(Event::Command(CommandEvent{command:command.to_string()}));
+    }
+
+    pub fn is_key_sequence_pending(&self)->bool{
+        self.keymap._pending.len() > 0
+    }
+
+    // the keys bound to a command, for showing next to it
+    pub fn command_keys(&self, command:&str, context:&str)->Option<String>{
+        self.keymap.bindings.iter().rev().find(|b| b.command == command && b.applies_in(context)).map(|b| b.keys_name())
+    }
+}
@@ -89,6 +89,7 @@ impl Cx{
  
     pub fn repaint(&mut self,layer:&CoreAnimationLayer, device:&Device, command_queue:&CommandQueue){
         let pool = unsafe { NSAutoreleasePool::new(cocoa::base::nil) };
+        self.profile_begin("repaint");
         if let Some(drawable) = layer.next_drawable() {
             self.prepare_frame();
             
@@ -129,6 +130,7 @@ impl Cx{
 
             //command_buffer.wait_until_completed();
         }
+        self.profile_end();
         unsafe { 
             msg_send![pool, release];
         }
@@ -286,6 +288,7 @@ impl Cx{
                 self.repaint_id += 1;
                 self.repaint(&layer, &device, &command_queue);
             }
+            self.profile_end_frame();
         }
     }
 
@@ -407,6 +410,7 @@ impl MetalBuffer{
 pub struct Texture2D{
     pub texture_id: usize,
     pub dirty:bool,
+    pub render_target:bool,
     pub image: Vec<u32>,
     pub width: usize,
     pub height:usize,
@@ -445,4 +449,8 @@ impl Texture2D{
         self.dirty = false;
       
     }
+
+    pub fn free_device(&mut self){
+        self.mtltexture = None;
+    }
 }
\ No newline at end of file
@@ -9,47 +9,73 @@ use time::precise_time_ns;
 use crate::cx::*;
 
 impl Cx {
-    pub fn exec_draw_list(&mut self, draw_list_id: usize) {
-        let draw_calls_len = self.draw_lists[draw_list_id].draw_calls_len;
-
-        for draw_call_id in 0..draw_calls_len {
-            let sub_list_id = self.draw_lists[draw_list_id].draw_calls[draw_call_id].sub_list_id;
-            if sub_list_id != 0 {
-                self.exec_draw_list(sub_list_id);
-            } else {
-                let draw_list = &mut self.draw_lists[draw_list_id];
+    // issues the draw batches planned for a list by plan_draw_batches, a batch of more than one
+    // draw call uploads their instances together into the buffer of the first once a frame.
+    // With a damage rect only the batches that can touch it are drawn
+    pub fn exec_draw_list(&mut self, draw_list_id: usize, damage: Option<Rect>) {
+        let mut batches = mem::replace(&mut self.draw_lists[draw_list_id].batches, Vec::new());
+        for batch in &mut batches {
+            if let (Some(damage), Some(bounds)) = (damage, batch.bounds) {
+                if !damage.intersects(bounds) {
+                    continue;
+                }
+            }
+            let (head_list_id, head_call_id) = batch.members[0];
+            let merge = batch.members.len() > 1 && batch.instance.len() == 0;
+            if merge {
+                for (list_id, call_id) in &batch.members {
+                    batch.instance.extend_from_slice(&self.draw_lists[*list_id].draw_calls[*call_id].instance);
+                }
+            }
+            self.batch_draw_list_uniforms(batch);
+            let draw_list = &mut self.draw_lists[head_list_id];
 
-                draw_list.set_clipping_uniforms();
+            let draw_call = &mut draw_list.draw_calls[head_call_id];
+            let sh = &self.shaders[draw_call.shader_id];
+            let csh = &self.compiled_shaders[draw_call.shader_id];
 
-                let draw_call = &mut draw_list.draw_calls[draw_call_id];
-                let sh = &self.shaders[draw_call.shader_id];
-                let csh = &self.compiled_shaders[draw_call.shader_id];
+            unsafe {
+                draw_call.platform.check_attached_vao(csh);
+
+                let instance = if batch.members.len() > 1 {
+                    draw_call.batched = true;
+                    if merge {Some(&batch.instance)} else {None}
+                } else if draw_call.instance_dirty || draw_call.batched {
+                    draw_call.batched = false;
+                    Some(&draw_call.instance)
+                } else {
+                    None
+                };
+                if let Some(instance) = instance {
+                    self.profiler.count_upload(instance.len() * mem::size_of::<f32>());
+                    draw_call.instance_dirty = false;
+                    // update the instance buffer data
+                    gl::BindBuffer(gl::ARRAY_BUFFER, draw_call.platform.vb);
+                    gl::BufferData(gl::ARRAY_BUFFER,
+                                   (instance.len() * mem::size_of::<f32>()) as gl::types::GLsizeiptr,
+                                   instance.as_ptr() as *const _, gl::STATIC_DRAW);
+                }
 
-                unsafe {
-                    draw_call.platform.check_attached_vao(csh);
-
-                    if draw_call.instance_dirty {
-                        draw_call.instance_dirty = false;
-                        // update the instance buffer data
-                        gl::BindBuffer(gl::ARRAY_BUFFER, draw_call.platform.vb);
-                        gl::BufferData(gl::ARRAY_BUFFER,
-                                       (draw_call.instance.len() * mem::size_of::<f32>()) as gl::types::GLsizeiptr,
-                                       draw_call.instance.as_ptr() as *const _, gl::STATIC_DRAW);
+                gl::UseProgram(csh.program);
+                gl::BindVertexArray(draw_call.platform.vao);
+                let instances = if batch.members.len() > 1 {batch.instance.len()} else {draw_call.instance.len()} / csh.instance_slots;
+                let indices = sh.geometry_indices.len();
+
+                Cx::set_uniform_buffer_fallback(&csh.uniforms_cx, &self.uniforms);
+                Cx::set_uniform_buffer_fallback(&csh.uniforms_dl, &batch.uniforms);
+                Cx::set_uniform_buffer_fallback(&csh.uniforms_dr, &draw_call.uniforms);
+                for tex_id in &draw_call.textures_2d {
+                    let tex = &self.textures_2d[*tex_id as usize];
+                    if tex.dirty && !tex.render_target {
+                        self.profiler.count_upload(tex.width * tex.height * 4);
                     }
-
-                    gl::UseProgram(csh.program);
-                    gl::BindVertexArray(draw_call.platform.vao);
-                    let instances = draw_call.instance.len() / csh.instance_slots;
-                    let indices = sh.geometry_indices.len();
-
-                    Cx::set_uniform_buffer_fallback(&csh.uniforms_cx, &self.uniforms);
-                    Cx::set_uniform_buffer_fallback(&csh.uniforms_dl, &draw_list.uniforms);
-                    Cx::set_uniform_buffer_fallback(&csh.uniforms_dr, &draw_call.uniforms);
-                    Cx::set_texture_slots(&csh.texture_slots, &draw_call.textures_2d, &mut self.textures_2d);
-                    gl::DrawElementsInstanced(gl::TRIANGLES, indices as i32, gl::UNSIGNED_INT, ptr::null(), instances as i32);
                 }
+                self.profiler.count_draw_call(instances);
+                Cx::set_texture_slots(&csh.texture_slots, &draw_call.textures_2d, &mut self.textures_2d);
+                gl::DrawElementsInstanced(gl::TRIANGLES, indices as i32, gl::UNSIGNED_INT, ptr::null(), instances as i32);
             }
         }
+        self.draw_lists[draw_list_id].batches = batches;
     }
 
     pub unsafe fn gl_string(raw_string: *const gl::types::GLubyte) -> String {
@@ -66,13 +92,87 @@ impl Cx {
             gl::BlendEquationSeparate(gl::FUNC_ADD, gl::FUNC_ADD);
             gl::BlendFuncSeparate(gl::ONE, gl::ONE_MINUS_SRC_ALPHA, gl::ONE, gl::ONE_MINUS_SRC_ALPHA);
             gl::Enable(gl::BLEND);
-            gl::ClearColor(self.clear_color.r, self.clear_color.g, self.clear_color.b, self.clear_color.a);
-            gl::Clear(gl::COLOR_BUFFER_BIT|gl::DEPTH_BUFFER_BIT);
         }
+        self.profile_begin("repaint");
         self.prepare_frame();
-        self.exec_draw_list(0);
+        // nothing changed on screen, leave the last frame up
+        if self.damage.len() == 0 {
+            self.profile_end();
+            return
+        }
+        self.profile_begin("render targets");
+        self.exec_render_targets();
+        self.profile_end();
+
+        // the frame is kept in a framebuffer of our own so the undamaged parts survive the swap
+        let dpi_factor = if self.target_dpi_factor > 0.0 {self.target_dpi_factor} else {1.0};
+        let paint_buffer = &mut self.platform.paint_buffer;
+        paint_buffer.width = ((self.target_size.x * dpi_factor) as usize).max(1);
+        paint_buffer.height = ((self.target_size.y * dpi_factor) as usize).max(1);
+        let (width, height) = (paint_buffer.width as i32, paint_buffer.height as i32);
+        if paint_buffer.bind_framebuffer() {
+            self.damage = vec![Rect {x: 0., y: 0., w: self.target_size.x, h: self.target_size.y}];
+        }
+        unsafe {
+            gl::Viewport(0, 0, width, height);
+            gl::Enable(gl::SCISSOR_TEST);
+        }
+        let damage = self.damage.clone();
+        for rect in damage {
+            // whole pixels around the rect, gl counts y from the bottom
+            let x1 = (rect.x * dpi_factor).floor() as i32;
+            let y1 = (rect.y * dpi_factor).floor() as i32;
+            let x2 = ((rect.x + rect.w) * dpi_factor).ceil() as i32;
+            let y2 = ((rect.y + rect.h) * dpi_factor).ceil() as i32;
+            unsafe {
+                gl::Scissor(x1, height - y2, x2 - x1, y2 - y1);
+                gl::ClearColor(self.clear_color.r, self.clear_color.g, self.clear_color.b, self.clear_color.a);
+                gl::Clear(gl::COLOR_BUFFER_BIT|gl::DEPTH_BUFFER_BIT);
+            }
+            self.profile_begin("exec draw list");
+            self.exec_draw_list(0, Some(rect));
+            self.profile_end();
+        }
+        unsafe {
+            gl::Disable(gl::SCISSOR_TEST);
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.platform.paint_buffer.gl_framebuffer.unwrap());
+            gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, 0);
+            gl::BlitFramebuffer(0, 0, width, height, 0, 0, width, height, gl::COLOR_BUFFER_BIT, gl::NEAREST);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
 
+        self.profile_begin("swap");
         glutin_context.swap_buffers().unwrap();
+        self.profile_end();
+        self.profile_end();
+    }
+
+    pub fn exec_render_targets(&mut self) {
+        let mut targets = Vec::new();
+        self.collect_render_targets(0, &mut targets);
+        if targets.len() == 0 {
+            return
+        }
+        let mut viewport = [0i32; 4];
+        unsafe {
+            gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+        }
+        let mut rendered = Vec::new();
+        for draw_list_id in targets {
+            if !self.render_target_is_stale(draw_list_id, &rendered) {
+                continue
+            }
+            rendered.push(draw_list_id);
+            let texture_id = self.prepare_render_target(draw_list_id);
+            self.textures_2d[texture_id].bind_render_target();
+            mem::swap(&mut self.uniforms, &mut self.draw_lists[draw_list_id].target_uniforms);
+            self.exec_draw_list(draw_list_id, None);
+            mem::swap(&mut self.uniforms, &mut self.draw_lists[draw_list_id].target_uniforms);
+        }
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl::Viewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+        }
     }
 
     fn resize_window_to_turtle(&mut self, glutin_context: &glutin::WindowedContext<glutin::PossiblyCurrent>) {
@@ -166,6 +266,7 @@ impl Cx {
                 self.paint_dirty = false;
                 self.repaint(&glutin_context);
             }
+            self.profile_end_frame();
 
             // wait for the next event blockingly so it stops eating power
             if self.playing_anim_areas.len() == 0 && self.redraw_areas.len() == 0 {
@@ -428,8 +529,14 @@ impl Cx {
     }
 
     pub fn compile_all_ogl_shaders(&mut self) {
+        self.profile_begin("shader compile");
+        let mut cache = if let Some(dir) = &self.platform.desktop.shader_cache_dir {
+            Some(ShaderCache::new(dir, &Self::ogl_driver_string()))
+        } else {
+            None
+        };
         for sh in &self.shaders {
-            let glsh = Self::compile_ogl_shader(&sh);
+            let glsh = Self::compile_ogl_shader(&sh, cache.as_mut());
             if let Ok(glsh) = glsh {
                 self.compiled_shaders.push(CompiledShader {
                     shader_id: self.compiled_shaders.len(),
@@ -442,6 +549,10 @@ impl Cx {
                 )
             }
         };
+        if let Some(cache) = cache {
+            self.platform.desktop.shader_cache_errors.extend(cache.errors);
+        }
+        self.profile_end();
     }
 
     pub fn compile_has_shader_error(compile: bool, shader: gl::types::GLuint, source: &str) -> Option<String> {
@@ -546,10 +657,65 @@ impl Cx {
         gl_texture_slots
     }
 
-    pub fn compile_ogl_shader(sh: &Shader) -> Result<CompiledShader, SlErr> {
-        let ash = Self::gl_assemble_shader(sh, GLShaderType::OpenGLNoPartialDeriv)?;
-        // now we have a pixel and a vertex shader
-        // so lets now pass it to GL
+    pub fn ogl_driver_string() -> String {
+        unsafe {
+            format!("{} {} {}",
+                    Self::gl_string(gl::GetString(gl::VENDOR)),
+                    Self::gl_string(gl::GetString(gl::RENDERER)),
+                    Self::gl_string(gl::GetString(gl::VERSION)))
+        }
+    }
+
+    pub fn ogl_supports_program_binary() -> bool {
+        if !gl::GetProgramBinary::is_loaded() || !gl::ProgramBinary::is_loaded() {
+            return false
+        }
+        let mut num_formats = 0;
+        unsafe {
+            gl::GetIntegerv(gl::NUM_PROGRAM_BINARY_FORMATS, &mut num_formats);
+        }
+        num_formats > 0
+    }
+
+    pub fn ogl_program_from_cache(entry: &ShaderCacheEntry) -> Option<gl::types::GLuint> {
+        if entry.binary.len() == 0 || !Self::ogl_supports_program_binary() {
+            return None
+        }
+        unsafe {
+            let program = gl::CreateProgram();
+            gl::ProgramBinary(program, entry.binary_format, entry.binary.as_ptr() as *const _, entry.binary.len() as i32);
+            let mut success = i32::from(gl::FALSE);
+            gl::GetProgramiv(program, gl::LINK_STATUS, &mut success);
+            if success != i32::from(gl::TRUE) {
+                // driver refused the binary, recompile from source
+                gl::DeleteProgram(program);
+                return None
+            }
+            Some(program)
+        }
+    }
+
+    pub fn ogl_program_binary(program: gl::types::GLuint) -> (u32, Vec<u8>) {
+        if !Self::ogl_supports_program_binary() {
+            return (0, Vec::new())
+        }
+        unsafe {
+            let mut len = 0;
+            gl::GetProgramiv(program, gl::PROGRAM_BINARY_LENGTH, &mut len);
+            if len <= 0 {
+                return (0, Vec::new())
+            }
+            let mut binary = Vec::<u8>::new();
+            binary.resize(len as usize, 0);
+            let mut format = 0;
+            let mut written = 0;
+            gl::GetProgramBinary(program, len, &mut written, &mut format, binary.as_mut_ptr() as *mut _);
+            binary.truncate(written as usize);
+            (format, binary)
+        }
+    }
+
+    pub fn compile_ogl_program(ash: &AssembledGLShader, retrievable: bool) -> Result<gl::types::GLuint, SlErr> {
         unsafe {
             let vs = gl::CreateShader(gl::VERTEX_SHADER);
             gl::ShaderSource(vs, 1, [ash.vertex.as_ptr() as *const _].as_ptr(), ptr::null());
@@ -572,6 +738,9 @@ impl Cx {
             let program = gl::CreateProgram();
             gl::AttachShader(program, vs);
             gl::AttachShader(program, fs);
+            if retrievable && Self::ogl_supports_program_binary() {
+                gl::ProgramParameteri(program, gl::PROGRAM_BINARY_RETRIEVABLE_HINT, gl::TRUE as i32);
+            }
             gl::LinkProgram(program);
             if let Some(error) = Self::compile_has_shader_error(false, program, "") {
                 return Err(SlErr {
@@ -580,6 +749,47 @@ impl Cx {
             }
             gl::DeleteShader(vs);
             gl::DeleteShader(fs);
+            Ok(program)
+        }
+    }
+
+    pub fn compile_ogl_shader(sh: &Shader, cache: Option<&mut ShaderCache>) -> Result<CompiledShader, SlErr> {
+        let hash = ShaderCache::shader_hash(sh);
+        let (ash, program) = if let Some(cache) = cache {
+            if let Some(entry) = cache.load(hash) {
+                // a hit doesn't generate the glsl, the layout comes straight from the shader
+                let mut ash = Self::gl_shader_layout(sh);
+                if let Some(program) = Self::ogl_program_from_cache(&entry) {
+                    (ash, program)
+                } else {
+                    ash.vertex = entry.vertex;
+                    ash.fragment = entry.fragment;
+                    let program = Self::compile_ogl_program(&ash, entry.binary.len() != 0)?;
+                    // an empty binary marks a driver without binaries, only a refused one gets replaced
+                    if entry.binary.len() != 0 {
+                        let (format, binary) = Self::ogl_program_binary(program);
+                        if let Err(err) = cache.store(hash, &ash.vertex, &ash.fragment, format, binary) {
+                            cache.errors.push(err);
+                        }
+                    }
+                    (ash, program)
+                }
+            } else {
+                let ash = Self::gl_assemble_shader(sh, GLShaderType::OpenGLNoPartialDeriv)?;
+                let program = Self::compile_ogl_program(&ash, true)?;
+                let (format, binary) = Self::ogl_program_binary(program);
+                if let Err(err) = cache.store(hash, &ash.vertex, &ash.fragment, format, binary) {
+                    cache.errors.push(err);
+                }
+                (ash, program)
+            }
+        } else {
+            let ash = Self::gl_assemble_shader(sh, GLShaderType::OpenGLNoPartialDeriv)?;
+            let program = Self::compile_ogl_program(&ash, false)?;
+            (ash, program)
+        };
+        // now we have a program, lets look up its attributes and uniforms
+        unsafe {
 
             let geom_attribs = Self::compile_get_attributes(program, "geomattr", ash.geometry_slots);
             let inst_attribs = Self::compile_get_attributes(program, "instattr", ash.instance_slots);
@@ -652,7 +862,7 @@ impl Cx {
 
             if loc.loc >= 0 {
                 let tex = &mut textures_2d[id];
-                if tex.dirty {
+                if tex.dirty && !tex.render_target {
                     tex.upload_to_device();
                 }
                 if let Some(gl_texture) = tex.gl_texture {
@@ -692,23 +902,6 @@ pub struct GLTextureSlot {
     pub name: String,
 }
 
-#[derive(Default, Clone)]
-pub struct AssembledGLShader {
-    pub geometry_slots: usize,
-    pub instance_slots: usize,
-    pub geometry_attribs: usize,
-    pub instance_attribs: usize,
-
-    pub uniforms_dr: Vec<ShVar>,
-    pub uniforms_dl: Vec<ShVar>,
-    pub uniforms_cx: Vec<ShVar>,
-    pub texture_slots: Vec<ShVar>,
-
-    pub fragment: String,
-    pub vertex: String,
-    pub named_instance_props: NamedInstanceProps,
-}
-
 #[derive(Default, Clone)]
 pub struct CompiledShader {
     pub shader_id: usize,
@@ -744,6 +937,7 @@ pub struct CxPlatform {
     pub last_mouse_pos: Vec2,
     pub is_cursor_in_window: bool,
     pub desktop: CxDesktop,
+    pub paint_buffer: Texture2D, // the window contents, repainted where damaged
 }
 
 #[derive(Clone, Default)]
@@ -811,10 +1005,14 @@ impl DrawCallPlatform {
 pub struct Texture2D {
     pub texture_id: usize,
     pub dirty: bool,
+    pub render_target: bool,
     pub image: Vec<u32>,
     pub width: usize,
     pub height: usize,
     pub gl_texture: Option<gl::types::GLuint>,
+    pub gl_framebuffer: Option<gl::types::GLuint>,
+    pub gl_depthbuffer: Option<gl::types::GLuint>,
+    pub gl_size: (usize, usize),
 }
 
 impl Texture2D {
@@ -847,4 +1045,187 @@ impl Texture2D {
 
         self.dirty = false;
     }
+
+    // deletes the gl texture and the framebuffer and depth buffer of a render target
+    pub fn free_device(&mut self) {
+        unsafe {
+            if let Some(tex_handle) = self.gl_texture.take() {
+                gl::DeleteTextures(1, &tex_handle);
+            }
+            if let Some(fb_handle) = self.gl_framebuffer.take() {
+                gl::DeleteFramebuffers(1, &fb_handle);
+            }
+            if let Some(db_handle) = self.gl_depthbuffer.take() {
+                gl::DeleteRenderbuffers(1, &db_handle);
+            }
+        }
+        self.gl_size = (0, 0);
+    }
+
+    // (re)allocates the texture with a framebuffer and depth buffer at its current size and binds it
+    pub fn bind_render_target(&mut self) {
+        self.bind_framebuffer();
+        unsafe {
+            gl::Viewport(0, 0, self.width as i32, self.height as i32);
+            gl::ClearColor(0.0, 0.0, 0.0, 0.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT|gl::DEPTH_BUFFER_BIT);
+        }
+        // never upload the cpu side image over what we rendered
+        self.dirty = false;
+    }
+
+    // binds the framebuffer, returns true when it was (re)allocated and holds nothing yet
+    pub fn bind_framebuffer(&mut self) -> bool {
+        unsafe {
+            if self.gl_framebuffer.is_none() {
+                let mut tex_handle = 0;
+                gl::GenTextures(1, &mut tex_handle);
+                self.gl_texture = Some(tex_handle);
+                let mut fb_handle = 0;
+                gl::GenFramebuffers(1, &mut fb_handle);
+                self.gl_framebuffer = Some(fb_handle);
+                let mut db_handle = 0;
+                gl::GenRenderbuffers(1, &mut db_handle);
+                self.gl_depthbuffer = Some(db_handle);
+                self.gl_size = (0, 0);
+            }
+            let tex_handle = self.gl_texture.unwrap();
+            let db_handle = self.gl_depthbuffer.unwrap();
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.gl_framebuffer.unwrap());
+            if self.gl_size != (self.width, self.height) {
+                self.gl_size = (self.width, self.height);
+                gl::BindTexture(gl::TEXTURE_2D, tex_handle);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+                gl::TexImage2D(gl::TEXTURE_2D, 0, gl::RGBA as i32, self.width as i32, self.height as i32, 0, gl::RGBA, gl::UNSIGNED_BYTE, ptr::null());
+                gl::BindTexture(gl::TEXTURE_2D, 0);
+                gl::FramebufferTexture2D(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::TEXTURE_2D, tex_handle, 0);
+
+                gl::BindRenderbuffer(gl::RENDERBUFFER, db_handle);
+                gl::RenderbufferStorage(gl::RENDERBUFFER, gl::DEPTH_COMPONENT24, self.width as i32, self.height as i32);
+                gl::BindRenderbuffer(gl::RENDERBUFFER, 0);
+                gl::FramebufferRenderbuffer(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, gl::RENDERBUFFER, db_handle);
+                return true
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+    use crate::quad::Quad;
+
+    type EglGetProcAddress = extern "C" fn(*const libc::c_char) -> *const libc::c_void;
+    type EglGetPlatformDisplay = extern "C" fn(u32, *mut libc::c_void, *const i32) -> *mut libc::c_void;
+    type EglInitialize = extern "C" fn(*mut libc::c_void, *mut i32, *mut i32) -> u32;
+    type EglBindApi = extern "C" fn(u32) -> u32;
+    type EglCreateContext = extern "C" fn(*mut libc::c_void, *mut libc::c_void, *mut libc::c_void, *const i32) -> *mut libc::c_void;
+    type EglMakeCurrent = extern "C" fn(*mut libc::c_void, *mut libc::c_void, *mut libc::c_void, *mut libc::c_void) -> u32;
+
+    // makes a surfaceless mesa egl context current and loads gl from it, so gl runs without
+    // a window. false where there is no such egl
+    fn egl_context() -> bool {
+        unsafe {
+            let lib = libc::dlopen(b"libEGL.so.1\0".as_ptr() as *const _, libc::RTLD_NOW);
+            if lib.is_null() {
+                return false
+            }
+            let sym = libc::dlsym(lib, b"eglGetProcAddress\0".as_ptr() as *const _);
+            if sym.is_null() {
+                return false
+            }
+            let get_proc: EglGetProcAddress = mem::transmute(sym);
+            let proc_addr = |name: &str| get_proc(CString::new(name).unwrap().as_ptr());
+            let names = ["eglGetPlatformDisplayEXT", "eglInitialize", "eglBindAPI", "eglCreateContext", "eglMakeCurrent"];
+            if names.iter().any( | name | proc_addr(name).is_null()) {
+                return false
+            }
+            let get_display: EglGetPlatformDisplay = mem::transmute(proc_addr("eglGetPlatformDisplayEXT"));
+            let initialize: EglInitialize = mem::transmute(proc_addr("eglInitialize"));
+            let bind_api: EglBindApi = mem::transmute(proc_addr("eglBindAPI"));
+            let create_context: EglCreateContext = mem::transmute(proc_addr("eglCreateContext"));
+            let make_current: EglMakeCurrent = mem::transmute(proc_addr("eglMakeCurrent"));
+            // EGL_PLATFORM_SURFACELESS_MESA, EGL_OPENGL_API, EGL_NONE
+            let display = get_display(0x31DD, ptr::null_mut(), ptr::null());
+            if display.is_null() || initialize(display, ptr::null_mut(), ptr::null_mut()) == 0 || bind_api(0x30A2) == 0 {
+                return false
+            }
+            let context = create_context(display, ptr::null_mut(), ptr::null_mut(), [0x3038].as_ptr());
+            if context.is_null() || make_current(display, ptr::null_mut(), ptr::null_mut(), context) == 0 {
+                return false
+            }
+            gl::load_with( | name | proc_addr(name));
+            true
+        }
+    }
+
+    fn target_pixels(cx: &mut Cx, texture_id: usize) -> Vec<[u8; 4]> {
+        let tex = &mut cx.textures_2d[texture_id];
+        tex.bind_framebuffer();
+        let mut pixels = vec![[0u8; 4]; tex.width * tex.height];
+        unsafe {
+            gl::ReadPixels(0, 0, tex.width as i32, tex.height as i32, gl::RGBA, gl::UNSIGNED_BYTE, pixels.as_mut_ptr() as *mut _);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+        pixels
+    }
+
+    #[test]
+    fn render_to_texture_draws_into_its_target_and_frees_it() {
+        if !egl_context() {
+            // nothing to render with on this machine
+            return
+        }
+        let mut cx = Cx::new_headless(Vec2 {x: 16., y: 16.});
+        cx.set_shader_cache_dir(None);
+        let mut quad = Quad {color: color("#f00"), ..Style::style(&mut cx)};
+        cx.compile_all_ogl_shaders();
+        let mut view = View::<NoScrollBar> {render_to_texture: true, ..Style::style(&mut cx)};
+        cx.headless_draw( | cx | {
+            view.begin_view(cx, &Layout {width: Bounds::Fix(8.), height: Bounds::Fix(4.), ..Default::default()});
+            quad.draw_quad(cx, Rect {x: 0., y: 0., w: 4., h: 4.});
+            view.end_view(cx);
+        });
+        cx.prepare_frame();
+        cx.exec_render_targets();
+
+        let texture_id = view.get_texture_id(&cx).unwrap();
+        assert_eq!((cx.textures_2d[texture_id].width, cx.textures_2d[texture_id].height), (8, 4));
+        let pixels = target_pixels(&mut cx, texture_id);
+        // the quad covers the left half, the right half keeps the clear color
+        for y in 0..4 {
+            for x in 0..8 {
+                let expect = if x < 4 {[255, 0, 0, 255]} else {[0, 0, 0, 0]};
+                assert_eq!(pixels[y * 8 + x], expect, "pixel {} {}", x, y);
+            }
+        }
+
+        let draw_list_id = view.draw_list_id.unwrap();
+        let gl_texture = cx.textures_2d[texture_id].gl_texture.unwrap();
+        view.destruct(&mut cx);
+        assert_eq!(view.draw_list_id, None);
+        assert_eq!(cx.draw_lists_free, vec![draw_list_id]);
+        assert_eq!(cx.textures_2d_free, vec![texture_id]);
+        assert_eq!(cx.draw_lists[draw_list_id].render_target, None);
+        assert_eq!(cx.textures_2d[texture_id].gl_texture, None);
+        assert_eq!(unsafe {gl::IsTexture(gl_texture)}, gl::FALSE);
+
+        // the next view that renders to a texture gets both back
+        let mut again = View::<NoScrollBar> {render_to_texture: true, ..Style::style(&mut cx)};
+        cx.headless_draw( | cx | {
+            again.begin_view(cx, &Layout {width: Bounds::Fix(2.), height: Bounds::Fix(2.), ..Default::default()});
+            again.end_view(cx);
+        });
+        assert_eq!(again.draw_list_id, Some(draw_list_id));
+        assert_eq!(again.get_texture_id(&cx), Some(texture_id));
+        assert!(cx.draw_lists_free.is_empty() && cx.textures_2d_free.is_empty());
+        cx.prepare_frame();
+        cx.exec_render_targets();
+        assert!(target_pixels(&mut cx, texture_id).iter().all( | pixel | *pixel == [0, 0, 0, 0]));
+    }
 }
@@ -0,0 +1,167 @@
+use crate::cx::*;
+
+#[derive(Clone, Debug)]
+pub struct ProfileSpan{
+    pub name:&'static str,
+    pub depth:usize,
+    pub start:f64, // ms since the profiler was enabled
+    pub end:f64
+}
+
+// everything measured between two presents
+#[derive(Clone, Default, Debug)]
+pub struct FrameProfile{
+    pub frame:u64,
+    pub spans:Vec<ProfileSpan>,
+    pub draw_calls:usize,
+    pub instances:usize,
+    pub uploaded_bytes:usize
+}
+
+impl FrameProfile{
+    pub fn start(&self)->f64{
+        self.spans.iter().fold(std::f64::INFINITY, |a, s| a.min(s.start))
+    }
+
+    pub fn end(&self)->f64{
+        self.spans.iter().fold(0., |a, s| a.max(s.end))
+    }
+
+    // the time spent in the outermost spans with this name, or all of them for None
+    pub fn busy_ms(&self, name:Option<&str>)->f64{
+        self.spans.iter().filter(|s| s.depth == 0 && (name.is_none() || name == Some(s.name))).map(|s| s.end - s.start).sum()
+    }
+}
+
+#[derive(Clone)]
+pub struct CxProfiler{
+    pub enabled:bool,
+    pub capacity:usize, // frames of history kept
+    pub history:Vec<FrameProfile>, // ring buffer, the oldest at _next once full
+    pub _next:usize,
+    pub _frame:FrameProfile,
+    pub _open:Vec<usize>, // spans begun but not ended
+    pub _time_base:f64,
+    pub _frame_id:u64
+}
+
+impl Default for CxProfiler{
+    fn default()->Self{
+        CxProfiler{
+            enabled:false,
+            capacity:240,
+            history:Vec::new(),
+            _next:0,
+            _frame:FrameProfile::default(),
+            _open:Vec::new(),
+            _time_base:0.,
+            _frame_id:0
+        }
+    }
+}
+
+impl CxProfiler{
+    // oldest first
+    pub fn frames(&self)->Vec<&FrameProfile>{
+        let (newer, older) = self.history.split_at(self._next.min(self.history.len()));
+        older.iter().chain(newer.iter()).collect()
+    }
+
+    pub fn last_frame(&self)->Option<&FrameProfile>{
+        self.frames().pop()
+    }
+
+    pub fn count_draw_call(&mut self, instances:usize){
+        if self.enabled{
+            self._frame.draw_calls += 1;
+            self._frame.instances += instances;
+        }
+    }
+
+    pub fn count_upload(&mut self, bytes:usize){
+        if self.enabled{
+            self._frame.uploaded_bytes += bytes;
+        }
+    }
+}
+
+impl Cx{
+    pub fn enable_profiler(&mut self, enabled:bool){
+        let profiler = &mut self.profiler;
+        profiler.enabled = enabled;
+        profiler.history.truncate(0);
+        profiler._next = 0;
+        profiler._frame = FrameProfile::default();
+        profiler._open.truncate(0);
+        profiler._time_base = Cx::profile_time_ms();
+    }
+
+    pub fn profile_begin(&mut self, name:&'static str){
+        if !self.profiler.enabled{
+            return
+        }
+        let time = Cx::profile_time_ms() - self.profiler._time_base;
+        let profiler = &mut self.profiler;
+        profiler._open.push(profiler._frame.spans.len());
+        profiler._frame.spans.push(ProfileSpan{
+            name:name,
+            depth:profiler._open.len() - 1,
+            start:time,
+            end:time
+        });
+    }
+
+    pub fn profile_end(&mut self){
+        if !self.profiler.enabled{
+            return
+        }
+        let time = Cx::profile_time_ms() - self.profiler._time_base;
+        let profiler = &mut self.profiler;
+        if let Some(index) = profiler._open.pop(){
+            profiler._frame.spans[index].end = time;
+        }
+    }
+
+    // called by the platform loop after a present, or where it goes idle
+    pub fn profile_end_frame(&mut self){
+        let profiler = &mut self.profiler;
+        if !profiler.enabled || profiler._frame.spans.len() == 0 || profiler._open.len() != 0{
+            return
+        }
+        profiler._frame_id += 1;
+        let mut frame = std::mem::replace(&mut profiler._frame, FrameProfile::default());
+        frame.frame = profiler._frame_id;
+        if profiler.history.len() < profiler.capacity{
+            profiler.history.push(frame);
+            profiler._next = profiler.history.len() % profiler.capacity;
+        }
+        else{
+            profiler.history[profiler._next] = frame;
+            profiler._next = (profiler._next + 1) % profiler.capacity;
+        }
+    }
+
+    // the history in the chrome://tracing json format, spans as complete events and the
+    // counts per frame as counter events
+    pub fn profiler_chrome_trace(&self)->String{
+        let mut events = Vec::new();
+        for frame in self.profiler.frames(){
+            let start = frame.start();
+            events.push(format!(
+                "{{\"name\":\"frame {}\",\"cat\":\"frame\",\"ph\":\"X\",\"ts\":{:.3},\"dur\":{:.3},\"pid\":1,\"tid\":1}}",
+                frame.frame, start * 1000., (frame.end() - start) * 1000.
+            ));
+            for span in &frame.spans{
+                events.push(format!(
+                    "{{\"name\":\"{}\",\"cat\":\"cx\",\"ph\":\"X\",\"ts\":{:.3},\"dur\":{:.3},\"pid\":1,\"tid\":1}}",
+                    span.name, span.start * 1000., (span.end - span.start) * 1000.
+                ));
+            }
+            events.push(format!(
+                "{{\"name\":\"gpu\",\"ph\":\"C\",\"ts\":{:.3},\"pid\":1,\"args\":{{\"draw_calls\":{},\"instances\":{},\"uploaded_bytes\":{}}}}}",
+                start * 1000., frame.draw_calls, frame.instances, frame.uploaded_bytes
+            ));
+        }
+        format!("{{\"displayTimeUnit\":\"ms\",\"traceEvents\":[\n{}\n]}}\n", events.join(",\n"))
+    }
+}
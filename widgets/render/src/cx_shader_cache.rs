@@ -0,0 +1,335 @@
+// On-disk cache of generated shader sources and (where the driver allows) program binaries.
+// Entries are keyed by the same Shader hash the shader_map dedups on and are tagged with
+// a driver string, so switching GPU, driver or makepad version invalidates them.
+// An entry with an empty binary records that the driver has none, the program is then
+// compiled from the cached source and the entry is left alone.
+
+use crate::cx::*;
+use std::hash::{Hash, Hasher};
+use std::io::prelude::*;
+use std::fs::File;
+use std::fs;
+
+const SHADER_CACHE_MAGIC:u32 = 0x4d4b_5348; // MKSH
+const SHADER_CACHE_VERSION:u32 = 2;
+
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct ShaderCacheEntry{
+    pub driver:String,
+    pub vertex:String,
+    pub fragment:String,
+    pub binary_format:u32,
+    pub binary:Vec<u8>
+}
+
+#[derive(Clone, Default)]
+pub struct ShaderCache{
+    pub dir:String,
+    pub driver:String,
+    // what went wrong reading or writing entries, the cache keeps working around it
+    pub errors:Vec<String>
+}
+
+// 64 bit FNV-1a. Unlike DefaultHasher its output doesn't change between rust releases,
+// so cache entries stay valid across toolchain upgrades
+pub struct ShaderHasher(u64);
+
+impl Default for ShaderHasher{
+    fn default()->ShaderHasher{
+        ShaderHasher(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for ShaderHasher{
+    fn write(&mut self, bytes:&[u8]){
+        for b in bytes{
+            self.0 = (self.0 ^ *b as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    // lengths hash the same on 32 and 64 bit
+    fn write_usize(&mut self, i:usize){
+        self.write(&(i as u64).to_le_bytes());
+    }
+
+    fn finish(&self)->u64{
+        self.0
+    }
+}
+
+impl ShaderCache{
+    pub fn new(dir:&str, driver:&str)->ShaderCache{
+        ShaderCache{
+            dir:dir.to_string(),
+            driver:format!("{} {} {}", env!("CARGO_PKG_VERSION"), SHADER_CACHE_VERSION, driver),
+            errors:Vec::new()
+        }
+    }
+
+    // the shader_map key is the shader before add_shader names it, so leave the name out
+    pub fn shader_hash(sh:&Shader)->u64{
+        let mut hasher = ShaderHasher::default();
+        Shader{
+            name:String::new(),
+            ..sh.clone()
+        }.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    pub fn entry_path(&self, hash:u64)->String{
+        format!("{}/{:016x}.shc", self.dir, hash)
+    }
+
+    // returns the entry only if it was written by the same driver, the hash covers the shader
+    // and the driver string the version of the generator, so the source doesn't need generating to compare
+    pub fn load(&mut self, hash:u64)->Option<ShaderCacheEntry>{
+        let path = self.entry_path(hash);
+        let mut data = Vec::new();
+        if let Ok(mut file) = File::open(&path){
+            if file.read_to_end(&mut data).is_err(){
+                self.errors.push(format!("Cannot read shader cache entry {}", path));
+                return None
+            }
+        }
+        else{
+            return None
+        }
+        match ShaderCacheEntry::from_bytes(&data){
+            Ok(entry)=>{
+                if entry.driver == self.driver{
+                    return Some(entry)
+                }
+            },
+            Err(err)=>{
+                self.errors.push(format!("Shader cache entry {} invalid: {}", path, err));
+            }
+        }
+        // stale, throw it away
+        let _ = fs::remove_file(&path);
+        None
+    }
+
+    pub fn store(&self, hash:u64, vertex:&str, fragment:&str, binary_format:u32, binary:Vec<u8>)->Result<(), String>{
+        let entry = ShaderCacheEntry{
+            driver:self.driver.clone(),
+            vertex:vertex.to_string(),
+            fragment:fragment.to_string(),
+            binary_format:binary_format,
+            binary:binary
+        };
+        if fs::create_dir_all(&self.dir).is_err(){
+            return Err(format!("Cannot create shader cache dir {}", self.dir))
+        }
+        let path = self.entry_path(hash);
+        if let Ok(mut file) = File::create(&path){
+            if file.write_all(&entry.to_bytes()).is_ok(){
+                return Ok(())
+            }
+        }
+        Err(format!("Cannot write shader cache entry {}", path))
+    }
+}
+
+impl ShaderCacheEntry{
+    pub fn to_bytes(&self)->Vec<u8>{
+        let mut out = Vec::new();
+        push_u32(&mut out, SHADER_CACHE_MAGIC);
+        push_u32(&mut out, SHADER_CACHE_VERSION);
+        push_bytes(&mut out, self.driver.as_bytes());
+        push_bytes(&mut out, self.vertex.as_bytes());
+        push_bytes(&mut out, self.fragment.as_bytes());
+        push_u32(&mut out, self.binary_format);
+        push_bytes(&mut out, &self.binary);
+        out
+    }
+
+    pub fn from_bytes(data:&[u8])->Result<ShaderCacheEntry, String>{
+        let mut pos = 0;
+        if read_u32(data, &mut pos)? != SHADER_CACHE_MAGIC{
+            return Err("Wrong magic".to_string())
+        }
+        if read_u32(data, &mut pos)? != SHADER_CACHE_VERSION{
+            return Err("Wrong version".to_string())
+        }
+        let driver = read_string(data, &mut pos)?;
+        let vertex = read_string(data, &mut pos)?;
+        let fragment = read_string(data, &mut pos)?;
+        let binary_format = read_u32(data, &mut pos)?;
+        let binary = read_bytes(data, &mut pos)?.to_vec();
+        Ok(ShaderCacheEntry{
+            driver:driver,
+            vertex:vertex,
+            fragment:fragment,
+            binary_format:binary_format,
+            binary:binary
+        })
+    }
+}
+
+fn push_u32(out:&mut Vec<u8>, v:u32){
+    out.extend_from_slice(&v.to_le_bytes());
+}
+
+fn push_bytes(out:&mut Vec<u8>, v:&[u8]){
+    push_u32(out, v.len() as u32);
+    out.extend_from_slice(v);
+}
+
+fn read_u32(data:&[u8], pos:&mut usize)->Result<u32, String>{
+    if *pos + 4 > data.len(){
+        return Err("Unexpected end of data".to_string())
+    }
+    let v = (data[*pos] as u32) | ((data[*pos+1] as u32)<<8) | ((data[*pos+2] as u32)<<16) | ((data[*pos+3] as u32)<<24);
+    *pos += 4;
+    Ok(v)
+}
+
+fn read_bytes<'a>(data:&'a [u8], pos:&mut usize)->Result<&'a [u8], String>{
+    let len = read_u32(data, pos)? as usize;
+    if *pos + len > data.len(){
+        return Err("Unexpected end of data".to_string())
+    }
+    let v = &data[*pos..*pos + len];
+    *pos += len;
+    Ok(v)
+}
+
+fn read_string(data:&[u8], pos:&mut usize)->Result<String, String>{
+    match String::from_utf8(read_bytes(data, pos)?.to_vec()){
+        Ok(s)=>Ok(s),
+        Err(_)=>Err("Invalid utf8".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn entry()->ShaderCacheEntry{
+        ShaderCacheEntry{
+            driver:"1 1 test driver".to_string(),
+            vertex:"void main(){}".to_string(),
+            fragment:"void main(){ gl_FragColor = vec4(1.0); }".to_string(),
+            binary_format:0x8e21,
+            binary:vec![0, 1, 2, 255]
+        }
+    }
+
+    fn temp_dir(name:&str)->String{
+        format!("{}/makepad_shader_cache_{}_{}", std::env::temp_dir().display(), name, std::process::id())
+    }
+
+    #[test]
+    fn bytes_round_trip(){
+        let entry = entry();
+        assert_eq!(ShaderCacheEntry::from_bytes(&entry.to_bytes()), Ok(entry.clone()));
+        let empty = ShaderCacheEntry::default();
+        assert_eq!(ShaderCacheEntry::from_bytes(&empty.to_bytes()), Ok(empty));
+    }
+
+    #[test]
+    fn bad_bytes_are_rejected(){
+        let bytes = entry().to_bytes();
+        assert_eq!(ShaderCacheEntry::from_bytes(&bytes[..bytes.len() - 1]), Err("Unexpected end of data".to_string()));
+        assert_eq!(ShaderCacheEntry::from_bytes(&[]), Err("Unexpected end of data".to_string()));
+        let mut magic = bytes.clone();
+        magic[0] ^= 1;
+        assert_eq!(ShaderCacheEntry::from_bytes(&magic), Err("Wrong magic".to_string()));
+        let mut version = bytes.clone();
+        version[4..8].copy_from_slice(&(SHADER_CACHE_VERSION + 1).to_le_bytes());
+        assert_eq!(ShaderCacheEntry::from_bytes(&version), Err("Wrong version".to_string()));
+    }
+
+    #[test]
+    fn other_driver_is_a_miss(){
+        let dir = temp_dir("driver");
+        let mut cache = ShaderCache::new(&dir, "gpu a");
+        cache.store(7, "vtx", "pix", 1, vec![9, 9]).unwrap();
+        assert!(cache.load(7).is_some_and(|entry| entry.binary == vec![9, 9] && entry.vertex == "vtx" && entry.fragment == "pix"));
+        assert!(cache.load(8).is_none());
+
+        let mut other = ShaderCache::new(&dir, "gpu b");
+        assert!(other.load(7).is_none());
+        // the stale entry is removed on the miss
+        assert!(!std::path::Path::new(&cache.entry_path(7)).exists());
+        assert!(cache.load(7).is_none());
+        assert!(cache.errors.is_empty() && other.errors.is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn no_binary_marker_is_a_hit(){
+        let dir = temp_dir("marker");
+        let mut cache = ShaderCache::new(&dir, "gpu without binaries");
+        cache.store(3, "vtx", "pix", 0, Vec::new()).unwrap();
+        let entry = cache.load(3).unwrap();
+        assert_eq!((entry.binary_format, entry.binary.len()), (0, 0));
+        assert_eq!((entry.vertex.as_str(), entry.fragment.as_str()), ("vtx", "pix"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn corrupt_entries_are_errors_and_removed(){
+        let dir = temp_dir("corrupt");
+        let mut cache = ShaderCache::new(&dir, "gpu");
+        cache.store(5, "vtx", "pix", 1, vec![1]).unwrap();
+        let path = cache.entry_path(5);
+        fs::write(&path, b"garbage").unwrap();
+        assert!(cache.load(5).is_none());
+        assert_eq!(cache.errors.len(), 1);
+        assert!(cache.errors[0].contains("invalid"));
+        assert!(!std::path::Path::new(&path).exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn shader_hasher_is_fnv1a(){
+        let fnv = |bytes:&[u8]|{
+            let mut hasher = ShaderHasher::default();
+            hasher.write(bytes);
+            hasher.finish()
+        };
+        // published FNV-1a 64 test vectors, these must never change or every cache entry goes stale
+        assert_eq!(fnv(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(fnv(b"foobar"), 0x8594_4171_f739_67e8);
+        let mut hasher = ShaderHasher::default();
+        hasher.write_usize(1);
+        assert_eq!(hasher.finish(), fnv(&1u64.to_le_bytes()));
+    }
+
+    #[test]
+    fn shader_hash_is_stable(){
+        let mut cx = Cx::default();
+        let sh = cx.new_shader();
+        let hash = ShaderCache::shader_hash(&sh);
+        assert_eq!(ShaderCache::shader_hash(&sh.clone()), hash);
+        assert_eq!(ShaderCache::shader_hash(&cx.new_shader()), hash);
+        // add_shader names the shader after the lookup, that doesn't change the key
+        let named = Shader{name:"quad".to_string(), ..sh.clone()};
+        assert_eq!(ShaderCache::shader_hash(&named), hash);
+        let mut other = sh.clone();
+        other.geometry_vertices.push(1.0);
+        assert_ne!(ShaderCache::shader_hash(&other), hash);
+    }
+
+    // a cache hit only lays the shader out, that has to match what assembling it gives
+    #[cfg(feature = "ogl")]
+    #[test]
+    fn cache_hit_layout_matches_the_assembled_shader(){
+        let mut cx = Cx::default();
+        let sh = crate::text::Text::def_text_shader(&mut cx);
+        let ash = Cx::gl_assemble_shader(&sh, GLShaderType::OpenGLNoPartialDeriv).unwrap_or_else(|err| panic!("{}", err.msg));
+        let layout = Cx::gl_shader_layout(&sh);
+        assert!(layout.vertex.is_empty() && layout.fragment.is_empty());
+        assert_eq!((layout.geometry_slots, layout.instance_slots), (ash.geometry_slots, ash.instance_slots));
+        let names = |vars:&Vec<ShVar>| vars.iter().map(|v| v.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&layout.uniforms_cx), names(&ash.uniforms_cx));
+        assert_eq!(names(&layout.uniforms_dl), names(&ash.uniforms_dl));
+        assert_eq!(names(&layout.uniforms_dr), names(&ash.uniforms_dr));
+        assert_eq!(names(&layout.texture_slots), names(&ash.texture_slots));
+        assert!(!layout.texture_slots.is_empty());
+        let props = |p:&NamedInstanceProps| p.props.iter().map(|p| (p.name.clone(), p.offset, p.slots)).collect::<Vec<_>>();
+        assert_eq!(props(&layout.named_instance_props), props(&ash.named_instance_props));
+    }
+}
@@ -20,26 +20,49 @@ impl Cx{
         }
 
         let is_abs = !layout.abs_start.is_none();
+        let width = layout.width.eval_width(self, layout.margin, is_abs);
+        let height = layout.height.eval_height(self, layout.margin, is_abs);
 
         let start = if is_abs{
             layout.abs_start.unwrap()
         }
         else{
             if let Some(parent) = self.turtles.last(){
-                Vec2{x:layout.margin.l+parent.walk.x, y:layout.margin.t+parent.walk.y}
+                // walking left we know where to start only if we know our width, otherwise end_turtle moves us
+                if let (Direction::Left, false) = (&parent.layout.direction, width.is_nan()){
+                    Vec2{x:parent.walk.x - layout.margin.r - width, y:layout.margin.t+parent.walk.y}
+                }
+                else{
+                    Vec2{x:layout.margin.l+parent.walk.x, y:layout.margin.t+parent.walk.y}
+                }
             }
             else{
                 Vec2{x:layout.margin.l, y:layout.margin.t}
             }
         };
-        let width = layout.width.eval_width(self, layout.margin, is_abs);
-        let height = layout.height.eval_height(self, layout.margin, is_abs);
-        
+
+        let mut layout = layout.clone();
+        if let Direction::Inline = layout.direction{
+            layout.direction = if self.rtl{Direction::Left}else{Direction::Right};
+        }
+        // walking left starts at the right edge, which a computed width doesnt have
+        if let (Direction::Left, true) = (&layout.direction, width.is_nan()){
+            layout.direction = Direction::Right;
+        }
+        let walk_x = if let Direction::Left = layout.direction{
+            start.x + width - layout.padding.r
+        }
+        else{
+            start.x + layout.padding.l
+        };
+
         self.turtles.push(Turtle{
             align_start:self.align_list.len(),
-            layout:layout.clone(),
+            flex_next_align:self.align_list.len(),
+            debug_start:self.debug_turtles.len(),
             start:start,
-            walk:Vec2{x:start.x + layout.padding.l, y:start.y + layout.padding.t},
+            walk:Vec2{x:walk_x, y:start.y + layout.padding.t},
+            layout:layout,
             biggest:0.0,
             bound_left_top:Vec2{x:std::f32::INFINITY, y:std::f32::INFINITY},
             bound_right_bottom:Vec2{x:std::f32::NEG_INFINITY, y:std::f32::NEG_INFINITY},
@@ -58,7 +81,45 @@ impl Cx{
         let h = max_zero_keep_nan(vh.eval_height(self, margin, false));
         let mut align_dx = 0.0;
         let mut align_dy = 0.0;
+        let align_end = self.align_list.len();
+        let debug_end = self.debug_turtles.len();
         let ret = if let Some(turtle) = self.turtles.last_mut(){
+            if turtle.layout.flex.is_some() || turtle.layout.grid.is_some(){
+                // measure pass, children line up at their own size and end_turtle arranges them.
+                // grid children all measure at the start, their cells are known when the grid ends
+                let w = if w.is_nan(){0.0}else{w};
+                let h = if h.is_nan(){0.0}else{h};
+                let x = turtle.walk.x + margin.l;
+                let y = turtle.walk.y + margin.t;
+                if let Some(flex) = &turtle.layout.flex{
+                    if turtle.layout.direction.is_horizontal(){
+                        turtle.walk.x += w + margin.l + margin.r + flex.gap;
+                        turtle.biggest = turtle.biggest.max(h + margin.t + margin.b);
+                    }
+                    else{
+                        turtle.walk.y += h + margin.t + margin.b + flex.gap;
+                        turtle.biggest = turtle.biggest.max(w + margin.l + margin.r);
+                    }
+                    turtle.bound_left_top.x = turtle.bound_left_top.x.min(x);
+                    turtle.bound_left_top.y = turtle.bound_left_top.y.min(y);
+                    turtle.bound_right_bottom.x = turtle.bound_right_bottom.x.max(x + w);
+                    turtle.bound_right_bottom.y = turtle.bound_right_bottom.y.max(y + h);
+                }
+                turtle.flex_children.push(FlexChild{
+                    rect:Rect{x:x, y:y, w:w, h:h},
+                    margin:margin,
+                    item:if let Some(old) = old_turtle{old.layout.flex_item}else{FlexItem::default()},
+                    cell:if let Some(old) = old_turtle{old.layout.grid_item}else{GridItem::default()},
+                    align:if let Some(old) = old_turtle{old.layout.align}else{Align::left_top()},
+                    align_start:turtle.flex_next_align,
+                    content_start:if let Some(old) = old_turtle{old.align_start}else{align_end},
+                    align_end:align_end,
+                    debug_start:if let Some(old) = old_turtle{old.debug_start}else{debug_end},
+                    debug_end:debug_end
+                });
+                turtle.flex_next_align = align_end;
+                return Rect{x:x, y:y, w:w, h:h}
+            }
             let (x,y) = match turtle.layout.direction{
                 Direction::Right=>{
                     match turtle.layout.line_wrap{
@@ -101,6 +162,48 @@ impl Cx{
                     }
                     (x,y)
                 },
+                Direction::Left=>{
+                    match turtle.layout.line_wrap{
+                        LineWrap::NewLine=>{
+                            if (turtle.walk.x - margin.r - w) <
+                                (turtle.start.x + turtle.layout.padding.l){
+                                let old_x = turtle.walk.x;
+                                let old_y = turtle.walk.y;
+                                turtle.walk.x = turtle.start.x + turtle.width - turtle.layout.padding.r;
+                                turtle.walk.y += turtle.biggest;
+                                turtle.biggest = 0.0;
+                                align_dx = turtle.walk.x - old_x;
+                                align_dy = turtle.walk.y - old_y;
+                            }
+                        },
+                        LineWrap::None=>{
+                        }
+                    }
+
+                    let x = turtle.walk.x - margin.r - w;
+                    let y = turtle.walk.y + margin.t;
+                    turtle.walk.x -= w + margin.l + margin.r;
+
+                    // a child turtle of computed width was drawn right of the walk, move it in
+                    if let Some(old_turtle) = old_turtle{
+                        align_dx = x - old_turtle.start.x;
+                        align_dy = y - old_turtle.start.y;
+                    }
+
+                    let biggest = h + margin.t + margin.b;
+                    if biggest > turtle.biggest{
+                        turtle.biggest = biggest;
+                    }
+                    let bound_x2 = x + w + if margin.r < 0.{margin.r} else{0.};
+                    if bound_x2 > turtle.bound_right_bottom.x{
+                        turtle.bound_right_bottom.x = bound_x2;
+                    }
+                    let bound_y2 = turtle.walk.y + h + margin.t + if margin.b < 0.{margin.b} else{0.};
+                    if bound_y2 > turtle.bound_right_bottom.y{
+                        turtle.bound_right_bottom.y = bound_y2;
+                    }
+                    (x,y)
+                },
                 _=>{
                     (turtle.walk.x + margin.l, turtle.walk.y + margin.t)
                 }
@@ -129,7 +232,7 @@ impl Cx{
 
         if align_dx != 0.0 || align_dy != 0.0{
             if let Some(old_turtle) = old_turtle{
-                self.do_align(align_dx, align_dy, old_turtle.align_start);
+                self.do_align(align_dx, align_dy, old_turtle.align_start, old_turtle.debug_start);
             }
         };
 
@@ -138,25 +241,38 @@ impl Cx{
 
     pub fn turtle_new_line(&mut self){
         if let Some(turtle) = self.turtles.last_mut(){
+            if turtle.layout.flex.is_some(){
+                return
+            }
             match turtle.layout.direction{
                 Direction::Right=>{
                     turtle.walk.x = turtle.start.x + turtle.layout.padding.l;
                     turtle.walk.y += turtle.biggest;
                     turtle.biggest = 0.0;
                 },
+                Direction::Left=>{
+                    turtle.walk.x = turtle.start.x + turtle.width - turtle.layout.padding.r;
+                    turtle.walk.y += turtle.biggest;
+                    turtle.biggest = 0.0;
+                },
                 _=>()
             }
         }
     }
 
-    fn do_align(&mut self, dx:f32, dy:f32, align_start:usize){
+    fn do_align(&mut self, dx:f32, dy:f32, align_start:usize, debug_start:usize){
+        for debug_turtle in self.debug_turtles.iter_mut().skip(debug_start){
+            debug_turtle.rect.x += dx;
+            debug_turtle.rect.y += dy;
+        }
 
+        // everything drawn since align_start, a text using fallback fonts has an item per font
         for i in align_start..self.align_list.len(){
             let align_item = &self.align_list[i];
-            return match align_item{
+            match align_item{
                 Area::Instance(inst)=>{
                     if inst.instance_count == 0{
-                        return;
+                        continue;
                     }
                     let draw_list = &mut self.draw_lists[inst.draw_list_id];
                     let draw_call = &mut draw_list.draw_calls[inst.draw_call_id];
@@ -260,19 +376,20 @@ impl Cx{
 
     // restarts the turtle with a new alignment, used for a<b>c layouts
     pub fn realign_turtle(&mut self, align:Align, set_used:bool){
-        let (align_delta, align_start) = if let Some(turtle) = self.turtles.last_mut(){
-            (Self::compute_align_turtle(&turtle), turtle.align_start)
+        let (align_delta, align_start, debug_start) = if let Some(turtle) = self.turtles.last_mut(){
+            (Self::compute_align_turtle(&turtle), turtle.align_start, turtle.debug_start)
         }
         else{
-            (Vec2::zero(), 0)
+            (Vec2::zero(), 0, 0)
         };
         if align_delta.x > 0.0 || align_delta.y > 0.0{
-            self.do_align(align_delta.x, align_delta.y, align_start);
+            self.do_align(align_delta.x, align_delta.y, align_start, debug_start);
         }
         // reset turtle props
         if let Some(turtle) = self.turtles.last_mut(){
             // restart align_list for the next pass
             turtle.align_start = self.align_list.len();
+            turtle.debug_start = self.debug_turtles.len();
             // subtract used size so 'fill' works
             turtle.layout.align = align; 
             if set_used{
@@ -333,10 +450,16 @@ impl Cx{
 
     // end a turtle returning computed geometry
     pub fn end_turtle(&mut self, guard_area:Area)->Rect{
-        let old = self.turtles.pop().unwrap();
+        let mut old = self.turtles.pop().unwrap();
         if guard_area != old.guard_area{
             panic!("End turtle guard area misaligned!, begin/end pair not matched begin {:?} end {:?}", old.guard_area,  guard_area)
         }
+        if old.layout.flex.is_some(){
+            self.arrange_flex(&mut old);
+        }
+        else if old.layout.grid.is_some(){
+            self.arrange_grid(&mut old);
+        }
 
         let w = if old.width.is_nan(){
             if old.bound_right_bottom.x == std::f32::NEG_INFINITY{ // nothing happened, use padding
@@ -363,31 +486,332 @@ impl Cx{
         };
 
         let margin = old.layout.margin.clone();
-        // if we have alignment set, we should now align our childnodes
-        let align_delta = Self::compute_align_turtle(&old);
+        // if we have alignment set, we should now align our childnodes, flex and grid did their own
+        let align_delta = if old.layout.flex.is_some() || old.layout.grid.is_some(){
+            Vec2::zero()
+        }
+        else{
+            Self::compute_align_turtle(&old)
+        };
         if align_delta.x > 0.0 || align_delta.y > 0.0{
-            self.do_align(align_delta.x, align_delta.y, old.align_start);
+            self.do_align(align_delta.x, align_delta.y, old.align_start, old.debug_start);
+        }
+
+        // record the turtle for the layout debugger, its rect is known once the parent walked it
+        let debug_index = self.debug_turtles.len();
+        if self.debug_layout{
+            self.debug_turtles.push(DebugTurtle{
+                rect:Rect::zero(),
+                layout:old.layout.clone(),
+                depth:self.turtles.len(),
+                draw_list_id:self.current_draw_list_id
+            });
         }
         
         // when a turtle is x-abs / y-abs you dont walk the parent
-        if !old.layout.abs_start.is_none(){
+        let rect = if !old.layout.abs_start.is_none(){
             let abs_start = if let Some(abs_start) = old.layout.abs_start{abs_start} else {Vec2::zero()};
             let w = if let Bounds::Fix(vw) = w{vw} else {0.};
             let h = if let Bounds::Fix(vh) = h{vh} else {0.};
-            return Rect{x:abs_start.x, y:abs_start.y, w:w, h:h};
+            Rect{x:abs_start.x, y:abs_start.y, w:w, h:h}
         }
+        else{
+            self.walk_turtle(w, h, margin, Some(&old))
+        };
 
         //if self.turtles.len() == 0{
         //    return Rect{x:0.0, y:0.0, w:old.width, h:old.height};
        // }
 
-        return self.walk_turtle(w, h, margin, Some(&old))
+        if let Some(debug_turtle) = self.debug_turtles.get_mut(debug_index){
+            debug_turtle.rect = rect;
+        }
+        rect
+    }
+
+    // arrange pass of a flex turtle, resolves the sizes along the axis from the measured children,
+    // then places them by justify and align_items and moves what they drew
+    fn arrange_flex(&mut self, turtle:&mut Turtle){
+        let flex = if let Some(flex) = &turtle.layout.flex{flex.clone()}else{return};
+        let children = std::mem::replace(&mut turtle.flex_children, Vec::new());
+        if children.is_empty(){
+            return
+        }
+        let horizontal = turtle.layout.direction.is_horizontal();
+        let reverse = match turtle.layout.direction{Direction::Left | Direction::Up=>true, _=>false};
+        let pad = turtle.layout.padding;
+        // main and cross axis as (start, size) of the inner rect, and per child (size, margin before, margin after)
+        let (main_start, main_size, cross_start, cross_size) = if horizontal{
+            (turtle.start.x + pad.l, turtle.width - pad.l - pad.r, turtle.start.y + pad.t, turtle.height - pad.t - pad.b)
+        }
+        else{
+            (turtle.start.y + pad.t, turtle.height - pad.t - pad.b, turtle.start.x + pad.l, turtle.width - pad.l - pad.r)
+        };
+        let axis = |c:&FlexChild| if horizontal{(c.rect.w, c.margin.l, c.margin.r)}else{(c.rect.h, c.margin.t, c.margin.b)};
+        let cross = |c:&FlexChild| if horizontal{(c.rect.h, c.margin.t, c.margin.b)}else{(c.rect.w, c.margin.l, c.margin.r)};
+
+        let gaps = flex.gap * (children.len() - 1) as f32;
+        let margins:f32 = children.iter().map(|c|{let (_, a, b) = axis(c); a + b}).sum();
+        let mut sizes:Vec<f32> = children.iter().map(|c| axis(c).0.max(c.item.min).min(c.item.max)).collect();
+        let main_size = if main_size.is_nan(){sizes.iter().sum::<f32>() + margins + gaps}else{main_size};
+
+        // grow into free space by grow, or shrink by shrink times size, freezing children at their min/max
+        let mut frozen = vec![false; children.len()];
+        let base = sizes.clone();
+        loop{
+            let free = main_size - sizes.iter().sum::<f32>() - margins - gaps;
+            let weight = |i:usize| if free > 0.0{children[i].item.grow}else{children[i].item.shrink * base[i]};
+            let total:f32 = (0..children.len()).filter(|i| !frozen[*i]).map(|i| weight(i)).sum();
+            if free.abs() < 0.01 || total <= 0.0{
+                break
+            }
+            let mut clamped = false;
+            for i in 0..children.len(){
+                if frozen[i]{
+                    continue
+                }
+                let size = sizes[i] + free * weight(i) / total;
+                let item = &children[i].item;
+                sizes[i] = size.max(item.min).min(item.max);
+                if sizes[i] != size{
+                    frozen[i] = true;
+                    clamped = true;
+                }
+            }
+            if !clamped{
+                break
+            }
+        }
+
+        // distribute what is left over by justify
+        let n = children.len() as f32;
+        let left_over = (main_size - sizes.iter().sum::<f32>() - margins - gaps).max(0.0);
+        let (mut pos, between) = match flex.justify{
+            Justify::Start=>(0.0, 0.0),
+            Justify::End=>(left_over, 0.0),
+            Justify::Center=>(left_over * 0.5, 0.0),
+            Justify::SpaceBetween=>(0.0, if n > 1.0{left_over / (n - 1.0)}else{0.0}),
+            Justify::SpaceAround=>(left_over / (2.0 * n), left_over / n),
+            Justify::SpaceEvenly=>(left_over / (n + 1.0), left_over / (n + 1.0))
+        };
+        let line = if cross_size.is_nan(){
+            children.iter().map(|c|{let (s, a, b) = cross(c); s + a + b}).fold(0.0, f32::max)
+        }
+        else{
+            cross_size
+        };
+        turtle.bound_left_top = Vec2{x:std::f32::INFINITY, y:std::f32::INFINITY};
+        turtle.bound_right_bottom = Vec2{x:std::f32::NEG_INFINITY, y:std::f32::NEG_INFINITY};
+        for (i, child) in children.iter().enumerate(){
+            let (_, before, after) = axis(child);
+            let mut main_pos = pos + before;
+            pos += before + sizes[i] + after + flex.gap + between;
+            if reverse{
+                main_pos = main_size - main_pos - sizes[i];
+            }
+            let (measured, before, after) = cross(child);
+            let (cross_pos, cross_len) = child.item.align_self.unwrap_or(flex.align_items).place(line, measured, before, after);
+            let rect = if horizontal{
+                Rect{x:main_start + main_pos, y:cross_start + cross_pos, w:sizes[i], h:cross_len}
+            }
+            else{
+                Rect{x:cross_start + cross_pos, y:main_start + main_pos, w:cross_len, h:sizes[i]}
+            };
+            self.move_flex_child(child, &rect);
+            turtle.bound_left_top.x = turtle.bound_left_top.x.min(rect.x);
+            turtle.bound_left_top.y = turtle.bound_left_top.y.min(rect.y);
+            turtle.bound_right_bottom.x = turtle.bound_right_bottom.x.max(rect.x + rect.w);
+            turtle.bound_right_bottom.y = turtle.bound_right_bottom.y.max(rect.y + rect.h);
+        }
+    }
+
+    // arrange pass of a grid turtle, places the children in cells row by row unless they ask for
+    // a row or column, sizes the tracks from what was measured and moves the children into their cells
+    fn arrange_grid(&mut self, turtle:&mut Turtle){
+        let grid = if let Some(grid) = &turtle.layout.grid{grid.clone()}else{return};
+        let children = std::mem::replace(&mut turtle.flex_children, Vec::new());
+        if children.is_empty(){
+            return
+        }
+        let cols = grid.columns.len().max(1);
+        let mut taken:Vec<Vec<bool>> = Vec::new();
+        let mut cursor = (0, 0);
+        let mut cells = Vec::new();
+        for child in &children{
+            let cell = &child.cell;
+            let col_span = cell.column_span.max(1).min(cols);
+            let row_span = cell.row_span.max(1);
+            let free = |taken:&Vec<Vec<bool>>, row:usize, col:usize|{
+                col + col_span <= cols && (row..row + row_span).all(|r| r >= taken.len() || (col..col + col_span).all(|c| !taken[r][c]))
+            };
+            let (row, col) = match (cell.row, cell.column){
+                (Some(row), Some(col))=>(row, col.min(cols - col_span)),
+                (Some(row), None)=>(row, (0..cols).find(|c| free(&taken, row, *c)).unwrap_or(0)),
+                (None, Some(col))=>{
+                    let col = col.min(cols - col_span);
+                    let mut row = 0;
+                    while !free(&taken, row, col){
+                        row += 1;
+                    }
+                    (row, col)
+                },
+                (None, None)=>{
+                    let (mut row, mut col) = cursor;
+                    while !free(&taken, row, col){
+                        col += 1;
+                        if col + col_span > cols{
+                            col = 0;
+                            row += 1;
+                        }
+                    }
+                    cursor = (row, col + col_span);
+                    (row, col)
+                }
+            };
+            while taken.len() < row + row_span{
+                taken.push(vec![false; cols]);
+            }
+            for r in row..row + row_span{
+                for c in col..col + col_span{
+                    taken[r][c] = true;
+                }
+            }
+            cells.push((row, col, row_span, col_span));
+        }
+
+        let pad = turtle.layout.padding;
+        let col_items:Vec<(usize, usize, f32)> = children.iter().zip(&cells).map(|(child, cell)|{
+            (cell.1, cell.3, child.rect.w + child.margin.l + child.margin.r)
+        }).collect();
+        let row_items:Vec<(usize, usize, f32)> = children.iter().zip(&cells).map(|(child, cell)|{
+            (cell.0, cell.2, child.rect.h + child.margin.t + child.margin.b)
+        }).collect();
+        let widths = size_grid_tracks(&grid.columns, cols, turtle.width - pad.l - pad.r, grid.column_gap, &col_items);
+        let heights = size_grid_tracks(&grid.rows, taken.len(), turtle.height - pad.t - pad.b, grid.row_gap, &row_items);
+        let span = |sizes:&[f32], gap:f32, start:usize, len:usize|{
+            let pos = sizes[0..start].iter().sum::<f32>() + gap * start as f32;
+            (pos, sizes[start..start + len].iter().sum::<f32>() + gap * (len - 1) as f32)
+        };
+        // right to left grids mirror the columns
+        let reverse = if let Direction::Left = turtle.layout.direction{true}else{false};
+        let total_w = span(&widths, grid.column_gap, 0, cols).1;
+
+        turtle.bound_left_top = Vec2{x:std::f32::INFINITY, y:std::f32::INFINITY};
+        turtle.bound_right_bottom = Vec2{x:std::f32::NEG_INFINITY, y:std::f32::NEG_INFINITY};
+        for (child, (row, col, row_span, col_span)) in children.iter().zip(cells){
+            let (mut cell_x, cell_w) = span(&widths, grid.column_gap, col, col_span);
+            let (cell_y, cell_h) = span(&heights, grid.row_gap, row, row_span);
+            if reverse{
+                cell_x = total_w - cell_x - cell_w;
+            }
+            let (x, w) = child.cell.justify_self.unwrap_or(grid.justify_items).place(cell_w, child.rect.w, child.margin.l, child.margin.r);
+            let (y, h) = child.cell.align_self.unwrap_or(grid.align_items).place(cell_h, child.rect.h, child.margin.t, child.margin.b);
+            let rect = Rect{
+                x:turtle.start.x + pad.l + cell_x + x,
+                y:turtle.start.y + pad.t + cell_y + y,
+                w:w,
+                h:h
+            };
+            self.move_flex_child(child, &rect);
+            turtle.bound_left_top.x = turtle.bound_left_top.x.min(rect.x);
+            turtle.bound_left_top.y = turtle.bound_left_top.y.min(rect.y);
+            turtle.bound_right_bottom.x = turtle.bound_right_bottom.x.max(rect.x + rect.w);
+            turtle.bound_right_bottom.y = turtle.bound_right_bottom.y.max(rect.y + rect.h);
+        }
+    }
+
+    // moves what a flex or grid child drew to its arranged rect. Instances covering the whole measured
+    // rect, like backgrounds, take the new size, the child's own content follows its align
+    fn move_flex_child(&mut self, child:&FlexChild, rect:&Rect){
+        let dx = rect.x - child.rect.x;
+        let dy = rect.y - child.rect.y;
+        let dw = rect.w - child.rect.w;
+        let dh = rect.h - child.rect.h;
+        if dx == 0.0 && dy == 0.0 && dw == 0.0 && dh == 0.0{
+            return
+        }
+        let covers = |x:f32, y:f32, w:f32, h:f32|{
+            (x - child.rect.x).abs() < 0.01 && (y - child.rect.y).abs() < 0.01 &&
+            (w - child.rect.w).abs() < 0.01 && (h - child.rect.h).abs() < 0.01
+        };
+        for index in child.align_start..child.align_end{
+            let (mx, my) = if index >= child.content_start{
+                (dx + child.align.fx * dw, dy + child.align.fy * dh)
+            }
+            else{
+                (dx, dy)
+            };
+            match &self.align_list[index]{
+                Area::Instance(inst)=>{
+                    if inst.instance_count == 0{
+                        continue;
+                    }
+                    let draw_call = &mut self.draw_lists[inst.draw_list_id].draw_calls[inst.draw_call_id];
+                    let csh = &self.compiled_shaders[draw_call.shader_id];
+                    let props = &csh.rect_instance_props;
+                    for i in 0..inst.instance_count{
+                        let o = inst.instance_offset + i * csh.instance_slots;
+                        let v = &mut draw_call.instance;
+                        if let (Some(x), Some(y), Some(w), Some(h)) = (props.x, props.y, props.w, props.h){
+                            if covers(v[o + x], v[o + y], v[o + w], v[o + h]){
+                                v[o + x] = rect.x;
+                                v[o + y] = rect.y;
+                                v[o + w] = rect.w;
+                                v[o + h] = rect.h;
+                                continue;
+                            }
+                        }
+                        if let Some(x) = props.x{
+                            v[o + x] += mx;
+                        }
+                        if let Some(y) = props.y{
+                            v[o + y] += my;
+                        }
+                    }
+                },
+                Area::DrawList(area_draw_list)=>{
+                    let draw_list = &mut self.draw_lists[area_draw_list.draw_list_id];
+                    let r = draw_list.rect;
+                    if covers(r.x, r.y, r.w, r.h){
+                        draw_list.rect = *rect;
+                    }
+                    else{
+                        draw_list.rect.x += mx;
+                        draw_list.rect.y += my;
+                    }
+                },
+                _=>()
+            }
+        }
+        // the layout debugger records of the child and the turtles in it, the last one is the child
+        let debug_end = child.debug_end.min(self.debug_turtles.len());
+        for debug_turtle in &mut self.debug_turtles[child.debug_start.min(debug_end)..debug_end]{
+            let r = debug_turtle.rect;
+            if covers(r.x, r.y, r.w, r.h){
+                debug_turtle.rect = *rect;
+            }
+            else{
+                debug_turtle.rect.x += dx + child.align.fx * dw;
+                debug_turtle.rect.y += dy + child.align.fy * dh;
+            }
+        }
     }
 
     pub fn width_left(&self, abs:bool)->f32{
         if !abs{
             if let Some(turtle) = self.turtles.last(){
-                let nan_val = max_zero_keep_nan(turtle.width - turtle.width_used - (turtle.walk.x-turtle.start.x) );
+                // flex hands out the space along its axis when it ends
+                // and grid on both
+                if turtle.layout.flex.is_some() && turtle.layout.direction.is_horizontal() || turtle.layout.grid.is_some(){
+                    return std::f32::NAN
+                }
+                let walked = if let Direction::Left = turtle.layout.direction{
+                    turtle.start.x + turtle.width - turtle.walk.x
+                }
+                else{
+                    turtle.walk.x - turtle.start.x
+                };
+                let nan_val = max_zero_keep_nan(turtle.width - turtle.width_used - walked);
                 if nan_val.is_nan(){ // if we are a computed height, if some value is known, use that
                     if turtle.bound_right_bottom.x != std::f32::NEG_INFINITY{
                         return turtle.bound_right_bottom.x - turtle.start.x
@@ -399,6 +823,20 @@ impl Cx{
         return self.target_size.x
     }
 
+    // width left in the turtle, or the inner width of the nearest parent with a known width
+    // when the turtle computes its own
+    pub fn bounded_width_left(&self)->f32{
+        for (index, turtle) in self.turtles.iter().enumerate().rev(){
+            if !turtle.width.is_nan(){
+                if index == self.turtles.len() - 1{
+                    return self.width_left(false)
+                }
+                return max_zero_keep_nan(turtle.width - (turtle.layout.padding.l + turtle.layout.padding.r))
+            }
+        }
+        self.target_size.x
+    }
+
     pub fn width_total(&self, abs:bool)->f32{
         if !abs{
             if let Some(turtle) = self.turtles.last(){
@@ -417,6 +855,9 @@ impl Cx{
     pub fn height_left(&self, abs:bool)->f32{
         if !abs{
             if let Some(turtle) = self.turtles.last(){
+                if turtle.layout.flex.is_some() && !turtle.layout.direction.is_horizontal() || turtle.layout.grid.is_some(){
+                    return std::f32::NAN
+                }
                 let nan_val = max_zero_keep_nan(turtle.height - turtle.height_used - (turtle.walk.y -turtle.start.y) );
                 if nan_val.is_nan(){ // if we are a computed height, if some value is known, use that
                     if turtle.bound_right_bottom.y != std::f32::NEG_INFINITY{
@@ -473,7 +914,7 @@ pub fn debug_pt(x:f32, y:f32, color:i32, s:&str){
         })*/
 
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub enum Bounds{
     Fill,
     Fix(f32),
@@ -586,12 +1027,13 @@ impl Padding{
 }
 
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub enum Direction{
     Left,
     Right,
     Up,
-    Down
+    Down,
+    Inline // Right, or Left when cx.rtl is set
 }
 
 impl Default for Direction{
@@ -600,6 +1042,15 @@ impl Default for Direction{
     }
 }
 
+impl Direction{
+    pub fn is_horizontal(&self)->bool{
+        match self{
+            Direction::Up | Direction::Down=>false,
+            _=>true
+        }
+    }
+}
+
 #[derive(Clone)]
 pub enum Axis{
     Horizontal,
@@ -612,7 +1063,7 @@ impl Default for Axis{
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub enum LineWrap{
     None,
     NewLine
@@ -623,7 +1074,154 @@ impl Default for LineWrap{
     }
 }
 
-#[derive(Clone, Default)]
+// how a flex turtle places its children along the direction
+#[derive(Clone, Copy, Debug)]
+pub enum Justify{
+    Start,
+    End,
+    Center,
+    SpaceBetween,
+    SpaceAround,
+    SpaceEvenly
+}
+
+impl Default for Justify{
+    fn default()->Self{
+        Justify::Start
+    }
+}
+
+// how a flex turtle places its children across the direction
+#[derive(Clone, Copy, Debug)]
+pub enum FlexAlign{
+    Start,
+    End,
+    Center,
+    Stretch
+}
+
+impl Default for FlexAlign{
+    fn default()->Self{
+        FlexAlign::Start
+    }
+}
+
+impl FlexAlign{
+    // position and size of a child measuring 'size' with margins 'before' and 'after' in a line of 'line'
+    pub fn place(&self, line:f32, size:f32, before:f32, after:f32)->(f32, f32){
+        match self{
+            FlexAlign::Start=>(before, size),
+            FlexAlign::End=>(line - after - size, size),
+            FlexAlign::Center=>(before + (line - before - after - size) * 0.5, size),
+            FlexAlign::Stretch=>(before, (line - before - after).max(0.0))
+        }
+    }
+}
+
+// setting Layout::flex lays the children out in one line along the direction,
+// sizes along it come from their bounds and then grow or shrink to fit the turtle
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Flex{
+    pub justify:Justify,
+    pub align_items:FlexAlign,
+    pub gap:f32
+}
+
+// the Layout of a child in a flex turtle, min and max apply along the direction
+#[derive(Clone, Copy, Debug)]
+pub struct FlexItem{
+    pub grow:f32,
+    pub shrink:f32,
+    pub min:f32,
+    pub max:f32,
+    pub align_self:Option<FlexAlign>
+}
+
+impl Default for FlexItem{
+    fn default()->Self{
+        FlexItem{
+            grow:0.0,
+            shrink:1.0,
+            min:0.0,
+            max:std::f32::INFINITY,
+            align_self:None
+        }
+    }
+}
+
+// the size of a grid row or column
+#[derive(Clone, Copy, Debug)]
+pub enum GridTrack{
+    Fix(f32),
+    Fr(f32), // a share of the space the other tracks leave
+    Auto // fits its children
+}
+
+// setting Layout::grid places the children in cells of columns and rows, rows past the
+// given ones are added as Auto when the children need them
+#[derive(Clone, Debug)]
+pub struct Grid{
+    pub columns:Vec<GridTrack>,
+    pub rows:Vec<GridTrack>,
+    pub column_gap:f32,
+    pub row_gap:f32,
+    pub justify_items:FlexAlign,
+    pub align_items:FlexAlign
+}
+
+impl Default for Grid{
+    fn default()->Self{
+        Grid{
+            columns:vec![GridTrack::Fr(1.0)],
+            rows:Vec::new(),
+            column_gap:0.0,
+            row_gap:0.0,
+            justify_items:FlexAlign::Stretch,
+            align_items:FlexAlign::Stretch
+        }
+    }
+}
+
+// the Layout of a child in a grid turtle, without a row or column it takes the next free cell
+#[derive(Clone, Copy, Debug)]
+pub struct GridItem{
+    pub column:Option<usize>,
+    pub row:Option<usize>,
+    pub column_span:usize,
+    pub row_span:usize,
+    pub justify_self:Option<FlexAlign>,
+    pub align_self:Option<FlexAlign>
+}
+
+impl Default for GridItem{
+    fn default()->Self{
+        GridItem{
+            column:None,
+            row:None,
+            column_span:1,
+            row_span:1,
+            justify_self:None,
+            align_self:None
+        }
+    }
+}
+
+// a child as measured by walk_turtle, with the align_list range of what it drew
+#[derive(Clone, Default, Debug)]
+pub struct FlexChild{
+    pub rect:Rect,
+    pub margin:Margin,
+    pub item:FlexItem,
+    pub cell:GridItem,
+    pub align:Align,
+    pub align_start:usize,
+    pub content_start:usize, // the child turtle's own content, before it are its backgrounds
+    pub align_end:usize,
+    pub debug_start:usize,
+    pub debug_end:usize
+}
+
+#[derive(Clone, Default, Debug)]
 pub struct Layout{
     pub margin:Margin,
     pub padding:Padding,
@@ -633,6 +1231,10 @@ pub struct Layout{
     pub abs_start:Option<Vec2>,
     pub width:Bounds,
     pub height:Bounds,
+    pub flex:Option<Flex>,
+    pub flex_item:FlexItem,
+    pub grid:Option<Grid>,
+    pub grid_item:GridItem
 }
 
 #[derive(Clone, Default)]
@@ -648,14 +1250,71 @@ pub struct Turtle{
     pub height_used:f32,
     pub biggest:f32,
     pub layout:Layout,
-    pub guard_area:Area
-} 
+    pub guard_area:Area,
+    pub flex_children:Vec<FlexChild>,
+    pub flex_next_align:usize,
+    pub debug_start:usize
+}
+
+// a turtle as it ended, recorded when Cx::debug_layout is set. The rect is in the
+// coordinates of its draw list, before scrolling
+#[derive(Clone, Debug)]
+pub struct DebugTurtle{
+    pub rect:Rect,
+    pub layout:Layout,
+    pub depth:usize,
+    pub draw_list_id:usize
+}
 //#[derive(Clone, Default)]
 //pub struct CxTurtle{
  //   pub debug_pts:RefCell<Vec<(f32,f32,i32)>>
 //}
 
 
+// sizes 'count' grid tracks from the (start, span, size) of the children in them, 'inner' is NaN
+// when the grid computes its size and fr tracks then fit their children like auto ones
+fn size_grid_tracks(tracks:&[GridTrack], count:usize, inner:f32, gap:f32, items:&[(usize, usize, f32)])->Vec<f32>{
+    let track = |i:usize| if i < tracks.len(){tracks[i]}else{GridTrack::Auto};
+    let mut sizes:Vec<f32> = (0..count).map(|i| if let GridTrack::Fix(v) = track(i){v}else{0.0}).collect();
+    for (start, span, size) in items{
+        if let (1, GridTrack::Auto) = (span, track(*start)){
+            sizes[*start] = sizes[*start].max(*size);
+        }
+    }
+    let fr_total:f32 = (0..count).map(|i| if let GridTrack::Fr(fr) = track(i){fr}else{0.0}).sum();
+    if fr_total > 0.0{
+        let unit = if inner.is_nan(){
+            items.iter().map(|(start, span, size)|{
+                match (span, track(*start)){
+                    (1, GridTrack::Fr(fr)) if fr > 0.0=>size / fr,
+                    _=>0.0
+                }
+            }).fold(0.0, f32::max)
+        }
+        else{
+            ((inner - sizes.iter().sum::<f32>() - gap * (count - 1) as f32) / fr_total).max(0.0)
+        };
+        for i in 0..count{
+            if let GridTrack::Fr(fr) = track(i){
+                sizes[i] = fr * unit;
+            }
+        }
+    }
+    // children spanning several tracks grow the auto ones among them by what they are short
+    for (start, span, size) in items{
+        if *span > 1{
+            let have = sizes[*start..start + span].iter().sum::<f32>() + gap * (span - 1) as f32;
+            let autos:Vec<usize> = (*start..start + span).filter(|i| if let GridTrack::Auto = track(*i){true}else{false}).collect();
+            if *size > have && !autos.is_empty(){
+                for i in &autos{
+                    sizes[*i] += (size - have) / autos.len() as f32;
+                }
+            }
+        }
+    }
+    sizes
+}
+
 pub fn max_zero_keep_nan(v:f32)->f32{
     if v.is_nan(){
         v
@@ -663,4 +1322,370 @@ pub fn max_zero_keep_nan(v:f32)->f32{
     else{
         f32::max(v,0.0)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(all(test, feature = "ogl"))]
+mod tests{
+    use super::*;
+    use crate::quad::*;
+
+    fn quad_cx()->(Cx, Quad){
+        let mut cx = Cx::new_headless(Vec2{x:1000., y:600.});
+        let quad = Quad::style(&mut cx);
+        cx.headless_compile().expect("shaders compile");
+        (cx, quad)
+    }
+
+    // draws in a root view and gives back the rects of the quads in the order they were drawn
+    fn quad_rects(cx:&mut Cx, quad:&mut Quad, draw:impl FnOnce(&mut Cx, &mut Quad))->Vec<Rect>{
+        cx.headless_draw(|cx| draw(cx, quad));
+
+        let draw_list = &cx.draw_lists[0];
+        let draw_call = draw_list.draw_calls[0..draw_list.draw_calls_len].iter().find(|dc| dc.shader_id == quad.shader_id).unwrap();
+        let csh = &cx.compiled_shaders[draw_call.shader_id];
+        let props = &csh.rect_instance_props;
+        draw_call.instance.chunks(csh.instance_slots).map(|inst|{
+            Rect{x:inst[props.x.unwrap()], y:inst[props.y.unwrap()], w:inst[props.w.unwrap()], h:inst[props.h.unwrap()]}
+        }).collect()
+    }
+
+    fn rect(x:f32, y:f32, w:f32, h:f32)->Rect{
+        Rect{x:x, y:y, w:w, h:h}
+    }
+
+    // a flex turtle of 300x50 with the children as quads, gives back where they ended up
+    fn flex_rects(flex:Flex, direction:Direction, children:&[Layout])->Vec<Rect>{
+        let (mut cx, mut quad) = quad_cx();
+        let rects = quad_rects(&mut cx, &mut quad, |cx, quad|{
+            let inst = quad.begin_quad(cx, &Layout{
+                width:Bounds::Fix(300.),
+                height:Bounds::Fix(50.),
+                direction:direction,
+                flex:Some(flex),
+                ..Default::default()
+            });
+            for child in children{
+                let child_inst = quad.begin_quad(cx, child);
+                quad.end_quad(cx, &child_inst);
+            }
+            quad.end_quad(cx, &inst);
+        });
+        rects[1..].to_vec()
+    }
+
+    fn item(w:f32, h:f32, flex_item:FlexItem)->Layout{
+        Layout{width:Bounds::Fix(w), height:Bounds::Fix(h), flex_item:flex_item, ..Default::default()}
+    }
+
+    fn grow(grow:f32)->FlexItem{
+        FlexItem{grow:grow, ..Default::default()}
+    }
+
+    fn xs(rects:&[Rect])->Vec<f32>{
+        rects.iter().map(|r| r.x).collect()
+    }
+
+    fn ws(rects:&[Rect])->Vec<f32>{
+        rects.iter().map(|r| r.w).collect()
+    }
+
+    fn close(a:Vec<f32>, b:Vec<f32>){
+        assert!(a.len() == b.len() && a.iter().zip(&b).all(|(a, b)| (a - b).abs() < 0.01), "{:?} != {:?}", a, b);
+    }
+
+    // an inline row of a 50 and a 60 wide child, and a computed one around a 40 wide quad
+    fn inline_rects(rtl:bool)->Vec<Rect>{
+        let (mut cx, mut quad) = quad_cx();
+        cx.set_rtl(rtl);
+        let rects = quad_rects(&mut cx, &mut quad, |cx, quad|{
+            let inst = quad.begin_quad(cx, &Layout{
+                width:Bounds::Fix(300.),
+                height:Bounds::Fix(50.),
+                direction:Direction::Inline,
+                ..Default::default()
+            });
+            quad.draw_quad_walk(cx, Bounds::Fix(50.), Bounds::Fix(20.), Margin::zero());
+            quad.draw_quad_walk(cx, Bounds::Fix(60.), Bounds::Fix(20.), Margin{l:0., t:0., r:10., b:0.});
+            let computed = quad.begin_quad(cx, &Layout{width:Bounds::Compute, height:Bounds::Compute, ..Default::default()});
+            quad.draw_quad_walk(cx, Bounds::Fix(40.), Bounds::Fix(20.), Margin::zero());
+            quad.end_quad(cx, &computed);
+            quad.end_quad(cx, &inst);
+        });
+        rects[1..].to_vec()
+    }
+
+    #[test]
+    fn inline_direction_follows_rtl(){
+        let ltr = inline_rects(false);
+        close(xs(&ltr), vec![0., 50., 120., 120.]);
+        // right to left the row starts at the right edge, margins mirror along
+        let rtl = inline_rects(true);
+        close(ws(&rtl), vec![50., 60., 40., 40.]);
+        close(xs(&rtl), vec![250., 180., 140., 140.]);
+    }
+
+    #[test]
+    fn grow_shares_the_free_space_by_ratio(){
+        let rects = flex_rects(Flex::default(), Direction::Right, &[item(50., 20., grow(1.)), item(50., 20., grow(2.)), item(50., 20., grow(0.))]);
+        // 150 free, a third and two thirds of it
+        close(ws(&rects), vec![100., 150., 50.]);
+        close(xs(&rects), vec![0., 100., 250.]);
+    }
+
+    #[test]
+    fn shrink_is_weighted_by_size(){
+        let rects = flex_rects(Flex::default(), Direction::Right, &[
+            item(200., 20., FlexItem{shrink:1., ..Default::default()}),
+            item(100., 20., FlexItem{shrink:1., ..Default::default()}),
+            item(100., 20., FlexItem{shrink:0., ..Default::default()})
+        ]);
+        // 100 too wide, taken 2:1 from the first two
+        close(ws(&rects), vec![133.33, 66.67, 100.]);
+        close(xs(&rects), vec![0., 133.33, 200.]);
+    }
+
+    #[test]
+    fn min_and_max_freeze_and_hand_the_rest_on(){
+        let rects = flex_rects(Flex::default(), Direction::Right, &[
+            item(50., 20., FlexItem{grow:1., max:80., ..Default::default()}),
+            item(50., 20., grow(1.))
+        ]);
+        close(ws(&rects), vec![80., 220.]);
+        let rects = flex_rects(Flex::default(), Direction::Right, &[
+            item(200., 20., FlexItem{min:180., ..Default::default()}),
+            item(200., 20., FlexItem::default())
+        ]);
+        close(ws(&rects), vec![180., 120.]);
+        // measured sizes outside min and max are clamped before anything grows
+        let rects = flex_rects(Flex::default(), Direction::Right, &[
+            item(10., 20., FlexItem{min:40., ..Default::default()}),
+            item(100., 20., FlexItem{max:60., ..Default::default()})
+        ]);
+        close(ws(&rects), vec![40., 60.]);
+    }
+
+    #[test]
+    fn justify_places_the_left_over_space(){
+        let justify = |justify:Justify|{
+            let flex = Flex{justify:justify, ..Default::default()};
+            xs(&flex_rects(flex, Direction::Right, &[item(50., 20., FlexItem::default()), item(50., 20., FlexItem::default()), item(50., 20., FlexItem::default())]))
+        };
+        close(justify(Justify::Start), vec![0., 50., 100.]);
+        close(justify(Justify::End), vec![150., 200., 250.]);
+        close(justify(Justify::Center), vec![75., 125., 175.]);
+        close(justify(Justify::SpaceBetween), vec![0., 125., 250.]);
+        close(justify(Justify::SpaceAround), vec![25., 125., 225.]);
+        close(justify(Justify::SpaceEvenly), vec![37.5, 125., 212.5]);
+        // a growing child leaves nothing to justify
+        let flex = Flex{justify:Justify::Center, ..Default::default()};
+        close(xs(&flex_rects(flex, Direction::Right, &[item(50., 20., grow(1.)), item(50., 20., FlexItem::default())])), vec![0., 250.]);
+    }
+
+    #[test]
+    fn align_items_places_across(){
+        let align = |align_items:FlexAlign|{
+            let flex = Flex{align_items:align_items, ..Default::default()};
+            let rect = flex_rects(flex, Direction::Right, &[item(50., 20., FlexItem::default())])[0];
+            (rect.y, rect.h)
+        };
+        assert_eq!(align(FlexAlign::Start), (0., 20.));
+        assert_eq!(align(FlexAlign::End), (30., 20.));
+        assert_eq!(align(FlexAlign::Center), (15., 20.));
+        assert_eq!(align(FlexAlign::Stretch), (0., 50.));
+        // align_self wins, margins keep their distance
+        let flex = Flex{align_items:FlexAlign::Stretch, ..Default::default()};
+        let rects = flex_rects(flex, Direction::Right, &[
+            item(50., 20., FlexItem{align_self:Some(FlexAlign::End), ..Default::default()}),
+            Layout{margin:Margin{l:0., t:5., r:0., b:10.}, ..item(50., 20., FlexItem::default())}
+        ]);
+        assert_eq!(rects, vec![rect(0., 30., 50., 20.), rect(50., 5., 50., 35.)]);
+    }
+
+    #[test]
+    fn gaps_go_between_children(){
+        let flex = Flex{gap:10., ..Default::default()};
+        let rects = flex_rects(flex, Direction::Right, &[item(50., 20., FlexItem::default()), item(50., 20., FlexItem::default()), item(50., 20., FlexItem::default())]);
+        close(xs(&rects), vec![0., 60., 120.]);
+        // and are not handed out when growing
+        let rects = flex_rects(flex, Direction::Right, &[item(50., 20., grow(1.)), item(50., 20., grow(1.)), item(50., 20., grow(1.))]);
+        close(ws(&rects), vec![93.33, 93.33, 93.33]);
+        close(xs(&rects), vec![0., 103.33, 206.67]);
+        // nor left over to justify
+        let flex = Flex{gap:10., justify:Justify::SpaceBetween, ..Default::default()};
+        close(xs(&flex_rects(flex, Direction::Right, &[item(50., 20., FlexItem::default()), item(50., 20., FlexItem::default())])), vec![0., 250.]);
+    }
+
+    // a grid turtle of 300x200 with the children as quads, gives back where they ended up
+    fn grid_rects(grid:Grid, direction:Direction, children:&[Layout])->Vec<Rect>{
+        let (mut cx, mut quad) = quad_cx();
+        let rects = quad_rects(&mut cx, &mut quad, |cx, quad|{
+            let inst = quad.begin_quad(cx, &Layout{
+                width:Bounds::Fix(300.),
+                height:Bounds::Fix(200.),
+                direction:direction,
+                grid:Some(grid),
+                ..Default::default()
+            });
+            for child in children{
+                let child_inst = quad.begin_quad(cx, child);
+                quad.end_quad(cx, &child_inst);
+            }
+            quad.end_quad(cx, &inst);
+        });
+        rects[1..].to_vec()
+    }
+
+    fn cell(w:f32, h:f32, grid_item:GridItem)->Layout{
+        Layout{width:Bounds::Fix(w), height:Bounds::Fix(h), grid_item:grid_item, ..Default::default()}
+    }
+
+    fn ys(rects:&[Rect])->Vec<f32>{
+        rects.iter().map(|r| r.y).collect()
+    }
+
+    fn hs(rects:&[Rect])->Vec<f32>{
+        rects.iter().map(|r| r.h).collect()
+    }
+
+    #[test]
+    fn columns_and_reversed_rows(){
+        let flex = Flex{gap:5., ..Default::default()};
+        let rects = flex_rects(flex, Direction::Down, &[item(20., 10., FlexItem::default()), item(20., 10., grow(1.))]);
+        assert_eq!(rects, vec![rect(0., 0., 20., 10.), rect(0., 15., 20., 35.)]);
+        let rects = flex_rects(flex, Direction::Left, &[item(50., 20., FlexItem::default()), item(60., 20., FlexItem::default())]);
+        close(xs(&rects), vec![250., 185.]);
+    }
+
+    #[test]
+    fn fixed_fr_and_auto_tracks(){
+        let grid = Grid{columns:vec![GridTrack::Fix(50.), GridTrack::Fr(1.), GridTrack::Fr(2.), GridTrack::Auto], ..Default::default()};
+        let rects = grid_rects(grid, Direction::Right, &[
+            cell(20., 10., GridItem::default()),
+            cell(20., 10., GridItem::default()),
+            cell(20., 10., GridItem::default()),
+            cell(30., 10., GridItem::default()),
+            cell(20., 25., GridItem::default()),
+            cell(20., 5., GridItem::default())
+        ]);
+        // the auto column fits its child, the fr ones share the 220 left 1:2
+        close(ws(&rects[0..4]), vec![50., 73.33, 146.67, 30.]);
+        close(xs(&rects[0..4]), vec![0., 50., 123.33, 270.]);
+        // rows past the given ones are auto, the second is as high as its highest child
+        close(ys(&rects), vec![0., 0., 0., 0., 10., 10.]);
+        close(hs(&rects), vec![10., 10., 10., 10., 25., 25.]);
+        close(xs(&rects[4..]), vec![0., 50.]);
+    }
+
+    #[test]
+    fn fr_rows_take_the_height_left(){
+        let grid = Grid{columns:vec![GridTrack::Fr(1.)], rows:vec![GridTrack::Fix(40.), GridTrack::Fr(1.)], ..Default::default()};
+        let rects = grid_rects(grid, Direction::Right, &[cell(20., 10., GridItem::default()), cell(20., 10., GridItem::default())]);
+        assert_eq!(rects, vec![rect(0., 0., 300., 40.), rect(0., 40., 300., 160.)]);
+    }
+
+    #[test]
+    fn spans_and_placement(){
+        let grid = Grid{columns:vec![GridTrack::Fr(1.), GridTrack::Fr(1.), GridTrack::Fr(1.)], ..Default::default()};
+        let rects = grid_rects(grid, Direction::Right, &[
+            cell(20., 10., GridItem{column_span:2, ..Default::default()}),
+            cell(20., 10., GridItem{row_span:2, ..Default::default()}),
+            cell(20., 10., GridItem::default()),
+            cell(20., 10., GridItem::default()),
+            // the first row with its column free
+            cell(20., 10., GridItem{column:Some(2), ..Default::default()}),
+            // explicit cells may overlap
+            cell(20., 10., GridItem{row:Some(0), column:Some(1), ..Default::default()})
+        ]);
+        close(xs(&rects), vec![0., 200., 0., 100., 200., 100.]);
+        close(ys(&rects), vec![0., 0., 10., 10., 20., 0.]);
+        close(ws(&rects), vec![200., 100., 100., 100., 100., 100.]);
+        close(hs(&rects), vec![10., 20., 10., 10., 10., 10.]);
+    }
+
+    #[test]
+    fn gaps_go_between_tracks(){
+        let grid = Grid{columns:vec![GridTrack::Fix(50.), GridTrack::Fr(1.)], column_gap:10., row_gap:5., ..Default::default()};
+        let rects = grid_rects(grid, Direction::Right, &[
+            cell(20., 10., GridItem::default()),
+            cell(20., 10., GridItem::default()),
+            cell(20., 10., GridItem::default()),
+            cell(20., 10., GridItem::default()),
+            cell(20., 10., GridItem{column_span:2, ..Default::default()})
+        ]);
+        close(xs(&rects), vec![0., 60., 0., 60., 0.]);
+        close(ys(&rects), vec![0., 0., 15., 15., 30.]);
+        // the fr column gets what the gap leaves, a span covers the gap too
+        close(ws(&rects), vec![50., 240., 50., 240., 300.]);
+    }
+
+    #[test]
+    fn spanning_children_grow_auto_tracks(){
+        let grid = Grid{columns:vec![GridTrack::Auto, GridTrack::Auto], justify_items:FlexAlign::Start, ..Default::default()};
+        let rects = grid_rects(grid, Direction::Right, &[
+            cell(40., 10., GridItem::default()),
+            cell(40., 10., GridItem::default()),
+            cell(120., 10., GridItem{column_span:2, ..Default::default()})
+        ]);
+        // 40 short, shared by both columns
+        close(xs(&rects), vec![0., 60., 0.]);
+        close(ws(&rects), vec![40., 40., 120.]);
+    }
+
+    #[test]
+    fn items_align_in_their_cells(){
+        let grid = Grid{
+            columns:vec![GridTrack::Fix(100.)],
+            rows:vec![GridTrack::Fix(50.)],
+            justify_items:FlexAlign::Center,
+            align_items:FlexAlign::Start,
+            ..Default::default()
+        };
+        let rects = grid_rects(grid.clone(), Direction::Right, &[cell(20., 10., GridItem::default())]);
+        assert_eq!(rects, vec![rect(40., 0., 20., 10.)]);
+        let rects = grid_rects(grid.clone(), Direction::Right, &[
+            cell(20., 10., GridItem{justify_self:Some(FlexAlign::End), align_self:Some(FlexAlign::Center), ..Default::default()})
+        ]);
+        assert_eq!(rects, vec![rect(80., 20., 20., 10.)]);
+        // stretching keeps the margins
+        let rects = grid_rects(grid, Direction::Right, &[
+            Layout{margin:Margin{l:5., t:0., r:5., b:0.}, ..cell(20., 10., GridItem{justify_self:Some(FlexAlign::Stretch), ..Default::default()})}
+        ]);
+        assert_eq!(rects, vec![rect(5., 0., 90., 10.)]);
+    }
+
+    #[test]
+    fn left_grids_mirror_the_columns(){
+        let grid = Grid{columns:vec![GridTrack::Fix(50.), GridTrack::Fix(100.)], ..Default::default()};
+        let rects = grid_rects(grid, Direction::Left, &[cell(20., 10., GridItem::default()), cell(20., 10., GridItem::default())]);
+        close(xs(&rects), vec![100., 0.]);
+        close(ws(&rects), vec![50., 100.]);
+    }
+
+    #[test]
+    fn grid_children_move_what_they_drew_into_their_cells(){
+        let (mut cx, mut quad) = quad_cx();
+        let rects = quad_rects(&mut cx, &mut quad, |cx, quad|{
+            let inst = quad.begin_quad(cx, &Layout{
+                width:Bounds::Fix(300.),
+                height:Bounds::Fix(200.),
+                grid:Some(Grid{columns:vec![GridTrack::Fix(100.), GridTrack::Fix(100.)], rows:vec![GridTrack::Fix(50.)], ..Default::default()}),
+                ..Default::default()
+            });
+            quad.draw_quad_walk(cx, Bounds::Fix(10.), Bounds::Fix(10.), Margin::zero());
+            // a computed background with a quad centered in it
+            let bg = quad.begin_quad(cx, &Layout{
+                width:Bounds::Compute,
+                height:Bounds::Compute,
+                padding:Padding{l:5., t:5., r:5., b:5.},
+                align:Align::center(),
+                ..Default::default()
+            });
+            quad.draw_quad_walk(cx, Bounds::Fix(20.), Bounds::Fix(10.), Margin::zero());
+            quad.end_quad(cx, &bg);
+            quad.end_quad(cx, &inst);
+        });
+        // the background takes the cell, what is in it follows its align
+        assert_eq!(rects[1..].to_vec(), vec![rect(0., 0., 100., 50.), rect(100., 0., 100., 50.), rect(140., 20., 20., 10.)]);
+    }
+
+}
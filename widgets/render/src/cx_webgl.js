@@ -518,7 +518,8 @@
 				stencil: canvas.getAttribute("nostencil")?false:true,
 				antialias: canvas.getAttribute("antialias")?true:false,
 				premultipliedAlpha: canvas.getAttribute("premultipliedAlpha")?true:false,
-				preserveDrawingBuffer: canvas.getAttribute("preserveDrawingBuffer")?true:false,
+				// repaints only redraw the damaged parts, the rest has to stay
+				preserveDrawingBuffer: true,
 				preferLowPowerToHighPerformance: true
 			}
 	
@@ -1120,6 +1121,54 @@
 			gl.clear(gl.COLOR_BUFFER_BIT|gl.DEPTH_BUFFER_BIT);			
 		}
 
+		begin_render_target(texture_id, width, height){
+			var gl = this.gl;
+			var gl_tex = this.textures[texture_id] || gl.createTexture();
+			var rt = gl_tex.render_target;
+			if(!rt){
+				rt = gl_tex.render_target = {
+					framebuffer:gl.createFramebuffer(),
+					depthbuffer:gl.createRenderbuffer(),
+					width:0,
+					height:0
+				};
+			}
+			gl.bindFramebuffer(gl.FRAMEBUFFER, rt.framebuffer);
+			if(rt.width != width || rt.height != height){
+				rt.width = width;
+				rt.height = height;
+				gl.bindTexture(gl.TEXTURE_2D, gl_tex);
+				gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_MAG_FILTER, gl.LINEAR);
+				gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_MIN_FILTER, gl.LINEAR);
+				gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_WRAP_S, gl.CLAMP_TO_EDGE);
+				gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_WRAP_T, gl.CLAMP_TO_EDGE);
+				gl.texImage2D(gl.TEXTURE_2D, 0, gl.RGBA, width, height, 0, gl.RGBA, gl.UNSIGNED_BYTE, null);
+				gl.framebufferTexture2D(gl.FRAMEBUFFER, gl.COLOR_ATTACHMENT0, gl.TEXTURE_2D, gl_tex, 0);
+				gl.bindRenderbuffer(gl.RENDERBUFFER, rt.depthbuffer);
+				gl.renderbufferStorage(gl.RENDERBUFFER, gl.DEPTH_COMPONENT16, width, height);
+				gl.framebufferRenderbuffer(gl.FRAMEBUFFER, gl.DEPTH_ATTACHMENT, gl.RENDERBUFFER, rt.depthbuffer);
+			}
+			this.textures[texture_id] = gl_tex;
+			gl.viewport(0, 0, width, height);
+			this.clear(0, 0, 0, 0);
+		}
+
+		end_render_target(){
+			var gl = this.gl;
+			gl.bindFramebuffer(gl.FRAMEBUFFER, null);
+			gl.viewport(0, 0, this.canvas.width, this.canvas.height);
+		}
+
+		begin_scissor(x, y, w, h){
+			var gl = this.gl;
+			gl.enable(gl.SCISSOR_TEST);
+			gl.scissor(x, y, w, h);
+		}
+
+		end_scissor(){
+			this.gl.disable(this.gl.SCISSOR_TEST);
+		}
+
 		set_mouse_cursor(id){
 			document.body.style.cursor = this.cursor_map[id] || 'default'
 		}
@@ -1237,6 +1286,25 @@
 		},
 		function text_copy_response_16(self){
 			self.text_copy_response = self.parse_string();
+		},
+		function begin_render_target_17(self){
+			let texture_id = self.mu32[self.parse++];
+			let width = self.mu32[self.parse++];
+			let height = self.mu32[self.parse++];
+			self.begin_render_target(texture_id, width, height);
+		},
+		function end_render_target_18(self){
+			self.end_render_target();
+		},
+		function begin_scissor_19(self){
+			let x = self.mu32[self.parse++];
+			let y = self.mu32[self.parse++];
+			let w = self.mu32[self.parse++];
+			let h = self.mu32[self.parse++];
+			self.begin_scissor(x|0, y|0, w|0, h|0);
+		},
+		function end_scissor_20(self){
+			self.end_scissor();
 		}
 	]
 	
@@ -1307,7 +1375,11 @@
 			if(!wasmfile) continue
 			fetch(wasmfile)
 				.then(response => response.arrayBuffer())
-				.then(bytes => WebAssembly.instantiate(bytes, {}))
+				.then(bytes => WebAssembly.instantiate(bytes, {
+					env:{
+						performance_now:()=>performance.now()
+					}
+				}))
 				.then(results => {
 					wasm_instances.push(
 						new WasmApp(canvas, results)
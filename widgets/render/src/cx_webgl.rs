@@ -5,62 +5,128 @@ use crate::cx::*;
 use std::alloc;
 
 impl Cx{
-     pub fn exec_draw_list(&mut self, draw_list_id: usize){
-        // tad ugly otherwise the borrow checker locks 'self' and we can't recur
-        let draw_calls_len = self.draw_lists[draw_list_id].draw_calls_len;
+    // issues the draw batches planned for a list by plan_draw_batches, the merged instances of a
+    // batch stay in it until the js side has read them. With a damage rect only the batches
+    // that can touch it are drawn
+    pub fn exec_draw_list(&mut self, draw_list_id: usize, damage:Option<Rect>){
+        let mut batches = mem::replace(&mut self.draw_lists[draw_list_id].batches, Vec::new());
+        for batch in &mut batches{
+            if let (Some(damage), Some(bounds)) = (damage, batch.bounds){
+                if !damage.intersects(bounds){
+                    continue;
+                }
+            }
+            let (head_list_id, head_call_id) = batch.members[0];
+            let merge = batch.members.len() > 1 && batch.instance.len() == 0;
+            if merge{
+                for (list_id, call_id) in &batch.members{
+                    batch.instance.extend_from_slice(&self.draw_lists[*list_id].draw_calls[*call_id].instance);
+                }
+            }
+            self.batch_draw_list_uniforms(batch);
+            let draw_list = &mut self.draw_lists[head_list_id];
 
-        for draw_call_id in 0..draw_calls_len{
+            let draw_call = &mut draw_list.draw_calls[head_call_id];
+            let csh = &self.compiled_shaders[draw_call.shader_id];
 
-            let sub_list_id = self.draw_lists[draw_list_id].draw_calls[draw_call_id].sub_list_id;
-            if sub_list_id != 0{
-                self.exec_draw_list(sub_list_id);
+            let instance = if batch.members.len() > 1{
+                draw_call.batched = true;
+                if merge{Some(&batch.instance)}else{None}
+            }
+            else if draw_call.instance_dirty || draw_call.batched{
+                draw_call.batched = false;
+                Some(&draw_call.instance)
             }
             else{
-                let draw_list = &mut self.draw_lists[draw_list_id];
-
-                draw_list.set_clipping_uniforms();
-
-                let draw_call = &mut draw_list.draw_calls[draw_call_id];
-                let csh = &self.compiled_shaders[draw_call.shader_id];
-
-                if draw_call.instance_dirty{
-                    draw_call.instance_dirty = false;
-                    // update the instance buffer data
-                    draw_call.platform.check_attached_vao(csh, &mut self.platform);
-
-                    self.platform.from_wasm.alloc_array_buffer(
-                        draw_call.platform.inst_vb_id,
-                        draw_call.instance.len(),
-                        draw_call.instance.as_ptr() as *const f32
-                    );
-                }
+                None
+            };
+            if let Some(instance) = instance{
+                self.profiler.count_upload(instance.len() * mem::size_of::<f32>());
+                draw_call.instance_dirty = false;
+                // update the instance buffer data
+                draw_call.platform.check_attached_vao(csh, &mut self.platform);
+
+                self.platform.from_wasm.alloc_array_buffer(
+                    draw_call.platform.inst_vb_id,
+                    instance.len(),
+                    instance.as_ptr() as *const f32
+                );
+            }
 
-                // update/alloc textures?
-                for tex_id in &draw_call.textures_2d{
-                    let tex = &mut self.textures_2d[*tex_id as usize];
-                    if tex.dirty{
-                        tex.upload_to_device(&mut self.platform);
-                    }
+            // update/alloc textures?
+            for tex_id in &draw_call.textures_2d{
+                let tex = &mut self.textures_2d[*tex_id as usize];
+                if tex.dirty && !tex.render_target{
+                    self.profiler.count_upload(tex.width * tex.height * 4);
+                    tex.upload_to_device(&mut self.platform);
                 }
-                self.platform.from_wasm.draw_call(
-                    draw_call.shader_id,
-                    draw_call.platform.vao_id,
-                    &self.uniforms,
-                    self.redraw_id as usize, // update once a frame
-                    &draw_list.uniforms,
-                    draw_list_id, // update on drawlist change
-                    &draw_call.uniforms,
-                    draw_call.draw_call_id, // update on drawcall id change
-                    &draw_call.textures_2d
-                );
             }
+            let instances = if batch.members.len() > 1{batch.instance.len()}else{draw_call.instance.len()} / csh.instance_slots.max(1);
+            self.profiler.count_draw_call(instances);
+            self.platform.from_wasm.draw_call(
+                draw_call.shader_id,
+                draw_call.platform.vao_id,
+                &self.uniforms,
+                self.redraw_id as usize, // update once a frame
+                &batch.uniforms,
+                head_list_id, // update on drawlist change
+                &draw_call.uniforms,
+                draw_call.draw_call_id, // update on drawcall id change
+                &draw_call.textures_2d
+            );
         }
+        self.draw_lists[draw_list_id].batches = batches;
     }
 
     pub fn repaint(&mut self){
-        self.platform.from_wasm.clear(self.clear_color.r, self.clear_color.g, self.clear_color.b, self.clear_color.a);
-        self.prepare_frame();        
-        self.exec_draw_list(0);
+        self.profile_begin("repaint");
+        self.prepare_frame();
+        // nothing changed on screen, the canvas keeps the last frame
+        if self.damage.len() == 0{
+            self.profile_end();
+            return
+        }
+        self.profile_begin("render targets");
+        self.exec_render_targets();
+        self.profile_end();
+        let dpi_factor = if self.target_dpi_factor > 0.0{self.target_dpi_factor}else{1.0};
+        let height = (self.target_size.y * dpi_factor) as i32;
+        let damage = self.damage.clone();
+        for rect in damage{
+            // whole pixels around the rect, gl counts y from the bottom
+            let x1 = (rect.x * dpi_factor).floor() as i32;
+            let y1 = (rect.y * dpi_factor).floor() as i32;
+            let x2 = ((rect.x + rect.w) * dpi_factor).ceil() as i32;
+            let y2 = ((rect.y + rect.h) * dpi_factor).ceil() as i32;
+            self.platform.from_wasm.begin_scissor(x1, height - y2, x2 - x1, y2 - y1);
+            self.platform.from_wasm.clear(self.clear_color.r, self.clear_color.g, self.clear_color.b, self.clear_color.a);
+            self.profile_begin("exec draw list");
+            self.exec_draw_list(0, Some(rect));
+            self.profile_end();
+        }
+        self.platform.from_wasm.end_scissor();
+        self.profile_end();
+    }
+
+    pub fn exec_render_targets(&mut self){
+        let mut targets = Vec::new();
+        self.collect_render_targets(0, &mut targets);
+        let mut rendered = Vec::new();
+        for draw_list_id in targets{
+            if !self.render_target_is_stale(draw_list_id, &rendered){
+                continue
+            }
+            rendered.push(draw_list_id);
+            let texture_id = self.prepare_render_target(draw_list_id);
+            let tex = &mut self.textures_2d[texture_id];
+            tex.dirty = false;
+            self.platform.from_wasm.begin_render_target(texture_id, tex.width, tex.height);
+            // the js side reads uniforms after we return, so the target gets its own buffer
+            mem::swap(&mut self.uniforms, &mut self.draw_lists[draw_list_id].target_uniforms);
+            self.exec_draw_list(draw_list_id, None);
+            mem::swap(&mut self.uniforms, &mut self.draw_lists[draw_list_id].target_uniforms);
+            self.platform.from_wasm.end_render_target();
+        }
     }
 
     // incoming to_wasm. There is absolutely no other entrypoint
@@ -334,6 +400,7 @@ impl Cx{
             self.repaint_id += 1;
             self.repaint();
         }
+        self.profile_end_frame();
 
         // free the received message
         to_wasm.dealloc();
@@ -361,6 +428,17 @@ impl Cx{
         self.platform.from_wasm.log(val)
     }
 
+    // the clock the profiler reads, performance.now() on the js side
+    pub fn profile_time_ms()->f64{
+        unsafe{performance_now()}
+    }
+
+    // the browser cant write files, so the trace goes to the console to copy from
+    pub fn save_chrome_trace(&mut self, _path:&str){
+        let trace = self.profiler_chrome_trace();
+        self.platform.from_wasm.log(&trace);
+    }
+
     pub fn read_file(&mut self, path:&str)->u64{
         let id = self.platform.file_read_id;
         self.platform.from_wasm.read_file(id as u32, path);
@@ -377,6 +455,7 @@ impl Cx{
     }
 
     pub fn compile_all_webgl_shaders(&mut self){
+        self.profile_begin("shader compile");
         for sh in &self.shaders{
             let csh = Self::compile_webgl_shader(self.compiled_shaders.len(), &sh, &mut self.platform);
             if let Ok(csh) = csh{
@@ -392,6 +471,7 @@ impl Cx{
                 )
             }
         };
+        self.profile_end();
     }
 
     pub fn compile_webgl_shader(shader_id:usize, sh:&Shader, platform:&mut CxPlatform)->Result<CompiledShader, SlErr>{
@@ -723,6 +803,7 @@ impl DrawCallPlatform{
 pub struct Texture2D{
     pub texture_id: usize,
     pub dirty:bool,
+    pub render_target:bool,
     pub image: Vec<u32>,
     pub width: usize,
     pub height:usize
@@ -740,6 +821,10 @@ impl Texture2D{
         platform.from_wasm.alloc_texture(self.texture_id, self.width, self.height, &self.image);
         self.dirty = false;
     }
+
+    // the js side keeps its texture under our id and replaces it when the id gets allocated again
+    pub fn free_device(&mut self){
+    }
 }
 
 
@@ -1037,6 +1122,33 @@ impl FromWasm{
         self.add_string(response);
     }
 
+    pub fn begin_render_target(&mut self, texture_id:usize, width:usize, height:usize){
+        self.fit(4);
+        self.mu32(17);
+        self.mu32(texture_id as u32);
+        self.mu32(width as u32);
+        self.mu32(height as u32);
+    }
+
+    pub fn end_render_target(&mut self){
+        self.fit(1);
+        self.mu32(18);
+    }
+
+    pub fn begin_scissor(&mut self, x:i32, y:i32, w:i32, h:i32){
+        self.fit(5);
+        self.mu32(19);
+        self.mu32(x as u32);
+        self.mu32(y as u32);
+        self.mu32(w as u32);
+        self.mu32(h as u32);
+    }
+
+    pub fn end_scissor(&mut self){
+        self.fit(1);
+        self.mu32(20);
+    }
+
     fn add_string(&mut self, msg:&str){
         let len = msg.chars().count();
         self.fit(len + 1);
@@ -1121,6 +1233,11 @@ impl ToWasm{
     }
 }
 
+// provided by the js side when it instantiates us
+extern "C"{
+    fn performance_now()->f64;
+}
+
 // for use with sending wasm vec data
 #[export_name = "alloc_wasm_vec"]
 pub unsafe extern "C" fn alloc_wasm_vec(bytes:u32)->u32{
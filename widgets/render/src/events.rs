@@ -156,6 +156,12 @@ pub struct TextCopyEvent{
     pub response:Option<String>
 }
 
+// a named command run from somewhere else than the keys, it goes to the key focus
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommandEvent{
+    pub command:String
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum Event{
     None,
@@ -179,7 +185,8 @@ pub enum Event{
     KeyDown(KeyEvent),
     KeyUp(KeyEvent),
     TextInput(TextInputEvent),
-    TextCopy(TextCopyEvent)
+    TextCopy(TextCopyEvent),
+    Command(CommandEvent)
 }
 
 impl Default for Event{
@@ -272,6 +279,11 @@ impl Event{
                     );
                 }
             },
+            Event::Command(_)=>{
+                if area == cx.key_focus{
+                    return self.clone();
+                }
+            },
             Event::Animate(_)=>{
                 for anim in &cx.playing_anim_areas{
                     if anim.area == area{
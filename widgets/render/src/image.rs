@@ -0,0 +1,131 @@
+use crate::cx::*;
+use crate::quad::*;
+
+#[derive(Clone, Copy)]
+pub enum ImageFit{
+    Stretch,
+    Contain, // fit the whole image, letterboxed
+    Cover, // fill the rect, cropping the image
+    NineSlice(Padding) // borders in image pixels keep their size, the middle stretches
+}
+
+#[derive(Clone)]
+pub struct Image{
+    pub shader_id:usize,
+    pub do_scroll:bool,
+    pub fit:ImageFit,
+    pub color:Color
+}
+
+impl Style for Image{
+    fn style(cx:&mut Cx)->Self{
+        let sh = Self::def_image_shader(cx);
+        Self{
+            shader_id:cx.add_shader(sh, "Image"),
+            do_scroll:true,
+            fit:ImageFit::Contain,
+            color:color("white")
+        }
+    }
+}
+
+impl Image{
+    pub fn def_image_shader(cx:&mut Cx)->Shader{
+        let mut sh = Quad::def_quad_shader(cx);
+        sh.add_ast(shader_ast!({
+            let texture:texture2d<Texture>;
+            let uv:vec4<Instance>;
+
+            fn pixel()->vec4{
+                let s:vec4 = sample2d(texture, uv.xy + (uv.zw - uv.xy) * pos) * color;
+                return vec4(s.rgb*s.a, s.a);
+            }
+        }));
+        sh
+    }
+
+    // Compute bounds take the size of the image
+    pub fn draw_image_walk(&mut self, cx:&mut Cx, image_id:usize, w:Bounds, h:Bounds, margin:Margin)->Area{
+        let image = cx.images[image_id].clone();
+        let w = if let Bounds::Compute = w{Bounds::Fix(image.width as f32)}else{w};
+        let h = if let Bounds::Compute = h{Bounds::Fix(image.height as f32)}else{h};
+        if !image.loaded{
+            cx.walk_turtle(w, h, margin, None);
+            return Area::Empty
+        }
+        let inst = cx.new_aligned_texture_instance(self.shader_id, image.texture_id, self.instance_count()).inst;
+        if inst.need_uniforms_now(cx){
+            inst.push_uniform_float(cx, if self.do_scroll{1.0}else{0.0});
+            inst.push_uniform_texture_2d(cx, image.texture_id);
+        }
+        let geom = cx.walk_turtle(w, h, margin, None);
+        self.push_image_instances(cx, &inst, &image, geom);
+        inst.into_area()
+    }
+
+    pub fn draw_image(&mut self, cx:&mut Cx, image_id:usize, rect:Rect)->Area{
+        let image = cx.images[image_id].clone();
+        if !image.loaded{
+            return Area::Empty
+        }
+        let inst = cx.new_aligned_texture_instance(self.shader_id, image.texture_id, self.instance_count()).inst;
+        if inst.need_uniforms_now(cx){
+            inst.push_uniform_float(cx, if self.do_scroll{1.0}else{0.0});
+            inst.push_uniform_texture_2d(cx, image.texture_id);
+        }
+        let pos = cx.turtle_origin();
+        self.push_image_instances(cx, &inst, &image, Rect{x:pos.x+rect.x, y:pos.y+rect.y, w:rect.w, h:rect.h});
+        inst.into_area()
+    }
+
+    fn instance_count(&self)->usize{
+        if let ImageFit::NineSlice(_) = self.fit{9}else{1}
+    }
+
+    fn push_image_instances(&self, cx:&mut Cx, inst:&InstanceArea, image:&CxImage, rect:Rect){
+        let iw = image.width as f32;
+        let ih = image.height as f32;
+        match self.fit{
+            ImageFit::Stretch=>{
+                self.push_quad(cx, inst, rect, [0.0,0.0,1.0,1.0]);
+            },
+            ImageFit::Contain=>{
+                let scale = (rect.w / iw).min(rect.h / ih);
+                let (w, h) = (iw * scale, ih * scale);
+                self.push_quad(cx, inst, Rect{x:rect.x + 0.5 * (rect.w - w), y:rect.y + 0.5 * (rect.h - h), w:w, h:h}, [0.0,0.0,1.0,1.0]);
+            },
+            ImageFit::Cover=>{
+                let scale = (rect.w / iw).max(rect.h / ih);
+                let u = 0.5 * rect.w / (iw * scale);
+                let v = 0.5 * rect.h / (ih * scale);
+                self.push_quad(cx, inst, rect, [0.5 - u, 0.5 - v, 0.5 + u, 0.5 + v]);
+            },
+            ImageFit::NineSlice(border)=>{
+                // shrink the borders if the rect is smaller than they are
+                let sx = (rect.w / (border.l + border.r)).min(1.0);
+                let sy = (rect.h / (border.t + border.b)).min(1.0);
+                let xs = [rect.x, rect.x + border.l * sx, rect.x + rect.w - border.r * sx, rect.x + rect.w];
+                let ys = [rect.y, rect.y + border.t * sy, rect.y + rect.h - border.b * sy, rect.y + rect.h];
+                let us = [0.0, border.l / iw, 1.0 - border.r / iw, 1.0];
+                let vs = [0.0, border.t / ih, 1.0 - border.b / ih, 1.0];
+                for y in 0..3{
+                    for x in 0..3{
+                        self.push_quad(cx, inst,
+                            Rect{x:xs[x], y:ys[y], w:xs[x+1] - xs[x], h:ys[y+1] - ys[y]},
+                            [us[x], vs[y], us[x+1], vs[y+1]]
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn push_quad(&self, cx:&mut Cx, inst:&InstanceArea, rect:Rect, uv:[f32;4]){
+        let data = [
+            /*x,y,w,h*/rect.x,rect.y,rect.w,rect.h,
+            /*color*/self.color.r,self.color.g,self.color.b,self.color.a,
+            /*uv*/uv[0],uv[1],uv[2],uv[3]
+        ];
+        inst.push_slice(cx, &data);
+    }
+}
@@ -20,12 +20,24 @@ mod cx_glsl;
 #[cfg(any(feature = "ogl", feature="mtl"))]
 mod cx_desktop; 
 
+#[cfg(any(feature = "ogl", feature="mtl"))]
+mod cx_shader_cache; 
+
 // shared modules
 mod cx; 
 mod cx_turtle;
 mod cx_fonts;
+mod ttf;
+mod bidi;
+mod linebreak;
+mod cx_images;
+mod cx_atlas;
 mod cx_cursor;
 mod cx_drawlist; 
+mod cx_batch;
+mod cx_damage;
+mod cx_profile;
+mod cx_keymap;
 mod animator;
 mod elements;
 mod math;
@@ -34,11 +46,14 @@ mod shader;
 mod area;
 mod view;
 mod shadergen;
+mod shaderlib;
 mod quad;
+mod image;
 mod text;
 mod events;
 
 pub use crate::cx::*;
 pub use crate::quad::*;
+pub use crate::image::*;
 pub use crate::text::*;
 pub use crate::elements::*;
@@ -0,0 +1,454 @@
+// Unicode line breaking (UAX #14): finds where text may or must wrap. The class
+// table is derived from general categories plus the explicitly listed classes,
+// south east asian scripts break like letters as there is no dictionary.
+
+use self::LineBreakClass::*;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LineBreakClass{
+    BK, CR, LF, NL, SP, ZW, ZWJ, CM, WJ, GL, BA, BB, B2, HY, CB, CL, CP, EX, IN, NS,
+    OP, QU, IS, NU, PO, PR, SY, AL, HL, ID, EB, EM, H2, H3, JL, JV, JT, RI
+}
+
+// the break opportunity after a char
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LineBreak{
+    None,
+    Allowed,
+    Mandatory
+}
+
+// non AL ranges above ascii
+const LINE_BREAK_RANGES:[(u32, u32, LineBreakClass);802] = [
+    (0x80, 0x84, CM), (0x85, 0x85, NL), (0x86, 0x9f, CM), (0xa0, 0xa0, GL), (0xa1, 0xa1, OP),
+    (0xa2, 0xa2, PO), (0xa3, 0xa5, PR), (0xab, 0xab, QU), (0xad, 0xad, BA), (0xb0, 0xb0, PO),
+    (0xb1, 0xb1, PR), (0xb4, 0xb4, BB), (0xbb, 0xbb, QU), (0xbf, 0xbf, OP), (0x2c8, 0x2c8, BB),
+    (0x2cc, 0x2cc, BB), (0x2df, 0x2df, BB), (0x300, 0x34e, CM), (0x34f, 0x34f, GL), (0x350, 0x35b, CM),
+    (0x35c, 0x362, GL), (0x363, 0x36f, CM), (0x37e, 0x37e, IS), (0x483, 0x489, CM), (0x589, 0x589, IS),
+    (0x58a, 0x58c, BA), (0x58f, 0x590, PR), (0x591, 0x5bd, CM), (0x5be, 0x5be, BA), (0x5bf, 0x5bf, CM),
+    (0x5c1, 0x5c2, CM), (0x5c4, 0x5c5, CM), (0x5c6, 0x5c6, EX), (0x5c7, 0x5cf, CM), (0x5d0, 0x5f2, HL),
+    (0x609, 0x60a, PO), (0x60b, 0x60b, PR), (0x60c, 0x60d, IS), (0x610, 0x61a, CM), (0x61b, 0x61b, EX),
+    (0x61e, 0x61f, EX), (0x64b, 0x65f, CM), (0x660, 0x669, NU), (0x66a, 0x66a, PO), (0x670, 0x670, CM),
+    (0x6d4, 0x6d4, EX), (0x6d6, 0x6dc, CM), (0x6df, 0x6e4, CM), (0x6e7, 0x6e8, CM), (0x6ea, 0x6ed, CM),
+    (0x6f0, 0x6f9, NU), (0x711, 0x711, CM), (0x730, 0x74c, CM), (0x7a6, 0x7b0, CM), (0x7c0, 0x7c9, NU),
+    (0x7eb, 0x7f3, CM), (0x7f8, 0x7f8, IS), (0x7f9, 0x7f9, EX), (0x7fd, 0x7fd, CM), (0x7fe, 0x7ff, PR),
+    (0x816, 0x819, CM), (0x81b, 0x823, CM), (0x825, 0x827, CM), (0x829, 0x82f, CM), (0x859, 0x85d, CM),
+    (0x898, 0x89f, CM), (0x8ca, 0x8e1, CM), (0x8e3, 0x903, CM), (0x93a, 0x93c, CM), (0x93e, 0x94f, CM),
+    (0x951, 0x957, CM), (0x962, 0x963, CM), (0x966, 0x96f, NU), (0x981, 0x984, CM), (0x9bc, 0x9bc, CM),
+    (0x9be, 0x9cd, CM), (0x9d7, 0x9db, CM), (0x9e2, 0x9e5, CM), (0x9e6, 0x9ef, NU), (0x9f2, 0x9f3, PR),
+    (0x9fb, 0x9fb, PR), (0x9fe, 0xa04, CM), (0xa3c, 0xa58, CM), (0xa66, 0xa6f, NU), (0xa70, 0xa71, CM),
+    (0xa75, 0xa75, CM), (0xa81, 0xa84, CM), (0xabc, 0xabc, CM), (0xabe, 0xacf, CM), (0xae2, 0xae5, CM),
+    (0xae6, 0xaef, NU), (0xaf1, 0xaf8, PR), (0xafa, 0xb04, CM), (0xb3c, 0xb3c, CM), (0xb3e, 0xb5b, CM),
+    (0xb62, 0xb65, CM), (0xb66, 0xb6f, NU), (0xb82, 0xb82, CM), (0xbbe, 0xbcf, CM), (0xbd7, 0xbe5, CM),
+    (0xbe6, 0xbef, NU), (0xbf9, 0xbf9, PR), (0xc00, 0xc04, CM), (0xc3c, 0xc3c, CM), (0xc3e, 0xc57, CM),
+    (0xc62, 0xc65, CM), (0xc66, 0xc76, NU), (0xc81, 0xc83, CM), (0xcbc, 0xcbc, CM), (0xcbe, 0xcdc, CM),
+    (0xce2, 0xce5, CM), (0xce6, 0xcf0, NU), (0xd00, 0xd03, CM), (0xd3b, 0xd3c, CM), (0xd3e, 0xd4d, CM),
+    (0xd57, 0xd57, CM), (0xd62, 0xd65, CM), (0xd66, 0xd6f, NU), (0xd81, 0xd84, CM), (0xdca, 0xde5, CM),
+    (0xde6, 0xdf1, NU), (0xdf2, 0xdf3, CM), (0xe31, 0xe31, CM), (0xe34, 0xe3e, CM), (0xe47, 0xe4e, CM),
+    (0xeb1, 0xeb1, CM), (0xeb4, 0xebc, CM), (0xec8, 0xecf, CM), (0xf01, 0xf04, BB), (0xf06, 0xf07, BB),
+    (0xf08, 0xf08, GL), (0xf09, 0xf0a, BB), (0xf0b, 0xf0b, BA), (0xf0c, 0xf0c, GL), (0xf0d, 0xf11, EX),
+    (0xf12, 0xf12, GL), (0xf14, 0xf14, EX), (0xf18, 0xf19, CM), (0xf20, 0xf29, NU), (0xf35, 0xf35, CM),
+    (0xf37, 0xf37, CM), (0xf39, 0xf39, CM), (0xf3a, 0xf3a, OP), (0xf3b, 0xf3b, CL), (0xf3c, 0xf3c, OP),
+    (0xf3d, 0xf3d, CL), (0xf3e, 0xf3f, CM), (0xf71, 0xf84, CM), (0xf86, 0xf87, CM), (0xf8d, 0xfbd, CM),
+    (0xfc6, 0xfc6, CM), (0xfd0, 0xfd1, BB), (0xfd3, 0xfd3, BB), (0x102b, 0x103e, CM), (0x1056, 0x1059, CM),
+    (0x105e, 0x1060, CM), (0x1062, 0x1064, CM), (0x1067, 0x106d, CM), (0x1071, 0x1074, CM),
+    (0x1082, 0x108d, CM), (0x108f, 0x108f, CM), (0x109a, 0x109d, CM), (0x1100, 0x115f, JL),
+    (0x1160, 0x11a7, JV), (0x11a8, 0x11ff, JT), (0x135d, 0x135f, CM), (0x1361, 0x1361, BA),
+    (0x1680, 0x1680, BA), (0x169b, 0x169b, OP), (0x169c, 0x169f, CL), (0x1712, 0x171e, CM),
+    (0x1732, 0x1734, CM), (0x1752, 0x175f, CM), (0x1772, 0x177f, CM), (0x17b4, 0x17d3, CM),
+    (0x17d6, 0x17d6, NS), (0x17d8, 0x17d8, BA), (0x17da, 0x17da, BA), (0x17dd, 0x17df, CM),
+    (0x1802, 0x1803, EX), (0x1806, 0x1806, BB), (0x1808, 0x1809, EX), (0x180b, 0x180d, CM),
+    (0x180e, 0x180e, GL), (0x180f, 0x180f, CM), (0x1810, 0x181f, NU), (0x1885, 0x1886, CM),
+    (0x18a9, 0x18a9, CM), (0x1920, 0x193f, CM), (0x1944, 0x1945, EX), (0x1946, 0x194f, NU),
+    (0x1a17, 0x1a1d, CM), (0x1a55, 0x1a7f, CM), (0x1ab0, 0x1b04, CM), (0x1b34, 0x1b44, CM),
+    (0x1b50, 0x1b59, NU), (0x1b6b, 0x1b73, CM), (0x1b80, 0x1b82, CM), (0x1ba1, 0x1bad, CM),
+    (0x1bb0, 0x1bb9, NU), (0x1be6, 0x1bfb, CM), (0x1c24, 0x1c3a, CM), (0x1c40, 0x1c4c, NU),
+    (0x1c50, 0x1c59, NU), (0x1cd0, 0x1cd2, CM), (0x1cd4, 0x1ce8, CM), (0x1ced, 0x1ced, CM),
+    (0x1cf4, 0x1cf4, CM), (0x1cf7, 0x1cf9, CM), (0x1dc0, 0x1dff, CM), (0x1ffd, 0x1ffd, BB),
+    (0x2000, 0x2006, BA), (0x2007, 0x2007, GL), (0x2008, 0x200a, BA), (0x200b, 0x200b, ZW),
+    (0x200d, 0x200d, ZWJ), (0x2010, 0x2010, BA), (0x2011, 0x2011, GL), (0x2012, 0x2013, BA),
+    (0x2014, 0x2014, B2), (0x2018, 0x2019, QU), (0x201a, 0x201a, OP), (0x201b, 0x201d, QU),
+    (0x201e, 0x201e, OP), (0x201f, 0x201f, QU), (0x2024, 0x2026, IN), (0x2027, 0x2027, BA),
+    (0x2028, 0x2029, BK), (0x202f, 0x202f, GL), (0x2030, 0x2037, PO), (0x2039, 0x203a, QU),
+    (0x203c, 0x203d, NS), (0x2044, 0x2044, IS), (0x2045, 0x2045, OP), (0x2046, 0x2046, CL),
+    (0x2047, 0x2049, NS), (0x205f, 0x205f, BA), (0x2060, 0x2060, WJ), (0x207d, 0x207d, OP),
+    (0x207e, 0x207e, CL), (0x208d, 0x208d, OP), (0x208e, 0x208f, CL), (0x20a0, 0x20a6, PR),
+    (0x20a7, 0x20a7, PO), (0x20a8, 0x20b5, PR), (0x20b6, 0x20b6, PO), (0x20b7, 0x20ba, PR),
+    (0x20bb, 0x20bb, PO), (0x20bc, 0x20bd, PR), (0x20be, 0x20be, PO), (0x20bf, 0x20cf, PR),
+    (0x20d0, 0x20ff, CM), (0x2103, 0x2103, PO), (0x2109, 0x2109, PO), (0x2116, 0x2116, PR),
+    (0x2212, 0x2213, PR), (0x2308, 0x2308, OP), (0x2309, 0x2309, CL), (0x230a, 0x230a, OP),
+    (0x230b, 0x230b, CL), (0x231a, 0x231b, ID), (0x2329, 0x2329, OP), (0x232a, 0x232a, CL),
+    (0x23e9, 0x23ec, ID), (0x23f0, 0x23f0, ID), (0x23f3, 0x23f3, ID), (0x25fd, 0x25fe, ID),
+    (0x2614, 0x2615, ID), (0x261d, 0x261d, EB), (0x2648, 0x2653, ID), (0x267f, 0x267f, ID),
+    (0x2693, 0x2693, ID), (0x26a1, 0x26a1, ID), (0x26aa, 0x26ab, ID), (0x26bd, 0x26be, ID),
+    (0x26c4, 0x26c5, ID), (0x26ce, 0x26ce, ID), (0x26d4, 0x26d4, ID), (0x26ea, 0x26ea, ID),
+    (0x26f2, 0x26f3, ID), (0x26f5, 0x26f5, ID), (0x26f9, 0x26f9, EB), (0x26fa, 0x26fa, ID),
+    (0x26fd, 0x26fd, ID), (0x2705, 0x2705, ID), (0x270a, 0x270d, EB), (0x2728, 0x2728, ID),
+    (0x274c, 0x274c, ID), (0x274e, 0x274e, ID), (0x2753, 0x2755, ID), (0x2757, 0x2757, ID),
+    (0x275b, 0x275e, QU), (0x2762, 0x2763, EX), (0x2768, 0x2768, OP), (0x2769, 0x2769, CL),
+    (0x276a, 0x276a, OP), (0x276b, 0x276b, CL), (0x276c, 0x276c, OP), (0x276d, 0x276d, CL),
+    (0x276e, 0x276e, OP), (0x276f, 0x276f, CL), (0x2770, 0x2770, OP), (0x2771, 0x2771, CL),
+    (0x2772, 0x2772, OP), (0x2773, 0x2773, CL), (0x2774, 0x2774, OP), (0x2775, 0x2775, CL),
+    (0x2795, 0x2797, ID), (0x27b0, 0x27b0, ID), (0x27bf, 0x27bf, ID), (0x27c5, 0x27c5, OP),
+    (0x27c6, 0x27c6, CL), (0x27e6, 0x27e6, OP), (0x27e7, 0x27e7, CL), (0x27e8, 0x27e8, OP),
+    (0x27e9, 0x27e9, CL), (0x27ea, 0x27ea, OP), (0x27eb, 0x27eb, CL), (0x27ec, 0x27ec, OP),
+    (0x27ed, 0x27ed, CL), (0x27ee, 0x27ee, OP), (0x27ef, 0x27ef, CL), (0x2983, 0x2983, OP),
+    (0x2984, 0x2984, CL), (0x2985, 0x2985, OP), (0x2986, 0x2986, CL), (0x2987, 0x2987, OP),
+    (0x2988, 0x2988, CL), (0x2989, 0x2989, OP), (0x298a, 0x298a, CL), (0x298b, 0x298b, OP),
+    (0x298c, 0x298c, CL), (0x298d, 0x298d, OP), (0x298e, 0x298e, CL), (0x298f, 0x298f, OP),
+    (0x2990, 0x2990, CL), (0x2991, 0x2991, OP), (0x2992, 0x2992, CL), (0x2993, 0x2993, OP),
+    (0x2994, 0x2994, CL), (0x2995, 0x2995, OP), (0x2996, 0x2996, CL), (0x2997, 0x2997, OP),
+    (0x2998, 0x2998, CL), (0x29d8, 0x29d8, OP), (0x29d9, 0x29d9, CL), (0x29da, 0x29da, OP),
+    (0x29db, 0x29db, CL), (0x29fc, 0x29fc, OP), (0x29fd, 0x29fd, CL), (0x2b1b, 0x2b1c, ID),
+    (0x2b50, 0x2b50, ID), (0x2b55, 0x2b55, ID), (0x2cef, 0x2cf1, CM), (0x2cf9, 0x2cf9, EX),
+    (0x2cfe, 0x2cfe, EX), (0x2d7f, 0x2d7f, CM), (0x2de0, 0x2dff, CM), (0x2e00, 0x2e0d, QU),
+    (0x2e0e, 0x2e15, BA), (0x2e17, 0x2e17, BA), (0x2e18, 0x2e18, OP), (0x2e19, 0x2e19, BA),
+    (0x2e1c, 0x2e1d, QU), (0x2e20, 0x2e21, QU), (0x2e22, 0x2e22, OP), (0x2e23, 0x2e23, CL),
+    (0x2e24, 0x2e24, OP), (0x2e25, 0x2e25, CL), (0x2e26, 0x2e26, OP), (0x2e27, 0x2e27, CL),
+    (0x2e28, 0x2e28, OP), (0x2e29, 0x2e29, CL), (0x2e2a, 0x2e2d, BA), (0x2e2e, 0x2e2e, EX),
+    (0x2e30, 0x2e31, BA), (0x2e3a, 0x2e3b, B2), (0x2e42, 0x2e42, OP), (0x2e55, 0x2e55, OP),
+    (0x2e56, 0x2e56, CL), (0x2e57, 0x2e57, OP), (0x2e58, 0x2e58, CL), (0x2e59, 0x2e59, OP),
+    (0x2e5a, 0x2e5a, CL), (0x2e5b, 0x2e5b, OP), (0x2e5c, 0x2e5c, CL), (0x2e80, 0x2fff, ID),
+    (0x3000, 0x3000, BA), (0x3001, 0x3002, CL), (0x3003, 0x3004, ID), (0x3005, 0x3005, NS),
+    (0x3006, 0x3007, ID), (0x3008, 0x3008, OP), (0x3009, 0x3009, CL), (0x300a, 0x300a, OP),
+    (0x300b, 0x300b, CL), (0x300c, 0x300c, OP), (0x300d, 0x300d, CL), (0x300e, 0x300e, OP),
+    (0x300f, 0x300f, CL), (0x3010, 0x3010, OP), (0x3011, 0x3011, CL), (0x3012, 0x3013, ID),
+    (0x3014, 0x3014, OP), (0x3015, 0x3015, CL), (0x3016, 0x3016, OP), (0x3017, 0x3017, CL),
+    (0x3018, 0x3018, OP), (0x3019, 0x3019, CL), (0x301a, 0x301a, OP), (0x301b, 0x301b, CL),
+    (0x301c, 0x301c, NS), (0x301d, 0x301d, OP), (0x301e, 0x301f, CL), (0x3020, 0x3029, ID),
+    (0x302a, 0x302f, CM), (0x3030, 0x303a, ID), (0x303b, 0x303c, NS), (0x303d, 0x303e, ID),
+    (0x3040, 0x3040, ID), (0x3041, 0x3041, NS), (0x3042, 0x3042, ID), (0x3043, 0x3043, NS),
+    (0x3044, 0x3044, ID), (0x3045, 0x3045, NS), (0x3046, 0x3046, ID), (0x3047, 0x3047, NS),
+    (0x3048, 0x3048, ID), (0x3049, 0x3049, NS), (0x304a, 0x3062, ID), (0x3063, 0x3063, NS),
+    (0x3064, 0x3082, ID), (0x3083, 0x3083, NS), (0x3084, 0x3084, ID), (0x3085, 0x3085, NS),
+    (0x3086, 0x3086, ID), (0x3087, 0x3087, NS), (0x3088, 0x308d, ID), (0x308e, 0x308e, NS),
+    (0x308f, 0x3094, ID), (0x3095, 0x3096, NS), (0x3097, 0x3098, ID), (0x3099, 0x309a, CM),
+    (0x309b, 0x309e, NS), (0x309f, 0x309f, ID), (0x30a0, 0x30a1, NS), (0x30a2, 0x30a2, ID),
+    (0x30a3, 0x30a3, NS), (0x30a4, 0x30a4, ID), (0x30a5, 0x30a5, NS), (0x30a6, 0x30a6, ID),
+    (0x30a7, 0x30a7, NS), (0x30a8, 0x30a8, ID), (0x30a9, 0x30a9, NS), (0x30aa, 0x30c2, ID),
+    (0x30c3, 0x30c3, NS), (0x30c4, 0x30e2, ID), (0x30e3, 0x30e3, NS), (0x30e4, 0x30e4, ID),
+    (0x30e5, 0x30e5, NS), (0x30e6, 0x30e6, ID), (0x30e7, 0x30e7, NS), (0x30e8, 0x30ed, ID),
+    (0x30ee, 0x30ee, NS), (0x30ef, 0x30f4, ID), (0x30f5, 0x30f6, NS), (0x30f7, 0x30fa, ID),
+    (0x30fb, 0x30fe, NS), (0x30ff, 0x31ef, ID), (0x31f0, 0x31ff, NS), (0x3200, 0x3247, ID),
+    (0x3250, 0x4dbf, ID), (0x4e00, 0xa014, ID), (0xa015, 0xa015, NS), (0xa016, 0xa4cf, ID),
+    (0xa60e, 0xa60e, EX), (0xa620, 0xa629, NU), (0xa66f, 0xa672, CM), (0xa674, 0xa67d, CM),
+    (0xa69e, 0xa69f, CM), (0xa6f0, 0xa6f1, CM), (0xa802, 0xa802, CM), (0xa806, 0xa806, CM),
+    (0xa80b, 0xa80b, CM), (0xa823, 0xa827, CM), (0xa82c, 0xa82f, CM), (0xa838, 0xa838, PR),
+    (0xa876, 0xa87f, EX), (0xa880, 0xa881, CM), (0xa8b4, 0xa8cd, CM), (0xa8d0, 0xa8df, NU),
+    (0xa8e0, 0xa8f1, CM), (0xa8ff, 0xa8ff, CM), (0xa900, 0xa909, NU), (0xa926, 0xa92d, CM),
+    (0xa947, 0xa95e, CM), (0xa960, 0xa97f, JL), (0xa980, 0xa983, CM), (0xa9b3, 0xa9c0, CM),
+    (0xa9d0, 0xa9dd, NU), (0xa9e5, 0xa9e5, CM), (0xa9f0, 0xa9f9, NU), (0xaa29, 0xaa3f, CM),
+    (0xaa43, 0xaa43, CM), (0xaa4c, 0xaa4f, CM), (0xaa50, 0xaa5b, NU), (0xaa7b, 0xaa7d, CM),
+    (0xaab0, 0xaab0, CM), (0xaab2, 0xaab4, CM), (0xaab7, 0xaab8, CM), (0xaabe, 0xaabf, CM),
+    (0xaac1, 0xaac1, CM), (0xaaeb, 0xaaef, CM), (0xaaf5, 0xab00, CM), (0xabe3, 0xabea, CM),
+    (0xabec, 0xabef, CM), (0xabf0, 0xabff, NU), (0xac00, 0xd7af, H2), (0xd7b0, 0xd7ca, JV),
+    (0xd7cb, 0xd7ff, JT), (0xf900, 0xfaff, ID), (0xfb1d, 0xfb1d, HL), (0xfb1e, 0xfb1e, CM),
+    (0xfb1f, 0xfb28, HL), (0xfb2a, 0xfb4f, HL), (0xfd3e, 0xfd3e, CL), (0xfd3f, 0xfd3f, OP),
+    (0xfdfc, 0xfdfc, PO), (0xfe00, 0xfe0f, CM), (0xfe10, 0xfe10, IS), (0xfe11, 0xfe12, CL),
+    (0xfe13, 0xfe14, IS), (0xfe15, 0xfe16, EX), (0xfe17, 0xfe17, OP), (0xfe18, 0xfe18, CL),
+    (0xfe19, 0xfe1f, IN), (0xfe20, 0xfe2f, CM), (0xfe30, 0xfe34, ID), (0xfe35, 0xfe35, OP),
+    (0xfe36, 0xfe36, CL), (0xfe37, 0xfe37, OP), (0xfe38, 0xfe38, CL), (0xfe39, 0xfe39, OP),
+    (0xfe3a, 0xfe3a, CL), (0xfe3b, 0xfe3b, OP), (0xfe3c, 0xfe3c, CL), (0xfe3d, 0xfe3d, OP),
+    (0xfe3e, 0xfe3e, CL), (0xfe3f, 0xfe3f, OP), (0xfe40, 0xfe40, CL), (0xfe41, 0xfe41, OP),
+    (0xfe42, 0xfe42, CL), (0xfe43, 0xfe43, OP), (0xfe44, 0xfe44, CL), (0xfe45, 0xfe46, ID),
+    (0xfe47, 0xfe47, OP), (0xfe48, 0xfe48, CL), (0xfe49, 0xfe4f, ID), (0xfe50, 0xfe50, CL),
+    (0xfe51, 0xfe51, ID), (0xfe52, 0xfe53, CL), (0xfe54, 0xfe55, NS), (0xfe56, 0xfe57, EX),
+    (0xfe58, 0xfe58, ID), (0xfe59, 0xfe59, OP), (0xfe5a, 0xfe5a, CL), (0xfe5b, 0xfe5b, OP),
+    (0xfe5c, 0xfe5c, CL), (0xfe5d, 0xfe5d, OP), (0xfe5e, 0xfe5e, CL), (0xfe5f, 0xfe68, ID),
+    (0xfe69, 0xfe69, PR), (0xfe6a, 0xfe6a, PO), (0xfe6b, 0xfe6f, ID), (0xfeff, 0xff00, WJ),
+    (0xff01, 0xff01, EX), (0xff02, 0xff02, QU), (0xff03, 0xff03, ID), (0xff04, 0xff04, PR),
+    (0xff05, 0xff05, PO), (0xff06, 0xff06, ID), (0xff07, 0xff07, QU), (0xff08, 0xff08, OP),
+    (0xff09, 0xff09, CL), (0xff0a, 0xff0a, ID), (0xff0b, 0xff0b, PR), (0xff0c, 0xff0c, CL),
+    (0xff0d, 0xff0d, ID), (0xff0e, 0xff0e, CL), (0xff0f, 0xff0f, SY), (0xff10, 0xff19, NU),
+    (0xff1a, 0xff1b, NS), (0xff1c, 0xff1e, ID), (0xff1f, 0xff1f, EX), (0xff20, 0xff3a, ID),
+    (0xff3b, 0xff3b, OP), (0xff3c, 0xff3c, PR), (0xff3d, 0xff3d, CL), (0xff3e, 0xff5a, ID),
+    (0xff5b, 0xff5b, OP), (0xff5c, 0xff5c, ID), (0xff5d, 0xff5d, CL), (0xff5e, 0xff5e, ID),
+    (0xff5f, 0xff5f, OP), (0xff60, 0xff61, CL), (0xff62, 0xff62, OP), (0xff63, 0xff64, CL),
+    (0xff65, 0xff65, NS), (0xff67, 0xff70, NS), (0xff9e, 0xff9f, NS), (0xffe0, 0xffe0, PO),
+    (0xffe1, 0xffe1, PR), (0xffe2, 0xffe4, ID), (0xffe5, 0xffe7, PR), (0xfffc, 0xfffc, CB),
+    (0x101fd, 0x1027f, CM), (0x102e0, 0x102e0, CM), (0x10376, 0x1037f, CM), (0x104a0, 0x104af, NU),
+    (0x10a01, 0x10a0f, CM), (0x10a38, 0x10a3f, CM), (0x10ae5, 0x10aea, CM), (0x10d24, 0x10d2f, CM),
+    (0x10d30, 0x10e5f, NU), (0x10eab, 0x10eac, CM), (0x10f46, 0x10f50, CM), (0x10f82, 0x10f85, CM),
+    (0x11000, 0x11002, CM), (0x11038, 0x11046, CM), (0x11066, 0x1106f, NU), (0x11070, 0x11070, CM),
+    (0x11073, 0x11074, CM), (0x1107f, 0x11082, CM), (0x110b0, 0x110ba, CM), (0x110c2, 0x110cc, CM),
+    (0x110f0, 0x110ff, NU), (0x11100, 0x11102, CM), (0x11127, 0x11135, CM), (0x11136, 0x1113f, NU),
+    (0x11145, 0x11146, CM), (0x11173, 0x11173, CM), (0x11180, 0x11182, CM), (0x111b3, 0x111c0, CM),
+    (0x111c9, 0x111cc, CM), (0x111ce, 0x111cf, CM), (0x111d0, 0x111d9, NU), (0x1122c, 0x11237, CM),
+    (0x1123e, 0x1127f, CM), (0x112df, 0x112ef, CM), (0x112f0, 0x112ff, NU), (0x11300, 0x11304, CM),
+    (0x1133b, 0x1133c, CM), (0x1133e, 0x1134f, CM), (0x11357, 0x1135c, CM), (0x11362, 0x113ff, CM),
+    (0x11435, 0x11446, CM), (0x11450, 0x11459, NU), (0x1145e, 0x1145e, CM), (0x114b0, 0x114c3, CM),
+    (0x114d0, 0x1157f, NU), (0x115af, 0x115c0, CM), (0x115dc, 0x115ff, CM), (0x11630, 0x11640, CM),
+    (0x11650, 0x1165f, NU), (0x116ab, 0x116b7, CM), (0x116c0, 0x116ff, NU), (0x1171d, 0x1172f, CM),
+    (0x11730, 0x11739, NU), (0x1182c, 0x1183a, CM), (0x118e0, 0x118e9, NU), (0x11930, 0x1193e, CM),
+    (0x11940, 0x11940, CM), (0x11942, 0x11943, CM), (0x11950, 0x1199f, NU), (0x119d1, 0x119e0, CM),
+    (0x119e4, 0x119ff, CM), (0x11a01, 0x11a0a, CM), (0x11a33, 0x11a39, CM), (0x11a3b, 0x11a3e, CM),
+    (0x11a47, 0x11a4f, CM), (0x11a51, 0x11a5b, CM), (0x11a8a, 0x11a99, CM), (0x11c2f, 0x11c3f, CM),
+    (0x11c50, 0x11c59, NU), (0x11c92, 0x11cff, CM), (0x11d31, 0x11d45, CM), (0x11d47, 0x11d4f, CM),
+    (0x11d50, 0x11d5f, NU), (0x11d8a, 0x11d97, CM), (0x11da0, 0x11edf, NU), (0x11ef3, 0x11ef6, CM),
+    (0x11fdd, 0x11fe0, PR), (0x16a60, 0x16a6d, NU), (0x16ac0, 0x16acf, NU), (0x16af0, 0x16af4, CM),
+    (0x16b30, 0x16b36, CM), (0x16b50, 0x16b5a, NU), (0x16f4f, 0x16f4f, CM), (0x16f51, 0x16f92, CM),
+    (0x16fe0, 0x16fe3, ID), (0x16fe4, 0x16fff, CM), (0x17000, 0x1bbff, ID), (0x1bc9d, 0x1bc9e, CM),
+    (0x1cf00, 0x1cf4f, CM), (0x1d165, 0x1d169, CM), (0x1d16d, 0x1d172, CM), (0x1d17b, 0x1d182, CM),
+    (0x1d185, 0x1d18b, CM), (0x1d1aa, 0x1d1ad, CM), (0x1d242, 0x1d244, CM), (0x1d7ce, 0x1d7ff, NU),
+    (0x1da00, 0x1da36, CM), (0x1da3b, 0x1da6c, CM), (0x1da75, 0x1da75, CM), (0x1da84, 0x1da84, CM),
+    (0x1da9b, 0x1deff, CM), (0x1e000, 0x1e0ff, CM), (0x1e130, 0x1e136, CM), (0x1e140, 0x1e14d, NU),
+    (0x1e2ae, 0x1e2bf, CM), (0x1e2ec, 0x1e2ef, CM), (0x1e2f0, 0x1e2fe, NU), (0x1e2ff, 0x1e7df, PR),
+    (0x1e8d0, 0x1e8ff, CM), (0x1e944, 0x1e94a, CM), (0x1e950, 0x1e95d, NU), (0x1ecb0, 0x1ecb0, PR),
+    (0x1f000, 0x1f1e5, ID), (0x1f1e6, 0x1f1ff, RI), (0x1f200, 0x1f384, ID), (0x1f385, 0x1f385, EB),
+    (0x1f386, 0x1f3c1, ID), (0x1f3c2, 0x1f3c4, EB), (0x1f3c5, 0x1f3c6, ID), (0x1f3c7, 0x1f3c7, EB),
+    (0x1f3c8, 0x1f3c9, ID), (0x1f3ca, 0x1f3cc, EB), (0x1f3cd, 0x1f3fa, ID), (0x1f3fb, 0x1f3ff, EM),
+    (0x1f400, 0x1f441, ID), (0x1f442, 0x1f443, EB), (0x1f444, 0x1f445, ID), (0x1f446, 0x1f450, EB),
+    (0x1f451, 0x1f465, ID), (0x1f466, 0x1f478, EB), (0x1f479, 0x1f47b, ID), (0x1f47c, 0x1f47c, EB),
+    (0x1f47d, 0x1f480, ID), (0x1f481, 0x1f483, EB), (0x1f484, 0x1f484, ID), (0x1f485, 0x1f487, EB),
+    (0x1f488, 0x1f4a9, ID), (0x1f4aa, 0x1f4aa, EB), (0x1f4ab, 0x1f573, ID), (0x1f574, 0x1f575, EB),
+    (0x1f576, 0x1f579, ID), (0x1f57a, 0x1f57a, EB), (0x1f57b, 0x1f58f, ID), (0x1f590, 0x1f590, EB),
+    (0x1f591, 0x1f594, ID), (0x1f595, 0x1f596, EB), (0x1f597, 0x1f644, ID), (0x1f645, 0x1f647, EB),
+    (0x1f648, 0x1f64a, ID), (0x1f64b, 0x1f64f, EB), (0x1f650, 0x1f6a2, ID), (0x1f6a3, 0x1f6a3, EB),
+    (0x1f6a4, 0x1f6b3, ID), (0x1f6b4, 0x1f6b6, EB), (0x1f6b7, 0x1f6bf, ID), (0x1f6c0, 0x1f6c0, EB),
+    (0x1f6c1, 0x1f6cb, ID), (0x1f6cc, 0x1f6cc, EB), (0x1f6cd, 0x1f90b, ID), (0x1f90c, 0x1f90c, EB),
+    (0x1f90d, 0x1f90e, ID), (0x1f90f, 0x1f90f, EB), (0x1f910, 0x1f917, ID), (0x1f918, 0x1f91f, EB),
+    (0x1f920, 0x1f925, ID), (0x1f926, 0x1f926, EB), (0x1f927, 0x1f92f, ID), (0x1f930, 0x1f939, EB),
+    (0x1f93a, 0x1f93b, ID), (0x1f93c, 0x1f93e, EB), (0x1f93f, 0x1f976, ID), (0x1f977, 0x1f977, EB),
+    (0x1f978, 0x1f9b4, ID), (0x1f9b5, 0x1f9b6, EB), (0x1f9b7, 0x1f9b7, ID), (0x1f9b8, 0x1f9b9, EB),
+    (0x1f9ba, 0x1f9ba, ID), (0x1f9bb, 0x1f9bb, EB), (0x1f9bc, 0x1f9cc, ID), (0x1f9cd, 0x1f9cf, EB),
+    (0x1f9d0, 0x1f9d0, ID), (0x1f9d1, 0x1f9dd, EB), (0x1f9de, 0x1faff, ID), (0x1fbf0, 0x1ffff, NU),
+    (0x20000, 0x2fffd, ID), (0x30000, 0xe0000, ID), (0xe0100, 0xeffff, CM),
+];
+
+pub fn line_break_class(c:char)->LineBreakClass{
+    let u = c as u32;
+    if u < 0x80{
+        return match u{
+            0x0a=>LF,
+            0x0d=>CR,
+            0x0b | 0x0c=>BK,
+            0x09=>BA,
+            0x00..=0x1f | 0x7f=>CM,
+            0x20=>SP,
+            0x21 | 0x3f=>EX,
+            0x22 | 0x27=>QU,
+            0x24 | 0x2b | 0x5c=>PR,
+            0x25=>PO,
+            0x28 | 0x5b | 0x7b=>OP,
+            0x29 | 0x5d=>CP,
+            0x2c | 0x2e | 0x3a | 0x3b=>IS,
+            0x2d=>HY,
+            0x2f=>SY,
+            0x30..=0x39=>NU,
+            0x7c=>BA,
+            0x7d=>CL,
+            _=>AL
+        }
+    }
+    if u >= 0xac00 && u <= 0xd7a3{ // hangul syllables, LV or LVT
+        return if (u - 0xac00) % 28 == 0{H2}else{H3}
+    }
+    let mut lo = 0;
+    let mut hi = LINE_BREAK_RANGES.len();
+    while lo < hi{
+        let mid = (lo + hi) / 2;
+        let (start, end, class) = LINE_BREAK_RANGES[mid];
+        if u < start{
+            hi = mid;
+        }
+        else if u > end{
+            lo = mid + 1;
+        }
+        else{
+            return class
+        }
+    }
+    AL
+}
+
+// break opportunities after each char, the last char always ends in Mandatory (LB3)
+pub fn line_breaks(chars:&[char])->Vec<LineBreak>{
+    let mut out = vec![LineBreak::None; chars.len()];
+    if chars.len() == 0{
+        return out
+    }
+    // LB10, a combining mark without a base is a letter
+    let mut prev = match line_break_class(chars[0]){
+        CM | ZWJ=>AL,
+        class=>class
+    };
+    let mut before_space = prev; // the class before a run of spaces, for the SP* rules
+    let mut prev_zwj = line_break_class(chars[0]) == ZWJ;
+    let mut hebrew_hyphen = false; // HL followed by HY or BA, LB21a
+    let mut ri_count = if prev == RI{1}else{0};
+
+    for i in 1..chars.len(){
+        let raw = line_break_class(chars[i]);
+        out[i - 1] = line_break_pair(prev, raw, before_space, prev_zwj, hebrew_hyphen, ri_count);
+
+        // LB9, marks take the class of their base
+        let attached = (raw == CM || raw == ZWJ) && match prev{
+            BK | CR | LF | NL | SP | ZW=>false,
+            _=>true
+        };
+        if !attached{
+            let cur = if raw == CM || raw == ZWJ{AL}else{raw};
+            hebrew_hyphen = prev == HL && (cur == HY || cur == BA);
+            ri_count = if cur == RI{ri_count + 1}else{0};
+            prev = cur;
+        }
+        if prev != SP{
+            before_space = prev;
+        }
+        prev_zwj = raw == ZWJ;
+    }
+    out[chars.len() - 1] = LineBreak::Mandatory;
+    out
+}
+
+fn line_break_pair(prev:LineBreakClass, cur:LineBreakClass, before_space:LineBreakClass, prev_zwj:bool, hebrew_hyphen:bool, ri_count:usize)->LineBreak{
+    // LB4, LB5
+    match prev{
+        BK | LF | NL=>return LineBreak::Mandatory,
+        CR=>return if cur == LF{LineBreak::None}else{LineBreak::Mandatory},
+        _=>()
+    }
+    // LB6, LB7
+    match cur{
+        BK | CR | LF | NL | SP | ZW=>return LineBreak::None,
+        _=>()
+    }
+    // LB8
+    if before_space == ZW{
+        return LineBreak::Allowed
+    }
+    // LB8a, LB9
+    if prev_zwj || ((cur == CM || cur == ZWJ) && prev != SP){
+        return LineBreak::None
+    }
+    // LB10
+    let cur = if cur == CM || cur == ZWJ{AL}else{cur};
+    let no_break = match (prev, cur){
+        (WJ, _) | (_, WJ)=>true, // LB11
+        (GL, _)=>true, // LB12
+        (SP, GL) | (BA, GL) | (HY, GL)=>false, // LB12a
+        (_, GL)=>true,
+        (_, CL) | (_, CP) | (_, EX) | (_, IS) | (_, SY)=>true, // LB13
+        _=>false
+    };
+    if no_break{
+        return LineBreak::None
+    }
+    // LB14 to LB17, these look past spaces
+    match (before_space, cur){
+        (OP, _) | (QU, OP) | (CL, NS) | (CP, NS) | (B2, B2)=>return LineBreak::None,
+        _=>()
+    }
+    // LB18
+    if prev == SP{
+        return LineBreak::Allowed
+    }
+    // LB19, LB20
+    if prev == QU || cur == QU{
+        return LineBreak::None
+    }
+    if prev == CB || cur == CB{
+        return LineBreak::Allowed
+    }
+    // LB21a
+    if hebrew_hyphen{
+        return LineBreak::None
+    }
+    let no_break = match (prev, cur){
+        (_, BA) | (_, HY) | (_, NS) | (BB, _)=>true, // LB21
+        (SY, HL)=>true, // LB21b
+        (_, IN)=>true, // LB22
+        (AL, NU) | (HL, NU) | (NU, AL) | (NU, HL)=>true, // LB23
+        (PR, ID) | (PR, EB) | (PR, EM) | (ID, PO) | (EB, PO) | (EM, PO)=>true, // LB23a
+        (PR, AL) | (PR, HL) | (PO, AL) | (PO, HL) | (AL, PR) | (AL, PO) | (HL, PR) | (HL, PO)=>true, // LB24
+        (CL, PO) | (CP, PO) | (CL, PR) | (CP, PR) | (NU, PO) | (NU, PR) | (PO, OP) | (PO, NU) | (PR, OP) | (PR, NU) |
+        (HY, NU) | (IS, NU) | (NU, NU) | (SY, NU) | (OP, NU)=>true, // LB25
+        (JL, JL) | (JL, JV) | (JL, H2) | (JL, H3) | (JV, JV) | (JV, JT) | (H2, JV) | (H2, JT) | (JT, JT) | (H3, JT)=>true, // LB26
+        (JL, PO) | (JV, PO) | (JT, PO) | (H2, PO) | (H3, PO) | (PR, JL) | (PR, JV) | (PR, JT) | (PR, H2) | (PR, H3)=>true, // LB27
+        (AL, AL) | (AL, HL) | (HL, AL) | (HL, HL)=>true, // LB28
+        (IS, AL) | (IS, HL)=>true, // LB29
+        (AL, OP) | (HL, OP) | (NU, OP) | (CP, AL) | (CP, HL) | (CP, NU)=>true, // LB30
+        (RI, RI)=>ri_count % 2 == 1, // LB30a
+        (EB, EM)=>true, // LB30b
+        _=>false
+    };
+    if no_break{
+        return LineBreak::None
+    }
+    // LB31
+    LineBreak::Allowed
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    // the text with | after chars that allow a break and ! after those that must
+    fn marked(text:&str)->String{
+        let chars:Vec<char> = text.chars().collect();
+        let mut out = String::new();
+        for (c, brk) in chars.iter().zip(line_breaks(&chars)){
+            out.push(*c);
+            match brk{
+                LineBreak::None=>(),
+                LineBreak::Allowed=>out.push('|'),
+                LineBreak::Mandatory=>out.push('!')
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn classes(){
+        let classes:Vec<LineBreakClass> = "a (1)-,\n\t\"".chars().map(line_break_class).collect();
+        assert_eq!(classes, vec![AL, SP, OP, NU, CP, HY, IS, LF, BA, QU]);
+        for (c, class) in [
+            ('\r', CR), ('\u{85}', NL), ('\u{a0}', GL), ('\u{301}', CM), ('\u{5d0}', HL), ('\u{200b}', ZW),
+            ('\u{200d}', ZWJ), ('\u{2014}', B2), ('\u{2026}', IN), ('\u{2028}', BK), ('\u{3002}', CL),
+            ('\u{4e2d}', ID), ('\u{ac00}', H2), ('\u{ac01}', H3), ('\u{1100}', JL), ('\u{1f1e6}', RI),
+            ('\u{1f466}', EB), ('\u{1f3fb}', EM), ('\u{feff}', WJ), ('\u{e9}', AL)
+        ].iter(){
+            assert_eq!(line_break_class(*c), *class, "{:?}", c);
+        }
+    }
+
+    #[test]
+    fn mandatory_breaks(){
+        assert_eq!(line_breaks(&[]), vec![]);
+        assert_eq!(marked("a"), "a!");
+        assert_eq!(marked("a\nb"), "a\n!b!");
+        assert_eq!(marked("a\r\nb"), "a\r\n!b!");
+        assert_eq!(marked("a\rb"), "a\r!b!");
+        assert_eq!(marked("a\u{2028}b\u{85}c"), "a\u{2028}!b\u{85}!c!");
+        // no breaks between the line ends of empty lines but after each
+        assert_eq!(marked("\n\n"), "\n!\n!");
+    }
+
+    #[test]
+    fn optional_breaks(){
+        // after spaces, not before them
+        assert_eq!(marked("hello world"), "hello |world!");
+        assert_eq!(marked("a  b"), "a  |b!");
+        // after hyphens, around em dashes but not between two
+        assert_eq!(marked("foo-bar"), "foo-|bar!");
+        assert_eq!(marked("a\u{2014}\u{2014}b"), "a|\u{2014}\u{2014}|b!");
+        // not inside numbers, around punctuation and glue
+        assert_eq!(marked("$1.50, ok"), "$1.50, |ok!");
+        assert_eq!(marked("(a) b!"), "(a) |b!!");
+        assert_eq!(marked("a\u{a0}b c"), "a\u{a0}b |c!");
+        assert_eq!(marked("a\u{2060}b"), "a\u{2060}b!");
+        assert_eq!(marked("\"a\" b"), "\"a\" |b!");
+        // ideographs break anywhere, but not before small kana and closing marks
+        assert_eq!(marked("\u{4e2d}\u{6587}\u{3002}"), "\u{4e2d}|\u{6587}\u{3002}!");
+        assert_eq!(marked("\u{30ab}\u{30a1}"), "\u{30ab}\u{30a1}!");
+        // zero width space allows one
+        assert_eq!(marked("a\u{200b}b"), "a\u{200b}|b!");
+        // marks stay with their base
+        assert_eq!(marked("e\u{301} x\u{301}"), "e\u{301} |x\u{301}!");
+        // hangul jamo of one syllable stay together, regional indicators pair up
+        assert_eq!(marked("\u{1100}\u{1161}\u{11a8}\u{ac00}\u{11a8}"), "\u{1100}\u{1161}\u{11a8}|\u{ac00}\u{11a8}!");
+        assert_eq!(marked("\u{1f1e6}\u{1f1e7}\u{1f1e8}\u{1f1e9}"), "\u{1f1e6}\u{1f1e7}|\u{1f1e8}\u{1f1e9}!");
+        // an emoji base and its modifier
+        assert_eq!(marked("\u{1f466}\u{1f3fb}\u{1f466}"), "\u{1f466}\u{1f3fb}|\u{1f466}!");
+    }
+}
@@ -23,6 +23,20 @@ impl Rect{
             r.y + r.h < self.y
         )
     }
+    pub fn contains_rect(&self, r:Rect)->bool{
+        r.x >= self.x && r.y >= self.y && r.x + r.w <= self.x + self.w && r.y + r.h <= self.y + self.h
+    }
+    pub fn union(&self, r:Rect)->Rect{
+        let x = self.x.min(r.x);
+        let y = self.y.min(r.y);
+        Rect{x:x, y:y, w:(self.x + self.w).max(r.x + r.w) - x, h:(self.y + self.h).max(r.y + r.h) - y}
+    }
+    // the overlap, zero sized when there is none
+    pub fn clip(&self, r:Rect)->Rect{
+        let x = self.x.max(r.x);
+        let y = self.y.max(r.y);
+        Rect{x:x, y:y, w:((self.x + self.w).min(r.x + r.w) - x).max(0.), h:((self.y + self.h).min(r.y + r.h) - y).max(0.)}
+    }
 }
 
 #[derive(Clone, Copy, Default, Debug)]
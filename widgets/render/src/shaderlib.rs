@@ -0,0 +1,430 @@
+// Shared shader modules. A module is a named set of consts and fns registered on Cx
+// which any Shader can import from selectively. Everything in a module gets namespaced
+// with the module name as prefix (like the df_ library), so noise::hash2 becomes noise_hash2
+
+use crate::cx::*;
+use std::collections::HashMap;
+
+#[derive(Clone)]
+pub struct ShaderModule{
+    pub name:String,
+    pub ast:ShAst
+}
+
+impl ShaderModule{
+    pub fn new(name:&str, ast:ShAst)->Result<ShaderModule, String>{
+        let prefix = format!("{}_", name);
+        let local_fns:Vec<String> = ast.fns.iter().map(|f| f.name.clone()).collect();
+        let local_consts:Vec<String> = ast.consts.iter().map(|c| c.name.clone()).collect();
+        if ast.vars.len() != 0 || ast.types.len() != 0{
+            return Err(format!("Shader module {} can only contain consts and fns", name));
+        }
+        let namespace = |id:&str|->String{
+            if id.starts_with(&prefix){
+                id.to_string()
+            }
+            else{
+                format!("{}{}", prefix, id)
+            }
+        };
+
+        let mut out = ShAst{types:Vec::new(), vars:Vec::new(), consts:Vec::new(), fns:Vec::new()};
+        for shconst in &ast.consts{
+            let mut value = shconst.value.clone();
+            value.walk_ids(&mut |id, is_call|{
+                if !is_call && local_consts.contains(id){
+                    *id = namespace(id);
+                }
+            });
+            out.consts.push(ShConst{
+                name:namespace(&shconst.name),
+                ty:shconst.ty.clone(),
+                value:value
+            });
+        }
+        for shfn in &ast.fns{
+            let mut block = shfn.block.clone();
+            if let Some(block) = &mut block{
+                // args and the lets in scope shadow module consts
+                let mut scope:Vec<String> = shfn.args.iter().map(|a| a.name.clone()).collect();
+                block.walk_scoped_ids(&mut scope, &mut |id, is_call, scope|{
+                    if is_call && local_fns.contains(id) || !is_call && local_consts.contains(id) && !scope.contains(id){
+                        *id = namespace(id);
+                    }
+                });
+            }
+            out.fns.push(ShFn{
+                name:namespace(&shfn.name),
+                args:shfn.args.clone(),
+                ret:shfn.ret.clone(),
+                block:block
+            });
+        }
+        Ok(ShaderModule{
+            name:name.to_string(),
+            ast:out
+        })
+    }
+
+    // builds an ast with just the requested fns plus whatever module fns and consts they depend on
+    pub fn import_ast(&self, fn_names:&[&str])->Result<ShAst, String>{
+        let prefix = format!("{}_", self.name);
+        let mut want_fns:Vec<String> = Vec::new();
+        for fn_name in fn_names{
+            let full = if fn_name.starts_with(&prefix){fn_name.to_string()}else{format!("{}{}", prefix, fn_name)};
+            if self.ast.fns.iter().find(|f| f.name == full).is_none(){
+                return Err(format!("Shader module {} has no fn {}", self.name, fn_name));
+            }
+            want_fns.push(full);
+        }
+        // walk the call graph inside the module
+        let mut want_consts:Vec<String> = Vec::new();
+        let mut i = 0;
+        while i < want_fns.len(){
+            let shfn = self.ast.fns.iter().find(|f| f.name == want_fns[i]).unwrap().clone();
+            if let Some(mut block) = shfn.block{
+                block.walk_ids(&mut |id, is_call|{
+                    if is_call{
+                        if !want_fns.contains(id) && self.ast.fns.iter().find(|f| f.name == *id).is_some(){
+                            want_fns.push(id.clone());
+                        }
+                    }
+                    else if !want_consts.contains(id) && self.ast.consts.iter().find(|c| c.name == *id).is_some(){
+                        want_consts.push(id.clone());
+                    }
+                });
+            }
+            i += 1;
+        }
+        // consts can reference other consts
+        let mut i = 0;
+        while i < want_consts.len(){
+            let mut value = self.ast.consts.iter().find(|c| c.name == want_consts[i]).unwrap().value.clone();
+            value.walk_ids(&mut |id, is_call|{
+                if !is_call && !want_consts.contains(id) && self.ast.consts.iter().find(|c| c.name == *id).is_some(){
+                    want_consts.push(id.clone());
+                }
+            });
+            i += 1;
+        }
+        // keep module order so the import order doesn't change the shader hash
+        Ok(ShAst{
+            types:Vec::new(),
+            vars:Vec::new(),
+            consts:self.ast.consts.iter().filter(|c| want_consts.contains(&c.name)).cloned().collect(),
+            fns:self.ast.fns.iter().filter(|f| want_fns.contains(&f.name)).cloned().collect()
+        })
+    }
+
+    pub fn def_builtin_modules()->HashMap<String, ShaderModule>{
+        let mut modules = HashMap::new();
+        // the builtins only hold consts and fns, so they always make a module
+        for module in vec![ShaderModule::def_color(), ShaderModule::def_noise()].into_iter().flatten(){
+            modules.insert(module.name.clone(), module);
+        }
+        modules
+    }
+
+    pub fn def_color()->Result<ShaderModule, String>{
+        ShaderModule::new("color", shader_ast!({
+            fn hsv2rgb(c:vec4)->vec4{
+                let k:vec4 = vec4(1.0, 2.0 / 3.0, 1.0 / 3.0, 3.0);
+                let p:vec3 = abs(fract(vec3(c.x, c.x, c.x) + vec3(k.x, k.y, k.z)) * 6.0 - vec3(k.w, k.w, k.w));
+                return vec4(c.z * mix(vec3(k.x, k.x, k.x), clamp(p - vec3(k.x, k.x, k.x), vec3(0.0, 0.0, 0.0), vec3(1.0, 1.0, 1.0)), c.y), c.w);
+            }
+
+            fn rgb2hsv(c:vec4)->vec4{
+                let k:vec4 = vec4(0.0, -1.0 / 3.0, 2.0 / 3.0, -1.0);
+                let p:vec4 = mix(vec4(c.z, c.y, k.w, k.z), vec4(c.y, c.z, k.x, k.y), step(c.z, c.y));
+                let q:vec4 = mix(vec4(p.x, p.y, p.w, c.x), vec4(c.x, p.y, p.z, p.x), step(p.x, c.x));
+                let d:float = q.x - min(q.w, q.y);
+                let e:float = 0.0000000001;
+                return vec4(abs(q.z + (q.w - q.y) / (6.0 * d + e)), d / (q.x + e), q.x, c.w);
+            }
+
+            fn srgb_to_linear(c:vec4)->vec4{
+                return vec4(pow(vec3(c.x, c.y, c.z), vec3(2.2, 2.2, 2.2)), c.w);
+            }
+
+            fn linear_to_srgb(c:vec4)->vec4{
+                return vec4(pow(vec3(c.x, c.y, c.z), vec3(1.0 / 2.2, 1.0 / 2.2, 1.0 / 2.2)), c.w);
+            }
+        }))
+    }
+
+    pub fn def_noise()->Result<ShaderModule, String>{
+        ShaderModule::new("noise", shader_ast!({
+            const HASH_SCALE:float = 43758.5453;
+
+            fn hash2(p:vec2)->float{
+                return fract(sin(dot(p, vec2(12.9898, 78.233))) * HASH_SCALE);
+            }
+
+            fn value2(p:vec2)->float{
+                let i:vec2 = floor(p);
+                let f:vec2 = fract(p);
+                let u:vec2 = f * f * (3.0 - 2.0 * f);
+                return mix(
+                    mix(hash2(i), hash2(i + vec2(1.0, 0.0)), u.x),
+                    mix(hash2(i + vec2(0.0, 1.0)), hash2(i + vec2(1.0, 1.0)), u.x),
+                    u.y
+                );
+            }
+
+            fn fbm2(p:vec2)->float{
+                let v:float = 0.0;
+                let a:float = 0.5;
+                let q:vec2 = p;
+                for _i in 0..4{
+                    v += a * value2(q);
+                    q = q * 2.0;
+                    a = a * 0.5;
+                }
+                return v;
+            }
+        }))
+    }
+}
+
+impl Cx{
+    pub fn add_shader_module(&mut self, module:ShaderModule){
+        self.shader_modules.insert(module.name.clone(), module);
+    }
+
+    pub fn shader_module(&self, name:&str)->Result<&ShaderModule, String>{
+        if let Some(module) = self.shader_modules.get(name){
+            return Ok(module)
+        }
+        Err(format!("Cannot find shader module {}", name))
+    }
+
+    // only the imported fns end up in the shader asts, so only they count towards the shader_map key
+    pub fn import_shader_fns(&self, sh:&mut Shader, module:&str, fn_names:&[&str])->Result<(), String>{
+        let mut ast = self.shader_module(module)?.import_ast(fn_names)?;
+        // skip what a previous import already pulled in
+        ast.fns.retain(|f| sh.find_fn(&f.name).is_none());
+        ast.consts.retain(|c| sh.find_const(&c.name).is_none());
+        if ast.fns.len() != 0 || ast.consts.len() != 0{
+            sh.add_ast(ast);
+        }
+        Ok(())
+    }
+
+    pub fn import_shader_module(&self, sh:&mut Shader, module:&str)->Result<(), String>{
+        let names:Vec<String> = self.shader_module(module)?.ast.fns.iter().map(|f| f.name.clone()).collect();
+        let names:Vec<&str> = names.iter().map(|n| n.as_str()).collect();
+        self.import_shader_fns(sh, module, &names)
+    }
+}
+
+impl ShBlock{
+    pub fn walk_ids(&mut self, cb:&mut dyn FnMut(&mut String, bool)){
+        self.walk_scoped_ids(&mut Vec::new(), &mut |id:&mut String, is_call:bool, _:&[String]| cb(id, is_call));
+    }
+
+    // walk_ids that also passes the lets and loop vars in scope, a let is in scope
+    // from the statement after it to the end of its block
+    pub fn walk_scoped_ids(&mut self, scope:&mut Vec<String>, cb:&mut dyn FnMut(&mut String, bool, &[String])){
+        let scope_len = scope.len();
+        for stmt in &mut self.stmts{
+            match stmt.as_mut(){
+                ShStmt::ShLet(shlet)=>{
+                    shlet.init.walk_scoped_ids(scope, cb);
+                    scope.push(shlet.name.clone());
+                },
+                ShStmt::ShExpr(expr)=>expr.walk_scoped_ids(scope, cb),
+                ShStmt::ShSemi(expr)=>expr.walk_scoped_ids(scope, cb)
+            }
+        }
+        scope.truncate(scope_len);
+    }
+}
+
+impl ShExpr{
+    // calls back with every identifier and call name in the expression tree
+    pub fn walk_ids(&mut self, cb:&mut dyn FnMut(&mut String, bool)){
+        self.walk_scoped_ids(&mut Vec::new(), &mut |id:&mut String, is_call:bool, _:&[String]| cb(id, is_call));
+    }
+
+    pub fn walk_scoped_ids(&mut self, scope:&mut Vec<String>, cb:&mut dyn FnMut(&mut String, bool, &[String])){
+        match self{
+            ShExpr::ShId(x)=>cb(&mut x.name, false, scope),
+            ShExpr::ShLit(_)=>(),
+            ShExpr::ShField(x)=>x.base.walk_scoped_ids(scope, cb),
+            ShExpr::ShIndex(x)=>{
+                x.base.walk_scoped_ids(scope, cb);
+                x.index.walk_scoped_ids(scope, cb);
+            },
+            ShExpr::ShAssign(x)=>{
+                x.left.walk_scoped_ids(scope, cb);
+                x.right.walk_scoped_ids(scope, cb);
+            },
+            ShExpr::ShAssignOp(x)=>{
+                x.left.walk_scoped_ids(scope, cb);
+                x.right.walk_scoped_ids(scope, cb);
+            },
+            ShExpr::ShBinary(x)=>{
+                x.left.walk_scoped_ids(scope, cb);
+                x.right.walk_scoped_ids(scope, cb);
+            },
+            ShExpr::ShUnary(x)=>x.expr.walk_scoped_ids(scope, cb),
+            ShExpr::ShParen(x)=>x.expr.walk_scoped_ids(scope, cb),
+            ShExpr::ShBlock(x)=>x.walk_scoped_ids(scope, cb),
+            ShExpr::ShCall(x)=>{
+                cb(&mut x.call, true, scope);
+                for arg in &mut x.args{
+                    arg.walk_scoped_ids(scope, cb);
+                }
+            },
+            ShExpr::ShIf(x)=>{
+                x.cond.walk_scoped_ids(scope, cb);
+                x.then_branch.walk_scoped_ids(scope, cb);
+                if let Some(else_branch) = &mut x.else_branch{
+                    else_branch.walk_scoped_ids(scope, cb);
+                }
+            },
+            ShExpr::ShWhile(x)=>{
+                x.cond.walk_scoped_ids(scope, cb);
+                x.body.walk_scoped_ids(scope, cb);
+            },
+            ShExpr::ShForLoop(x)=>{
+                x.from.walk_scoped_ids(scope, cb);
+                x.to.walk_scoped_ids(scope, cb);
+                scope.push(x.iter.clone());
+                x.body.walk_scoped_ids(scope, cb);
+                scope.pop();
+            },
+            ShExpr::ShReturn(x)=>{
+                if let Some(expr) = &mut x.expr{
+                    expr.walk_scoped_ids(scope, cb);
+                }
+            },
+            ShExpr::ShBreak(_)=>(),
+            ShExpr::ShContinue(_)=>()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    // the ids of a module fn in order, calls end in ()
+    fn ids(module:&ShaderModule, name:&str)->Vec<String>{
+        let mut block = module.ast.fns.iter().find(|f| f.name == name).unwrap().block.clone().unwrap();
+        let mut out = Vec::new();
+        block.walk_ids(&mut |id, is_call| out.push(if is_call{format!("{}()", id)}else{id.clone()}));
+        out
+    }
+
+    fn names(ast:&ShAst)->(Vec<String>, Vec<String>){
+        (ast.fns.iter().map(|f| f.name.clone()).collect(), ast.consts.iter().map(|c| c.name.clone()).collect())
+    }
+
+    #[test]
+    fn module_fns_and_consts_are_namespaced(){
+        let module = ShaderModule::new("m", shader_ast!({
+            const K:float = 2.0;
+            const K2:float = K * 2.0;
+
+            fn g(x:float)->float{
+                return x * K;
+            }
+
+            fn f(x:float, K2:float)->float{
+                let y:float = x;
+                if x > 0.0{
+                    let K:float = 3.0;
+                    y = y * K;
+                }
+                return g(y) + K + K2 + sin(y);
+            }
+        })).unwrap();
+        assert_eq!(names(&module.ast), (vec!["m_g".to_string(), "m_f".to_string()], vec!["m_K".to_string(), "m_K2".to_string()]));
+        assert_eq!(ids(&module, "m_g"), vec!["x", "m_K"]);
+        // the let in the if only shadows the const inside it, the arg in all of f
+        assert_eq!(ids(&module, "m_f"), vec!["x", "x", "y", "y", "K", "m_g()", "y", "m_K", "K2", "sin()", "y"]);
+        let mut value = module.ast.consts[1].value.clone();
+        let mut const_ids = Vec::new();
+        value.walk_ids(&mut |id, _| const_ids.push(id.clone()));
+        assert_eq!(const_ids, vec!["m_K"]);
+    }
+
+    #[test]
+    fn imports_pull_in_what_the_fns_use(){
+        let noise = ShaderModule::def_noise().unwrap();
+        let ast = noise.import_ast(&["value2"]).unwrap();
+        assert_eq!(names(&ast), (vec!["noise_hash2".to_string(), "noise_value2".to_string()], vec!["noise_HASH_SCALE".to_string()]));
+        // namespaced names work too
+        assert_eq!(noise.import_ast(&["noise_fbm2"]).unwrap().fns.len(), 3);
+        assert!(noise.import_ast(&["perlin2"]).is_err());
+    }
+
+    #[test]
+    fn bad_modules_and_names_are_errors(){
+        assert!(ShaderModule::new("bad", shader_ast!({
+            let x:float<Uniform>;
+        })).is_err());
+        let cx = Cx::default();
+        assert!(cx.shader_module("nope").is_err());
+        let mut sh = Shader::def();
+        assert!(cx.import_shader_fns(&mut sh, "nope", &["hash2"]).is_err());
+        assert!(cx.import_shader_fns(&mut sh, "noise", &["nope"]).is_err());
+        assert!(cx.import_shader_module(&mut sh, "nope").is_err());
+        assert_eq!(sh.asts.len(), 0);
+    }
+
+    #[test]
+    fn only_imported_fns_count_towards_the_shader_key(){
+        let mut cx = Cx::default();
+        let shader = |cx:&Cx, fns:&[&str]|->Shader{
+            let mut sh = Shader::def();
+            cx.import_shader_fns(&mut sh, "color", fns).unwrap();
+            sh
+        };
+        let a = cx.add_shader(shader(&cx, &["hsv2rgb"]), "a");
+        assert_eq!(cx.add_shader(shader(&cx, &["hsv2rgb"]), "b"), a);
+        // importing again adds nothing
+        let mut sh = shader(&cx, &["hsv2rgb"]);
+        cx.import_shader_fns(&mut sh, "color", &["color_hsv2rgb"]).unwrap();
+        assert_eq!(sh.asts.len(), 1);
+        assert_eq!(cx.add_shader(sh, "c"), a);
+        // another fn is another shader, whatever the order it is asked for in
+        let both = cx.add_shader(shader(&cx, &["hsv2rgb", "rgb2hsv"]), "d");
+        assert!(both != a);
+        assert_eq!(cx.add_shader(shader(&cx, &["rgb2hsv", "hsv2rgb"]), "e"), both);
+        // fns added to the module that the shader doesnt import change nothing
+        let mut color = ShaderModule::def_color().unwrap();
+        color.ast.fns.extend(ShaderModule::new("color", shader_ast!({
+            fn grey(c:vec4)->vec4{
+                return vec4(c.x, c.x, c.x, c.w);
+            }
+        })).unwrap().ast.fns);
+        cx.add_shader_module(color);
+        assert_eq!(cx.add_shader(shader(&cx, &["hsv2rgb"]), "f"), a);
+        assert_eq!(cx.shaders.len(), 2);
+    }
+
+    #[cfg(any(feature = "ogl", feature = "webgl"))]
+    #[test]
+    fn builtin_modules_compile_to_glsl(){
+        let mut cx = Cx::default();
+        let mut sh = crate::quad::Quad::def_quad_shader(&mut cx);
+        cx.import_shader_module(&mut sh, "color").unwrap();
+        cx.import_shader_module(&mut sh, "noise").unwrap();
+        sh.add_ast(shader_ast!({
+            fn pixel()->vec4{
+                let c:vec4 = color_rgb2hsv(color);
+                let n:float = noise_fbm2(pos * 8.0);
+                return color_linear_to_srgb(color_srgb_to_linear(color_hsv2rgb(vec4(c.x, c.y, n, 1.0))));
+            }
+        }));
+        let ash = match Cx::gl_assemble_shader(&sh, GLShaderType::OpenGL){
+            Ok(ash)=>ash,
+            Err(err)=>panic!("shader doesn't assemble: {}", err.msg)
+        };
+        for name in &["color_hsv2rgb", "color_rgb2hsv", "color_srgb_to_linear", "color_linear_to_srgb", "noise_fbm2", "noise_value2", "noise_hash2", "noise_HASH_SCALE"]{
+            assert!(ash.fragment.contains(name), "{} is missing from\n{}", name, ash.fragment);
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use crate::cx::*;
+use std::rc::Rc;
 //use std::iter::Peekable;
 
 #[derive(Clone)]
@@ -13,6 +14,7 @@ pub enum Wrapping{
 #[derive(Clone)]
 pub struct Text{
     pub font_id:usize,
+    pub font_fallbacks:Vec<usize>, // tried in order for glyphs font_id doesnt have
     pub shader_id:usize,
     pub text:String,
     pub color: Color,
@@ -20,6 +22,10 @@ pub struct Text{
     pub brightness:f32,
     pub line_spacing:f32,
     pub wrapping:Wrapping,
+    pub direction:TextDirection, // base direction of paragraphs, mixed text is reordered per line
+    pub ligatures:bool, // substitute ligatures of ttf fonts, combining marks are always positioned
+    pub monospace:bool, // every char takes a cell of the primary font, glyphs of fallback fonts are centered in theirs
+    pub _fallback_insts:Vec<(usize, AlignedInstance)>
 }
 
 impl Style for Text{
@@ -28,11 +34,16 @@ impl Style for Text{
         Self{
             shader_id:cx.add_shader(sh, "Text"),
             font_id:cx.load_font(&cx.font("normal_font")),
+            font_fallbacks:Vec::new(),
+            _fallback_insts:Vec::new(),
             text:"".to_string(),
             font_size:cx.size("font_size") as f32,
             line_spacing:1.15,
             brightness:1.0,
             wrapping:Wrapping::Word,
+            direction:TextDirection::Auto,
+            ligatures:false,
+            monospace:false,
             color:color("white")
         }
     }
@@ -75,7 +86,15 @@ impl Text{
             let clipped:vec2<Varying>;
             let rect:vec4<Varying>;
             let brightness:float<Uniform>;
+            let sdf_range:float<Uniform>;
             fn pixel()->vec4{
+                if font_tc.x < -0.5{ // missing glyph box
+                    df_viewport(clipped);
+                    let lo = min(rect.xy, rect.zw);
+                    let hi = max(rect.xy, rect.zw);
+                    df_rect(lo.x + 0.5, lo.y + 0.5, hi.x - lo.x - 1., hi.y - lo.y - 1.);
+                    return df_stroke(color, 1.);
+                }
                 if marker>0.5{
                     df_viewport(clipped);
                     let center = (rect.xy+rect.zw)*0.5;
@@ -85,6 +104,11 @@ impl Text{
                 else{
                     let s = sample2d(texture, tex_coord.xy);
                     let sig_dist =  max(min(s.r, s.g), min(max(s.r, s.g), s.b)) - 0.5;
+                    if sdf_range > 0.5{ // rasterized at load, the range turns the median into atlas pixels
+                        df_viewport(tex_coord * tex_size);
+                        df_shape = -sig_dist * sdf_range - 0.5 / df_aa;
+                        return df_fill(color*brightness);
+                    }
                     let scale = pow(df_antialias(clipped) * 0.002,0.5);
                     df_viewport(tex_coord * tex_size * 0.1);
                     df_shape = -sig_dist - 0.5 / df_aa;
@@ -130,145 +154,414 @@ impl Text{
         if !cx.fonts[self.font_id].loaded{
             self.font_id = 0;
         }
-        let aligned = cx.new_aligned_instance(self.shader_id, 0);
+        self._fallback_insts.truncate(0);
+        self.new_font_instance(cx, self.font_id)
+    }
+
+    // every font has its own texture, so it gets its own instance batch
+    fn new_font_instance(&mut self, cx:&mut Cx, font_id:usize)->AlignedInstance{
+        let aligned = cx.new_aligned_texture_instance(self.shader_id, cx.fonts[font_id].texture_id, 0);
         if aligned.inst.need_uniforms_now(cx){
             //texture,
-            aligned.inst.push_uniform_texture_2d(cx, cx.fonts[self.font_id].texture_id);
+            aligned.inst.push_uniform_texture_2d(cx, cx.fonts[font_id].texture_id);
             //tex_size
-            aligned.inst.push_uniform_vec2f(cx, cx.fonts[self.font_id].width as f32, cx.fonts[self.font_id].height as f32);
+            aligned.inst.push_uniform_vec2f(cx, cx.fonts[font_id].width as f32, cx.fonts[font_id].height as f32);
             aligned.inst.push_uniform_float(cx, self.brightness);
+            aligned.inst.push_uniform_float(cx, cx.fonts[font_id].sdf_range);
             //list_clip
             //area.push_uniform_vec4f(cx, -50000.0,-50000.0,50000.0,50000.0);
         }
         return aligned
     }
 
-    pub fn add_text<F>(&mut self, cx:&mut Cx, geom_x:f32, geom_y:f32, char_offset:usize, aligned:&mut AlignedInstance, chunk:&[char], mut char_callback:F)
+    // the first font of the fallback chain that has the glyph, the primary font draws the missing box
+    pub fn glyph_font_id(&self, cx:&mut Cx, c:char)->usize{
+        cx.load_font_glyphs(self.font_id, &[c]);
+        if cx.fonts[self.font_id].has_glyph(c as u32){
+            return self.font_id
+        }
+        for font_id in &self.font_fallbacks{
+            if !cx.fonts[*font_id].loaded{
+                continue
+            }
+            cx.load_font_glyphs(*font_id, &[c]);
+            if cx.fonts[*font_id].has_glyph(c as u32){
+                return *font_id
+            }
+        }
+        self.font_id
+    }
+
+    // char_callback gets (unicode, offset, x, w) in logical order, x is the edge a cursor
+    // before the char sits at, so the right edge of right to left chars
+    pub fn add_text<F>(&mut self, cx:&mut Cx, geom_x:f32, geom_y:f32, char_offset:usize, aligned:&mut AlignedInstance, chunk:&[char], char_callback:F)
     where F: FnMut(usize, usize, f32, f32)->f32
     {
-        let mut geom_x = geom_x;
-        let mut char_offset = char_offset;
-        let unicodes = &cx.fonts[self.font_id].unicodes;
-        let glyphs = &cx.fonts[self.font_id].glyphs;
-        let instance = {
-            let draw_list = &mut cx.draw_lists[aligned.inst.draw_list_id];
-            let draw_call = &mut draw_list.draw_calls[ aligned.inst.draw_call_id];
-            &mut draw_call.instance
-        };
+        let offsets:Vec<usize> = (char_offset..char_offset + chunk.len()).collect();
+        let (levels, para_level) = self.chunk_levels(chunk);
+        self.add_text_levels(cx, geom_x, geom_y, aligned, chunk, &levels, para_level, &offsets, char_callback);
+    }
+
+    fn chunk_levels(&self, chunk:&[char])->(Vec<u8>, u8){
+        if self.direction == TextDirection::Rtl || bidi_has_rtl(chunk){
+            let bidi = bidi_levels(chunk, self.direction);
+            let para_level = bidi.para_levels.first().cloned().unwrap_or(0);
+            (bidi.levels, para_level)
+        }
+        else{
+            (vec![0; chunk.len()], 0)
+        }
+    }
+
+    // emits one laid out line of chars, instances go out in visual order
+    fn add_text_levels<F>(&mut self, cx:&mut Cx, geom_x:f32, geom_y:f32, aligned:&mut AlignedInstance, chunk:&[char], levels:&[u8], para_level:u8, offsets:&[usize], mut char_callback:F)
+    where F: FnMut(usize, usize, f32, f32)->f32
+    {
+        let layout = self.layout_text(cx, chunk, levels, para_level);
+        let font_size = self.font_size;
+
+        let mut markers = vec![0.0; chunk.len()];
+        let mut visual = vec![0; chunk.len()];
+        for (i, glyph) in layout.glyphs.iter().enumerate(){
+            visual[glyph.logical] = i;
+        }
+        for logical in 0..chunk.len(){
+            let glyph = &layout.glyphs[visual[logical]];
+            let edge = if levels[logical] & 1 == 1{glyph.x + glyph.w}else{glyph.x};
+            let x = geom_x + edge * font_size;
+            markers[logical] = char_callback(chunk[logical] as usize, offsets[logical], x, glyph.w * font_size);
+        }
 
-        for wc in chunk{
-            let unicode = *wc as usize;
-            let slot = unicodes[unicode as usize];
-            let glyph = &glyphs[slot];
-            let w = glyph.advance * self.font_size;
-            let marker = char_callback(unicode, char_offset, geom_x, w);
+        for lg in &layout.glyphs{
+            // a ligature draws on its first char, the others are empty so cursors and hit testing stay per char
+            let (geom, tc) = if let Some(slot) = lg.slot{
+                let glyph = &cx.fonts[lg.font_id].glyphs[slot];
+                ([glyph.x1 + lg.dx, glyph.y1 + lg.dy, glyph.x2 + lg.dx, glyph.y2 + lg.dy], [glyph.tx1, glyph.ty1, glyph.tx2, glyph.ty2])
+            }
+            else{
+                ([0.0, 0.0, lg.w, 0.0], [0.0; 4])
+            };
             let data = [
-                /*font_geom*/ glyph.x1 ,glyph.y1 ,glyph.x2 ,glyph.y2,
-                /*font_tc*/ glyph.tx1 ,glyph.ty1 ,glyph.tx2 ,glyph.ty2,
+                /*font_geom*/ geom[0], geom[1], geom[2], geom[3],
+                /*font_tc*/ tc[0], tc[1], tc[2], tc[3],
                 /*color*/ self.color.r, self.color.g, self.color.b, self.color.a,
-                /*x*/ geom_x,
+                /*x*/ geom_x + lg.x * font_size,
                 /*y*/ geom_y,
                 // /*w*/ w,
                 // /*h*/ height,
-                /*font_size*/ self.font_size,
-                /*char_offset*/ char_offset as f32,
-                /*marker*/ marker,
+                /*font_size*/ font_size,
+                /*char_offset*/ offsets[lg.logical] as f32,
+                /*marker*/ markers[lg.logical],
                 // /*font_base*/ 1.0
             ];
-            instance.extend_from_slice(&data);
+            let inst = if lg.font_id == self.font_id{
+                &mut aligned.inst
+            }
+            else{
+                let index = if let Some(index) = self._fallback_insts.iter().position(|(id, _)| *id == lg.font_id){
+                    index
+                }
+                else{
+                    let fallback = self.new_font_instance(cx, lg.font_id);
+                    self._fallback_insts.push((lg.font_id, fallback));
+                    self._fallback_insts.len() - 1
+                };
+                &mut self._fallback_insts[index].1.inst
+            };
+            let draw_call = &mut cx.draw_lists[inst.draw_list_id].draw_calls[inst.draw_call_id];
+            draw_call.instance.extend_from_slice(&data);
             /*
             for i in 0..15{
                 instance.push(0.)
             }*/
-            
-            geom_x += w;
-            char_offset += 1;
-            aligned.inst.instance_count += 1;
+            inst.instance_count += 1;
+        }
+    }
+
+    // the same strings get drawn every frame, so layouts are cached in cx
+    pub fn layout_text(&self, cx:&mut Cx, chunk:&[char], levels:&[u8], para_level:u8)->Rc<TextLayout>{
+        let key = TextLayoutKey{
+            font_id:self.font_id,
+            font_fallbacks:&self.font_fallbacks,
+            ligatures:self.ligatures,
+            monospace:self.monospace,
+            para_level:para_level,
+            levels:if levels.iter().all(|l| *l == 0){&[]}else{levels},
+            chars:chunk
+        };
+        if let Some(layout) = cx.text_layouts.get(&key){
+            return layout
+        }
+        let layout = Rc::new(self.layout_text_uncached(cx, chunk, levels, para_level));
+        cx.text_layouts.insert(&key, layout.clone());
+        layout
+    }
+
+    pub fn layout_text_uncached(&self, cx:&mut Cx, chunk:&[char], levels:&[u8], para_level:u8)->TextLayout{
+        let font_ids:Vec<usize> = chunk.iter().map(|c| self.glyph_font_id(cx, *c)).collect();
+        let cell = if self.monospace{
+            let font = &cx.fonts[self.font_id];
+            Some(font.glyphs[font.glyph_slot(33)].advance)
         }
+        else{
+            None
+        };
+        let mut layout = TextLayout{
+            glyphs:Vec::with_capacity(chunk.len()),
+            width:0.0
+        };
+        for (start, end, level) in bidi_line_runs(chunk, levels, para_level){
+            let mut index = start;
+            let mut glyphs = Vec::new();
+            for shaped in self.shape_chunk(cx, &chunk[start..end], &font_ids[start..end]){
+                glyphs.push((index, shaped));
+                index += shaped.1;
+            }
+            // right to left runs reverse their glyphs, keeping attached marks behind their base
+            if level & 1 == 1{
+                let mut groups:Vec<Vec<(usize, (usize, usize, f32, f32, f32))>> = Vec::new();
+                for glyph in glyphs{
+                    match groups.last_mut(){
+                        Some(group) if (glyph.1).4 == 0.0=>group.push(glyph),
+                        _=>groups.push(vec![glyph])
+                    }
+                }
+                glyphs = groups.into_iter().rev().flatten().collect();
+            }
+            for (index, (slot, chars, dx, dy, advance)) in glyphs{
+                let (w, dx) = match cell{
+                    Some(cell) if advance != 0.0=>(cell, dx + (cell * chars as f32 - advance) * 0.5),
+                    Some(cell)=>(cell, dx),
+                    None=>(advance / chars as f32, dx)
+                };
+                for i in 0..chars{
+                    layout.glyphs.push(TextLayoutGlyph{
+                        logical:if level & 1 == 1{index + chars - 1 - i}else{index + i},
+                        font_id:font_ids[index],
+                        slot:if i == 0{Some(slot)}else{None},
+                        x:layout.width,
+                        w:w,
+                        dx:dx,
+                        dy:dy
+                    });
+                    layout.width += w;
+                }
+            }
+        }
+        layout
+    }
+
+    // glyphs as (slot, chars, dx, dy, advance) in ems, runs of a ttf font go through
+    // the shaper, other fonts map one glyph per char
+    pub fn shape_chunk(&self, cx:&mut Cx, chunk:&[char], font_ids:&[usize])->Vec<(usize, usize, f32, f32, f32)>{
+        let mut out = Vec::new();
+        let mut start = 0;
+        while start < chunk.len(){
+            let font_id = font_ids[start];
+            let mut end = start + 1;
+            while end < chunk.len() && font_ids[end] == font_id{
+                end += 1;
+            }
+            let first = out.len();
+            if let Some(ttf) = cx.fonts[font_id].ttf.clone(){
+                let mut index = start;
+                for shaped in ttf.shape(&chunk[start..end], self.ligatures){
+                    let slot = if shaped.chars == 1{
+                        cx.fonts[font_id].glyph_slot(chunk[index] as u32)
+                    }
+                    else{
+                        cx.load_font_glyph_id(font_id, shaped.glyph)
+                    };
+                    let em = ttf.units_per_em;
+                    out.push((slot, shaped.chars, shaped.dx / em, shaped.dy / em, shaped.advance / em));
+                    index += shaped.chars;
+                }
+            }
+            else{
+                for c in &chunk[start..end]{
+                    let slot = cx.fonts[font_id].glyph_slot(*c as u32);
+                    out.push((slot, 1, 0.0, 0.0, cx.fonts[font_id].glyphs[slot].advance));
+                }
+            }
+            // pair kerning between neighbouring single char glyphs, marks keep their zero advance
+            let font = &cx.fonts[font_id];
+            let mut index = start;
+            for i in first..out.len(){
+                if i + 1 < out.len() && out[i].1 == 1 && out[i + 1].1 == 1 && out[i + 1].4 != 0.0{
+                    out[i].4 += font.get_kern(chunk[index] as u32, chunk[index + 1] as u32);
+                }
+                index += out[i].1;
+            }
+            start = end;
+        }
+        out
     }
   
     pub fn end_text(&mut self, cx:&mut Cx, aligned:&AlignedInstance){
         cx.update_aligned_instance_count(aligned);
+        for (_, fallback) in &self._fallback_insts{
+            cx.update_aligned_instance_count(fallback);
+        }
+        self._fallback_insts.truncate(0);
     }
 
     pub fn draw_text(&mut self, cx:&mut Cx, text:&str)->Area{
         let mut aligned = self.begin_text(cx);
 
-        let mut chunk = Vec::new();
-        let mut width = 0.0;
-        let mut elipct = 0;
-        let font_size = self.font_size;
-        let mut iter = text.chars().peekable();
+        let mut chars:Vec<char> = text.chars().collect();
+        let mut offsets:Vec<usize> = (0..chars.len()).collect();
+        if let Wrapping::Ellipsis(ellipsis_width) = self.wrapping{
+            let max_width = ellipsis_width.min(cx.bounded_width_left());
+            self.ellipsize(cx, max_width, &mut chars, &mut offsets);
+        }
 
-        while let Some(c) = iter.next(){
-            let last = iter.peek().is_none();
+        // text with right to left chars is laid out per line after all chunks are walked
+        let bidi = if self.direction == TextDirection::Rtl || bidi_has_rtl(&chars){
+            Some(bidi_levels(&chars, self.direction))
+        }
+        else{
+            None
+        };
+        let mut lines:Vec<(Rect, Vec<char>, Vec<usize>)> = Vec::new();
 
-            let mut emit = last;
-            let slot = if c < '\u{10000}'{
-                cx.fonts[self.font_id].unicodes[c as usize]
-            } else{
-                0
+        let widths = self.char_widths(cx, &chars);
+        let breaks = match self.wrapping{
+            Wrapping::Char | Wrapping::Word | Wrapping::Line=>line_breaks(&chars),
+            _=>Vec::new()
+        };
+        let line_width = cx.width_total(false);
+        let mut start = 0;
+        let mut width = 0.0;
+        for i in 0..chars.len(){
+            width += widths[i];
+            let last = i + 1 == chars.len();
+            let brk = if breaks.len() > 0{breaks[i]}else{LineBreak::None};
+            let emit = last || match self.wrapping{
+                // anywhere, but marks stay with their base
+                Wrapping::Char=>brk != LineBreak::None || match line_break_class(chars[i + 1]){
+                    LineBreakClass::CM | LineBreakClass::ZWJ | LineBreakClass::LF=>false,
+                    _=>true
+                },
+                Wrapping::Word=>brk != LineBreak::None,
+                Wrapping::Line=>brk == LineBreak::Mandatory,
+                Wrapping::None | Wrapping::Ellipsis(_)=>false
             };
-
-            if slot != 0 {
-                let glyph = &cx.fonts[self.font_id].glyphs[slot];
-                width += glyph.advance * self.font_size;
-                match self.wrapping{
-                    Wrapping::Char=>{
-                        chunk.push(c);
-                        emit = true
-                    },
-                    Wrapping::Word=>{
-                        chunk.push(c);
-                        if c == 32 as char || c == 9 as char|| c == 10 as char|| c == 13 as char{
-                            emit = true;
-                        }
-                    },
-                    Wrapping::Line=>{
-                        chunk.push(c);
-                        if c == 10 as char|| c == 13 as char{
-                            emit = true;
-                        }
-                    },
-                    Wrapping::None=>{
-                        chunk.push(c);
-                    },
-                    Wrapping::Ellipsis(ellipsis_width)=>{
-                        if width>ellipsis_width{ // output ...
-                            if elipct < 3{
-                                chunk.push('.');
-                                elipct += 1;
-                            }
-                        }
-                        else{
-                            chunk.push(c)
-                        }
+            if !emit{
+                continue
+            }
+            let split = match self.wrapping{
+                Wrapping::Word=>width > line_width,
+                _=>false
+            };
+            if split{ // a word wider than a line breaks where it overflows
+                let mut piece_start = start;
+                let mut piece_width = 0.0;
+                for j in start..=i{
+                    if j > piece_start && piece_width + widths[j] > line_width{
+                        self.walk_chunk(cx, &mut aligned, &chars[piece_start..j], &offsets[piece_start..j], piece_width, &bidi, &mut lines);
+                        piece_start = j;
+                        piece_width = 0.0;
                     }
+                    piece_width += widths[j];
                 }
+                self.walk_chunk(cx, &mut aligned, &chars[piece_start..=i], &offsets[piece_start..=i], piece_width, &bidi, &mut lines);
             }
-            if emit{
-                let height = font_size * self.line_spacing;
-                let geom = cx.walk_turtle(
-                    Bounds::Fix(width), 
-                    Bounds::Fix(height), 
-                    Margin::zero(),
-                    None
-                );
-
-                self.add_text(cx, geom.x, geom.y, 0, &mut aligned, &chunk, |_,_,_,_|{0.0});
-                width = 0.0;
-                chunk.truncate(0);
-                match self.wrapping{
-                    Wrapping::Line=>{
-                        cx.turtle_new_line();
-                    },
-                    _=>()
-                    
-                }
+            else{
+                self.walk_chunk(cx, &mut aligned, &chars[start..=i], &offsets[start..=i], width, &bidi, &mut lines);
             }
+            let new_line = match self.wrapping{
+                Wrapping::Line=>true,
+                _=>brk == LineBreak::Mandatory && (!last || match line_break_class(chars[i]){
+                    LineBreakClass::BK | LineBreakClass::CR | LineBreakClass::LF | LineBreakClass::NL=>true,
+                    _=>false
+                })
+            };
+            if new_line{
+                cx.turtle_new_line();
+            }
+            start = i + 1;
+            width = 0.0;
         }
+        if let Some(bidi) = bidi{
+            for (geom, chars, offsets) in lines{
+                let levels:Vec<u8> = offsets.iter().map(|o| bidi.levels[*o]).collect();
+                let para_level = bidi.para_levels[offsets[0]];
+                self.add_text_levels(cx, geom.x, geom.y, &mut aligned, &chars, &levels, para_level, &offsets, |_,_,_,_|{0.0});
+            }
+        }
+        // glyphs from fallback fonts are in draw calls of their own, the area covers them too
+        let mut members = vec![aligned.inst];
+        members.extend(self._fallback_insts.iter().map(|(_, fallback)| fallback.inst));
         self.end_text(cx, &aligned);
-        aligned.inst.into_area()
+        cx.new_area_group(&members)
+    }
+
+    fn walk_chunk(&mut self, cx:&mut Cx, aligned:&mut AlignedInstance, chunk:&[char], offsets:&[usize], width:f32, bidi:&Option<BidiLevels>, lines:&mut Vec<(Rect, Vec<char>, Vec<usize>)>){
+        let height = self.font_size * self.line_spacing;
+        let geom = cx.walk_turtle(
+            Bounds::Fix(width),
+            Bounds::Fix(height),
+            Margin::zero(),
+            None
+        );
+        if bidi.is_none(){
+            self.add_text_levels(cx, geom.x, geom.y, aligned, chunk, &vec![0; chunk.len()], 0, offsets, |_,_,_,_|{0.0});
+            return
+        }
+        match lines.last_mut(){
+            Some(line) if line.0.y == geom.y=>{
+                let x2 = (line.0.x + line.0.w).max(geom.x + geom.w);
+                line.0.x = line.0.x.min(geom.x);
+                line.0.w = x2 - line.0.x;
+                line.1.extend_from_slice(chunk);
+                line.2.extend_from_slice(offsets);
+            },
+            _=>lines.push((geom, chunk.to_vec(), offsets.to_vec()))
+        }
+    }
+
+    // advance per char in pixels, from the cached layout of the whole text
+    pub fn char_widths(&self, cx:&mut Cx, chars:&[char])->Vec<f32>{
+        let layout = self.layout_text(cx, chars, &vec![0; chars.len()], 0);
+        let mut widths = vec![0.0; chars.len()];
+        for glyph in &layout.glyphs{
+            widths[glyph.logical] = glyph.w * self.font_size;
+        }
+        widths
+    }
+
+    // cuts the text to fit max_width, ending in the fonts ellipsis char or three dots
+    fn ellipsize(&self, cx:&mut Cx, max_width:f32, chars:&mut Vec<char>, offsets:&mut Vec<usize>){
+        let widths = self.char_widths(cx, chars);
+        if max_width.is_nan() || widths.iter().sum::<f32>() <= max_width{
+            return
+        }
+        let font_id = self.glyph_font_id(cx, '\u{2026}');
+        let ellipsis = if cx.fonts[font_id].has_glyph(0x2026){vec!['\u{2026}']}else{vec!['.'; 3]};
+        let ellipsis_width:f32 = self.char_widths(cx, &ellipsis).iter().sum();
+        let mut end = 0;
+        let mut width = ellipsis_width;
+        while end < chars.len() && width + widths[end] <= max_width{
+            width += widths[end];
+            end += 1;
+        }
+        // keep marks with their base and drop the spaces before the ellipsis
+        while end > 0 && match line_break_class(chars[end]){
+            LineBreakClass::CM | LineBreakClass::ZWJ=>true,
+            _=>false
+        }{
+            end -= 1;
+        }
+        while end > 0 && chars[end - 1].is_whitespace(){
+            end -= 1;
+        }
+        let offset = offsets[end];
+        chars.truncate(end);
+        offsets.truncate(end);
+        for c in ellipsis{
+            chars.push(c);
+            offsets.push(offset);
+        }
     }
 
     pub fn find_closest_offset(&self, cx:&Cx, area:&Area, pos:Vec2)->usize{
@@ -287,6 +580,16 @@ impl Text{
         let line_spacing = self.line_spacing;
         let mut index = 0;
         if let Some(read) = read{
+            // right to left chars show up as char offsets running backwards along a line,
+            // a cursor at their left edge is after them and one at their right edge before them
+            let edge_of = |index:usize, right:bool|->usize{
+                let get = |o:usize, index:usize| read.buffer[read.offset + o + index * read.slots];
+                let y = get(y_o, index);
+                let offset = get(char_offset_o, index);
+                let rtl = (index + 1 < read.count && get(y_o, index + 1) == y && get(char_offset_o, index + 1) < offset) ||
+                    (index > 0 && get(y_o, index - 1) == y && get(char_offset_o, index - 1) > offset);
+                offset as usize + if rtl != right{1}else{0}
+            };
             while index < read.count{
                 let y = read.buffer[read.offset + y_o + index * read.slots];
                 let font_size = read.buffer[read.offset + font_size_o + index * read.slots];
@@ -298,7 +601,7 @@ impl Text{
                         let w = read.buffer[read.offset + font_geom_o + index * read.slots] * font_size;
                         if x > spos.x + w*0.5 || y > spos.y{
                             let prev_index = if index == 0{0}else{index - 1};
-                            return read.buffer[read.offset + char_offset_o +  prev_index * read.slots] as usize;
+                            return edge_of(prev_index, false);
                         }
                         index += 1;
                     }
@@ -308,13 +611,17 @@ impl Text{
             if read.count == 0{
                 return 0
             }
-            return read.buffer[read.offset + char_offset_o +  (read.count - 1) * read.slots] as usize;
+            // on the last char, its nearest edge
+            let last = read.count - 1;
+            let x = read.buffer[read.offset + x_o + last * read.slots];
+            let w = read.buffer[read.offset + font_geom_o + last * read.slots] * read.buffer[read.offset + font_size_o + last * read.slots];
+            return edge_of(last, spos.x > x + w*0.5);
         }
         return 0
     }
 
     pub fn get_monospace_size(&self, cx:&Cx, font_size:Option<f32>)->Vec2{
-        let slot = cx.fonts[self.font_id].unicodes[33 as usize];
+        let slot = cx.fonts[self.font_id].glyph_slot(33);
         let glyph = &cx.fonts[self.font_id].glyphs[slot];
         let font_size = if let Some(font_size) = font_size{font_size}else{self.font_size};
         Vec2{
@@ -322,4 +629,333 @@ impl Text{
             y:self.line_spacing * font_size
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(all(test, feature = "ogl"))]
+mod tests{
+    use super::*;
+
+    // a monospace font, every char is as wide as the first of advance
+    fn text_cx()->(Cx, Text, f32){
+        let mut cx = Cx::new_headless(Vec2{x:1000., y:600.});
+        cx.set_font("normal_font", "../../resources/ubuntu_mono_256.font");
+        cx.set_size("font_size", 10.0);
+        let text = Text::style(&mut cx);
+        cx.headless_compile().expect("shaders compile");
+        assert!(cx.fonts[text.font_id].loaded);
+        let advance = text.char_widths(&mut cx, &['a'])[0];
+        (cx, text, advance)
+    }
+
+    // draws the text in a box width wide, and gives back the drawn chars per line
+    fn draw_lines(cx:&mut Cx, text:&mut Text, s:&str, width:f32)->Vec<String>{
+        let area = cx.headless_draw(|cx|{
+            cx.begin_turtle(&Layout{width:Bounds::Fix(width), height:Bounds::Fix(500.), line_wrap:LineWrap::NewLine, ..Default::default()}, Area::Empty);
+            let area = text.draw_text(cx, s);
+            cx.end_turtle(Area::Empty);
+            area
+        });
+
+        let inst = if let Area::Instance(inst) = area{inst}else{panic!("text has no instances")};
+        let draw_call = &cx.draw_lists[inst.draw_list_id].draw_calls[inst.draw_call_id];
+        let csh = &cx.compiled_shaders[draw_call.shader_id];
+        let prop = |name:&str| csh.named_instance_props.props.iter().find(|p| p.name == name).unwrap().offset;
+        let (x, y, offset) = (prop("x"), prop("y"), prop("char_offset"));
+        let chars:Vec<char> = s.chars().collect();
+        let mut glyphs:Vec<(f32, f32, char)> = draw_call.instance.chunks(csh.instance_slots).map(|inst|{
+            (inst[y], inst[x], chars[inst[offset] as usize])
+        }).collect();
+        glyphs.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let mut lines:Vec<(f32, String)> = Vec::new();
+        for (y, _, c) in glyphs{
+            match lines.last_mut(){
+                Some(line) if line.0 == y=>line.1.push(c),
+                _=>lines.push((y, c.to_string()))
+            }
+        }
+        lines.into_iter().map(|(_, line)| line).collect()
+    }
+
+    // the mono_font_fallback of the dark style
+    const MONO_TTF:&str = "../../resources/DejaVuSansMono.ttf";
+
+    // the prebaked mono font falling back to a ttf with box drawing
+    fn fallback_cx()->(Cx, Text){
+        let mut cx = Cx::new_headless(Vec2{x:1000., y:600.});
+        cx.set_font("normal_font", "../../resources/ubuntu_mono_256.font");
+        cx.set_size("font_size", 10.0);
+        let mut text = Text::style(&mut cx);
+        text.font_fallbacks = vec![cx.load_font(MONO_TTF)];
+        cx.headless_compile().expect("shaders compile");
+        assert!(cx.fonts[text.font_fallbacks[0]].loaded);
+        (cx, text)
+    }
+
+    // (char_offset, x, y) of every glyph the text shader drew, from all its batches
+    fn drawn_glyphs(cx:&Cx, text:&Text)->Vec<(usize, f32, f32)>{
+        let draw_list = &cx.draw_lists[0];
+        let mut glyphs = Vec::new();
+        for draw_call in draw_list.draw_calls[0..draw_list.draw_calls_len].iter().filter(|dc| dc.shader_id == text.shader_id){
+            let csh = &cx.compiled_shaders[draw_call.shader_id];
+            let prop = |name:&str| csh.named_instance_props.props.iter().find(|p| p.name == name).unwrap().offset;
+            let (x, y, offset) = (prop("x"), prop("y"), prop("char_offset"));
+            for inst in draw_call.instance.chunks(csh.instance_slots){
+                glyphs.push((inst[offset] as usize, inst[x], inst[y]));
+            }
+        }
+        glyphs.sort_by_key(|g| g.0);
+        glyphs
+    }
+
+    fn ellipsized(cx:&mut Cx, text:&Text, s:&str, max_width:f32)->String{
+        let mut chars:Vec<char> = s.chars().collect();
+        let mut offsets:Vec<usize> = (0..chars.len()).collect();
+        text.ellipsize(cx, max_width, &mut chars, &mut offsets);
+        chars.into_iter().collect()
+    }
+
+    #[test]
+    fn word_wraps_at_break_opportunities(){
+        let (mut cx, mut text, advance) = text_cx();
+        text.wrapping = Wrapping::Word;
+        assert_eq!(draw_lines(&mut cx, &mut text, "one two three", 9. * advance), vec!["one two ", "three"]);
+        // hyphens are break opportunities as well, and a newline always breaks
+        assert_eq!(draw_lines(&mut cx, &mut text, "well-known x", 7. * advance), vec!["well-", "known x"]);
+        assert_eq!(draw_lines(&mut cx, &mut text, "a\nb c", 100. * advance), vec!["a\n", "b c"]);
+        // a word wider than the line breaks where it overflows
+        assert_eq!(draw_lines(&mut cx, &mut text, "abcdefghij k", 4. * advance), vec!["abcd", "efgh", "ij k"]);
+    }
+
+    #[test]
+    fn char_wraps_anywhere(){
+        let (mut cx, mut text, advance) = text_cx();
+        text.wrapping = Wrapping::Char;
+        assert_eq!(draw_lines(&mut cx, &mut text, "one two", 3. * advance), vec!["one", " tw", "o"]);
+        // but keeps marks with their base
+        assert_eq!(draw_lines(&mut cx, &mut text, "abe\u{301}", 3. * advance), vec!["ab", "e\u{301}"]);
+    }
+
+    #[test]
+    fn line_wraps_only_at_newlines(){
+        let (mut cx, mut text, advance) = text_cx();
+        text.wrapping = Wrapping::Line;
+        assert_eq!(draw_lines(&mut cx, &mut text, "one two\nthree", 3. * advance), vec!["one two\n", "three"]);
+    }
+
+    #[test]
+    fn none_keeps_one_line(){
+        let (mut cx, mut text, advance) = text_cx();
+        text.wrapping = Wrapping::None;
+        assert_eq!(draw_lines(&mut cx, &mut text, "one two three", 3. * advance), vec!["one two three"]);
+    }
+
+    #[test]
+    fn ellipsis_cuts_to_the_width(){
+        let (mut cx, mut text, advance) = text_cx();
+        // use the fonts ellipsis, one char wide like every char here
+        let dot = cx.fonts[text.font_id].unicodes.get('.' as u32).unwrap();
+        cx.fonts[text.font_id].unicodes.insert(0x2026, dot);
+        assert_eq!(ellipsized(&mut cx, &text, "short", 5. * advance), "short");
+        assert_eq!(ellipsized(&mut cx, &text, "a longer title", 6. * advance), "a lon\u{2026}");
+        // spaces before the ellipsis go, marks stay with their base
+        assert_eq!(ellipsized(&mut cx, &text, "ab cd", 4. * advance), "ab\u{2026}");
+        assert_eq!(ellipsized(&mut cx, &text, "abe\u{301}f", 4. * advance), "ab\u{2026}");
+        assert_eq!(ellipsized(&mut cx, &text, "abcdef", 0.), "\u{2026}");
+
+        // drawn it is one line, cut to the Ellipsis width or the width the turtle has left
+        text.wrapping = Wrapping::Ellipsis(6. * advance);
+        assert_eq!(draw_lines(&mut cx, &mut text, "a longer title", 100. * advance).concat().chars().count(), 6);
+        assert_eq!(draw_lines(&mut cx, &mut text, "a longer title", 4. * advance).concat().chars().count(), 4);
+        assert_eq!(draw_lines(&mut cx, &mut text, "fits", 4. * advance), vec!["fits"]);
+    }
+
+    #[test]
+    fn ellipsis_falls_back_to_dots(){
+        let (mut cx, text, advance) = text_cx();
+        let missing = cx.fonts[text.font_id].missing_slot;
+        cx.fonts[text.font_id].unicodes.insert(0x2026, missing);
+        assert!(!cx.fonts[text.font_id].has_glyph(0x2026));
+        assert_eq!(ellipsized(&mut cx, &text, "a longer title", 6. * advance), "a l...");
+        assert_eq!(ellipsized(&mut cx, &text, "a longer title", 5. * advance), "a...");
+    }
+
+    #[test]
+    fn crlf_lines_draw_no_box_for_the_return(){
+        let (mut cx, mut text, advance) = text_cx();
+        text.wrapping = Wrapping::Line;
+        let lines = draw_lines(&mut cx, &mut text, "ab\r\ncd\r\n", 100. * advance);
+        assert_eq!(lines.iter().map(|line| line.trim_end()).collect::<Vec<_>>(), vec!["ab", "cd"]);
+        let glyphs = drawn_glyphs(&cx, &text);
+        let font = &cx.fonts[text.font_id];
+        assert_eq!(font.glyphs[font.glyph_slot('\r' as u32)].advance, 0.0);
+        assert!(font.has_glyph('\r' as u32));
+        // the return takes no room, the newline sits right after the b
+        assert!((glyphs[3].1 - glyphs[2].1).abs() < 0.01);
+        assert!((glyphs[2].1 - glyphs[1].1 - advance).abs() < 0.01);
+        // and the second line starts where the first did
+        assert!((glyphs[4].1 - glyphs[0].1).abs() < 0.01);
+    }
+
+    #[test]
+    fn hit_testing_rtl_text(){
+        let (mut cx, mut text, advance) = text_cx();
+        text.wrapping = Wrapping::Line;
+        // hebrew after latin, drawn as ab ' ' gimel bet alef
+        let area = cx.headless_draw(|cx|{
+            cx.begin_turtle(&Layout{width:Bounds::Fix(500.), height:Bounds::Fix(100.), ..Default::default()}, Area::Empty);
+            let area = text.draw_text(cx, "ab \u{5d0}\u{5d1}\u{5d2}");
+            cx.end_turtle(Area::Empty);
+            area
+        });
+        let glyphs = drawn_glyphs(&cx, &text);
+        let x0 = glyphs[0].1;
+        let visual:Vec<usize> = {
+            let mut by_x = glyphs.clone();
+            by_x.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+            by_x.iter().map(|g| g.0).collect()
+        };
+        assert_eq!(visual, vec![0, 1, 2, 5, 4, 3]);
+        let y = glyphs[0].2 + 0.5 * text.font_size;
+        let hit = |cx:&Cx, cols:f32| text.find_closest_offset(cx, &area, Vec2{x:x0 + cols * advance, y:y});
+        // ltr chars, the left half is before the char
+        assert_eq!(hit(&cx, 1.2), 1);
+        assert_eq!(hit(&cx, 1.8), 2);
+        // rtl chars have their logical start on the right, alef is the 6th visually
+        assert_eq!(hit(&cx, 5.8), 3);
+        assert_eq!(hit(&cx, 5.2), 4);
+        // the left half of gimel is the end of the text
+        assert_eq!(hit(&cx, 3.8), 5);
+        assert_eq!(hit(&cx, 3.2), 6);
+        // past the last char is its right edge, so before alef
+        assert_eq!(hit(&cx, 8.0), 3);
+    }
+
+    #[test]
+    fn text_in_a_grid_cell_follows_the_cell(){
+        let (mut cx, mut text, advance) = text_cx();
+        let draw = |cx:&mut Cx, text:&mut Text, grid:Option<Grid>|->Rect{
+            let mut rect = Rect::zero();
+            cx.headless_draw(|cx|{
+                cx.begin_turtle(&Layout{width:Bounds::Fix(300.), height:Bounds::Fix(200.), grid:grid, ..Default::default()}, Area::Empty);
+                cx.begin_turtle(&Layout{
+                    width:Bounds::Compute,
+                    height:Bounds::Compute,
+                    align:Align::center(),
+                    grid_item:GridItem{column:Some(1), ..Default::default()},
+                    ..Default::default()
+                }, Area::Empty);
+                text.draw_text(cx, "ab");
+                rect = cx.end_turtle(Area::Empty);
+                cx.end_turtle(Area::Empty);
+            });
+            rect
+        };
+        let measured = draw(&mut cx, &mut text, None);
+        let plain = drawn_glyphs(&cx, &text);
+        let grid = Grid{columns:vec![GridTrack::Fix(100.), GridTrack::Fix(200.)], rows:vec![GridTrack::Fix(50.)], ..Default::default()};
+        draw(&mut cx, &mut text, Some(grid));
+        let placed = drawn_glyphs(&cx, &text);
+        // centered in the second column
+        let dx = 100. + (200. - 2. * advance) * 0.5;
+        let dy = (50. - measured.h) * 0.5;
+        assert_eq!(placed.len(), 2);
+        for (p, g) in plain.iter().zip(&placed){
+            assert!((g.1 - p.1 - dx).abs() < 0.01 && (g.2 - p.2 - dy).abs() < 0.01, "{:?} moved to {:?}", p, g);
+        }
+    }
+
+    #[test]
+    fn fallback_glyphs_follow_alignment(){
+        let (mut cx, mut text) = fallback_cx();
+        assert!(!cx.fonts[text.font_id].has_glyph(0x2500));
+        let mut draw = |cx:&mut Cx, align:Align|{
+            cx.headless_draw(|cx|{
+                cx.begin_turtle(&Layout{width:Bounds::Fix(200.), height:Bounds::Fix(100.), align:align, ..Default::default()}, Area::Empty);
+                text.draw_text(cx, "a\u{2500}b");
+                cx.end_turtle(Area::Empty);
+            });
+            drawn_glyphs(cx, &text)
+        };
+        let left = draw(&mut cx, Align::left_top());
+        let centered = draw(&mut cx, Align::center());
+        assert_eq!(left.len(), 3);
+        // the box drawing char comes from its own batch and moves with the others
+        let (dx, dy) = (centered[0].1 - left[0].1, centered[0].2 - left[0].2);
+        assert!(dx > 50. && dy > 30.);
+        for (l, c) in left.iter().zip(&centered){
+            assert_eq!(l.0, c.0);
+            assert!((c.1 - l.1 - dx).abs() < 0.001 && (c.2 - l.2 - dy).abs() < 0.001, "glyph {} didn't align", l.0);
+        }
+    }
+
+    #[test]
+    fn draw_text_area_covers_fallback_glyphs(){
+        let (mut cx, mut text) = fallback_cx();
+        let (plain, mixed, only_fallback) = cx.headless_draw(|cx|{
+            (text.draw_text(cx, "ab"), text.draw_text(cx, "a\u{2500}b\u{2500}"), text.draw_text(cx, "\u{2500}"))
+        });
+        assert!(if let Area::Instance(inst) = plain{inst.instance_count == 2}else{false});
+        assert!(if let Area::Instance(inst) = only_fallback{inst.instance_count == 1}else{false});
+        assert!(if let Area::Group(_) = mixed{mixed.is_valid(&cx)}else{false});
+        let members = mixed.group_members(&cx);
+        let counts:Vec<usize> = members.iter().map(|m| if let Area::Instance(inst) = m{inst.instance_count}else{0}).collect();
+        assert_eq!(counts, vec![2, 2]);
+
+        // writes reach the glyphs of every font, reads come from the primary font
+        let red = color("#f00");
+        mixed.write_color(&mut cx, "color", red);
+        let color_o = mixed.get_prop_offset(&cx, "color");
+        for member in &members{
+            let read = member.get_read_ref(&cx).unwrap();
+            for i in 0..read.count{
+                let o = read.offset + color_o + i * read.slots;
+                assert_eq!(&read.buffer[o..o + 4], &[red.r, red.g, red.b, red.a]);
+            }
+        }
+        let rgba = |c:Color| [c.r, c.g, c.b, c.a];
+        assert_eq!(rgba(mixed.read_color(&cx, "color")), rgba(red));
+        assert_eq!(rgba(plain.read_color(&cx, "color")), rgba(text.color));
+
+        // the next redraw of the list drops the group
+        cx.headless_draw(|_|{});
+        assert!(!mixed.is_valid(&cx) && mixed.group_members(&cx).is_empty());
+    }
+
+    #[test]
+    fn monospace_puts_fallback_glyphs_on_the_cell_grid(){
+        let (mut cx, mut text) = fallback_cx();
+        let fallback = text.font_fallbacks[0];
+        // the fallback is monospace itself and has box drawing
+        let advances:Vec<f32> = ['a', 'W', '\u{2500}', '\u{2502}'].iter().map(|c|{
+            cx.load_font_glyphs(fallback, &[*c]);
+            let font = &cx.fonts[fallback];
+            assert!(font.has_glyph(*c as u32));
+            font.glyphs[font.glyph_slot(*c as u32)].advance
+        }).collect();
+        assert!(advances.iter().all(|a| *a == advances[0]));
+
+        let cell = text.get_monospace_size(&cx, None).x;
+        let chars:Vec<char> = "a\u{2500}\u{2500}b".chars().collect();
+        // dejavu is wider than the cells of ubuntu mono
+        let widths = text.char_widths(&mut cx, &chars);
+        assert!((widths[1] - cell).abs() > 0.5);
+
+        text.monospace = true;
+        assert!(text.char_widths(&mut cx, &chars).iter().all(|w| (w - cell).abs() < 0.001));
+        // the wider box glyph is centered in its cell
+        let layout = text.layout_text(&mut cx, &chars, &[0; 4], 0);
+        let cell_em = cell / text.font_size;
+        assert!((layout.glyphs[1].dx - (cell_em - advances[2]) * 0.5).abs() < 0.0001);
+        assert_eq!(layout.glyphs[0].dx, 0.0);
+
+        cx.headless_draw(|cx|{
+            cx.begin_turtle(&Layout{width:Bounds::Fix(200.), height:Bounds::Fix(100.), ..Default::default()}, Area::Empty);
+            text.draw_text(cx, "a\u{2500}\u{2500}b");
+            cx.end_turtle(Area::Empty);
+        });
+        let glyphs = drawn_glyphs(&cx, &text);
+        assert_eq!(glyphs.len(), 4);
+        for (i, glyph) in glyphs.iter().enumerate(){
+            assert!((glyph.1 - glyphs[0].1 - i as f32 * cell).abs() < 0.001, "char {} is off the grid", i);
+        }
+    }
+}
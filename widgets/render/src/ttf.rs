@@ -0,0 +1,2296 @@
+use std::collections::HashMap;
+
+// TrueType / OpenType parsing, enough to lay out and rasterize text:
+// cmap, hmtx, glyf or CFF outlines, pair kerning from kern or GPOS,
+// GSUB ligatures and GPOS mark attachment for shaping.
+// All values are in font units unless stated otherwise.
+
+#[derive(Clone, Default, Debug)]
+pub struct TTFCmapRange{
+    pub start:u32,
+    pub end:u32,
+    pub glyph:u32 // glyph of start, the range maps consecutively
+}
+
+#[derive(Clone, Default, Debug)]
+pub struct TTFOutline{
+    pub contours:Vec<Vec<(f32, f32)>>, // flattened closed polygons
+    pub x_min:f32,
+    pub y_min:f32,
+    pub x_max:f32,
+    pub y_max:f32
+}
+
+#[derive(Clone, Debug)]
+pub enum TTFPairKern{
+    Pairs(HashMap<(u16, u16), i16>),
+    Classes{
+        coverage:TTFCoverage,
+        class1:TTFClassDef,
+        class2:TTFClassDef,
+        class2_count:usize,
+        values:Vec<i16>
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum TTFCoverage{
+    Glyphs(Vec<u16>),
+    Ranges(Vec<(u16, u16)>)
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct TTFClassDef{
+    pub ranges:Vec<(u16, u16, u16)> // start, end, class
+}
+
+// GPOS mark to base or mark to mark attachment
+#[derive(Clone, Debug)]
+pub struct TTFMarkAttach{
+    pub lookup:u16,
+    pub to_marks:bool,
+    pub mark_coverage:TTFCoverage,
+    pub base_coverage:TTFCoverage,
+    pub marks:Vec<(u16, i16, i16)>, // class, anchor x, anchor y
+    pub bases:Vec<Vec<Option<(i16, i16)>>> // anchor per mark class
+}
+
+// the lookups the default language system of a script enables, in lookup order
+#[derive(Clone, Debug, Default)]
+pub struct TTFScript{
+    pub tag:[u8;4],
+    pub lookups:Vec<u16>
+}
+
+// a glyph after shaping, covering one or more chars
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TTFShaped{
+    pub glyph:u32,
+    pub chars:usize,
+    pub dx:f32, // offset from the pen position in font units
+    pub dy:f32,
+    pub advance:f32 // attached marks dont advance
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct TTFCff{
+    pub char_strings:Vec<(usize, usize)>,
+    pub global_subrs:Vec<(usize, usize)>,
+    pub local_subrs:Vec<Vec<(usize, usize)>>, // per font dict
+    pub fd_select:Vec<u8> // per glyph font dict, empty for non CID fonts
+}
+
+#[derive(Clone, Default, Debug)]
+pub struct TrueTypeFont{
+    pub data:Vec<u8>,
+    pub units_per_em:f32,
+    pub ascender:f32,
+    pub descender:f32,
+    pub line_gap:f32,
+    pub num_glyphs:usize,
+    pub cmap:Vec<TTFCmapRange>,
+    pub advances:Vec<u16>,
+    pub loca:Vec<usize>,
+    pub glyf:usize,
+    pub cff:Option<TTFCff>,
+    pub kern_pairs:HashMap<(u16, u16), i16>,
+    pub gpos_kern:Vec<TTFPairKern>,
+    pub gpos_marks:Vec<TTFMarkAttach>,
+    pub gpos_scripts:Vec<TTFScript>, // mark and mkmk lookups per script
+    pub gsub_ligatures:Vec<(u16, HashMap<u16, Vec<(Vec<u16>, u16)>>)>, // per lookup, first glyph to (rest, ligature)
+    pub gsub_scripts:Vec<TTFScript> // liga and clig lookups per script
+}
+
+fn ttf_err<T>(what:&str)->Result<T, String>{
+    Err(format!("TTF {} out of bounds", what))
+}
+
+pub fn ttf_u8(data:&[u8], pos:usize)->Result<u8, String>{
+    if pos >= data.len(){
+        return ttf_err("u8")
+    }
+    Ok(data[pos])
+}
+
+pub fn ttf_u16(data:&[u8], pos:usize)->Result<u16, String>{
+    if pos + 2 > data.len(){
+        return ttf_err("u16")
+    }
+    Ok(((data[pos] as u16) << 8) | data[pos+1] as u16)
+}
+
+pub fn ttf_i16(data:&[u8], pos:usize)->Result<i16, String>{
+    Ok(ttf_u16(data, pos)? as i16)
+}
+
+// the opentype script tag of a char, None for the common and inherited chars
+// like digits, punctuation and combining marks that take the script of their run
+pub fn unicode_script(c:char)->Option<[u8;4]>{
+    let tag = match c as u32{
+        0x41..=0x5a | 0x61..=0x7a | 0xaa | 0xba | 0xc0..=0xd6 | 0xd8..=0xf6 | 0xf8..=0x24f | 0x1e00..=0x1eff | 0xfb00..=0xfb06=>b"latn",
+        0x370..=0x373 | 0x376..=0x3ff | 0x1f00..=0x1fff=>b"grek",
+        0x400..=0x52f=>b"cyrl",
+        0x531..=0x58f=>b"armn",
+        0x591..=0x5ff | 0xfb1d..=0xfb4f=>b"hebr",
+        0x600..=0x6ff | 0x750..=0x77f | 0xfb50..=0xfdff | 0xfe70..=0xfeff=>b"arab",
+        0xe01..=0xe7f=>b"thai",
+        0x10a0..=0x10ff=>b"geor",
+        0x1100..=0x11ff | 0x3130..=0x318f | 0xac00..=0xd7af=>b"hang",
+        0x3041..=0x30ff=>b"kana",
+        0x3400..=0x4dbf | 0x4e00..=0x9fff=>b"hani",
+        _=>return None
+    };
+    Some(*tag)
+}
+
+pub fn ttf_u32(data:&[u8], pos:usize)->Result<u32, String>{
+    if pos + 4 > data.len(){
+        return ttf_err("u32")
+    }
+    Ok(((data[pos] as u32) << 24) | ((data[pos+1] as u32) << 16) | ((data[pos+2] as u32) << 8) | data[pos+3] as u32)
+}
+
+impl TrueTypeFont{
+    pub fn is_ttf(data:&[u8])->bool{
+        data.len() > 4 && (&data[0..4] == b"\x00\x01\x00\x00" || &data[0..4] == b"OTTO" || &data[0..4] == b"true" || &data[0..4] == b"ttcf")
+    }
+
+    pub fn parse(data:Vec<u8>)->Result<TrueTypeFont, String>{
+        TrueTypeFont::parse_face(data, 0)
+    }
+
+    // a face of a font collection (.ttc), a single font only has face 0. The table
+    // offsets of all faces count from the start of the file
+    pub fn parse_face(data:Vec<u8>, face:usize)->Result<TrueTypeFont, String>{
+        if !TrueTypeFont::is_ttf(&data){
+            return Err("Not a TrueType or OpenType font".to_string())
+        }
+        let dir = if &data[0..4] == b"ttcf"{
+            if face >= ttf_u32(&data, 8)? as usize{
+                return Err(format!("Font collection has no face {}", face))
+            }
+            ttf_u32(&data, 12 + face * 4)? as usize
+        }
+        else if face != 0{
+            return Err(format!("Font has no face {}", face))
+        }
+        else{
+            0
+        };
+        let mut tables = HashMap::new();
+        let num_tables = ttf_u16(&data, dir + 4)? as usize;
+        for i in 0..num_tables{
+            let rec = dir + 12 + i * 16;
+            if rec + 16 > data.len(){
+                return ttf_err("table directory")
+            }
+            let tag = String::from_utf8_lossy(&data[rec..rec+4]).to_string();
+            tables.insert(tag, (ttf_u32(&data, rec + 8)? as usize, ttf_u32(&data, rec + 12)? as usize));
+        }
+        let table = |tag:&str|->Result<usize, String>{
+            if let Some((offset, _)) = tables.get(tag){
+                return Ok(*offset)
+            }
+            Err(format!("TTF missing table {}", tag))
+        };
+
+        let head = table("head")?;
+        let hhea = table("hhea")?;
+        let maxp = table("maxp")?;
+        let mut ttf = TrueTypeFont{
+            units_per_em:ttf_u16(&data, head + 18)? as f32,
+            ascender:ttf_i16(&data, hhea + 4)? as f32,
+            descender:ttf_i16(&data, hhea + 6)? as f32,
+            line_gap:ttf_i16(&data, hhea + 8)? as f32,
+            num_glyphs:ttf_u16(&data, maxp + 4)? as usize,
+            ..Default::default()
+        };
+
+        // horizontal metrics, the last advance repeats
+        let hmtx = table("hmtx")?;
+        let num_hmetrics = (ttf_u16(&data, hhea + 34)? as usize).max(1);
+        let mut advance = 0;
+        for i in 0..ttf.num_glyphs{
+            if i < num_hmetrics{
+                advance = ttf_u16(&data, hmtx + i * 4)?;
+            }
+            ttf.advances.push(advance);
+        }
+
+        ttf.cmap = TrueTypeFont::parse_cmap(&data, table("cmap")?)?;
+
+        if let Ok(glyf) = table("glyf"){
+            let loca = table("loca")?;
+            let long_loca = ttf_i16(&data, head + 50)? != 0;
+            for i in 0..ttf.num_glyphs + 1{
+                ttf.loca.push(if long_loca{
+                    ttf_u32(&data, loca + i * 4)? as usize
+                } else {
+                    ttf_u16(&data, loca + i * 2)? as usize * 2
+                });
+            }
+            ttf.glyf = glyf;
+        }
+        else if let Ok(cff) = table("CFF "){
+            ttf.cff = Some(TrueTypeFont::parse_cff(&data, cff)?);
+        }
+        else{
+            return Err("TTF has no glyf or CFF outlines".to_string())
+        }
+
+        if let Ok(kern) = table("kern"){
+            ttf.kern_pairs = TrueTypeFont::parse_kern(&data, kern).unwrap_or(HashMap::new());
+        }
+        if let Ok(gpos) = table("GPOS"){
+            // pair kerning doesnt know the script, it takes the kern lookups of all of them
+            let kern = TrueTypeFont::script_lookups(&data, gpos, &[b"kern"]).unwrap_or(Vec::new());
+            ttf.gpos_kern = TrueTypeFont::parse_gpos_kern(&data, gpos, &TrueTypeFont::all_lookups(&kern)).unwrap_or(Vec::new());
+            ttf.gpos_scripts = TrueTypeFont::script_lookups(&data, gpos, &[b"mark", b"mkmk"]).unwrap_or(Vec::new());
+            ttf.gpos_marks = TrueTypeFont::parse_gpos_marks(&data, gpos, &TrueTypeFont::all_lookups(&ttf.gpos_scripts)).unwrap_or(Vec::new());
+        }
+        if let Ok(gsub) = table("GSUB"){
+            ttf.gsub_scripts = TrueTypeFont::script_lookups(&data, gsub, &[b"liga", b"clig"]).unwrap_or(Vec::new());
+            ttf.gsub_ligatures = TrueTypeFont::parse_gsub_ligatures(&data, gsub, &TrueTypeFont::all_lookups(&ttf.gsub_scripts)).unwrap_or(Vec::new());
+        }
+        ttf.data = data;
+        Ok(ttf)
+    }
+
+    pub fn glyph_index(&self, unicode:u32)->u32{
+        let mut lo = 0;
+        let mut hi = self.cmap.len();
+        while lo < hi{
+            let mid = (lo + hi) / 2;
+            let range = &self.cmap[mid];
+            if unicode < range.start{
+                hi = mid;
+            }
+            else if unicode > range.end{
+                lo = mid + 1;
+            }
+            else{
+                // a malformed cmap can map past the glyphs the font has
+                return match range.glyph.checked_add(unicode - range.start){
+                    Some(glyph) if (glyph as usize) < self.num_glyphs=>glyph,
+                    _=>0
+                }
+            }
+        }
+        0
+    }
+
+    pub fn advance(&self, glyph:u32)->f32{
+        if (glyph as usize) < self.advances.len(){
+            return self.advances[glyph as usize] as f32
+        }
+        0.0
+    }
+
+    // kerning in font units between two glyphs, GPOS first, kern table otherwise
+    pub fn pair_kern(&self, left:u32, right:u32)->f32{
+        let (left, right) = (left as u16, right as u16);
+        for sub in &self.gpos_kern{
+            match sub{
+                TTFPairKern::Pairs(pairs)=>if let Some(kern) = pairs.get(&(left, right)){
+                    return *kern as f32
+                },
+                TTFPairKern::Classes{coverage, class1, class2, class2_count, values}=>{
+                    if coverage.index(left).is_some(){
+                        let index = class1.class(left) as usize * class2_count + class2.class(right) as usize;
+                        if index < values.len() && values[index] != 0{
+                            return values[index] as f32
+                        }
+                    }
+                }
+            }
+        }
+        if let Some(kern) = self.kern_pairs.get(&(left, right)){
+            return *kern as f32
+        }
+        0.0
+    }
+
+    fn parse_cmap(data:&[u8], cmap:usize)->Result<Vec<TTFCmapRange>, String>{
+        let num = ttf_u16(data, cmap + 2)? as usize;
+        let mut best = None;
+        let mut best_score = 0;
+        for i in 0..num{
+            let rec = cmap + 4 + i * 8;
+            let platform = ttf_u16(data, rec)?;
+            let encoding = ttf_u16(data, rec + 2)?;
+            let offset = cmap + ttf_u32(data, rec + 4)? as usize;
+            let format = ttf_u16(data, offset)?;
+            let score = match (platform, encoding, format){
+                (3, 10, 12)=>4,
+                (0, _, 12)=>3,
+                (3, 1, 4)=>2,
+                (0, _, 4)=>1,
+                _=>0
+            };
+            if score > best_score{
+                best_score = score;
+                best = Some(offset);
+            }
+        }
+        let offset = if let Some(offset) = best{offset}else{return Err("TTF has no unicode cmap".to_string())};
+        let mut ranges:Vec<TTFCmapRange> = Vec::new();
+        if ttf_u16(data, offset)? == 12{
+            let groups = ttf_u32(data, offset + 12)? as usize;
+            for i in 0..groups{
+                let g = offset + 16 + i * 12;
+                ranges.push(TTFCmapRange{
+                    start:ttf_u32(data, g)?,
+                    end:ttf_u32(data, g + 4)?,
+                    glyph:ttf_u32(data, g + 8)?
+                });
+            }
+        }
+        else{
+            let seg_count = ttf_u16(data, offset + 6)? as usize / 2;
+            let ends = offset + 14;
+            let starts = ends + seg_count * 2 + 2;
+            let deltas = starts + seg_count * 2;
+            let range_offsets = deltas + seg_count * 2;
+            for s in 0..seg_count{
+                let end = ttf_u16(data, ends + s * 2)? as u32;
+                let start = ttf_u16(data, starts + s * 2)? as u32;
+                let delta = ttf_u16(data, deltas + s * 2)? as u32;
+                let range_offset = ttf_u16(data, range_offsets + s * 2)? as usize;
+                if start > end || start == 0xffff{
+                    continue
+                }
+                for c in start..=end{
+                    let glyph = if range_offset == 0{
+                        (c + delta) & 0xffff
+                    }
+                    else{
+                        let pos = range_offsets + s * 2 + range_offset + (c - start) as usize * 2;
+                        let g = ttf_u16(data, pos)? as u32;
+                        if g == 0{0}else{(g + delta) & 0xffff}
+                    };
+                    if glyph == 0{
+                        continue
+                    }
+                    // extend the last range when consecutive
+                    if let Some(last) = ranges.last_mut(){
+                        if last.end + 1 == c && last.glyph + (c - last.start) == glyph{
+                            last.end = c;
+                            continue
+                        }
+                    }
+                    ranges.push(TTFCmapRange{start:c, end:c, glyph:glyph});
+                }
+            }
+        }
+        ranges.sort_by_key(|r| r.start);
+        Ok(ranges)
+    }
+
+    fn parse_kern(data:&[u8], kern:usize)->Result<HashMap<(u16, u16), i16>, String>{
+        let mut pairs = HashMap::new();
+        let num = ttf_u16(data, kern + 2)? as usize;
+        let mut sub = kern + 4;
+        for _ in 0..num{
+            let length = ttf_u16(data, sub + 2)? as usize;
+            let coverage = ttf_u16(data, sub + 4)?;
+            // horizontal format 0 only
+            if coverage >> 8 == 0 && coverage & 1 != 0{
+                let num_pairs = ttf_u16(data, sub + 6)? as usize;
+                for i in 0..num_pairs{
+                    let p = sub + 14 + i * 6;
+                    pairs.insert((ttf_u16(data, p)?, ttf_u16(data, p + 2)?), ttf_i16(data, p + 4)?);
+                }
+            }
+            sub += length;
+        }
+        Ok(pairs)
+    }
+
+    // the lookups each script enables for the given features through its default language system,
+    // scripts without one enable nothing as there is no language to pick another by. works on GPOS and GSUB
+    fn script_lookups(data:&[u8], table:usize, tags:&[&[u8]])->Result<Vec<TTFScript>, String>{
+        let scripts = table + ttf_u16(data, table + 4)? as usize;
+        let features = table + ttf_u16(data, table + 6)? as usize;
+        let mut out = Vec::new();
+        for i in 0..ttf_u16(data, scripts)? as usize{
+            let rec = scripts + 2 + i * 6;
+            let script = scripts + ttf_u16(data, rec + 4)? as usize;
+            let mut tag = [0u8;4];
+            tag.copy_from_slice(&data[rec..rec + 4]);
+            let mut lookups = Vec::new();
+            let lang_sys = ttf_u16(data, script)? as usize;
+            if lang_sys != 0{
+                let lang_sys = script + lang_sys;
+                let required = ttf_u16(data, lang_sys + 2)?;
+                let mut feature_ids = if required != 0xffff{vec![required]}else{Vec::new()};
+                for j in 0..ttf_u16(data, lang_sys + 4)? as usize{
+                    feature_ids.push(ttf_u16(data, lang_sys + 6 + j * 2)?);
+                }
+                for feature_id in feature_ids{
+                    let rec = features + 2 + feature_id as usize * 6;
+                    if rec + 4 > data.len() || !tags.contains(&&data[rec..rec + 4]){
+                        continue
+                    }
+                    let feature = features + ttf_u16(data, rec + 4)? as usize;
+                    for j in 0..ttf_u16(data, feature + 2)? as usize{
+                        let id = ttf_u16(data, feature + 4 + j * 2)?;
+                        if !lookups.contains(&id){
+                            lookups.push(id);
+                        }
+                    }
+                }
+                lookups.sort();
+            }
+            out.push(TTFScript{tag:tag, lookups:lookups});
+        }
+        Ok(out)
+    }
+
+    fn all_lookups(scripts:&[TTFScript])->Vec<u16>{
+        let mut ids:Vec<u16> = scripts.iter().flat_map(|s| s.lookups.iter().cloned()).collect();
+        ids.sort();
+        ids.dedup();
+        ids
+    }
+
+    // the lookups of a script, or of the default script or latin when the font doesnt have it
+    fn lookups_of<'a>(scripts:&'a [TTFScript], tag:Option<[u8;4]>)->&'a [u16]{
+        for want in tag.iter().chain([*b"DFLT", *b"latn"].iter()){
+            if let Some(script) = scripts.iter().find(|s| s.tag == *want){
+                return &script.lookups
+            }
+        }
+        &[]
+    }
+
+    // the subtables of the lookups as (lookup id, type, offset) in lookup order,
+    // extension subtables are resolved, works on GPOS and GSUB
+    fn feature_subtables(data:&[u8], table:usize, lookup_ids:&[u16], extension:u16)->Result<Vec<(u16, u16, usize)>, String>{
+        let lookups = table + ttf_u16(data, table + 8)? as usize;
+        let mut out = Vec::new();
+        for id in lookup_ids{
+            let lookup = lookups + ttf_u16(data, lookups + 2 + *id as usize * 2)? as usize;
+            let lookup_type = ttf_u16(data, lookup)?;
+            for i in 0..ttf_u16(data, lookup + 4)? as usize{
+                let mut sub = lookup + ttf_u16(data, lookup + 6 + i * 2)? as usize;
+                let mut sub_type = lookup_type;
+                if sub_type == extension{
+                    sub_type = ttf_u16(data, sub + 2)?;
+                    sub = sub + ttf_u32(data, sub + 4)? as usize;
+                }
+                out.push((*id, sub_type, sub));
+            }
+        }
+        Ok(out)
+    }
+
+    fn parse_gpos_kern(data:&[u8], gpos:usize, lookup_ids:&[u16])->Result<Vec<TTFPairKern>, String>{
+        let mut out = Vec::new();
+        for (_, sub_type, sub) in TrueTypeFont::feature_subtables(data, gpos, lookup_ids, 9)?{
+            if sub_type == 2{
+                if let Some(pair) = TrueTypeFont::parse_pair_pos(data, sub)?{
+                    out.push(pair);
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    fn parse_gpos_marks(data:&[u8], gpos:usize, lookup_ids:&[u16])->Result<Vec<TTFMarkAttach>, String>{
+        let mut out = Vec::new();
+        for (lookup_id, sub_type, sub) in TrueTypeFont::feature_subtables(data, gpos, lookup_ids, 9)?{
+            if sub_type != 4 && sub_type != 6{
+                continue
+            }
+            let anchor = |pos:usize|->Result<(i16, i16), String>{Ok((ttf_i16(data, pos + 2)?, ttf_i16(data, pos + 4)?))};
+            let class_count = ttf_u16(data, sub + 6)? as usize;
+            let mark_array = sub + ttf_u16(data, sub + 8)? as usize;
+            let base_array = sub + ttf_u16(data, sub + 10)? as usize;
+            let mut marks = Vec::new();
+            for i in 0..ttf_u16(data, mark_array)? as usize{
+                let rec = mark_array + 2 + i * 4;
+                let (x, y) = anchor(mark_array + ttf_u16(data, rec + 2)? as usize)?;
+                marks.push((ttf_u16(data, rec)?, x, y));
+            }
+            let mut bases = Vec::new();
+            for i in 0..ttf_u16(data, base_array)? as usize{
+                let mut anchors = Vec::new();
+                for c in 0..class_count{
+                    let off = ttf_u16(data, base_array + 2 + (i * class_count + c) * 2)? as usize;
+                    anchors.push(if off == 0{None}else{Some(anchor(base_array + off)?)});
+                }
+                bases.push(anchors);
+            }
+            out.push(TTFMarkAttach{
+                lookup:lookup_id,
+                to_marks:sub_type == 6,
+                mark_coverage:TTFCoverage::parse(data, sub + ttf_u16(data, sub + 2)? as usize)?,
+                base_coverage:TTFCoverage::parse(data, sub + ttf_u16(data, sub + 4)? as usize)?,
+                marks:marks,
+                bases:bases
+            });
+        }
+        Ok(out)
+    }
+
+    fn parse_gsub_ligatures(data:&[u8], gsub:usize, lookup_ids:&[u16])->Result<Vec<(u16, HashMap<u16, Vec<(Vec<u16>, u16)>>)>, String>{
+        let mut out:Vec<(u16, HashMap<u16, Vec<(Vec<u16>, u16)>>)> = Vec::new();
+        for (lookup_id, sub_type, sub) in TrueTypeFont::feature_subtables(data, gsub, lookup_ids, 7)?{
+            if sub_type != 4{
+                continue
+            }
+            if out.last().map_or(true, |(id, _)| *id != lookup_id){
+                out.push((lookup_id, HashMap::new()));
+            }
+            let map = &mut out.last_mut().unwrap().1;
+            let firsts = TTFCoverage::parse(data, sub + ttf_u16(data, sub + 2)? as usize)?.glyphs();
+            for i in 0..(ttf_u16(data, sub + 4)? as usize).min(firsts.len()){
+                let set = sub + ttf_u16(data, sub + 6 + i * 2)? as usize;
+                let ligs = map.entry(firsts[i]).or_insert_with(Vec::new);
+                for j in 0..ttf_u16(data, set)? as usize{
+                    let lig = set + ttf_u16(data, set + 2 + j * 2)? as usize;
+                    let glyph = ttf_u16(data, lig)?;
+                    let count = ttf_u16(data, lig + 2)? as usize;
+                    let mut rest = Vec::new();
+                    for k in 1..count{
+                        rest.push(ttf_u16(data, lig + 2 + k * 2)?);
+                    }
+                    ligs.push((rest, glyph));
+                }
+                // longest match wins
+                ligs.sort_by(|a, b| b.0.len().cmp(&a.0.len()));
+            }
+        }
+        Ok(out)
+    }
+
+    pub fn is_mark(&self, glyph:u32)->bool{
+        self.gpos_marks.iter().any(|m| m.mark_coverage.index(glyph as u16).is_some())
+    }
+
+    // maps chars to glyphs, applies ligatures if asked and positions combining marks on their base,
+    // each script run with the lookups of its script. common chars and marks join the run they are in
+    pub fn shape(&self, chars:&[char], ligatures:bool)->Vec<TTFShaped>{
+        let mut out = Vec::new();
+        let mut start = 0;
+        while start < chars.len(){
+            let script = chars[start..].iter().find_map(|c| unicode_script(*c));
+            let mut end = start + 1;
+            while end < chars.len() && unicode_script(chars[end]).map_or(true, |s| Some(s) == script){
+                end += 1;
+            }
+            out.extend(self.shape_script(&chars[start..end], script, ligatures));
+            start = end;
+        }
+        out
+    }
+
+    fn shape_script(&self, chars:&[char], script:Option<[u8;4]>, ligatures:bool)->Vec<TTFShaped>{
+        let mut out:Vec<TTFShaped> = chars.iter().map(|c|{
+            let glyph = self.glyph_index(*c as u32);
+            TTFShaped{glyph:glyph, chars:1, advance:self.advance(glyph), ..Default::default()}
+        }).collect();
+        if ligatures{
+            let lookup_ids = TrueTypeFont::lookups_of(&self.gsub_scripts, script);
+            for (_, lookup) in self.gsub_ligatures.iter().filter(|(id, _)| lookup_ids.contains(id)){
+                let mut i = 0;
+                while i < out.len(){
+                    if let Some(ligs) = lookup.get(&(out[i].glyph as u16)){
+                        for (rest, lig) in ligs{
+                            if i + rest.len() < out.len() && rest.iter().enumerate().all(|(k, g)| out[i + 1 + k].glyph as u16 == *g){
+                                let chars:usize = out[i..=i + rest.len()].iter().map(|s| s.chars).sum();
+                                out[i] = TTFShaped{glyph:*lig as u32, chars:chars, advance:self.advance(*lig as u32), ..Default::default()};
+                                out.drain(i + 1..=i + rest.len());
+                                break
+                            }
+                        }
+                    }
+                    i += 1;
+                }
+            }
+        }
+        let lookup_ids = TrueTypeFont::lookups_of(&self.gpos_scripts, script);
+        if lookup_ids.len() > 0{
+            let marks:Vec<&TTFMarkAttach> = self.gpos_marks.iter().filter(|a| lookup_ids.contains(&a.lookup)).collect();
+            for i in 1..out.len(){
+                let mark = out[i].glyph as u16;
+                // mark to mark first, so stacked marks dont overlap
+                let attachments = marks.iter().filter(|a| a.to_marks).chain(marks.iter().filter(|a| !a.to_marks));
+                for attach in attachments{
+                    let mark_index = if let Some(index) = attach.mark_coverage.index(mark){index}else{continue};
+                    // mark to base skips over other marks, mark to mark only looks at the previous glyph
+                    let mut base = i - 1;
+                    if !attach.to_marks{
+                        while base > 0 && self.is_mark(out[base].glyph){
+                            base -= 1;
+                        }
+                    }
+                    let base_index = if let Some(index) = attach.base_coverage.index(out[base].glyph as u16){index}else{continue};
+                    if mark_index >= attach.marks.len() || base_index >= attach.bases.len(){
+                        continue
+                    }
+                    let (class, mx, my) = attach.marks[mark_index];
+                    if let Some(Some((bx, by))) = attach.bases[base_index].get(class as usize){
+                        let pen:f32 = out[base..i].iter().map(|s| s.advance).sum();
+                        out[i].dx = out[base].dx + *bx as f32 - mx as f32 - pen;
+                        out[i].dy = out[base].dy + *by as f32 - my as f32;
+                        out[i].advance = 0.0;
+                        break
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    fn parse_pair_pos(data:&[u8], sub:usize)->Result<Option<TTFPairKern>, String>{
+        let format = ttf_u16(data, sub)?;
+        let coverage = TTFCoverage::parse(data, sub + ttf_u16(data, sub + 2)? as usize)?;
+        let vf1 = ttf_u16(data, sub + 4)?;
+        let vf2 = ttf_u16(data, sub + 6)?;
+        if vf1 & 4 == 0{ // no x advance on the first glyph, not kerning
+            return Ok(None)
+        }
+        let size1 = vf1.count_ones() as usize * 2;
+        let size2 = vf2.count_ones() as usize * 2;
+        let x_adv = (vf1 & 3).count_ones() as usize * 2;
+        if format == 1{
+            let mut pairs = HashMap::new();
+            let glyphs = coverage.glyphs();
+            for i in 0..ttf_u16(data, sub + 8)? as usize{
+                if i >= glyphs.len(){
+                    break
+                }
+                let set = sub + ttf_u16(data, sub + 10 + i * 2)? as usize;
+                for j in 0..ttf_u16(data, set)? as usize{
+                    let rec = set + 2 + j * (2 + size1 + size2);
+                    let kern = ttf_i16(data, rec + 2 + x_adv)?;
+                    if kern != 0{
+                        pairs.insert((glyphs[i], ttf_u16(data, rec)?), kern);
+                    }
+                }
+            }
+            return Ok(Some(TTFPairKern::Pairs(pairs)))
+        }
+        if format == 2{
+            let class1 = TTFClassDef::parse(data, sub + ttf_u16(data, sub + 8)? as usize)?;
+            let class2 = TTFClassDef::parse(data, sub + ttf_u16(data, sub + 10)? as usize)?;
+            let class1_count = ttf_u16(data, sub + 12)? as usize;
+            let class2_count = ttf_u16(data, sub + 14)? as usize;
+            let mut values = Vec::with_capacity(class1_count * class2_count);
+            for i in 0..class1_count * class2_count{
+                values.push(ttf_i16(data, sub + 16 + i * (size1 + size2) + x_adv)?);
+            }
+            return Ok(Some(TTFPairKern::Classes{
+                coverage:coverage,
+                class1:class1,
+                class2:class2,
+                class2_count:class2_count,
+                values:values
+            }))
+        }
+        Ok(None)
+    }
+
+    pub fn outline(&self, glyph:u32)->Result<TTFOutline, String>{
+        let mut outline = TTFOutline::default();
+        if let Some(cff) = &self.cff{
+            cff.outline(&self.data, glyph as usize, &mut outline)?;
+        }
+        else{
+            self.glyf_outline(glyph as usize, &mut outline, [1.0, 0.0, 0.0, 1.0, 0.0, 0.0], 0)?;
+        }
+        outline.compute_bounds();
+        Ok(outline)
+    }
+
+    // transform is a, b, c, d, dx, dy as in the composite glyph spec
+    fn glyf_outline(&self, glyph:usize, outline:&mut TTFOutline, t:[f32;6], depth:usize)->Result<(), String>{
+        if glyph + 1 >= self.loca.len() || depth > 8{
+            return Ok(())
+        }
+        let start = self.glyf + self.loca[glyph];
+        if self.loca[glyph + 1] <= self.loca[glyph]{
+            return Ok(()) // empty glyph
+        }
+        let data = &self.data;
+        let num_contours = ttf_i16(data, start)?;
+        if num_contours >= 0{
+            let num_contours = num_contours as usize;
+            let mut end_points = Vec::new();
+            for i in 0..num_contours{
+                end_points.push(ttf_u16(data, start + 10 + i * 2)? as usize);
+            }
+            let num_points = if let Some(last) = end_points.last(){last + 1}else{0};
+            let ins_len = ttf_u16(data, start + 10 + num_contours * 2)? as usize;
+            let mut pos = start + 12 + num_contours * 2 + ins_len;
+            let mut flags = Vec::with_capacity(num_points);
+            while flags.len() < num_points{
+                let flag = ttf_u8(data, pos)?;
+                pos += 1;
+                flags.push(flag);
+                if flag & 8 != 0{
+                    let repeat = ttf_u8(data, pos)?;
+                    pos += 1;
+                    for _ in 0..repeat{
+                        flags.push(flag);
+                    }
+                }
+            }
+            let mut xs = Vec::with_capacity(num_points);
+            let mut v = 0i32;
+            for flag in flags.iter().take(num_points){
+                if flag & 2 != 0{
+                    let d = ttf_u8(data, pos)? as i32;
+                    pos += 1;
+                    v += if flag & 16 != 0{d}else{-d};
+                }
+                else if flag & 16 == 0{
+                    v += ttf_i16(data, pos)? as i32;
+                    pos += 2;
+                }
+                xs.push(v);
+            }
+            let mut ys = Vec::with_capacity(num_points);
+            v = 0;
+            for flag in flags.iter().take(num_points){
+                if flag & 4 != 0{
+                    let d = ttf_u8(data, pos)? as i32;
+                    pos += 1;
+                    v += if flag & 32 != 0{d}else{-d};
+                }
+                else if flag & 32 == 0{
+                    v += ttf_i16(data, pos)? as i32;
+                    pos += 2;
+                }
+                ys.push(v);
+            }
+            let mut first = 0;
+            for end in end_points{
+                if end < first || end >= num_points{
+                    break
+                }
+                let pts:Vec<((f32, f32), bool)> = (first..=end).map(|i|{
+                    let (x, y) = (xs[i] as f32, ys[i] as f32);
+                    ((t[0] * x + t[2] * y + t[4], t[1] * x + t[3] * y + t[5]), flags[i] & 1 != 0)
+                }).collect();
+                outline.add_quadratic_contour(&pts, self.units_per_em);
+                first = end + 1;
+            }
+        }
+        else{
+            let mut pos = start + 10;
+            loop{
+                let flags = ttf_u16(data, pos)?;
+                let component = ttf_u16(data, pos + 2)? as usize;
+                pos += 4;
+                let (dx, dy) = if flags & 1 != 0{
+                    pos += 4;
+                    (ttf_i16(data, pos - 4)? as f32, ttf_i16(data, pos - 2)? as f32)
+                } else {
+                    pos += 2;
+                    (ttf_u8(data, pos - 2)? as i8 as f32, ttf_u8(data, pos - 1)? as i8 as f32)
+                };
+                // point matched components are not supported, place them at the origin
+                let (dx, dy) = if flags & 2 != 0{(dx, dy)}else{(0.0, 0.0)};
+                let f2dot14 = |pos:usize|->Result<f32, String>{Ok(ttf_i16(data, pos)? as f32 / 16384.0)};
+                let mut m = [1.0, 0.0, 0.0, 1.0];
+                if flags & 8 != 0{
+                    m[0] = f2dot14(pos)?;
+                    m[3] = m[0];
+                    pos += 2;
+                }
+                else if flags & 0x40 != 0{
+                    m[0] = f2dot14(pos)?;
+                    m[3] = f2dot14(pos + 2)?;
+                    pos += 4;
+                }
+                else if flags & 0x80 != 0{
+                    m = [f2dot14(pos)?, f2dot14(pos + 2)?, f2dot14(pos + 4)?, f2dot14(pos + 6)?];
+                    pos += 8;
+                }
+                // concatenate with the parent transform
+                let ct = [
+                    t[0] * m[0] + t[2] * m[1],
+                    t[1] * m[0] + t[3] * m[1],
+                    t[0] * m[2] + t[2] * m[3],
+                    t[1] * m[2] + t[3] * m[3],
+                    t[0] * dx + t[2] * dy + t[4],
+                    t[1] * dx + t[3] * dy + t[5]
+                ];
+                self.glyf_outline(component, outline, ct, depth + 1)?;
+                if flags & 0x20 == 0{
+                    break
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_cff(data:&[u8], cff:usize)->Result<TTFCff, String>{
+        let header_size = ttf_u8(data, cff + 2)? as usize;
+        let (_names, pos) = cff_index(data, cff + header_size)?;
+        let (top_dicts, pos) = cff_index(data, pos)?;
+        let (_strings, pos) = cff_index(data, pos)?;
+        let (global_subrs, _) = cff_index(data, pos)?;
+        if top_dicts.len() == 0{
+            return Err("CFF has no top dict".to_string())
+        }
+        let top = cff_dict(data, top_dicts[0].0, top_dicts[0].1)?;
+        let char_strings_off = if let Some(v) = top.get(&17){v[0] as usize}else{return Err("CFF has no charstrings".to_string())};
+        let (char_strings, _) = cff_index(data, cff + char_strings_off)?;
+        let mut out = TTFCff{
+            char_strings:char_strings,
+            global_subrs:global_subrs,
+            ..Default::default()
+        };
+        let private_subrs = |dict:&HashMap<u16, Vec<f64>>|->Result<Vec<(usize, usize)>, String>{
+            if let Some(private) = dict.get(&18){
+                if private.len() >= 2{
+                    let (size, offset) = (private[0] as usize, cff + private[1] as usize);
+                    let pd = cff_dict(data, offset, offset + size)?;
+                    if let Some(subrs) = pd.get(&19){
+                        return Ok(cff_index(data, offset + subrs[0] as usize)?.0)
+                    }
+                }
+            }
+            Ok(Vec::new())
+        };
+        if let Some(fd_array) = top.get(&(1200 + 36)){
+            // CID keyed, every font dict has its own private subrs
+            let (fds, _) = cff_index(data, cff + fd_array[0] as usize)?;
+            for (start, end) in fds{
+                out.local_subrs.push(private_subrs(&cff_dict(data, start, end)?)?);
+            }
+            if let Some(fd_select) = top.get(&(1200 + 37)){
+                let pos = cff + fd_select[0] as usize;
+                let num_glyphs = out.char_strings.len();
+                match ttf_u8(data, pos)?{
+                    0=>for i in 0..num_glyphs{
+                        out.fd_select.push(ttf_u8(data, pos + 1 + i)?);
+                    },
+                    3=>{
+                        let num_ranges = ttf_u16(data, pos + 1)? as usize;
+                        out.fd_select = vec![0; num_glyphs];
+                        for i in 0..num_ranges{
+                            let first = ttf_u16(data, pos + 3 + i * 3)? as usize;
+                            let fd = ttf_u8(data, pos + 5 + i * 3)?;
+                            let next = ttf_u16(data, pos + 6 + i * 3)? as usize;
+                            for g in first..next.min(num_glyphs){
+                                out.fd_select[g] = fd;
+                            }
+                        }
+                    },
+                    _=>()
+                }
+            }
+        }
+        else{
+            out.local_subrs.push(private_subrs(&top)?);
+        }
+        Ok(out)
+    }
+}
+
+impl TTFCoverage{
+    fn parse(data:&[u8], pos:usize)->Result<TTFCoverage, String>{
+        let count = ttf_u16(data, pos + 2)? as usize;
+        if ttf_u16(data, pos)? == 1{
+            let mut glyphs = Vec::with_capacity(count);
+            for i in 0..count{
+                glyphs.push(ttf_u16(data, pos + 4 + i * 2)?);
+            }
+            return Ok(TTFCoverage::Glyphs(glyphs))
+        }
+        let mut ranges = Vec::with_capacity(count);
+        for i in 0..count{
+            ranges.push((ttf_u16(data, pos + 4 + i * 6)?, ttf_u16(data, pos + 6 + i * 6)?));
+        }
+        Ok(TTFCoverage::Ranges(ranges))
+    }
+
+    pub fn index(&self, glyph:u16)->Option<usize>{
+        match self{
+            TTFCoverage::Glyphs(glyphs)=>glyphs.binary_search(&glyph).ok(),
+            TTFCoverage::Ranges(ranges)=>{
+                let mut index = 0;
+                for (start, end) in ranges{
+                    if glyph >= *start && glyph <= *end{
+                        return Some(index + (glyph - start) as usize)
+                    }
+                    index += (end - start) as usize + 1;
+                }
+                None
+            }
+        }
+    }
+
+    pub fn glyphs(&self)->Vec<u16>{
+        match self{
+            TTFCoverage::Glyphs(glyphs)=>glyphs.clone(),
+            TTFCoverage::Ranges(ranges)=>ranges.iter().flat_map(|(s, e)| *s..=*e).collect()
+        }
+    }
+}
+
+impl TTFClassDef{
+    fn parse(data:&[u8], pos:usize)->Result<TTFClassDef, String>{
+        let mut ranges = Vec::new();
+        if ttf_u16(data, pos)? == 1{
+            let start = ttf_u16(data, pos + 2)?;
+            for i in 0..ttf_u16(data, pos + 4)? as usize{
+                let class = ttf_u16(data, pos + 6 + i * 2)?;
+                if class != 0{
+                    ranges.push((start + i as u16, start + i as u16, class));
+                }
+            }
+        }
+        else{
+            for i in 0..ttf_u16(data, pos + 2)? as usize{
+                let r = pos + 4 + i * 6;
+                ranges.push((ttf_u16(data, r)?, ttf_u16(data, r + 2)?, ttf_u16(data, r + 4)?));
+            }
+        }
+        ranges.sort();
+        Ok(TTFClassDef{ranges:ranges})
+    }
+
+    pub fn class(&self, glyph:u16)->u16{
+        let index = match self.ranges.binary_search_by(|r| r.0.cmp(&glyph)){
+            Ok(index)=>index,
+            Err(0)=>return 0,
+            Err(index)=>index - 1
+        };
+        let (_, end, class) = self.ranges[index];
+        if glyph <= end{class}else{0}
+    }
+}
+
+// returns the (start, end) of every object and the position after the index
+fn cff_index(data:&[u8], pos:usize)->Result<(Vec<(usize, usize)>, usize), String>{
+    let count = ttf_u16(data, pos)? as usize;
+    if count == 0{
+        return Ok((Vec::new(), pos + 2))
+    }
+    let off_size = ttf_u8(data, pos + 2)? as usize;
+    let read_off = |i:usize|->Result<usize, String>{
+        let mut v = 0;
+        for j in 0..off_size{
+            v = (v << 8) | ttf_u8(data, pos + 3 + i * off_size + j)? as usize;
+        }
+        Ok(v)
+    };
+    let base = pos + 3 + (count + 1) * off_size - 1;
+    let mut objects = Vec::with_capacity(count);
+    for i in 0..count{
+        objects.push((base + read_off(i)?, base + read_off(i + 1)?));
+    }
+    let end = base + read_off(count)?;
+    Ok((objects, end))
+}
+
+// two byte operators are stored as 1200 + second byte
+fn cff_dict(data:&[u8], start:usize, end:usize)->Result<HashMap<u16, Vec<f64>>, String>{
+    let mut dict = HashMap::new();
+    let mut operands = Vec::new();
+    let mut pos = start;
+    while pos < end{
+        let b0 = ttf_u8(data, pos)?;
+        pos += 1;
+        match b0{
+            0..=21=>{
+                let op = if b0 == 12{
+                    pos += 1;
+                    1200 + ttf_u8(data, pos - 1)? as u16
+                } else {
+                    b0 as u16
+                };
+                dict.insert(op, operands.clone());
+                operands.truncate(0);
+            },
+            28=>{
+                operands.push(ttf_i16(data, pos)? as f64);
+                pos += 2;
+            },
+            29=>{
+                operands.push(ttf_u32(data, pos)? as i32 as f64);
+                pos += 4;
+            },
+            30=>{ // real number, nibble encoded
+                let mut s = String::new();
+                'real: loop{
+                    let b = ttf_u8(data, pos)?;
+                    pos += 1;
+                    for nibble in &[b >> 4, b & 15]{
+                        match nibble{
+                            0..=9=>s.push((b'0' + nibble) as char),
+                            10=>s.push('.'),
+                            11=>s.push('E'),
+                            12=>s.push_str("E-"),
+                            14=>s.push('-'),
+                            15=>break 'real,
+                            _=>()
+                        }
+                    }
+                }
+                operands.push(s.parse().unwrap_or(0.0));
+            },
+            32..=246=>operands.push(b0 as f64 - 139.0),
+            247..=250=>{
+                operands.push((b0 as f64 - 247.0) * 256.0 + ttf_u8(data, pos)? as f64 + 108.0);
+                pos += 1;
+            },
+            251..=254=>{
+                operands.push(-(b0 as f64 - 251.0) * 256.0 - ttf_u8(data, pos)? as f64 - 108.0);
+                pos += 1;
+            },
+            _=>()
+        }
+    }
+    Ok(dict)
+}
+
+fn cff_subr_bias(count:usize)->isize{
+    if count < 1240{107} else if count < 33900{1131} else {32768}
+}
+
+struct CffState{
+    stack:Vec<f32>,
+    x:f32,
+    y:f32,
+    num_stems:usize,
+    have_width:bool,
+    open:bool,
+    contour:Vec<(f32, f32)>,
+    done:bool
+}
+
+impl TTFCff{
+    fn outline(&self, data:&[u8], glyph:usize, outline:&mut TTFOutline)->Result<(), String>{
+        if glyph >= self.char_strings.len(){
+            return Ok(())
+        }
+        let fd = if self.fd_select.len() > glyph{self.fd_select[glyph] as usize}else{0};
+        let local = if fd < self.local_subrs.len(){&self.local_subrs[fd][..]}else{&[]};
+        let mut st = CffState{
+            stack:Vec::new(),
+            x:0.0,
+            y:0.0,
+            num_stems:0,
+            have_width:false,
+            open:false,
+            contour:Vec::new(),
+            done:false
+        };
+        let (start, end) = self.char_strings[glyph];
+        self.run(data, start, end, local, &mut st, outline, 0)?;
+        st.close(outline);
+        Ok(())
+    }
+
+    fn run(&self, data:&[u8], start:usize, end:usize, local:&[(usize, usize)], st:&mut CffState, outline:&mut TTFOutline, depth:usize)->Result<(), String>{
+        if depth > 10{
+            return Err("CFF subroutines nested too deep".to_string())
+        }
+        let mut pos = start;
+        while pos < end && !st.done{
+            let b0 = ttf_u8(data, pos)?;
+            pos += 1;
+            match b0{
+                28=>{
+                    st.stack.push(ttf_i16(data, pos)? as f32);
+                    pos += 2;
+                },
+                32..=246=>st.stack.push(b0 as f32 - 139.0),
+                247..=250=>{
+                    st.stack.push((b0 as f32 - 247.0) * 256.0 + ttf_u8(data, pos)? as f32 + 108.0);
+                    pos += 1;
+                },
+                251..=254=>{
+                    st.stack.push(-(b0 as f32 - 251.0) * 256.0 - ttf_u8(data, pos)? as f32 - 108.0);
+                    pos += 1;
+                },
+                255=>{
+                    st.stack.push(ttf_u32(data, pos)? as i32 as f32 / 65536.0);
+                    pos += 4;
+                },
+                1 | 3 | 18 | 23=>{ // stems
+                    st.take_width(st.stack.len() % 2 == 1);
+                    st.num_stems += st.stack.len() / 2;
+                    st.stack.truncate(0);
+                },
+                19 | 20=>{ // hintmask, cntrmask, may imply vstem
+                    st.take_width(st.stack.len() % 2 == 1);
+                    st.num_stems += st.stack.len() / 2;
+                    st.stack.truncate(0);
+                    pos += (st.num_stems + 7) / 8;
+                },
+                21=>{
+                    st.take_width(st.stack.len() > 2);
+                    let (dx, dy) = (st.arg(0), st.arg(1));
+                    st.move_to(outline, dx, dy);
+                },
+                22=>{
+                    st.take_width(st.stack.len() > 1);
+                    let dx = st.arg(0);
+                    st.move_to(outline, dx, 0.0);
+                },
+                4=>{
+                    st.take_width(st.stack.len() > 1);
+                    let dy = st.arg(0);
+                    st.move_to(outline, 0.0, dy);
+                },
+                5=>{
+                    let mut i = 0;
+                    while i + 1 < st.stack.len(){
+                        let (dx, dy) = (st.stack[i], st.stack[i + 1]);
+                        st.line_to(dx, dy);
+                        i += 2;
+                    }
+                    st.stack.truncate(0);
+                },
+                6 | 7=>{ // alternating h/v lines
+                    let mut horizontal = b0 == 6;
+                    for i in 0..st.stack.len(){
+                        let d = st.stack[i];
+                        if horizontal{st.line_to(d, 0.0)}else{st.line_to(0.0, d)}
+                        horizontal = !horizontal;
+                    }
+                    st.stack.truncate(0);
+                },
+                8=>{
+                    let mut i = 0;
+                    while i + 5 < st.stack.len(){
+                        let a = [st.stack[i], st.stack[i+1], st.stack[i+2], st.stack[i+3], st.stack[i+4], st.stack[i+5]];
+                        st.curve_to(a);
+                        i += 6;
+                    }
+                    st.stack.truncate(0);
+                },
+                24=>{ // rcurveline
+                    let mut i = 0;
+                    while i + 7 < st.stack.len(){
+                        let a = [st.stack[i], st.stack[i+1], st.stack[i+2], st.stack[i+3], st.stack[i+4], st.stack[i+5]];
+                        st.curve_to(a);
+                        i += 6;
+                    }
+                    if i + 1 < st.stack.len(){
+                        let (dx, dy) = (st.stack[i], st.stack[i + 1]);
+                        st.line_to(dx, dy);
+                    }
+                    st.stack.truncate(0);
+                },
+                25=>{ // rlinecurve
+                    let mut i = 0;
+                    while i + 7 < st.stack.len(){
+                        let (dx, dy) = (st.stack[i], st.stack[i + 1]);
+                        st.line_to(dx, dy);
+                        i += 2;
+                    }
+                    if i + 5 < st.stack.len(){
+                        let a = [st.stack[i], st.stack[i+1], st.stack[i+2], st.stack[i+3], st.stack[i+4], st.stack[i+5]];
+                        st.curve_to(a);
+                    }
+                    st.stack.truncate(0);
+                },
+                26 | 27=>{ // vvcurveto, hhcurveto
+                    let mut i = 0;
+                    let mut d1 = 0.0;
+                    if st.stack.len() % 4 == 1{
+                        d1 = st.stack[0];
+                        i = 1;
+                    }
+                    while i + 3 < st.stack.len(){
+                        let s = &st.stack;
+                        let a = if b0 == 26{
+                            [d1, s[i], s[i+1], s[i+2], 0.0, s[i+3]]
+                        } else {
+                            [s[i], d1, s[i+1], s[i+2], s[i+3], 0.0]
+                        };
+                        st.curve_to(a);
+                        d1 = 0.0;
+                        i += 4;
+                    }
+                    st.stack.truncate(0);
+                },
+                30 | 31=>{ // vhcurveto, hvcurveto
+                    let mut horizontal = b0 == 31;
+                    let mut i = 0;
+                    while i + 3 < st.stack.len(){
+                        let s = &st.stack;
+                        let last = if i + 5 == s.len(){s[i + 4]}else{0.0};
+                        let a = if horizontal{
+                            [s[i], 0.0, s[i+1], s[i+2], last, s[i+3]]
+                        } else {
+                            [0.0, s[i], s[i+1], s[i+2], s[i+3], last]
+                        };
+                        st.curve_to(a);
+                        horizontal = !horizontal;
+                        i += 4;
+                    }
+                    st.stack.truncate(0);
+                },
+                10 | 29=>{ // callsubr, callgsubr
+                    let subrs = if b0 == 10{local}else{&self.global_subrs[..]};
+                    let index = st.stack.pop().unwrap_or(0.0) as isize + cff_subr_bias(subrs.len());
+                    if index >= 0 && (index as usize) < subrs.len(){
+                        let (s, e) = subrs[index as usize];
+                        self.run(data, s, e, local, st, outline, depth + 1)?;
+                    }
+                },
+                11=>return Ok(()),
+                14=>{ // endchar
+                    st.take_width(st.stack.len() == 1 || st.stack.len() == 5);
+                    st.close(outline);
+                    st.done = true;
+                },
+                12=>{
+                    let b1 = ttf_u8(data, pos)?;
+                    pos += 1;
+                    let s = st.stack.clone();
+                    match b1{
+                        35 if s.len() >= 12=>{ // flex
+                            st.curve_to([s[0], s[1], s[2], s[3], s[4], s[5]]);
+                            st.curve_to([s[6], s[7], s[8], s[9], s[10], s[11]]);
+                        },
+                        34 if s.len() >= 7=>{ // hflex
+                            st.curve_to([s[0], 0.0, s[1], s[2], s[3], 0.0]);
+                            st.curve_to([s[4], 0.0, s[5], -s[2], s[6], 0.0]);
+                        },
+                        36 if s.len() >= 9=>{ // hflex1
+                            st.curve_to([s[0], s[1], s[2], s[3], s[4], 0.0]);
+                            st.curve_to([s[5], 0.0, s[6], s[7], s[8], -(s[1] + s[3] + s[7])]);
+                        },
+                        37 if s.len() >= 11=>{ // flex1
+                            let dx = s[0] + s[2] + s[4] + s[6] + s[8];
+                            let dy = s[1] + s[3] + s[5] + s[7] + s[9];
+                            st.curve_to([s[0], s[1], s[2], s[3], s[4], s[5]]);
+                            if dx.abs() > dy.abs(){
+                                st.curve_to([s[6], s[7], s[8], s[9], s[10], -dy]);
+                            }
+                            else{
+                                st.curve_to([s[6], s[7], s[8], s[9], -dx, s[10]]);
+                            }
+                        },
+                        _=>()
+                    }
+                    st.stack.truncate(0);
+                },
+                _=>{
+                    st.stack.truncate(0);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl CffState{
+    // the first stack clearing operator can carry the advance width, we use hmtx instead
+    fn take_width(&mut self, has_width:bool){
+        if !self.have_width{
+            self.have_width = true;
+            if has_width && self.stack.len() > 0{
+                self.stack.remove(0);
+            }
+        }
+    }
+
+    fn arg(&self, i:usize)->f32{
+        if i < self.stack.len(){self.stack[i]}else{0.0}
+    }
+
+    fn close(&mut self, outline:&mut TTFOutline){
+        if self.open && self.contour.len() > 2{
+            outline.contours.push(std::mem::replace(&mut self.contour, Vec::new()));
+        }
+        self.contour.truncate(0);
+        self.open = false;
+    }
+
+    fn move_to(&mut self, outline:&mut TTFOutline, dx:f32, dy:f32){
+        self.close(outline);
+        self.x += dx;
+        self.y += dy;
+        self.contour.push((self.x, self.y));
+        self.open = true;
+        self.stack.truncate(0);
+    }
+
+    fn line_to(&mut self, dx:f32, dy:f32){
+        self.x += dx;
+        self.y += dy;
+        self.contour.push((self.x, self.y));
+    }
+
+    fn curve_to(&mut self, a:[f32;6]){
+        let p0 = (self.x, self.y);
+        let p1 = (p0.0 + a[0], p0.1 + a[1]);
+        let p2 = (p1.0 + a[2], p1.1 + a[3]);
+        let p3 = (p2.0 + a[4], p2.1 + a[5]);
+        let len = dist(p0, p1) + dist(p1, p2) + dist(p2, p3);
+        let steps = ((len / 40.0).ceil() as usize).max(2).min(24);
+        for i in 1..=steps{
+            let t = i as f32 / steps as f32;
+            let u = 1.0 - t;
+            let (a, b, c, d) = (u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t);
+            self.contour.push((
+                a * p0.0 + b * p1.0 + c * p2.0 + d * p3.0,
+                a * p0.1 + b * p1.1 + c * p2.1 + d * p3.1
+            ));
+        }
+        self.x = p3.0;
+        self.y = p3.1;
+    }
+}
+
+fn dist(a:(f32, f32), b:(f32, f32))->f32{
+    ((a.0 - b.0) * (a.0 - b.0) + (a.1 - b.1) * (a.1 - b.1)).sqrt()
+}
+
+impl TTFOutline{
+    // on curve flags as in glyf, consecutive off curve points imply an on curve point between them
+    pub fn add_quadratic_contour(&mut self, pts:&[((f32, f32), bool)], units_per_em:f32){
+        if pts.len() < 2{
+            return
+        }
+        let n = pts.len();
+        let mid = |a:(f32, f32), b:(f32, f32)|((a.0 + b.0) * 0.5, (a.1 + b.1) * 0.5);
+        // find a starting on curve point
+        let (start, start_index) = if let Some(i) = pts.iter().position(|p| p.1){
+            (pts[i].0, i)
+        } else {
+            (mid(pts[0].0, pts[1].0), 0)
+        };
+        let mut contour = vec![start];
+        let mut ctrl:Option<(f32, f32)> = None;
+        let mut last = start;
+        let step_len = units_per_em / 40.0;
+        for k in 1..=n{
+            let (p, on) = pts[(start_index + k) % n];
+            if on{
+                if let Some(c) = ctrl{
+                    flatten_quad(&mut contour, last, c, p, step_len);
+                }
+                else{
+                    contour.push(p);
+                }
+                ctrl = None;
+                last = p;
+            }
+            else{
+                if let Some(c) = ctrl{
+                    let m = mid(c, p);
+                    flatten_quad(&mut contour, last, c, m, step_len);
+                    last = m;
+                }
+                ctrl = Some(p);
+            }
+        }
+        if let Some(c) = ctrl{
+            flatten_quad(&mut contour, last, c, start, step_len);
+        }
+        if contour.len() > 2{
+            self.contours.push(contour);
+        }
+    }
+
+    pub fn compute_bounds(&mut self){
+        let mut first = true;
+        for contour in &self.contours{
+            for (x, y) in contour{
+                if first{
+                    self.x_min = *x;
+                    self.x_max = *x;
+                    self.y_min = *y;
+                    self.y_max = *y;
+                    first = false;
+                }
+                self.x_min = self.x_min.min(*x);
+                self.x_max = self.x_max.max(*x);
+                self.y_min = self.y_min.min(*y);
+                self.y_max = self.y_max.max(*y);
+            }
+        }
+    }
+
+    // renders a signed distance field, 0.5 on the edge and rising inside,
+    // scale is pixels per font unit and pad is the distance range in pixels on each side.
+    // Rows go top down. Returns width, height and the pixel offset of the glyph origin
+    pub fn rasterize_sdf(&self, scale:f32, pad:usize)->(usize, usize, f32, f32, Vec<u8>){
+        if self.contours.len() == 0{
+            return (0, 0, 0.0, 0.0, Vec::new())
+        }
+        let x0 = (self.x_min * scale).floor() - pad as f32;
+        let y0 = (self.y_min * scale).floor() - pad as f32;
+        let w = ((self.x_max * scale).ceil() - x0) as usize + pad;
+        let h = ((self.y_max * scale).ceil() - y0) as usize + pad;
+        let segs:Vec<((f32, f32), (f32, f32))> = self.contours.iter().flat_map(|c|{
+            (0..c.len()).map(move |i|{
+                let a = c[i];
+                let b = c[(i + 1) % c.len()];
+                ((a.0 * scale - x0, a.1 * scale - y0), (b.0 * scale - x0, b.1 * scale - y0))
+            })
+        }).collect();
+        let range = pad as f32;
+        let mut out = vec![0u8; w * h];
+        for py in 0..h{
+            // flip so row 0 is the top
+            let y = (h - 1 - py) as f32 + 0.5;
+            for px in 0..w{
+                let x = px as f32 + 0.5;
+                let mut min_dist = std::f32::MAX;
+                let mut winding = 0;
+                for (a, b) in &segs{
+                    // distance to segment
+                    let (ex, ey) = (b.0 - a.0, b.1 - a.1);
+                    let len = ex * ex + ey * ey;
+                    let t = if len > 0.0{(((x - a.0) * ex + (y - a.1) * ey) / len).max(0.0).min(1.0)}else{0.0};
+                    let (dx, dy) = (x - a.0 - t * ex, y - a.1 - t * ey);
+                    min_dist = min_dist.min(dx * dx + dy * dy);
+                    // nonzero winding
+                    if a.1 <= y{
+                        if b.1 > y && ex * (y - a.1) - (x - a.0) * ey > 0.0{
+                            winding += 1;
+                        }
+                    }
+                    else if b.1 <= y && ex * (y - a.1) - (x - a.0) * ey < 0.0{
+                        winding -= 1;
+                    }
+                }
+                let d = if winding != 0{min_dist.sqrt()}else{-min_dist.sqrt()};
+                let v = (0.5 + 0.5 * d / range).max(0.0).min(1.0);
+                out[py * w + px] = (v * 255.0) as u8;
+            }
+        }
+        (w, h, -x0, -y0, out)
+    }
+
+    // renders a multi channel signed distance field with the same layout as rasterize_sdf,
+    // pixels are 0xRRGGBB. The median of the three channels keeps corners sharp when the
+    // field is magnified, the edge coloring follows msdfgen with corners found by angle
+    pub fn rasterize_msdf(&self, scale:f32, pad:usize)->(usize, usize, f32, f32, Vec<u32>){
+        if self.contours.len() == 0{
+            return (0, 0, 0.0, 0.0, Vec::new())
+        }
+        let x0 = (self.x_min * scale).floor() - pad as f32;
+        let y0 = (self.y_min * scale).floor() - pad as f32;
+        let w = ((self.x_max * scale).ceil() - x0) as usize + pad;
+        let h = ((self.y_max * scale).ceil() - y0) as usize + pad;
+        let mut segs = Vec::new();
+        let mut area = 0.0;
+        for contour in &self.contours{
+            let mut pts:Vec<(f32, f32)> = Vec::new();
+            for (x, y) in contour{
+                let p = (x * scale - x0, y * scale - y0);
+                if pts.last().map_or(true, |l| dist(*l, p) > 1e-4){
+                    pts.push(p);
+                }
+            }
+            while pts.len() > 1 && dist(pts[0], *pts.last().unwrap()) <= 1e-4{
+                pts.pop();
+            }
+            if pts.len() < 3{
+                continue
+            }
+            for i in 0..pts.len(){
+                let (a, b) = (pts[i], pts[(i + 1) % pts.len()]);
+                area += a.0 * b.1 - b.0 * a.1;
+            }
+            color_msdf_contour(&pts, &mut segs);
+        }
+        if segs.is_empty(){
+            return (0, 0, 0.0, 0.0, Vec::new())
+        }
+        // inside is left of the edges for counter clockwise outlines
+        let orient = if area < 0.0{-1.0}else{1.0};
+        let range = pad as f32;
+        let cutoff = range + 2.0;
+        let mut field = vec![[0.0f32;3]; w * h];
+        let mut inside = vec![false; w * h];
+        let mut seed = [0;3];
+        for py in 0..h{
+            let y = (h - 1 - py) as f32 + 0.5;
+            // nonzero winding along the row, pixels left of a crossing get its direction
+            let crossings:Vec<(f32, i32)> = segs.iter().filter_map(|s|{
+                if (s.a.1 <= y) == (s.b.1 <= y){
+                    return None
+                }
+                let cx = s.a.0 + (y - s.a.1) / (s.b.1 - s.a.1) * (s.b.0 - s.a.0);
+                Some((cx, if s.b.1 > y{1}else{-1}))
+            }).collect();
+            // segments by vertical distance to the row, the scan stops once that exceeds the bound
+            let mut rows:Vec<(f32, usize)> = segs.iter().enumerate().map(|(i, s)| ((s.a.1.min(s.b.1) - y).max(y - s.a.1.max(s.b.1)), i)).collect();
+            rows.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+            for px in 0..w{
+                let x = px as f32 + 0.5;
+                let mut min_dist = std::f32::MAX;
+                // per channel nearest segment as distance, orthogonality, index
+                let mut nearest = [(std::f32::MAX, 0.0, 0); 3];
+                // beyond the range the field saturates, so further segments dont matter
+                let mut furthest = cutoff;
+                // the nearest segments of the previous pixel go first to tighten the bound
+                for (dy, i) in seed.iter().map(|i| (0.0, *i)).chain(rows.iter().copied()){
+                    if dy > furthest + 1e-4{
+                        break
+                    }
+                    let s = &segs[i];
+                    // skip segments whose bounding box is further than every channel's nearest
+                    let bx = (s.a.0.min(s.b.0) - x).max(x - s.a.0.max(s.b.0));
+                    if bx > furthest + 1e-4{
+                        continue
+                    }
+                    let (ex, ey) = (s.b.0 - s.a.0, s.b.1 - s.a.1);
+                    let len = ex * ex + ey * ey;
+                    let t = (((x - s.a.0) * ex + (y - s.a.1) * ey) / len).max(0.0).min(1.0);
+                    let (dx, dy) = (x - s.a.0 - t * ex, y - s.a.1 - t * ey);
+                    let d = (dx * dx + dy * dy).sqrt();
+                    min_dist = min_dist.min(d);
+                    let ortho = if d > 0.0{((ex * dy - ey * dx) / (len.sqrt() * d)).abs()}else{1.0};
+                    for c in 0..3{
+                        if s.color & (1<<c) != 0{
+                            let n = &mut nearest[c];
+                            if d < n.0 - 1e-4 || (d < n.0 + 1e-4 && ortho > n.1){
+                                *n = (d, ortho, i);
+                            }
+                        }
+                    }
+                    furthest = nearest[0].0.max(nearest[1].0).max(nearest[2].0).min(cutoff);
+                }
+                seed = [nearest[0].2, nearest[1].2, nearest[2].2];
+                let winding:i32 = crossings.iter().filter(|(cx, _)| *cx > x).map(|(_, dir)| dir).sum();
+                let o = py * w + px;
+                inside[o] = winding != 0;
+                let min_dist = min_dist.min(cutoff);
+                let sdf = if inside[o]{min_dist}else{-min_dist};
+                for c in 0..3{
+                    let (d, _, i) = nearest[c];
+                    field[o][c] = if d == std::f32::MAX{sdf}else{orient * segs[i].pseudo_distance(x, y, d)};
+                }
+                // the median has to agree with the winding, otherwise fall back to the plain distance
+                if (median3(field[o]) > 0.0) != inside[o]{
+                    field[o] = [sdf;3];
+                }
+            }
+        }
+        // neighbours whose channels cross the edge differently interpolate into artifacts,
+        // flatten the one furthest from the edge to its median
+        let threshold = 1.001;
+        let mut clashes = Vec::new();
+        for py in 0..h{
+            for px in 0..w{
+                let a = field[py * w + px];
+                if (px + 1 < w && msdf_clash(a, field[py * w + px + 1], threshold)) || (px > 0 && msdf_clash(a, field[py * w + px - 1], threshold))
+                    || (py + 1 < h && msdf_clash(a, field[(py + 1) * w + px], threshold)) || (py > 0 && msdf_clash(a, field[(py - 1) * w + px], threshold)){
+                    clashes.push(py * w + px);
+                }
+            }
+        }
+        for o in clashes{
+            field[o] = [median3(field[o]);3];
+        }
+        let quantize = |d:f32| ((0.5 + 0.5 * d / range).max(0.0).min(1.0) * 255.0) as u32;
+        let out = field.iter().map(|f| (quantize(f[0])<<16) | (quantize(f[1])<<8) | quantize(f[2])).collect();
+        (w, h, -x0, -y0, out)
+    }
+}
+
+// a straight piece of a flattened contour with its msdf channels as bits, first and last
+// mark the ends of an edge between two corners, where the distance extends past the end
+struct MsdfSegment{
+    a:(f32, f32),
+    b:(f32, f32),
+    color:u8,
+    first:bool,
+    last:bool
+}
+
+impl MsdfSegment{
+    // signed by the side of the segment, positive on the left
+    fn pseudo_distance(&self, x:f32, y:f32, dist:f32)->f32{
+        let (ex, ey) = (self.b.0 - self.a.0, self.b.1 - self.a.1);
+        let len = (ex * ex + ey * ey).sqrt();
+        let t = ((x - self.a.0) * ex + (y - self.a.1) * ey) / (len * len);
+        let cross = (ex * (y - self.a.1) - ey * (x - self.a.0)) / len;
+        if (t < 0.0 && self.first) || (t > 1.0 && self.last){
+            return cross
+        }
+        if cross < 0.0{-dist}else{dist}
+    }
+}
+
+const MSDF_WHITE:u8 = 7;
+const MSDF_COLORS:[u8;3] = [6, 5, 3]; // cyan, magenta, yellow
+// sine of the turn above which a vertex counts as a corner
+const MSDF_CORNER:f32 = 0.5;
+
+fn median3(v:[f32;3])->f32{
+    v[0].min(v[1]).max(v[0].max(v[1]).min(v[2]))
+}
+
+fn msdf_clash(a:[f32;3], b:[f32;3], threshold:f32)->bool{
+    // sort the channel pairs from biggest to smallest difference
+    let mut pairs = [(a[0], b[0]), (a[1], b[1]), (a[2], b[2])];
+    pairs.sort_by(|p, q| (q.1 - q.0).abs().partial_cmp(&(p.1 - p.0).abs()).unwrap_or(std::cmp::Ordering::Equal));
+    (pairs[1].1 - pairs[1].0).abs() >= threshold
+        && !(b[0] == b[1] && b[0] == b[2])
+        && pairs[2].0.abs() >= pairs[2].1.abs()
+}
+
+// splits a closed polygon into edges at its corners and gives neighbouring edges
+// different channel pairs, smooth contours stay white and a single corner gets a teardrop
+fn color_msdf_contour(pts:&[(f32, f32)], segs:&mut Vec<MsdfSegment>){
+    let n = pts.len();
+    let dir = |i:usize|{
+        let (a, b) = (pts[i % n], pts[(i + 1) % n]);
+        let len = dist(a, b);
+        ((b.0 - a.0) / len, (b.1 - a.1) / len)
+    };
+    // corner i sits at the start of segment i
+    let corners:Vec<usize> = (0..n).filter(|i|{
+        let (p, q) = (dir(i + n - 1), dir(*i));
+        p.0 * q.0 + p.1 * q.1 <= 0.0 || (p.0 * q.1 - p.1 * q.0).abs() > MSDF_CORNER
+    }).collect();
+    let start = if let Some(c) = corners.first(){*c}else{0};
+    let mut colors = vec![MSDF_WHITE; n];
+    if corners.len() == 1{
+        for i in 0..n{
+            colors[(start + i) % n] = [MSDF_COLORS[1], MSDF_WHITE, MSDF_COLORS[2]][(3 * i / n).min(2)];
+        }
+    }
+    else if corners.len() > 1{
+        let mut color = 0;
+        let mut corner = 0;
+        for i in 0..n{
+            let index = (start + i) % n;
+            if corner + 1 < corners.len() && corners[corner + 1] == index{
+                corner += 1;
+                color = (color + 1) % 3;
+                // the last edge borders the first one as well
+                if corner == corners.len() - 1 && color == 0{
+                    color = 1;
+                }
+            }
+            colors[index] = MSDF_COLORS[color];
+        }
+    }
+    for i in 0..n{
+        segs.push(MsdfSegment{
+            a:pts[i],
+            b:pts[(i + 1) % n],
+            color:colors[i],
+            first:colors[(i + n - 1) % n] != colors[i] || corners.contains(&i),
+            last:colors[(i + 1) % n] != colors[i] || corners.contains(&((i + 1) % n))
+        });
+    }
+}
+
+fn flatten_quad(out:&mut Vec<(f32, f32)>, p0:(f32, f32), p1:(f32, f32), p2:(f32, f32), step_len:f32){
+    let len = dist(p0, p1) + dist(p1, p2);
+    let steps = ((len / step_len).ceil() as usize).max(1).min(16);
+    for i in 1..=steps{
+        let t = i as f32 / steps as f32;
+        let u = 1.0 - t;
+        out.push((
+            u * u * p0.0 + 2.0 * u * t * p1.0 + t * t * p2.0,
+            u * u * p0.1 + 2.0 * u * t * p1.1 + t * t * p2.1
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn outline(contour:&[(f32, f32)])->TTFOutline{
+        let mut outline = TTFOutline{contours:vec![contour.to_vec()], ..Default::default()};
+        outline.compute_bounds();
+        outline
+    }
+
+    // the median of a pixel and the center of it in the space of the outline
+    fn medians(outline:&TTFOutline, scale:f32, pad:usize)->Vec<(f32, f32, f32)>{
+        let (w, h, ox, oy, pixels) = outline.rasterize_msdf(scale, pad);
+        assert_eq!(pixels.len(), w * h);
+        let mut out = Vec::new();
+        for py in 0..h{
+            for px in 0..w{
+                let p = pixels[py * w + px];
+                let channels = [(p>>16) as f32 / 255.0, ((p>>8) & 0xff) as f32 / 255.0, (p & 0xff) as f32 / 255.0];
+                let x = (px as f32 + 0.5 - ox) / scale;
+                let y = ((h - 1 - py) as f32 + 0.5 - oy) / scale;
+                out.push((x, y, median3(channels)));
+            }
+        }
+        out
+    }
+
+    fn be16(out:&mut Vec<u8>, v:u16){
+        out.extend_from_slice(&v.to_be_bytes());
+    }
+
+    fn be32(out:&mut Vec<u8>, v:u32){
+        out.extend_from_slice(&v.to_be_bytes());
+    }
+
+    // a font file of the given tables, an OpenType one when there is a CFF table
+    fn sfnt(tables:&[(&str, Vec<u8>)])->Vec<u8>{
+        let mut out = Vec::new();
+        let cff = tables.iter().any(|(tag, _)| *tag == "CFF ");
+        out.extend_from_slice(if cff{b"OTTO"}else{b"\x00\x01\x00\x00"});
+        be16(&mut out, tables.len() as u16);
+        out.extend_from_slice(&[0; 6]);
+        let mut offset = 12 + tables.len() * 16;
+        for (tag, data) in tables{
+            out.extend_from_slice(tag.as_bytes());
+            be32(&mut out, 0);
+            be32(&mut out, offset as u32);
+            be32(&mut out, data.len() as u32);
+            offset += (data.len() + 3) & !3;
+        }
+        for (_, data) in tables{
+            out.extend_from_slice(data);
+            out.resize((out.len() + 3) & !3, 0);
+        }
+        out
+    }
+
+    // a font collection of sfnt files, their table offsets moved to count from the start of the collection
+    fn ttc(faces:&[Vec<u8>])->Vec<u8>{
+        let mut out = Vec::new();
+        out.extend_from_slice(b"ttcf");
+        be32(&mut out, 0x10000);
+        be32(&mut out, faces.len() as u32);
+        let mut base = 12 + faces.len() * 4;
+        for face in faces{
+            be32(&mut out, base as u32);
+            base += face.len();
+        }
+        for face in faces{
+            let base = out.len() as u32;
+            let start = out.len();
+            out.extend_from_slice(face);
+            let num_tables = u16::from_be_bytes([face[4], face[5]]) as usize;
+            for i in 0..num_tables{
+                let rec = start + 12 + i * 16 + 8;
+                let offset = u32::from_be_bytes([out[rec], out[rec + 1], out[rec + 2], out[rec + 3]]);
+                out[rec..rec + 4].copy_from_slice(&(offset + base).to_be_bytes());
+            }
+        }
+        out
+    }
+
+    fn lang_sys(features:&[u16])->Vec<u8>{
+        let mut out = Vec::new();
+        be16(&mut out, 0);
+        be16(&mut out, 0xffff);
+        be16(&mut out, features.len() as u16);
+        for feature in features{
+            be16(&mut out, *feature);
+        }
+        out
+    }
+
+    // a count, then (tag, offset) records of the tables behind them, offsets from the start of the list
+    fn record_list(items:&[(&[u8], Vec<u8>)])->Vec<u8>{
+        let mut out = Vec::new();
+        be16(&mut out, items.len() as u16);
+        let mut offset = 2 + items.iter().map(|(tag, _)| tag.len() + 2).sum::<usize>();
+        for (tag, table) in items{
+            out.extend_from_slice(tag);
+            be16(&mut out, offset as u16);
+            offset += table.len();
+        }
+        for (_, table) in items{
+            out.extend_from_slice(table);
+        }
+        out
+    }
+
+    // a GSUB with a two glyph ligature (first, second, ligature) per lookup, scripts as
+    // (tag, features of the default language system, languages with their features)
+    fn gsub(scripts:&[(&[u8;4], Option<&[u16]>, &[(&[u8;4], &[u16])])], features:&[(&[u8;4], u16)], ligatures:&[(u16, u16, u16)])->Vec<u8>{
+        let scripts:Vec<(&[u8], Vec<u8>)> = scripts.iter().map(|(tag, default, langs)|{
+            let mut script = Vec::new();
+            let mut offset = 4 + langs.len() * 6;
+            be16(&mut script, if default.is_some(){offset as u16}else{0});
+            be16(&mut script, langs.len() as u16);
+            offset += default.map_or(0, |features| lang_sys(features).len());
+            for (lang, features) in langs.iter(){
+                script.extend_from_slice(*lang);
+                be16(&mut script, offset as u16);
+                offset += lang_sys(features).len();
+            }
+            if let Some(features) = default{
+                script.extend_from_slice(&lang_sys(features));
+            }
+            for (_, features) in langs.iter(){
+                script.extend_from_slice(&lang_sys(features));
+            }
+            (&tag[..], script)
+        }).collect();
+        let features:Vec<(&[u8], Vec<u8>)> = features.iter().map(|(tag, lookup)|{
+            let mut feature = Vec::new();
+            for v in &[0, 1, *lookup]{
+                be16(&mut feature, *v);
+            }
+            (&tag[..], feature)
+        }).collect();
+        let lookups:Vec<(&[u8], Vec<u8>)> = ligatures.iter().map(|(first, second, lig)|{
+            let mut lookup = Vec::new();
+            // lookup, ligature subst, coverage, ligature set, ligature
+            for v in &[4, 0, 1, 8, 1, 8, 1, 14, 1, 1, *first, 1, 4, *lig, 2, *second]{
+                be16(&mut lookup, *v);
+            }
+            (&b""[..], lookup)
+        }).collect();
+        let (scripts, features, lookups) = (record_list(&scripts), record_list(&features), record_list(&lookups));
+        let mut out = Vec::new();
+        for v in &[1, 0, 10, 10 + scripts.len() as u16, 10 + (scripts.len() + features.len()) as u16]{
+            be16(&mut out, *v);
+        }
+        out.extend_from_slice(&scripts);
+        out.extend_from_slice(&features);
+        out.extend_from_slice(&lookups);
+        out
+    }
+
+    // head, hhea, maxp and hmtx of a font with 1000 units per em
+    fn metric_tables(num_glyphs:u16, advances:&[u16], long_loca:bool)->Vec<(&'static str, Vec<u8>)>{
+        let mut head = vec![0; 54];
+        head[18..20].copy_from_slice(&1000u16.to_be_bytes());
+        head[50..52].copy_from_slice(&(long_loca as u16).to_be_bytes());
+        let mut hhea = vec![0; 36];
+        hhea[4..6].copy_from_slice(&800i16.to_be_bytes());
+        hhea[6..8].copy_from_slice(&(-200i16).to_be_bytes());
+        hhea[34..36].copy_from_slice(&(advances.len() as u16).to_be_bytes());
+        let mut maxp = Vec::new();
+        be32(&mut maxp, 0x5000);
+        be16(&mut maxp, num_glyphs);
+        let mut hmtx = Vec::new();
+        for advance in advances{
+            be16(&mut hmtx, *advance);
+            be16(&mut hmtx, 0);
+        }
+        vec![("head", head), ("hhea", hhea), ("maxp", maxp), ("hmtx", hmtx)]
+    }
+
+    // a cmap with one (platform, encoding) record per subtable
+    fn cmap(subtables:&[(u16, u16, Vec<u8>)])->Vec<u8>{
+        let mut out = Vec::new();
+        be16(&mut out, 0);
+        be16(&mut out, subtables.len() as u16);
+        let mut offset = 4 + subtables.len() * 8;
+        for (platform, encoding, data) in subtables{
+            be16(&mut out, *platform);
+            be16(&mut out, *encoding);
+            be32(&mut out, offset as u32);
+            offset += data.len();
+        }
+        for (_, _, data) in subtables{
+            out.extend_from_slice(data);
+        }
+        out
+    }
+
+    // A..C map to 1..3 by delta, a maps to 5 and b to nothing through the glyph id array
+    fn cmap_format4()->Vec<u8>{
+        let mut out = Vec::new();
+        for v in &[4, 0, 0, 6, 4, 1, 2]{
+            be16(&mut out, *v);
+        }
+        for v in &[67, 98, 0xffff, 0, 65, 97, 0xffff, 1u16.wrapping_sub(65), 0, 1, 0, 4, 0, 5, 0]{
+            be16(&mut out, *v);
+        }
+        out
+    }
+
+    fn cmap_format12(groups:&[(u32, u32, u32)])->Vec<u8>{
+        let mut out = Vec::new();
+        be16(&mut out, 12);
+        be16(&mut out, 0);
+        be32(&mut out, 16 + groups.len() as u32 * 12);
+        be32(&mut out, 0);
+        be32(&mut out, groups.len() as u32);
+        for (start, end, glyph) in groups{
+            be32(&mut out, *start);
+            be32(&mut out, *end);
+            be32(&mut out, *glyph);
+        }
+        out
+    }
+
+    // a simple glyf glyph of one contour, points as (x, y, on curve)
+    fn simple_glyph(pts:&[(i16, i16, bool)])->Vec<u8>{
+        let mut out = Vec::new();
+        be16(&mut out, 1);
+        out.extend_from_slice(&[0; 8]);
+        be16(&mut out, pts.len() as u16 - 1);
+        be16(&mut out, 0);
+        // full 16 bit deltas, so only the on curve bit is set
+        for (_, _, on) in pts{
+            out.push(*on as u8);
+        }
+        let (mut x, mut y) = (0, 0);
+        for (px, _, _) in pts{
+            be16(&mut out, (px - x) as u16);
+            x = *px;
+        }
+        for (_, py, _) in pts{
+            be16(&mut out, (py - y) as u16);
+            y = *py;
+        }
+        out
+    }
+
+    const DEJAVU_SANS:&str = "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf";
+    const DEJAVU_MONO:&str = "/usr/share/fonts/truetype/dejavu/DejaVuSansMono.ttf";
+
+    fn dejavu(path:&str)->TrueTypeFont{
+        TrueTypeFont::parse(std::fs::read(path).expect("dejavu is installed")).expect("dejavu parses")
+    }
+
+    #[test]
+    fn msdf_of_a_square(){
+        // 20 pixels wide with a range of 4 pixels
+        let (scale, pad) = (0.2, 4);
+        let range = pad as f32 / scale;
+        let square = outline(&[(0., 0.), (100., 0.), (100., 100.), (0., 100.)]);
+        let pixels = medians(&square, scale, pad);
+        assert_eq!(pixels.len(), 28 * 28);
+        let mut near_edge = 0;
+        for (x, y, m) in pixels{
+            let inside = x > 0.0 && x < 100.0 && y > 0.0 && y < 100.0;
+            assert_eq!(m > 0.5, inside, "median {} at {} {}", m, x, y);
+            // beside an edge the median is the distance to it, outside the corners it is the
+            // distance to the nearest edge line, which keeps the corner sharp
+            let dx = (0.0 - x).max(x - 100.0);
+            let dy = (0.0 - y).max(y - 100.0);
+            let distance = dx.max(dy);
+            if distance.abs() < range{
+                let expect = 0.5 - 0.5 * distance / range;
+                assert!((m - expect).abs() < 0.02, "median {} expected {} at {} {}", m, expect, x, y);
+                near_edge += 1;
+            }
+        }
+        assert!(near_edge > 0);
+    }
+
+    #[test]
+    fn msdf_of_a_clockwise_triangle(){
+        // the winding of the outline doesn't matter
+        let (scale, pad) = (0.2, 4);
+        let range = pad as f32 / scale;
+        let corners = [(0., 0.), (50., 100.), (100., 0.)];
+        let triangle = outline(&corners);
+        for (x, y, m) in medians(&triangle, scale, pad){
+            // signed distance to each side, positive on the inside
+            let sides:Vec<f32> = (0..3).map(|i|{
+                let (a, b) = (corners[i], corners[(i + 1) % 3]);
+                let (ex, ey) = (b.0 - a.0, b.1 - a.1);
+                (ey * (x - a.0) - ex * (y - a.1)) / (ex * ex + ey * ey).sqrt()
+            }).collect();
+            let inside = sides.iter().all(|d| *d > 0.0);
+            assert_eq!(m > 0.5, inside, "median {} at {} {}", m, x, y);
+            // inside the median is the distance to the nearest side
+            let nearest = sides.iter().cloned().fold(std::f32::MAX, f32::min);
+            if inside && nearest < range{
+                let expect = 0.5 + 0.5 * nearest / range;
+                assert!((m - expect).abs() < 0.02, "median {} expected {} at {} {}", m, expect, x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn cmap_format_4_deltas_and_glyph_ids(){
+        let mut tables = metric_tables(6, &[500], false);
+        tables.push(("cmap", cmap(&[(3, 1, cmap_format4())])));
+        tables.push(("glyf", Vec::new()));
+        tables.push(("loca", vec![0; 14]));
+        let ttf = TrueTypeFont::parse(sfnt(&tables)).expect("font parses");
+        let glyphs:Vec<u32> = "ABCDab".chars().map(|c| ttf.glyph_index(c as u32)).collect();
+        assert_eq!(glyphs, vec![1, 2, 3, 0, 5, 0]);
+        // consecutive codepoints and glyphs are one range
+        assert_eq!(ttf.cmap.len(), 2);
+        assert_eq!((ttf.cmap[0].start, ttf.cmap[0].end), (65, 67));
+    }
+
+    #[test]
+    fn cmap_format_12_wins_and_stays_in_range(){
+        let mut tables = metric_tables(20, &[500], false);
+        tables.push(("cmap", cmap(&[
+            (3, 1, cmap_format4()),
+            (3, 10, cmap_format12(&[(65, 67, 1), (0x1f600, 0x1f601, 10), (0x20000, 0x20010, 0xffff_fff8)]))
+        ])));
+        tables.push(("glyf", Vec::new()));
+        tables.push(("loca", vec![0; 42]));
+        let ttf = TrueTypeFont::parse(sfnt(&tables)).expect("font parses");
+        assert_eq!(ttf.glyph_index(66), 2);
+        assert_eq!(ttf.glyph_index(0x1f601), 11);
+        // the format 4 mapping of a is not used
+        assert_eq!(ttf.glyph_index(97), 0);
+        // a group that overflows or points past the glyphs maps to nothing
+        assert_eq!(ttf.glyph_index(0x20000), 0);
+        assert_eq!(ttf.glyph_index(0x20010), 0);
+    }
+
+    #[test]
+    fn hmtx_repeats_the_last_advance(){
+        let mut tables = metric_tables(4, &[500, 600], false);
+        tables.push(("cmap", cmap(&[(3, 1, cmap_format4())])));
+        tables.push(("glyf", Vec::new()));
+        tables.push(("loca", vec![0; 10]));
+        let ttf = TrueTypeFont::parse(sfnt(&tables)).expect("font parses");
+        assert_eq!(ttf.units_per_em, 1000.0);
+        assert_eq!((ttf.ascender, ttf.descender), (800.0, -200.0));
+        assert_eq!(ttf.advances, vec![500, 600, 600, 600]);
+        assert_eq!(ttf.advance(3), 600.0);
+        assert_eq!(ttf.advance(4), 0.0);
+    }
+
+    #[test]
+    fn collection_faces(){
+        let face = |advance:u16|{
+            let mut tables = metric_tables(4, &[advance], false);
+            tables.push(("cmap", cmap(&[(3, 1, cmap_format4())])));
+            tables.push(("glyf", Vec::new()));
+            tables.push(("loca", vec![0; 10]));
+            sfnt(&tables)
+        };
+        let data = ttc(&[face(500), face(700)]);
+        assert!(TrueTypeFont::is_ttf(&data));
+        assert_eq!(TrueTypeFont::parse(data.clone()).expect("face 0 parses").advance(1), 500.0);
+        let second = TrueTypeFont::parse_face(data.clone(), 1).expect("face 1 parses");
+        assert_eq!(second.advance(1), 700.0);
+        assert_eq!(second.glyph_index('A' as u32), 1);
+        assert_eq!(TrueTypeFont::parse_face(data, 2).err(), Some("Font collection has no face 2".to_string()));
+        assert_eq!(TrueTypeFont::parse_face(face(500), 1).err(), Some("Font has no face 1".to_string()));
+    }
+
+    #[test]
+    fn kern_table_pairs(){
+        let mut kern = Vec::new();
+        be16(&mut kern, 0);
+        be16(&mut kern, 2);
+        // horizontal, then a vertical subtable that is skipped
+        for (coverage, pairs) in &[(1u16, vec![(1u16, 2u16, -50i16), (2, 3, 20)]), (0, vec![(1, 3, -99)])]{
+            be16(&mut kern, 0);
+            be16(&mut kern, 14 + pairs.len() as u16 * 6);
+            be16(&mut kern, *coverage);
+            be16(&mut kern, pairs.len() as u16);
+            kern.extend_from_slice(&[0; 6]);
+            for (left, right, value) in pairs{
+                be16(&mut kern, *left);
+                be16(&mut kern, *right);
+                be16(&mut kern, *value as u16);
+            }
+        }
+        let mut tables = metric_tables(4, &[500], false);
+        tables.push(("cmap", cmap(&[(3, 1, cmap_format4())])));
+        tables.push(("glyf", Vec::new()));
+        tables.push(("kern", kern));
+        tables.push(("loca", vec![0; 10]));
+        let ttf = TrueTypeFont::parse(sfnt(&tables)).expect("font parses");
+        assert_eq!(ttf.pair_kern(1, 2), -50.0);
+        assert_eq!(ttf.pair_kern(2, 3), 20.0);
+        assert_eq!(ttf.pair_kern(2, 1), 0.0);
+        assert_eq!(ttf.pair_kern(1, 3), 0.0);
+    }
+
+    #[test]
+    fn glyf_simple_quadratic_and_composite_outlines(){
+        let square = simple_glyph(&[(0, 0, true), (100, 0, true), (100, 200, true), (0, 200, true)]);
+        let arch = simple_glyph(&[(0, 0, true), (50, 100, false), (100, 0, true)]);
+        // glyph 1 at half size, moved by 100, 50
+        let mut composite = Vec::new();
+        for v in &[0xffffu16, 0, 0, 0, 0, 0x000b, 1, 100, 50, 0x2000]{
+            be16(&mut composite, *v);
+        }
+        let mut glyf = Vec::new();
+        let mut loca = Vec::new();
+        be16(&mut loca, 0);
+        for glyph in &[Vec::new(), square, arch, composite]{
+            glyf.extend_from_slice(glyph);
+            glyf.resize((glyf.len() + 1) & !1, 0);
+            be16(&mut loca, glyf.len() as u16 / 2);
+        }
+        let mut tables = metric_tables(4, &[500], false);
+        tables.push(("cmap", cmap(&[(3, 1, cmap_format4())])));
+        tables.push(("glyf", glyf));
+        tables.push(("loca", loca));
+        let ttf = TrueTypeFont::parse(sfnt(&tables)).expect("font parses");
+        let bounds = |o:&TTFOutline| (o.x_min, o.y_min, o.x_max, o.y_max);
+
+        assert_eq!(ttf.outline(0).unwrap().contours.len(), 0);
+        let square = ttf.outline(1).unwrap();
+        assert_eq!(square.contours.len(), 1);
+        assert_eq!(&square.contours[0][0..4], &[(0., 0.), (100., 0.), (100., 200.), (0., 200.)]);
+        assert_eq!(bounds(&square), (0., 0., 100., 200.));
+        // the off curve point pulls the curve up to half its height
+        let arch = ttf.outline(2).unwrap();
+        assert!(arch.contours[0].len() > 4);
+        assert!(arch.y_max > 49.0 && arch.y_max <= 50.0, "arch peaks at {}", arch.y_max);
+        let composite = ttf.outline(3).unwrap();
+        assert_eq!(bounds(&composite), (100., 50., 150., 150.));
+        // past the glyphs is empty
+        assert_eq!(ttf.outline(9).unwrap().contours.len(), 0);
+    }
+
+    #[test]
+    fn cff_charstrings_and_subroutines(){
+        // a square drawn as 100 100 rmoveto 200 0 rlineto 0 200 rlineto, then -200 0 rlineto
+        // in the only global subroutine, and a curve
+        let square = vec![239, 239, 21, 247, 92, 139, 5, 139, 247, 92, 5, 32, 29, 14];
+        let curve = vec![139, 139, 21, 139, 239, 247, 92, 139, 139, 39, 8, 14];
+        let subr = vec![251, 92, 139, 5, 11];
+        let index = |objects:&[&[u8]]|->Vec<u8>{
+            let mut out = Vec::new();
+            be16(&mut out, objects.len() as u16);
+            if objects.is_empty(){
+                return out
+            }
+            out.push(1);
+            let mut offset = 1;
+            out.push(offset as u8);
+            for object in objects{
+                offset += object.len();
+                out.push(offset as u8);
+            }
+            for object in objects{
+                out.extend_from_slice(object);
+            }
+            out
+        };
+        let header = [1u8, 0, 4, 1];
+        let names = index(&[b"t"]);
+        let top_len = index(&[&[28, 0, 0, 17]]).len();
+        let strings = index(&[]);
+        let subrs = index(&[&subr]);
+        let char_strings_off = (header.len() + names.len() + top_len + strings.len() + subrs.len()) as u16;
+        let top = index(&[&[28, (char_strings_off >> 8) as u8, char_strings_off as u8, 17]]);
+        let mut cff = Vec::new();
+        for part in &[&header[..], &names, &top, &strings, &subrs, &index(&[&[14], &square, &curve])]{
+            cff.extend_from_slice(part);
+        }
+        let mut tables = metric_tables(3, &[500], false);
+        tables.push(("CFF ", cff));
+        tables.push(("cmap", cmap(&[(3, 1, cmap_format4())])));
+        let ttf = TrueTypeFont::parse(sfnt(&tables)).expect("font parses");
+        assert!(ttf.cff.is_some());
+        assert_eq!(ttf.glyph_index('B' as u32), 2);
+
+        assert_eq!(ttf.outline(0).unwrap().contours.len(), 0);
+        let square = ttf.outline(1).unwrap();
+        assert_eq!(square.contours, vec![vec![(100., 100.), (300., 100.), (300., 300.), (100., 300.)]]);
+        let curve = ttf.outline(2).unwrap();
+        assert_eq!(curve.contours.len(), 1);
+        assert_eq!((curve.x_min, curve.x_max), (0., 200.));
+        assert!((curve.y_max - 75.0).abs() < 0.5, "curve peaks at {}", curve.y_max);
+    }
+
+    #[test]
+    fn ligatures_follow_the_script_and_language_system(){
+        let gsub = gsub(&[
+            (b"cyrl", None, &[(b"SRB ", &[3])]),
+            (b"grek", Some(&[1]), &[]),
+            (b"latn", Some(&[0]), &[])
+        ], &[(b"liga", 0), (b"clig", 1), (b"liga", 2), (b"liga", 3)], &[(1, 2, 7), (3, 4, 8), (5, 6, 9), (1, 2, 10)]);
+        let mut tables = metric_tables(11, &[500], false);
+        // f i, and cyrillic lookalikes map to 1 and 2, greek phi iota to 3 and 4, digits 1 2 to 5 and 6
+        let groups = [(0x31, 0x32, 5), (0x66, 0x66, 1), (0x69, 0x69, 2), (0x3b9, 0x3b9, 4), (0x3c6, 0x3c6, 3), (0x444, 0x444, 1), (0x456, 0x456, 2)];
+        tables.push(("GSUB", gsub));
+        tables.push(("cmap", cmap(&[(3, 10, cmap_format12(&groups))])));
+        tables.push(("glyf", Vec::new()));
+        tables.push(("loca", vec![0; 24]));
+        let ttf = TrueTypeFont::parse(sfnt(&tables)).expect("font parses");
+        let lookups:Vec<(&[u8], &[u16])> = ttf.gsub_scripts.iter().map(|s| (&s.tag[..], &s.lookups[..])).collect();
+        assert_eq!(lookups, vec![(&b"cyrl"[..], &[][..]), (b"grek", &[1]), (b"latn", &[0])]);
+        // lookups no default language system enables are never parsed
+        assert_eq!(ttf.gsub_ligatures.iter().map(|(id, _)| *id).collect::<Vec<_>>(), vec![0, 1]);
+        let glyphs = |text:&str, ligatures:bool|->Vec<u32>{
+            let chars:Vec<char> = text.chars().collect();
+            ttf.shape(&chars, ligatures).iter().map(|s| s.glyph).collect()
+        };
+        // liga in latin, clig in greek, each run of a mixed text by its own script
+        assert_eq!(glyphs("fi", true), vec![7]);
+        assert_eq!(glyphs("\u{3c6}\u{3b9}", true), vec![8]);
+        assert_eq!(glyphs("fi\u{3c6}\u{3b9}fi", true), vec![7, 8, 7]);
+        assert_eq!(glyphs("fi", false), vec![1, 2]);
+        // digits fall back to latin as there is no DFLT, where their ligature isnt enabled
+        assert_eq!(glyphs("12", true), vec![5, 6]);
+        // cyrillic only enables a ligature for serbian, and has no default to fall back from
+        assert_eq!(glyphs("\u{444}\u{456}", true), vec![1, 2]);
+        let shaped = ttf.shape(&['f', 'i', 'x'], true);
+        assert_eq!((shaped[0].chars, shaped.len()), (2, 2));
+    }
+
+    #[test]
+    fn dejavu_ligatures_and_marks_by_script(){
+        let ttf = dejavu(DEJAVU_SANS);
+        let lookups = |scripts:&[TTFScript], tag:&[u8;4]| scripts.iter().find(|s| &s.tag == tag).map(|s| s.lookups.clone());
+        // f ligatures are latin only, the arabic ones only arabic, and the default script has none
+        let latn_liga = lookups(&ttf.gsub_scripts, b"latn").unwrap();
+        let arab_liga = lookups(&ttf.gsub_scripts, b"arab").unwrap();
+        assert!(latn_liga.len() > 0 && arab_liga.len() > 0 && latn_liga.iter().all(|id| !arab_liga.contains(id)));
+        assert_eq!(lookups(&ttf.gsub_scripts, b"DFLT"), Some(vec![]));
+        // hebrew has mark but no mkmk, the default script neither
+        let latn_marks = lookups(&ttf.gpos_scripts, b"latn").unwrap();
+        let hebr_marks = lookups(&ttf.gpos_scripts, b"hebr").unwrap();
+        assert!(hebr_marks.len() > 0 && hebr_marks.iter().all(|id| !latn_marks.contains(id)));
+        assert_eq!(lookups(&ttf.gpos_scripts, b"DFLT"), Some(vec![]));
+
+        let shape = |text:&str, ligatures:bool|->Vec<TTFShaped>{
+            let chars:Vec<char> = text.chars().collect();
+            ttf.shape(&chars, ligatures)
+        };
+        let f = ttf.glyph_index('f' as u32);
+        let office = shape("office", true);
+        assert_eq!(office.iter().map(|s| s.chars).collect::<Vec<_>>(), vec![1, 3, 1, 1]);
+        assert!(office[1].glyph != f && office[1].advance == ttf.advance(office[1].glyph));
+        assert_eq!(shape("office", false).len(), 6);
+        // ffi and fi are different ligatures
+        assert!(shape("fi", true)[0].glyph != office[1].glyph && shape("fi", true)[0].chars == 2);
+
+        // an acute goes above the base, centered over it rather than after it
+        let acute = shape("a\u{301}", false);
+        let a_advance = ttf.advance(ttf.glyph_index('a' as u32));
+        assert_eq!((acute[0].dx, acute[0].dy, acute[1].advance), (0.0, 0.0, 0.0));
+        assert!(acute[1].dx < 0.0 && acute[1].dx > -a_advance);
+        // higher over a capital, and a second acute stacks on the first through mkmk
+        assert!(shape("A\u{301}", false)[1].dy > acute[1].dy);
+        let stacked = shape("a\u{301}\u{301}", false);
+        assert_eq!(stacked[1], acute[1]);
+        assert!(stacked[2].dy > stacked[1].dy && stacked[2].advance == 0.0);
+    }
+
+    #[test]
+    fn dejavu_cmap_metrics_and_outlines(){
+        let ttf = dejavu(DEJAVU_SANS);
+        assert_eq!(ttf.units_per_em, 2048.0);
+        assert!(ttf.num_glyphs > 5000);
+        for c in &['A', 'e', '\u{e9}', '\u{416}', '\u{20ac}', '\u{2500}']{
+            assert!(ttf.glyph_index(*c as u32) != 0, "{} is not mapped", c);
+        }
+        assert_eq!(ttf.glyph_index(0x10ffff), 0);
+        // o has a hole, space has no outline
+        let o = ttf.outline(ttf.glyph_index('o' as u32)).unwrap();
+        assert_eq!(o.contours.len(), 2);
+        assert!(o.x_min > 0.0 && o.x_max < ttf.advance(ttf.glyph_index('o' as u32)));
+        assert_eq!(ttf.outline(ttf.glyph_index(' ' as u32)).unwrap().contours.len(), 0);
+        // the accented e is a composite of e and the accent above it
+        let e = ttf.outline(ttf.glyph_index('e' as u32)).unwrap();
+        let e_acute = ttf.outline(ttf.glyph_index(0xe9)).unwrap();
+        assert_eq!(e_acute.contours.len(), e.contours.len() + 1);
+        assert!(e_acute.y_max > e.y_max);
+        // A V kerns closer
+        assert!(ttf.pair_kern(ttf.glyph_index('A' as u32), ttf.glyph_index('V' as u32)) < 0.0);
+
+        let mono = dejavu(DEJAVU_MONO);
+        let advance = mono.advance(mono.glyph_index('M' as u32));
+        assert!(advance > 0.0);
+        for c in "il.W\u{2500}".chars(){
+            assert_eq!(mono.advance(mono.glyph_index(c as u32)), advance, "{} is not monospaced", c);
+        }
+    }
+
+}
@@ -24,6 +24,7 @@ where TScrollBar: ScrollBarLike<TScrollBar> + Clone + ElementLife
     pub draw_list_id:Option<usize>,
     pub is_clipped:bool,
     pub is_overlay:bool,// this view is an overlay, rendered last
+    pub render_to_texture:bool,// this view renders into a texture instead of its parent
     pub scroll_h:Option<TScrollBar>,
     pub scroll_v:Option<TScrollBar>,
 }
@@ -35,6 +36,7 @@ where TScrollBar: ScrollBarLike<TScrollBar> + Clone + ElementLife
         Self{
             is_clipped:true,
             is_overlay:false,
+            render_to_texture:false,
             draw_list_id:None,
             scroll_h:None,
             scroll_v:None
@@ -42,6 +44,25 @@ where TScrollBar: ScrollBarLike<TScrollBar> + Clone + ElementLife
     }
 }
 
+impl<TScrollBar> ElementLife for View<TScrollBar>
+where TScrollBar: ScrollBarLike<TScrollBar> + Clone + ElementLife
+{
+    fn construct(&mut self, _cx:&mut Cx){}
+
+    // gives the draw list and the texture it renders into back to cx
+    fn destruct(&mut self, cx:&mut Cx){
+        if let Some(draw_list_id) = self.draw_list_id.take(){
+            cx.free_draw_list(draw_list_id);
+        }
+        if let Some(scroll_h) = &mut self.scroll_h{
+            scroll_h.destruct(cx);
+        }
+        if let Some(scroll_v) = &mut self.scroll_v{
+            scroll_v.destruct(cx);
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct NoScrollBar{
 }
@@ -88,14 +109,24 @@ where TScrollBar: ScrollBarLike<TScrollBar> + Clone + ElementLife
                 self.draw_list_id =  Some(cx.draw_lists.len());
                 cx.draw_lists.push(DrawList{..Default::default()});
             }
+            let render_target = if self.render_to_texture{
+                let tex = cx.new_empty_texture_2d();
+                tex.render_target = true;
+                Some(tex.texture_id)
+            }
+            else{
+                None
+            };
             let draw_list = &mut cx.draw_lists[self.draw_list_id.unwrap()];
             draw_list.initialize(self.is_clipped, cx.redraw_id);
+            draw_list.render_target = render_target;
         }
         else{
             // set len to 0
             let draw_list = &mut cx.draw_lists[self.draw_list_id.unwrap()];
             draw_list.redraw_id = cx.redraw_id;
             draw_list.draw_calls_len = 0;
+            draw_list.area_groups.truncate(0);
         }
         let draw_list_id = self.draw_list_id.unwrap();
         
@@ -280,6 +311,14 @@ where TScrollBar: ScrollBarLike<TScrollBar> + Clone + ElementLife
     }
 
 
+    // the texture a render_to_texture view draws into, bind it with push_uniform_texture_2d
+    pub fn get_texture_id(&self, cx:&Cx)->Option<usize>{
+        if let Some(draw_list_id) = self.draw_list_id{
+            return cx.draw_lists[draw_list_id].render_target
+        }
+        None
+    }
+
     pub fn redraw_view_area(&self, cx:&mut Cx){
         if let Some(draw_list_id) = self.draw_list_id{
             let draw_list = &cx.draw_lists[draw_list_id];
@@ -0,0 +1,446 @@
+use render::*;
+
+// a query box over a list of commands or files, fuzzy matched as you type. The app opens it
+// with the items and gets the picked one back as an event
+#[derive(Clone)]
+pub struct CommandPalette{
+    pub view:View<NoScrollBar>,
+    pub bg:Quad,
+    pub input_bg:Quad,
+    pub row_bg:Quad,
+    pub cursor:Quad,
+    pub text:Text,
+    pub bg_color:Color,
+    pub input_color:Color,
+    pub selected_color:Color,
+    pub text_color:Color,
+    pub match_color:Color,
+    pub detail_color:Color,
+    pub width:f32,
+    pub top:f32,
+    pub row_height:f32,
+    pub visible_rows:usize,
+    pub mode:Option<PaletteMode>, // None while closed
+    pub query:String,
+    pub items:Vec<PaletteItem>,
+    pub _matches:Vec<(usize, Vec<usize>)>, // item and the chars of its label that matched, best first
+    pub _selected:usize,
+    pub _scroll:usize, // the first match drawn, only visible_rows of them are
+    pub _bg_area:Area,
+    pub _hit_state:HitState,
+    pub _panel_rect:Rect,
+    pub _row_rects:Vec<(usize, Rect)>,
+    pub _last_focus:Area,
+    pub _take_focus:bool
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PaletteMode{
+    Commands,
+    Files
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PaletteItem{
+    pub label:String, // what the query matches
+    pub detail:String, // shown on the right, the keys of a command
+    pub value:String // the command name or the file path
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CommandPaletteEvent{
+    None,
+    Command{command:String},
+    OpenFile{path:String}
+}
+
+impl Style for CommandPalette{
+    fn style(cx:&mut Cx)->Self{
+        Self::register_commands(cx);
+        Self{
+            view:View{
+                is_overlay:true,
+                is_clipped:false,
+                ..Style::style(cx)
+            },
+            bg:Quad{
+                ..Style::style(cx)
+            },
+            input_bg:Quad{
+                ..Style::style(cx)
+            },
+            row_bg:Quad{
+                ..Style::style(cx)
+            },
+            cursor:Quad{
+                ..Style::style(cx)
+            },
+            text:Text{
+                wrapping:Wrapping::None,
+                ..Style::style(cx)
+            },
+            bg_color:color("#252526"),
+            input_color:color("#3c3c3c"),
+            selected_color:color("#094771"),
+            text_color:color("#ccc"),
+            match_color:color("#4af"),
+            detail_color:color("#888"),
+            width:500.,
+            top:40.,
+            row_height:22.,
+            visible_rows:12,
+            mode:None,
+            query:String::new(),
+            items:Vec::new(),
+            _matches:Vec::new(),
+            _selected:0,
+            _scroll:0,
+            _bg_area:Area::Empty,
+            _hit_state:HitState{..Default::default()},
+            _panel_rect:Rect::zero(),
+            _row_rects:Vec::new(),
+            _last_focus:Area::Empty,
+            _take_focus:false
+        }
+    }
+}
+
+impl CommandPalette{
+    pub fn register_commands(cx:&mut Cx){
+        let commands = [
+            ("palette.select_prev", "Select Previous", "Up"),
+            ("palette.select_next", "Select Next", "Down"),
+            ("palette.page_up", "Page Up", "PageUp"),
+            ("palette.page_down", "Page Down", "PageDown"),
+            ("palette.delete_left", "Delete Left", "Backspace"),
+            ("palette.accept", "Run Selected", "Enter"),
+            ("palette.close", "Close", "Escape"),
+        ];
+        for (name, title, keys) in commands.iter(){
+            cx.register_command(name, title);
+            cx.bind_keys(keys, name, Some("palette")).expect("default palette keys parse");
+        }
+    }
+
+    // every registered command with the keys it has where it applies, the palette's own left out
+    pub fn command_items(cx:&Cx)->Vec<PaletteItem>{
+        cx.keymap.commands.iter().filter(|c| !c.name.starts_with("palette.")).map(|command|{
+            let context = command.name.split('.').next().unwrap_or("");
+            PaletteItem{
+                label:format!("{}: {}", Self::context_title(context), command.title),
+                detail:cx.command_keys(&command.name, context).unwrap_or_default(),
+                value:command.name.clone()
+            }
+        }).collect()
+    }
+
+    pub fn file_items(paths:&[String])->Vec<PaletteItem>{
+        paths.iter().map(|path| PaletteItem{
+            label:path.trim_start_matches('/').to_string(),
+            detail:String::new(),
+            value:path.clone()
+        }).collect()
+    }
+
+    // file_tree becomes File Tree
+    fn context_title(context:&str)->String{
+        context.split('_').map(|word|{
+            let mut chars = word.chars();
+            match chars.next(){
+                Some(first)=>first.to_uppercase().chain(chars).collect::<String>(),
+                None=>String::new()
+            }
+        }).collect::<Vec<_>>().join(" ")
+    }
+
+    pub fn open_palette(&mut self, cx:&mut Cx, mode:PaletteMode, items:Vec<PaletteItem>){
+        if self.mode.is_none(){
+            self._last_focus = cx.key_focus;
+        }
+        self.mode = Some(mode);
+        self.items = items;
+        self.query = String::new();
+        self._take_focus = true;
+        self.update_matches();
+        self.view.redraw_view_area(cx);
+    }
+
+    // gives the key focus back to where it was when it opened, unless it went somewhere else
+    pub fn close_palette(&mut self, cx:&mut Cx){
+        if self.mode.is_none(){
+            return
+        }
+        if cx.key_focus == self._bg_area{
+            cx.set_key_focus(self._last_focus);
+        }
+        self.mode = None;
+        self._last_focus = Area::Empty;
+        self.view.redraw_view_area(cx);
+    }
+
+    pub fn matches(&self)->Vec<&PaletteItem>{
+        self._matches.iter().map(|(index, _)| &self.items[*index]).collect()
+    }
+
+    pub fn set_query(&mut self, cx:&mut Cx, query:&str){
+        self.query = query.to_string();
+        self.update_matches();
+        self.view.redraw_view_area(cx);
+    }
+
+    fn update_matches(&mut self){
+        let mut scored:Vec<(i32, usize, Vec<usize>)> = Vec::new();
+        for (index, item) in self.items.iter().enumerate(){
+            if let Some((score, chars)) = fuzzy_match(&self.query, &item.label){
+                scored.push((score, index, chars));
+            }
+        }
+        // stable, so equal scores keep the order they were given in
+        let items = &self.items;
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then(items[a.1].label.len().cmp(&items[b.1].label.len())));
+        self._matches = scored.into_iter().map(|(_, index, chars)| (index, chars)).collect();
+        self._selected = 0;
+        self._scroll = 0;
+    }
+
+    fn select(&mut self, cx:&mut Cx, selected:isize){
+        if self._matches.len() == 0{
+            return
+        }
+        self._selected = selected.max(0).min(self._matches.len() as isize - 1) as usize;
+        if self._selected < self._scroll{
+            self._scroll = self._selected;
+        }
+        else if self._selected >= self._scroll + self.visible_rows{
+            self._scroll = self._selected + 1 - self.visible_rows;
+        }
+        self.view.redraw_view_area(cx);
+    }
+
+    fn accept(&mut self, cx:&mut Cx)->CommandPaletteEvent{
+        let mode = self.mode.clone();
+        let value = self._matches.get(self._selected).map(|(index, _)| self.items[*index].value.clone());
+        self.close_palette(cx);
+        match (mode, value){
+            (Some(PaletteMode::Commands), Some(command))=>CommandPaletteEvent::Command{command:command},
+            (Some(PaletteMode::Files), Some(path))=>CommandPaletteEvent::OpenFile{path:path},
+            _=>CommandPaletteEvent::None
+        }
+    }
+
+    pub fn exec_command(&mut self, cx:&mut Cx, command:&str)->CommandPaletteEvent{
+        let selected = self._selected as isize;
+        let page = self.visible_rows as isize - 1;
+        match command{
+            "palette.select_prev"=>self.select(cx, selected - 1),
+            "palette.select_next"=>self.select(cx, selected + 1),
+            "palette.page_up"=>self.select(cx, selected - page),
+            "palette.page_down"=>self.select(cx, selected + page),
+            "palette.delete_left"=>{
+                let mut query = self.query.clone();
+                query.pop();
+                self.set_query(cx, &query);
+            },
+            "palette.accept"=>return self.accept(cx),
+            "palette.close"=>self.close_palette(cx),
+            _=>()
+        }
+        CommandPaletteEvent::None
+    }
+
+    pub fn handle_command_palette(&mut self, cx:&mut Cx, event:&mut Event)->CommandPaletteEvent{
+        if self.mode.is_none(){
+            return CommandPaletteEvent::None
+        }
+        match event.hits(cx, self._bg_area, &mut self._hit_state){
+            Event::KeyDown(ke)=>{
+                if let Some(command) = cx.key_command(&ke, "palette"){
+                    return self.exec_command(cx, &command)
+                }
+            },
+            Event::Command(ce)=>{
+                return self.exec_command(cx, &ce.command)
+            },
+            Event::TextInput(te)=>{
+                let mut query = self.query.clone();
+                query.extend(te.input.chars().filter(|c| !c.is_control()));
+                self.set_query(cx, &query);
+            },
+            Event::FingerDown(fe)=>{
+                if let Some((index, _)) = self._row_rects.iter().find(|(_, rect)| rect.contains(fe.abs.x, fe.abs.y)){
+                    self._selected = *index;
+                    return self.accept(cx)
+                }
+            },
+            Event::FingerScroll(fe)=>{
+                let rows = (fe.scroll.y / self.row_height).round() as isize;
+                let max_scroll = self._matches.len().saturating_sub(self.visible_rows) as isize;
+                self._scroll = (self._scroll as isize + rows).max(0).min(max_scroll) as usize;
+                self.view.redraw_view_area(cx);
+            },
+            _=>()
+        }
+        // clicking anywhere else closes it
+        if let Event::FingerDown(fe) = event{
+            if !self._panel_rect.contains(fe.abs.x, fe.abs.y){
+                self.close_palette(cx);
+            }
+        }
+        CommandPaletteEvent::None
+    }
+
+    // call after everything else is drawn
+    pub fn draw_command_palette(&mut self, cx:&mut Cx){
+        let mode = if let Some(mode) = &self.mode{mode.clone()} else{return};
+        self.view.begin_view(cx, &Layout{
+            abs_start:Some(Vec2::zero()),
+            ..Default::default()
+        });
+        self.bg.color = self.bg_color;
+        let inst = self.bg.begin_quad(cx, &Layout{
+            abs_start:Some(Vec2{x:((cx.target_size.x - self.width) * 0.5).max(0.), y:self.top}),
+            width:Bounds::Fix(self.width),
+            height:Bounds::Compute,
+            padding:Padding::all(6.),
+            ..Default::default()
+        });
+
+        // the query with a caret
+        self.input_bg.color = self.input_color;
+        let input = self.input_bg.begin_quad(cx, &Layout{
+            width:Bounds::Fill,
+            height:Bounds::Fix(self.row_height + 4.),
+            padding:Padding{l:6., t:0., r:6., b:0.},
+            align:Align::left_center(),
+            margin:Margin{l:0., t:0., r:0., b:4.},
+            ..Default::default()
+        });
+        self.text.color = self.text_color;
+        let prefix = if mode == PaletteMode::Commands{">"} else{""};
+        self.text.draw_text(cx, &format!("{}{}", prefix, self.query));
+        self.cursor.color = self.text_color;
+        self.cursor.draw_quad_walk(cx, Bounds::Fix(1.), Bounds::Fix(self.row_height - 6.), Margin{l:1., t:0., r:0., b:0.});
+        self.input_bg.end_quad(cx, &input);
+        cx.turtle_new_line();
+
+        self._row_rects.truncate(0);
+        if self._matches.len() == 0{
+            self.text.color = self.detail_color;
+            self.text.draw_text(cx, if mode == PaletteMode::Commands{"No matching commands"} else{"No matching files"});
+        }
+        let end = (self._scroll + self.visible_rows).min(self._matches.len());
+        for index in self._scroll..end{
+            let (item, chars) = &self._matches[index];
+            let item = &self.items[*item];
+            self.row_bg.color = if index == self._selected{self.selected_color} else{color("#0000")};
+            let row = self.row_bg.begin_quad(cx, &Layout{
+                width:Bounds::Fill,
+                height:Bounds::Fix(self.row_height),
+                padding:Padding{l:6., t:0., r:6., b:0.},
+                align:Align::left_center(),
+                ..Default::default()
+            });
+            // the label in runs, the matched chars in their own color
+            let label:Vec<char> = item.label.chars().collect();
+            let mut start = 0;
+            while start < label.len(){
+                let matched = chars.contains(&start);
+                let mut end = start + 1;
+                while end < label.len() && chars.contains(&end) == matched{
+                    end += 1;
+                }
+                self.text.color = if matched{self.match_color} else{self.text_color};
+                self.text.draw_text(cx, &label[start..end].iter().collect::<String>());
+                start = end;
+            }
+            if item.detail.len() > 0{
+                cx.begin_turtle(&Layout{
+                    width:Bounds::Fill,
+                    height:Bounds::Fill,
+                    align:Align::right_center(),
+                    ..Default::default()
+                }, Area::Empty);
+                self.text.color = self.detail_color;
+                self.text.draw_text(cx, &item.detail);
+                cx.end_turtle(Area::Empty);
+            }
+            let area = self.row_bg.end_quad(cx, &row);
+            self._row_rects.push((index, area.get_rect_scrolled(cx)));
+            cx.turtle_new_line();
+        }
+        let bg_area = self.bg.end_quad(cx, &inst);
+        self._panel_rect = bg_area.get_rect_scrolled(cx);
+        if self._take_focus{
+            self._take_focus = false;
+            cx.set_key_focus(bg_area);
+        }
+        else{
+            cx.update_area_refs(self._bg_area, bg_area);
+        }
+        self._bg_area = bg_area;
+        self.view.end_view(cx);
+    }
+}
+
+// scores how well a query matches the text as a subsequence, higher is better, and which chars
+// it matched. Matches at the start of words and runs of matching chars score more, gaps less
+pub fn fuzzy_match(query:&str, text:&str)->Option<(i32, Vec<usize>)>{
+    let query:Vec<char> = query.chars().filter(|c| !c.is_whitespace()).flat_map(|c| c.to_lowercase()).collect();
+    if query.len() == 0{
+        return Some((0, Vec::new()))
+    }
+    let chars:Vec<char> = text.chars().collect();
+    let lower:Vec<char> = chars.iter().map(|c| c.to_lowercase().next().unwrap_or(*c)).collect();
+    let word_start = |i:usize|{
+        i == 0 || !chars[i - 1].is_alphanumeric() || chars[i - 1].is_lowercase() && chars[i].is_uppercase()
+    };
+    // if the rest of the query still fits in the text after a position
+    let fits = |from:usize, rest:&[char]|{
+        let mut q = 0;
+        for c in &lower[from..]{
+            if q < rest.len() && *c == rest[q]{
+                q += 1;
+            }
+        }
+        q == rest.len()
+    };
+    let mut best:Option<(i32, Vec<usize>)> = None;
+    // greedy from every place the first char matches, keeping the best
+    for start in 0..lower.len(){
+        if lower[start] != query[0]{
+            continue
+        }
+        let mut matched = vec![start];
+        for (k, qc) in query.iter().enumerate().skip(1){
+            let from = matched[matched.len() - 1] + 1;
+            // a later word start beats the nearest char unless that one continues a run
+            let nearest = (from..lower.len()).find(|i| lower[*i] == *qc);
+            let at_word = (from..lower.len()).find(|i| lower[*i] == *qc && word_start(*i) && fits(*i + 1, &query[k + 1..]));
+            match (nearest, at_word){
+                (Some(n), Some(w)) if n != from=>matched.push(w),
+                (Some(n), _)=>matched.push(n),
+                _=>break
+            }
+        }
+        if matched.len() < query.len(){
+            // starting later only leaves less text to match in
+            break
+        }
+        let mut score = 0;
+        for (k, i) in matched.iter().enumerate(){
+            score += 1;
+            if word_start(*i){
+                score += 8;
+            }
+            if k > 0{
+                let gap = (i - matched[k - 1] - 1) as i32;
+                score += if gap == 0{5} else{-gap.min(5)};
+            }
+        }
+        score -= (start as i32).min(10);
+        if best.as_ref().map_or(true, |(s, _)| score > *s){
+            best = Some((score, matched));
+        }
+    }
+    best
+}
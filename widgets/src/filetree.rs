@@ -18,7 +18,8 @@ pub struct FileTree{
     pub root_node:FileNode,
     pub animator:Animator,
     pub row_height:f32,
-    pub row_padding:Padding
+    pub row_padding:Padding,
+    pub _view_area:Area
 }
 
 impl ElementLife for FileTree{
@@ -43,6 +44,25 @@ pub enum NodeState{
     Closed
 }
 
+impl NodeState{
+    fn toggle(&mut self){
+        *self = match self{
+            NodeState::Opening(fac)=>{
+                NodeState::Closing(1.0 - *fac)
+            },
+            NodeState::Closing(fac)=>{
+                NodeState::Opening(1.0 - *fac)
+            },
+            NodeState::Open=>{
+                NodeState::Closing(1.0)
+            },
+            NodeState::Closed=>{
+                NodeState::Opening(1.0)
+            }
+        };
+    }
+}
+
 #[derive(Clone)]
 pub struct NodeDraw{
     hit_state:HitState,
@@ -165,6 +185,7 @@ impl Style for FileTree{
     fn style(cx:&mut Cx)->Self{
         let filler_sh = Self::def_filler_shader(cx);
         let drag_bg_shader = Self::def_drag_bg_shader(cx);
+        Self::register_commands(cx);
         Self{
             row_height:20.,
             row_padding:Padding{l:5.,t:0.,r:0.,b:1.},
@@ -211,6 +232,7 @@ impl Style for FileTree{
             },
             animator:Animator::new(Anim::empty()),
             _drag_move:None,
+            _view_area:Area::Empty,
         }
     }
 }
@@ -327,6 +349,16 @@ impl FileTree{
     }
 
     pub fn handle_file_tree(&mut self, cx:&mut Cx, event:&mut Event)->FileTreeEvent{
+        let command = match event{
+            Event::KeyDown(ke) if cx.key_focus == self._view_area=>cx.key_command(ke, "file_tree"),
+            Event::Command(ce) if cx.key_focus == self._view_area=>Some(ce.command.clone()),
+            _=>None
+        };
+        if let Some(command) = command{
+            if let Some(path) = self.exec_command(cx, &command){
+                return FileTreeEvent::SelectFile{path:path}
+            }
+        }
         // alright. someone clicking on the tree items.
         let mut file_walker = FileWalker::new(&mut self.root_node);
         let mut counter = 0;
@@ -357,21 +389,10 @@ impl FileTree{
                     unmark_nodes = true;
                     node_draw.animator.play_anim(cx, Self::get_over_anim(cx, counter, node_draw.marked != 0));
 
+                    // the tree takes the keys for its commands
+                    cx.set_key_focus(self._view_area);
                     if let FileNode::Folder{state,..} = node{
-                        *state = match state{
-                            NodeState::Opening(fac)=>{
-                                NodeState::Closing(1.0 - *fac)
-                            },
-                            NodeState::Closing(fac)=>{
-                                NodeState::Opening(1.0 - *fac)
-                            },
-                            NodeState::Open=>{
-                                NodeState::Closing(1.0)
-                            },
-                            NodeState::Closed=>{
-                                NodeState::Opening(1.0)
-                            }
-                        };
+                        state.toggle();
                         // start the redraw loop
                         self.view.redraw_view_area(cx);
                     }
@@ -462,8 +483,98 @@ impl FileTree{
         FileTreeEvent::None
     }
 
+    // every file in the tree, also inside closed folders, as the paths SelectFile gives
+    pub fn file_paths(&self)->Vec<String>{
+        fn collect(node:&FileNode, path:&str, out:&mut Vec<String>){
+            let path = format!("{}/{}", path, node.name());
+            match node{
+                FileNode::File{..}=>out.push(path),
+                FileNode::Folder{folder, ..}=>for child in folder{
+                    collect(child, &path, out);
+                }
+            }
+        }
+        let mut out = Vec::new();
+        if let FileNode::Folder{folder, name, ..} = &self.root_node{
+            for child in folder{
+                collect(child, name, &mut out);
+            }
+        }
+        out
+    }
+
+    // the commands the tree runs from the keymap, with their default keys
+    pub fn register_commands(cx:&mut Cx){
+        let commands = [
+            ("file_tree.select_prev", "Select Previous File", "Up"),
+            ("file_tree.select_next", "Select Next File", "Down"),
+            ("file_tree.open", "Open File Or Folder", "Enter"),
+        ];
+        for (name, title, keys) in commands.iter(){
+            cx.register_command(name, title);
+            cx.bind_keys(keys, name, Some("file_tree")).expect("default file tree keys parse");
+        }
+    }
+
+    // runs a named command, returns the path of a file to open
+    pub fn exec_command(&mut self, cx:&mut Cx, command:&str)->Option<String>{
+        // the marked row among the ones that are showing
+        let mut showing = Vec::new();
+        let mut marked = None;
+        let mut file_walker = FileWalker::new(&mut self.root_node);
+        while let Some((_depth, _index, _len, node)) = file_walker.walk(){
+            let is_marked = node.get_draw().as_ref().map_or(false, |d| d.marked != 0);
+            let closing = file_walker.current_closing();
+            if is_marked && !closing{
+                marked = Some(showing.iter().filter(|s| **s).count());
+            }
+            showing.push(!closing);
+        }
+        let rows = showing.iter().filter(|s| **s).count();
+        let target = match command{
+            "file_tree.select_prev"=>marked.map(|m| if m > 0{m - 1} else{0}).unwrap_or(0),
+            "file_tree.select_next"=>marked.map(|m| (m + 1).min(rows.max(1) - 1)).unwrap_or(0),
+            "file_tree.open"=>marked?,
+            _=>return None
+        };
+
+        let mut row = 0;
+        let mut file_walker = FileWalker::new(&mut self.root_node);
+        for is_showing in showing{
+            let node = if let Some((_depth, _index, _len, node)) = file_walker.walk(){node} else{break};
+            let is_target = is_showing && row == target;
+            if is_showing{
+                row += 1;
+            }
+            if command == "file_tree.open"{
+                if !is_target{
+                    continue;
+                }
+                if let FileNode::Folder{state, ..} = node{
+                    state.toggle();
+                    self.view.redraw_view_area(cx);
+                    return None
+                }
+                return Some(file_walker.current_path())
+            }
+            if let Some(node_draw) = node.get_draw(){
+                let marked = if is_target{cx.event_id} else{0};
+                if node_draw.marked != marked{
+                    node_draw.marked = marked;
+                    node_draw.animator.play_anim(cx, Self::get_default_anim(cx, row.max(1) - 1, is_target));
+                }
+            }
+        }
+        None
+    }
+
     pub fn draw_file_tree(&mut self, cx:&mut Cx){
         self.view.begin_view(cx, &Layout{..Default::default()});
+        let view_area = self.view.get_view_area(cx);
+        if self._view_area != Area::Empty{
+            cx.update_area_refs(self._view_area, view_area);
+        }
+        self._view_area = view_area;
         let mut file_walker = FileWalker::new(&mut self.root_node);
         
         // lets draw the filetree
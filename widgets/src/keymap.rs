@@ -0,0 +1,61 @@
+use render::*;
+use serde_json::Value;
+
+// a keymap file is a list of bindings in the shape of vscode's keybindings.json
+//   [{"keys":"Ctrl+K Ctrl+C", "command":"editor.fold_all", "when":"editor"}]
+// a command starting with - removes that command's bindings on those keys, or all of them without keys
+// read from serde_json values, the serde derive of this serde version warns on every struct
+#[derive(Debug)]
+struct JsonBinding{
+    keys:String,
+    command:String,
+    when:Option<String>
+}
+
+impl JsonBinding{
+    fn from_value(value:&Value)->Result<JsonBinding, String>{
+        let field = |name:&str|->Result<Option<String>, String>{
+            match value.get(name){
+                None | Some(Value::Null)=>Ok(None),
+                Some(Value::String(s))=>Ok(Some(s.clone())),
+                Some(other)=>Err(format!("keymap: {} is not a string in {}", name, other))
+            }
+        };
+        if !value.is_object(){
+            return Err(format!("keymap: binding {} is not an object", value))
+        }
+        Ok(JsonBinding{
+            keys:field("keys")?.unwrap_or_default(),
+            command:field("command")?.ok_or_else(|| format!("keymap: missing command in {}", value))?,
+            when:field("when")?
+        })
+    }
+}
+
+// adds the bindings on top of what the widgets bound by default, returns how many were applied
+pub fn load_keymap_from_json(cx:&mut Cx, json_data:&str)->Result<usize, String>{
+    let value:Value = serde_json::from_str(json_data).map_err(|e| format!("keymap: {}", e))?;
+    let bindings = match value{
+        Value::Array(values)=>values.iter().map(JsonBinding::from_value).collect::<Result<Vec<_>, _>>()?,
+        _=>return Err("keymap: expected a list of bindings".to_string())
+    };
+    let mut errors = Vec::new();
+    let mut applied = 0;
+    for binding in &bindings{
+        let result = if binding.command.starts_with('-'){
+            let keys = if binding.keys.trim().is_empty(){None} else{Some(binding.keys.as_ref())};
+            cx.unbind_keys(keys, &binding.command[1..])
+        }
+        else{
+            cx.bind_keys(&binding.keys, &binding.command, binding.when.as_ref().map(|w| w.as_ref()))
+        };
+        match result{
+            Ok(_)=>applied += 1,
+            Err(err)=>errors.push(format!("{} ({})", err, binding.command))
+        }
+    }
+    if !errors.is_empty(){
+        return Err(format!("keymap: {}", errors.join(", ")))
+    }
+    Ok(applied)
+}
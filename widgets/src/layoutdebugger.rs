@@ -0,0 +1,248 @@
+use render::*;
+
+// toggle_layout outlines every turtle of the frame and shows the Layout of the one under the mouse,
+// toggle_tree opens the draw list tree next to it. The app binds them to F12 and shift+F12
+#[derive(Clone)]
+pub struct LayoutDebugger{
+    pub view:View<NoScrollBar>,
+    pub outline:Quad,
+    pub fill:Quad,
+    pub text:Text,
+    pub depth_hue:f32, // hue step from one turtle depth to the next
+    pub margin_color:Color,
+    pub padding_color:Color,
+    pub content_color:Color,
+    pub panel_width:f32,
+    pub show_layout:bool,
+    pub show_tree:bool,
+    pub _turtles:Vec<DebugTurtle>,
+    pub _hover:Option<usize>,
+    pub _mouse:Vec2,
+    pub _tree_scroll:usize
+}
+
+impl Style for LayoutDebugger{
+    fn style(cx:&mut Cx)->Self{
+        let outline_sh = Self::def_outline_shader(cx);
+        Self{
+            view:View{
+                is_overlay:true,
+                is_clipped:false,
+                ..Style::style(cx)
+            },
+            outline:Quad{
+                shader_id:cx.add_shader(outline_sh, "LayoutDebugger.outline"),
+                ..Style::style(cx)
+            },
+            fill:Quad{
+                ..Style::style(cx)
+            },
+            text:Text{
+                color:color("white"),
+                wrapping:Wrapping::None,
+                ..Style::style(cx)
+            },
+            depth_hue:0.17,
+            margin_color:color("#f9a040"),
+            padding_color:color("#8fc060"),
+            content_color:color("#60a0e0"),
+            panel_width:420.0,
+            show_layout:false,
+            show_tree:false,
+            _turtles:Vec::new(),
+            _hover:None,
+            _mouse:Vec2::zero(),
+            _tree_scroll:0
+        }
+    }
+}
+
+impl LayoutDebugger{
+    pub fn def_outline_shader(cx:&mut Cx)->Shader{
+        let mut sh = Quad::def_quad_shader(cx);
+        cx.import_shader_fns(&mut sh, "color", &["hsv2rgb"]).expect("color is a builtin shader module");
+        sh.add_ast(shader_ast!({
+            let hue:float<Instance>;
+            fn pixel()->vec4{
+                df_viewport(pos * vec2(w, h));
+                df_rect(0.5, 0.5, w - 1., h - 1.);
+                return df_stroke(color_hsv2rgb(vec4(fract(hue), 0.7, 1.0, color.a)), 1.);
+            }
+        }));
+        sh
+    }
+
+    pub fn toggle_layout(&mut self, cx:&mut Cx){
+        self.show_layout = !self.show_layout;
+        self._hover = None;
+        cx.debug_layout = self.show_layout;
+        cx.redraw_area(Area::All);
+    }
+
+    pub fn toggle_tree(&mut self, cx:&mut Cx){
+        self.show_tree = !self.show_tree;
+        self._tree_scroll = 0;
+        cx.redraw_area(Area::All);
+    }
+
+    pub fn handle_layout_debugger(&mut self, cx:&mut Cx, event:&mut Event){
+        match event{
+            Event::FingerHover(fe)=>{
+                if self.show_layout{
+                    self._mouse = fe.abs;
+                    let hover = self.find_hover(cx, fe.abs);
+                    if hover != self._hover{
+                        self._hover = hover;
+                        self.view.redraw_view_area(cx);
+                    }
+                }
+            },
+            Event::FingerScroll(fe)=>{
+                if self.show_tree && fe.abs.x > cx.target_size.x - self.panel_width{
+                    let lines = (fe.scroll.y / 16.0) as isize;
+                    self._tree_scroll = (self._tree_scroll as isize + lines).max(0) as usize;
+                    self.view.redraw_view_area(cx);
+                }
+            },
+            _=>()
+        }
+    }
+
+    // the deepest turtle under the mouse, the last drawn one on ties
+    fn find_hover(&self, cx:&Cx, pos:Vec2)->Option<usize>{
+        let mut hover:Option<usize> = None;
+        for (index, turtle) in self._turtles.iter().enumerate(){
+            if Self::screen_rect(cx, turtle).contains(pos.x, pos.y){
+                if hover.is_none() || turtle.depth >= self._turtles[hover.unwrap()].depth{
+                    hover = Some(index);
+                }
+            }
+        }
+        hover
+    }
+
+    fn screen_rect(cx:&Cx, turtle:&DebugTurtle)->Rect{
+        let scroll = if turtle.draw_list_id < cx.draw_lists.len(){
+            cx.draw_lists[turtle.draw_list_id].get_scroll_pos()
+        }
+        else{
+            Vec2::zero()
+        };
+        Rect{x:turtle.rect.x - scroll.x, y:turtle.rect.y - scroll.y, w:turtle.rect.w, h:turtle.rect.h}
+    }
+
+    // call after everything else is drawn
+    pub fn draw_layout_debugger(&mut self, cx:&mut Cx){
+        if !self.show_layout && !self.show_tree{
+            return
+        }
+        // take the records before our own turtles add to them
+        self._turtles = std::mem::replace(&mut cx.debug_turtles, Vec::new());
+        let mut tree = Vec::new();
+        if self.show_tree{
+            let stats = cx.frame_stats;
+            tree.push((0, format!("{} lists {} draw calls {} batched {} instances", stats.draw_lists, stats.draw_calls, stats.batched_draw_calls, stats.instances)));
+            cx.debug_draw_tree_recur(0, 0, &mut tree);
+        }
+
+        self.view.begin_view(cx, &Layout{
+            abs_start:Some(Vec2::zero()),
+            ..Default::default()
+        });
+
+        if self.show_layout{
+            for turtle in &self._turtles{
+                let rect = Self::screen_rect(cx, turtle);
+                let inst = self.outline.draw_quad(cx, rect);
+                inst.push_float(cx, turtle.depth as f32 * self.depth_hue);
+            }
+            if let Some(hover) = self._hover{
+                if let Some(turtle) = self._turtles.get(hover).cloned(){
+                    self.draw_hover(cx, &turtle);
+                }
+            }
+        }
+        if self.show_tree{
+            self.draw_tree(cx, &tree);
+        }
+        self.view.end_view(cx);
+    }
+
+    fn draw_hover(&mut self, cx:&mut Cx, turtle:&DebugTurtle){
+        let rect = Self::screen_rect(cx, turtle);
+        let layout = &turtle.layout;
+        let (m, p) = (layout.margin, layout.padding);
+        let margin_rect = Rect{x:rect.x - m.l, y:rect.y - m.t, w:rect.w + m.l + m.r, h:rect.h + m.t + m.b};
+        let content_rect = Rect{x:rect.x + p.l, y:rect.y + p.t, w:(rect.w - p.l - p.r).max(0.), h:(rect.h - p.t - p.b).max(0.)};
+        self.fill.color = self.margin_color;
+        self.fill.color.a = 0.35;
+        self.draw_frame(cx, margin_rect, rect);
+        self.fill.color = self.padding_color;
+        self.fill.color.a = 0.35;
+        self.draw_frame(cx, rect, content_rect);
+        self.fill.color = self.content_color;
+        self.fill.color.a = 0.25;
+        self.fill.draw_quad(cx, content_rect);
+
+        let mut lines = vec![
+            format!("depth {} draw list {}", turtle.depth, turtle.draw_list_id),
+            format!("rect {} {} {} x {}", rect.x, rect.y, rect.w, rect.h),
+            format!("width {:?} height {:?}", layout.width, layout.height),
+            format!("margin {} {} {} {}", m.l, m.t, m.r, m.b),
+            format!("padding {} {} {} {}", p.l, p.t, p.r, p.b),
+            format!("direction {:?} line_wrap {:?} align {} {}", layout.direction, layout.line_wrap, layout.align.fx, layout.align.fy),
+        ];
+        if let Some(abs_start) = layout.abs_start{
+            lines.push(format!("abs_start {} {}", abs_start.x, abs_start.y));
+        }
+        if let Some(flex) = &layout.flex{
+            lines.push(format!("flex {:?}", flex));
+        }
+        if let Some(grid) = &layout.grid{
+            lines.push(format!("grid {:?}", grid));
+        }
+
+        self.fill.color = color("#222");
+        let inst = self.fill.begin_quad(cx, &Layout{
+            abs_start:Some(Vec2{x:self._mouse.x + 16., y:self._mouse.y + 16.}),
+            width:Bounds::Compute,
+            height:Bounds::Compute,
+            padding:Padding::all(6.),
+            ..Default::default()
+        });
+        for line in &lines{
+            self.text.draw_text(cx, line);
+            cx.turtle_new_line();
+        }
+        self.fill.end_quad(cx, &inst);
+    }
+
+    // the four strips between an outer and an inner rect
+    fn draw_frame(&mut self, cx:&mut Cx, outer:Rect, inner:Rect){
+        self.fill.draw_quad(cx, Rect{x:outer.x, y:outer.y, w:outer.w, h:inner.y - outer.y});
+        self.fill.draw_quad(cx, Rect{x:outer.x, y:inner.y + inner.h, w:outer.w, h:outer.y + outer.h - inner.y - inner.h});
+        self.fill.draw_quad(cx, Rect{x:outer.x, y:inner.y, w:inner.x - outer.x, h:inner.h});
+        self.fill.draw_quad(cx, Rect{x:inner.x + inner.w, y:inner.y, w:outer.x + outer.w - inner.x - inner.w, h:inner.h});
+    }
+
+    fn draw_tree(&mut self, cx:&mut Cx, tree:&[(usize, String)]){
+        self.fill.color = color("#181818");
+        self.fill.color.a = 0.9;
+        let inst = self.fill.begin_quad(cx, &Layout{
+            abs_start:Some(Vec2{x:cx.target_size.x - self.panel_width, y:0.}),
+            width:Bounds::Fix(self.panel_width),
+            height:Bounds::Fix(cx.target_size.y),
+            padding:Padding::all(6.),
+            ..Default::default()
+        });
+        self._tree_scroll = self._tree_scroll.min(tree.len().max(1) - 1);
+        for (depth, line) in tree.iter().skip(self._tree_scroll){
+            if cx.get_rel_turtle_walk().y > cx.target_size.y - 20.{
+                break
+            }
+            self.text.draw_text(cx, &format!("{}{}", "  ".repeat(*depth), line));
+            cx.turtle_new_line();
+        }
+        self.fill.end_quad(cx, &inst);
+    }
+}
@@ -17,6 +17,14 @@ mod style;
 pub use crate::style::*;
 mod filetree;
 pub use crate::filetree::*;
+mod layoutdebugger;
+pub use crate::layoutdebugger::*;
+mod profilerhud;
+pub use crate::profilerhud::*;
+mod keymap;
+pub use crate::keymap::*;
+mod commandpalette;
+pub use crate::commandpalette::*;
 //mod textbuffer;
 //pub use crate::textbuffer::*;
 //mod editor;
@@ -0,0 +1,155 @@
+use render::*;
+
+// shows the cost of the last frames as a graph with their counts, save_trace writes the
+// history as a chrome trace. The app binds them to F10 and shift+F10
+#[derive(Clone)]
+pub struct ProfilerHud{
+    pub view:View<NoScrollBar>,
+    pub bg:Quad,
+    pub bar:Quad,
+    pub text:Text,
+    pub span_colors:Vec<(&'static str, Color)>,
+    pub other_color:Color,
+    pub budget_color:Color,
+    pub budget_ms:f64, // the line in the graph
+    pub graph_ms:f64, // what the full graph height stands for
+    pub graph_height:f32,
+    pub bar_width:f32,
+    pub bars:usize,
+    pub trace_path:String,
+    pub show:bool
+}
+
+impl Style for ProfilerHud{
+    fn style(cx:&mut Cx)->Self{
+        Self{
+            view:View{
+                is_overlay:true,
+                is_clipped:false,
+                ..Style::style(cx)
+            },
+            bg:Quad{
+                color:color("#111e"),
+                ..Style::style(cx)
+            },
+            bar:Quad{
+                ..Style::style(cx)
+            },
+            text:Text{
+                color:color("white"),
+                wrapping:Wrapping::None,
+                ..Style::style(cx)
+            },
+            span_colors:vec![
+                ("event", color("#49e")),
+                ("draw", color("#6c6")),
+                ("repaint", color("#e94")),
+            ],
+            other_color:color("#999"),
+            budget_color:color("#f44"),
+            budget_ms:1000. / 60.,
+            graph_ms:1000. / 30.,
+            graph_height:60.,
+            bar_width:2.,
+            bars:120,
+            trace_path:"./profile_trace.json".to_string(),
+            show:false
+        }
+    }
+}
+
+impl ProfilerHud{
+    pub fn toggle_hud(&mut self, cx:&mut Cx){
+        self.show = !self.show;
+        cx.enable_profiler(self.show);
+        cx.redraw_area(Area::All);
+    }
+
+    // only while profiling, there is no history otherwise
+    pub fn save_trace(&mut self, cx:&mut Cx){
+        if cx.profiler.enabled{
+            cx.save_chrome_trace(&self.trace_path);
+        }
+    }
+
+    pub fn handle_profiler_hud(&mut self, cx:&mut Cx, event:&mut Event){
+        match event{
+            Event::None | Event::Draw=>(),
+            _=>{
+                // show the frames this event caused, one frame late
+                if self.show{
+                    self.view.redraw_view_area(cx);
+                }
+            }
+        }
+    }
+
+    fn span_color(&self, name:&str)->Color{
+        if let Some((_, color)) = self.span_colors.iter().find(|(n, _)| *n == name){
+            *color
+        }
+        else{
+            self.other_color
+        }
+    }
+
+    // call after everything else is drawn
+    pub fn draw_profiler_hud(&mut self, cx:&mut Cx){
+        if !self.show{
+            return
+        }
+        let frames = cx.profiler.frames();
+        let frames = &frames[frames.len().saturating_sub(self.bars)..];
+        // the outermost spans of every frame stack up to its bar
+        let stacks:Vec<Vec<(Color, f64)>> = frames.iter().map(|frame|{
+            frame.spans.iter().filter(|s| s.depth == 0).map(|s| (self.span_color(s.name), s.end - s.start)).collect()
+        }).collect();
+        let busy:Vec<f64> = frames.iter().map(|f| f.busy_ms(None)).collect();
+        let last = frames.last().map(|f| (*f).clone()).unwrap_or_default();
+        let avg = if busy.len() > 0{busy.iter().sum::<f64>() / busy.len() as f64}else{0.};
+        let max = busy.iter().fold(0., |a:f64, b| a.max(*b));
+        let mut lines = vec![
+            format!("frame {:.2} ms  avg {:.2}  max {:.2}", last.busy_ms(None), avg, max),
+            self.span_colors.iter().map(|(name, _)| format!("{} {:.2}", name, last.busy_ms(Some(name)))).collect::<Vec<_>>().join("  "),
+            format!("draw calls {}  instances {}  uploaded {:.1} KB", last.draw_calls, last.instances, last.uploaded_bytes as f64 / 1024.),
+        ];
+        if cx.frame_stats.draw_calls > 0{
+            lines.push(format!("batched {} of {} draw calls  damage {} rects", cx.frame_stats.batched_draw_calls, cx.frame_stats.draw_calls, cx.damage.len()));
+        }
+
+        self.view.begin_view(cx, &Layout{
+            abs_start:Some(Vec2::zero()),
+            ..Default::default()
+        });
+        let width = self.bars as f32 * self.bar_width;
+        let inst = self.bg.begin_quad(cx, &Layout{
+            abs_start:Some(Vec2{x:8., y:8.}),
+            width:Bounds::Fix(width + 12.),
+            height:Bounds::Compute,
+            padding:Padding::all(6.),
+            ..Default::default()
+        });
+        for line in &lines{
+            self.text.draw_text(cx, line);
+            cx.turtle_new_line();
+        }
+        let graph = cx.walk_turtle(Bounds::Fix(width), Bounds::Fix(self.graph_height), Margin::zero(), None);
+        let origin = cx.turtle_origin();
+        let (gx, gy) = (graph.x - origin.x, graph.y - origin.y);
+        let scale = self.graph_height / self.graph_ms as f32;
+        let offset = (self.bars - stacks.len()) as f32 * self.bar_width;
+        for (index, stack) in stacks.iter().enumerate(){
+            let mut bottom = gy + self.graph_height;
+            for (color, ms) in stack{
+                let h = (*ms as f32 * scale).min(bottom - gy);
+                self.bar.color = *color;
+                self.bar.draw_quad(cx, Rect{x:gx + offset + index as f32 * self.bar_width, y:bottom - h, w:self.bar_width, h:h});
+                bottom -= h;
+            }
+        }
+        self.bar.color = self.budget_color;
+        self.bar.draw_quad(cx, Rect{x:gx, y:gy + self.graph_height - self.budget_ms as f32 * scale, w:width, h:1.});
+        self.bg.end_quad(cx, &inst);
+        self.view.end_view(cx);
+    }
+}
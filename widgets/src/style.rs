@@ -1,5 +1,14 @@
 use render::*;
 
+// where the platforms keep a cjk font with a monospace face, #n picks the face of a collection
+const SYSTEM_MONO_CJK:[&str;5] = [
+    "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc#5",
+    "/usr/share/fonts/google-noto-cjk/NotoSansCJK-Regular.ttc#5",
+    "/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc#5",
+    "C:/Windows/Fonts/msgothic.ttc",
+    "/System/Library/Fonts/Hiragino Sans GB.ttc"
+];
+
 pub fn set_dark_style(cx:&mut Cx){
     
     cx.set_font("normal_font", "resources/ubuntu_regular_256.font.html");
@@ -9,6 +18,11 @@ pub fn set_dark_style(cx:&mut Cx){
     //cx.set_font("mono_font", "resources/monofur_55_256.font");
     
     //cx.set_font("mono_font", "resources/inconsolata_regular_256.font");
+    // looked in, in order, for glyphs the mono font doesnt have. DejaVu Sans Mono has box drawing
+    // and most alphabets, cjk comes from the monospace face of a cjk font on the system
+    let mut mono_fallback = vec!["resources/DejaVuSansMono.ttf"];
+    mono_fallback.extend(SYSTEM_MONO_CJK.iter().find(|name| std::path::Path::new(font_file_and_face(name).0).exists()));
+    cx.set_font_list("mono_font_fallback", &mono_fallback);
     cx.set_font("icon_font", "resources/fontawesome.font");
     cx.set_size("font_size", 11.0);
 
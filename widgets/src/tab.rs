@@ -48,7 +48,10 @@ impl Style for Tab{
                 margin:Margin{l:-4.,t:3.,r:4.,b:0.},
                 ..Style::style(cx)
             },
-            text:Text{..Style::style(cx)},
+            text:Text{
+                wrapping:Wrapping::Ellipsis(200.),
+                ..Style::style(cx)
+            },
             animator:Animator::new(Anim::empty()),
             _is_selected:false,
             _is_focussed:false,
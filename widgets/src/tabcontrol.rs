@@ -177,6 +177,7 @@ impl TabControl{
         self.tabs_view.begin_view(cx, &Layout{
             width:Bounds::Fill,
             height:Bounds::Compute,
+            direction:Direction::Inline,
            ..Default::default()
         });
         //self.tabs.mark();